@@ -0,0 +1,248 @@
+//! Adapters that change the behavior of readers.
+
+use std::{
+    future::Future,
+    io::{IoSliceMut, Result},
+};
+
+use super::{BufRead, Initializer, Read};
+
+/// A reader that reads at most a limited number of bytes from another reader.
+///
+/// This is created by [`super::ReadExt::take`].
+#[derive(Debug)]
+pub struct Take<R> {
+    inner: R,
+    limit: u64,
+}
+
+impl<R> Take<R> {
+    pub(super) fn new(inner: R, limit: u64) -> Self {
+        Self { inner, limit }
+    }
+
+    /// Returns the number of bytes that can be read before EOF.
+    ///
+    /// This can be less than the number of bytes left in the underlying
+    /// reader.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Sets the number of bytes that can be read before EOF.
+    ///
+    /// This is the same as creating a new `Take` with the underlying reader.
+    pub fn set_limit(&mut self, limit: u64) {
+        self.limit = limit;
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// Reading from the underlying reader directly doesn't change the limit.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `Take`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for Take<R> {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        async move {
+            // Clamps the buffer so that the underlying reader never reads past
+            // the limit.
+            let len = clamp(buf.len(), self.limit);
+            if len == 0 {
+                return Ok(0);
+            }
+            let n = self.inner.read(&mut buf[..len]).await?;
+            self.limit -= n as u64;
+            Ok(n)
+        }
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        let mut left = self.limit;
+        let mut bufs: Vec<IoSliceMut<'a>> = bufs
+            .iter_mut()
+            .map_while(|buf| {
+                let len = clamp(buf.len(), left);
+                left -= len as u64;
+                (len > 0).then(|| IoSliceMut::new(&mut buf[..len]))
+            })
+            .collect();
+        async move {
+            if bufs.is_empty() {
+                return Ok(0);
+            }
+            let n = self.inner.read_vectored(&mut bufs).await?;
+            self.limit -= n as u64;
+            Ok(n)
+        }
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        self.inner.is_read_vectored()
+    }
+
+    fn initializer(&self) -> Initializer {
+        self.inner.initializer()
+    }
+}
+
+impl<R: BufRead> BufRead for Take<R> {
+    type FillBuf<'a> = impl Future<Output = Result<&'a [u8]>> + 'a where Self: 'a;
+
+    fn fill_buf(&mut self) -> Self::FillBuf<'_> {
+        async move {
+            if self.limit == 0 {
+                return Ok(&[][..]);
+            }
+            let buf = self.inner.fill_buf().await?;
+            Ok(&buf[..clamp(buf.len(), self.limit)])
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let amt = clamp(amt, self.limit);
+        self.limit -= amt as u64;
+        self.inner.consume(amt);
+    }
+}
+
+/// Returns the smaller of `len` and `limit`.
+fn clamp(len: usize, limit: u64) -> usize {
+    len.min(limit.try_into().unwrap_or(usize::MAX))
+}
+
+/// A reader that reads from one reader until EOF, and then from another.
+///
+/// This is created by [`super::ReadExt::chain`].
+#[derive(Debug)]
+pub struct Chain<R1, R2> {
+    first: R1,
+    second: R2,
+    done_first: bool,
+}
+
+impl<R1, R2> Chain<R1, R2> {
+    pub(super) fn new(first: R1, second: R2) -> Self {
+        Self {
+            first,
+            second,
+            done_first: false,
+        }
+    }
+
+    /// Gets references to the underlying readers.
+    pub fn get_ref(&self) -> (&R1, &R2) {
+        (&self.first, &self.second)
+    }
+
+    /// Gets mutable references to the underlying readers.
+    ///
+    /// Reading from the first reader directly doesn't make this read from it
+    /// again once it has reached EOF.
+    pub fn get_mut(&mut self) -> (&mut R1, &mut R2) {
+        (&mut self.first, &mut self.second)
+    }
+
+    /// Unwraps this `Chain`, returning the underlying readers.
+    pub fn into_inner(self) -> (R1, R2) {
+        (self.first, self.second)
+    }
+}
+
+impl<R1: Read, R2: Read> Read for Chain<R1, R2> {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        async move {
+            // Each read goes to one of the readers, so that the first reader
+            // is drained before the second one is touched.
+            if !self.done_first {
+                let n = self.first.read(buf).await?;
+                if n > 0 || buf.is_empty() {
+                    return Ok(n);
+                }
+                self.done_first = true;
+            }
+            self.second.read(buf).await
+        }
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        // The future can't capture the lifetime of the buffers, so reborrows
+        // them for the lifetime of `bufs`.
+        let mut bufs: Vec<IoSliceMut<'a>> = bufs.iter_mut().map(|b| IoSliceMut::new(b)).collect();
+        async move {
+            if !self.done_first {
+                let n = self.first.read_vectored(&mut bufs).await?;
+                if n > 0 || bufs.iter().all(|b| b.is_empty()) {
+                    return Ok(n);
+                }
+                self.done_first = true;
+            }
+            self.second.read_vectored(&mut bufs).await
+        }
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        if self.done_first {
+            self.second.is_read_vectored()
+        } else {
+            self.first.is_read_vectored()
+        }
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The buffer can be passed to either reader.
+        if self.first.initializer().should_initialize()
+            || self.second.initializer().should_initialize()
+        {
+            Initializer::zeroing()
+        } else {
+            unsafe { Initializer::nop() }
+        }
+    }
+}
+
+impl<R1: BufRead, R2: BufRead> BufRead for Chain<R1, R2> {
+    type FillBuf<'a> = impl Future<Output = Result<&'a [u8]>> + 'a where Self: 'a;
+
+    fn fill_buf(&mut self) -> Self::FillBuf<'_> {
+        async move {
+            if !self.done_first {
+                // Filling a non-empty buffer again doesn't read, so this
+                // doesn't cost another read of the first reader.
+                if !self.first.fill_buf().await?.is_empty() {
+                    return self.first.fill_buf().await;
+                }
+                self.done_first = true;
+            }
+            self.second.fill_buf().await
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.done_first {
+            self.second.consume(amt);
+        } else {
+            self.first.consume(amt);
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Type-erased readers and writers.
+
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+use std::{
+    fmt,
+    future::Future,
+    io::{IoSlice, IoSliceMut, Result},
+    pin::Pin,
+};
+
+use super::{Initializer, Read, Write};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// An object-safe version of [`Read`].
+///
+/// This is public but unreachable, so that it can bound the constructors.
+pub trait DynRead: Send {
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> BoxFuture<'a, Result<usize>>;
+
+    fn read_vectored<'a>(
+        &'a mut self,
+        bufs: &'a mut [IoSliceMut<'_>],
+    ) -> BoxFuture<'a, Result<usize>>;
+
+    fn is_read_vectored(&self) -> bool;
+
+    fn initializer(&self) -> Initializer;
+
+    #[cfg(unix)]
+    fn splice_source(&mut self) -> Option<(RawFd, Option<&mut u64>)>;
+}
+
+impl<T> DynRead for T
+where
+    T: Read + Send,
+    for<'a> T::Read<'a>: Send,
+    for<'a> T::ReadVectored<'a>: Send,
+{
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(Read::read(self, buf))
+    }
+
+    fn read_vectored<'a>(
+        &'a mut self,
+        bufs: &'a mut [IoSliceMut<'_>],
+    ) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(Read::read_vectored(self, bufs))
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        Read::is_read_vectored(self)
+    }
+
+    fn initializer(&self) -> Initializer {
+        Read::initializer(self)
+    }
+
+    #[cfg(unix)]
+    fn splice_source(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        Read::splice_source(self)
+    }
+}
+
+/// An object-safe version of [`Write`].
+pub trait DynWrite: Send {
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> BoxFuture<'a, Result<usize>>;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> BoxFuture<'a, Result<usize>>;
+
+    fn is_write_vectored(&self) -> bool;
+
+    fn flush(&mut self) -> BoxFuture<'_, Result<()>>;
+
+    fn shutdown(&mut self) -> BoxFuture<'_, Result<()>>;
+
+    #[cfg(unix)]
+    fn splice_sink(&mut self) -> Option<(RawFd, Option<&mut u64>)>;
+}
+
+impl<T> DynWrite for T
+where
+    T: Write + Send,
+    for<'a> T::Write<'a>: Send,
+    for<'a> T::WriteVectored<'a>: Send,
+    for<'a> T::Flush<'a>: Send,
+    for<'a> T::Shutdown<'a>: Send,
+{
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(Write::write(self, buf))
+    }
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(Write::write_vectored(self, bufs))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        Write::is_write_vectored(self)
+    }
+
+    fn flush(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(Write::flush(self))
+    }
+
+    fn shutdown(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(Write::shutdown(self))
+    }
+
+    #[cfg(unix)]
+    fn splice_sink(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        Write::splice_sink(self)
+    }
+}
+
+pub trait DynStream: DynRead + DynWrite {}
+
+impl<T: DynRead + DynWrite> DynStream for T {}
+
+macro_rules! impl_read {
+    ($ty:ident) => {
+        impl Read for $ty {
+            type Read<'a> = BoxFuture<'a, Result<usize>>;
+
+            fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+                self.0.read(buf)
+            }
+
+            type ReadVectored<'a> = BoxFuture<'a, Result<usize>>;
+
+            fn read_vectored<'a>(
+                &'a mut self,
+                bufs: &'a mut [IoSliceMut<'_>],
+            ) -> Self::ReadVectored<'a> {
+                self.0.read_vectored(bufs)
+            }
+
+            fn is_read_vectored(&self) -> bool {
+                self.0.is_read_vectored()
+            }
+
+            fn initializer(&self) -> Initializer {
+                self.0.initializer()
+            }
+
+            #[cfg(unix)]
+            fn splice_source(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+                self.0.splice_source()
+            }
+        }
+    };
+}
+
+macro_rules! impl_write {
+    ($ty:ident) => {
+        impl Write for $ty {
+            type Write<'a> = BoxFuture<'a, Result<usize>>;
+
+            fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+                self.0.write(buf)
+            }
+
+            type WriteVectored<'a> = BoxFuture<'a, Result<usize>>;
+
+            fn write_vectored<'a>(
+                &'a mut self,
+                bufs: &'a [IoSlice<'_>],
+            ) -> Self::WriteVectored<'a> {
+                self.0.write_vectored(bufs)
+            }
+
+            fn is_write_vectored(&self) -> bool {
+                self.0.is_write_vectored()
+            }
+
+            #[cfg(unix)]
+            fn splice_sink(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+                self.0.splice_sink()
+            }
+
+            type Flush<'a> = BoxFuture<'a, Result<()>>;
+
+            fn flush(&mut self) -> Self::Flush<'_> {
+                self.0.flush()
+            }
+
+            type Shutdown<'a> = BoxFuture<'a, Result<()>>;
+
+            fn shutdown(&mut self) -> Self::Shutdown<'_> {
+                self.0.shutdown()
+            }
+        }
+    };
+}
+
+/// A type-erased [`Read`].
+///
+/// The traits of this crate return a distinct future type for each
+/// implementation, so they can't be used as trait objects. This wraps a
+/// reader in a trait object whose futures are boxed instead, so that
+/// readers of different types can be used as the same type.
+///
+/// Each operation allocates its future, and polls it through a virtual call,
+/// which is small compared to a system call but adds up for small reads.
+/// Wrap the reader in a [`super::BufReader`] before boxing it if that matters.
+pub struct BoxedReader(Box<dyn DynRead>);
+
+impl BoxedReader {
+    /// Boxes `reader`, which must be [`Send`] and return [`Send`] futures.
+    pub fn new<R: DynRead + 'static>(reader: R) -> Self {
+        Self(Box::new(reader))
+    }
+}
+
+impl_read!(BoxedReader);
+
+/// A type-erased [`Write`].
+///
+/// See also [`BoxedReader`] for the cost of the type erasure.
+pub struct BoxedWriter(Box<dyn DynWrite>);
+
+impl BoxedWriter {
+    /// Boxes `writer`, which must be [`Send`] and return [`Send`] futures.
+    pub fn new<W: DynWrite + 'static>(writer: W) -> Self {
+        Self(Box::new(writer))
+    }
+}
+
+impl_write!(BoxedWriter);
+
+/// A type-erased object that implements both [`Read`] and [`Write`].
+///
+/// See also [`BoxedReader`] for the cost of the type erasure.
+pub struct BoxedStream(Box<dyn DynStream>);
+
+impl BoxedStream {
+    /// Boxes `stream`, which must be [`Send`] and return [`Send`] futures.
+    pub fn new<S: DynStream + 'static>(stream: S) -> Self {
+        Self(Box::new(stream))
+    }
+}
+
+impl_read!(BoxedStream);
+impl_write!(BoxedStream);
+
+impl fmt::Debug for BoxedReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedReader").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for BoxedWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedWriter").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for BoxedStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedStream").finish_non_exhaustive()
+    }
+}
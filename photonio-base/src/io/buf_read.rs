@@ -0,0 +1,184 @@
+//! Primitives for asynchronous buffered reads.
+
+use std::{
+    future::{ready, Future, Ready},
+    io::{ErrorKind, Result},
+};
+
+use super::{read::Utf8Guard, Read};
+
+/// Reads bytes from an object with an internal buffer.
+///
+/// This allows reading small pieces of data, like lines or headers, without a
+/// read of the underlying object for each piece.
+pub trait BufRead: Read {
+    /// A future that resolves to the result of [`Self::fill_buf`].
+    type FillBuf<'a>: Future<Output = Result<&'a [u8]>> + 'a
+    where
+        Self: 'a;
+
+    /// Returns the buffered data, filling the buffer from the underlying
+    /// object if it is empty.
+    ///
+    /// An empty slice means the underlying object has reached EOF. The data is
+    /// not consumed until [`Self::consume`] is called.
+    fn fill_buf(&mut self) -> Self::FillBuf<'_>;
+
+    /// Marks `amt` bytes of the buffered data as read.
+    ///
+    /// `amt` is clamped to the length of the buffered data.
+    fn consume(&mut self, amt: usize);
+}
+
+/// Provides extension methods for [`BufRead`].
+pub trait BufReadExt {
+    /// A future that resolves to the result of [`Self::read_until`].
+    type ReadUntil<'a>: Future<Output = Result<usize>> + 'a
+    where
+        Self: 'a;
+
+    /// Reads bytes until `byte` or EOF, appending them to `buf`.
+    ///
+    /// The delimiter is included in `buf` if it is found. If an error occurs,
+    /// the bytes read so far are kept in `buf`.
+    ///
+    /// Returns the number of bytes read, which is 0 at EOF.
+    ///
+    /// See also [`std::io::BufRead::read_until`].
+    fn read_until<'a>(&'a mut self, byte: u8, buf: &'a mut Vec<u8>) -> Self::ReadUntil<'a>;
+
+    /// A future that resolves to the result of [`Self::read_line`].
+    type ReadLine<'a>: Future<Output = Result<usize>> + 'a
+    where
+        Self: 'a;
+
+    /// Reads bytes until a newline or EOF, appending them to `buf`.
+    ///
+    /// The newline is included in `buf` if it is found.
+    ///
+    /// Returns an error of [`ErrorKind::InvalidData`] if the bytes are not
+    /// valid UTF-8, in which case `buf` is left unchanged. The bytes are still
+    /// consumed from this object.
+    ///
+    /// Returns the number of bytes read, which is 0 at EOF.
+    ///
+    /// See also [`std::io::BufRead::read_line`].
+    fn read_line<'a>(&'a mut self, buf: &'a mut String) -> Self::ReadLine<'a>;
+
+    /// Returns the lines of this object.
+    ///
+    /// See also [`std::io::BufRead::lines`].
+    fn lines(self) -> Lines<Self>
+    where
+        Self: Sized;
+}
+
+impl<T> BufReadExt for T
+where
+    T: BufRead,
+{
+    type ReadUntil<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_until<'a>(&'a mut self, byte: u8, buf: &'a mut Vec<u8>) -> Self::ReadUntil<'a> {
+        read_until(self, byte, buf)
+    }
+
+    type ReadLine<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_line<'a>(&'a mut self, buf: &'a mut String) -> Self::ReadLine<'a> {
+        read_line(self, buf)
+    }
+
+    fn lines(self) -> Lines<Self> {
+        Lines { reader: self }
+    }
+}
+
+/// The lines of a [`BufRead`].
+///
+/// This is created by [`BufReadExt::lines`].
+#[derive(Debug)]
+pub struct Lines<R> {
+    reader: R,
+}
+
+impl<R: BufRead> Lines<R> {
+    /// Returns the next line, or `None` at EOF.
+    ///
+    /// The line doesn't include the trailing `\n` or `\r\n`. The last line is
+    /// returned even if it is not terminated.
+    pub async fn next_line(&mut self) -> Result<Option<String>> {
+        let mut line = String::new();
+        if read_line(&mut self.reader, &mut line).await? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Unwraps this `Lines`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+async fn read_until<T: BufRead>(reader: &mut T, byte: u8, buf: &mut Vec<u8>) -> Result<usize> {
+    let mut read = 0;
+    loop {
+        let (found, used) = {
+            let available = match reader.fill_buf().await {
+                Ok(available) => available,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            match available.iter().position(|&b| b == byte) {
+                Some(i) => {
+                    buf.extend_from_slice(&available[..=i]);
+                    (true, i + 1)
+                }
+                None => {
+                    buf.extend_from_slice(available);
+                    (false, available.len())
+                }
+            }
+        };
+        reader.consume(used);
+        read += used;
+        if found || used == 0 {
+            return Ok(read);
+        }
+    }
+}
+
+async fn read_line<T: BufRead>(reader: &mut T, buf: &mut String) -> Result<usize> {
+    let mut guard = Utf8Guard::new(buf);
+    let result = read_until(reader, b'\n', guard.bytes()).await;
+    guard.finish(result)
+}
+
+impl BufRead for &[u8] {
+    type FillBuf<'a> = Ready<Result<&'a [u8]>> where Self: 'a;
+
+    fn fill_buf(&mut self) -> Self::FillBuf<'_> {
+        ready(Ok(*self))
+    }
+
+    fn consume(&mut self, amt: usize) {
+        *self = &self[amt.min(self.len())..];
+    }
+}
@@ -0,0 +1,587 @@
+//! Buffered I/O adapters.
+
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+use std::{
+    fmt,
+    future::Future,
+    io::{Error, ErrorKind, IoSlice, IoSliceMut, Result},
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ptr,
+};
+
+use super::{BufRead, Initializer, Read, ReadBuf, Write};
+
+/// The default capacity of [`BufReader`] and [`BufWriter`].
+const DEFAULT_CAPACITY: usize = 8 << 10;
+
+/// Adds buffering to a reader.
+///
+/// Reads are served from an internal buffer, which is refilled with one large
+/// read of the underlying reader when it is empty. Reads at least as large as
+/// the buffer bypass it if it is empty.
+///
+/// This type is an async version of [`std::io::BufReader`].
+pub struct BufReader<R> {
+    inner: R,
+    buf: Box<[MaybeUninit<u8>]>,
+    pos: usize,
+    filled: usize,
+    // The buffer is initialized up to here, which is at least `filled`.
+    initialized: usize,
+}
+
+impl<R: Read> BufReader<R> {
+    /// Creates a reader with the default capacity, which is currently 8 KiB.
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, inner)
+    }
+
+    /// Creates a reader with a buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        let mut buf = Vec::with_capacity(capacity);
+        unsafe { buf.set_len(capacity) };
+        Self {
+            inner,
+            buf: buf.into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+            initialized: 0,
+        }
+    }
+}
+
+impl<R> BufReader<R> {
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Reading from the underlying reader directly skips the buffered data.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the buffered data that is not read yet.
+    ///
+    /// Unlike [`BufRead::fill_buf`], this never reads from the underlying
+    /// reader.
+    pub fn buffer(&self) -> &[u8] {
+        let buf = &self.buf[self.pos..self.filled];
+        unsafe { &*(buf as *const [MaybeUninit<u8>] as *const [u8]) }
+    }
+
+    /// Returns the capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the underlying reader.
+    ///
+    /// The buffered data is lost. Use [`Self::into_parts`] to keep it.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the underlying reader and the buffered data that is not read
+    /// yet.
+    pub fn into_parts(self) -> (R, Vec<u8>) {
+        let buf = self.buffer().to_vec();
+        (self.inner, buf)
+    }
+
+    fn discard_buffer(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+impl<R: Read> Read for BufReader<R> {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        async move {
+            if self.pos == self.filled && buf.len() >= self.capacity() {
+                self.discard_buffer();
+                return self.inner.read(buf).await;
+            }
+            let data = self.fill_buf().await?;
+            let n = data.len().min(buf.len());
+            buf[..n].copy_from_slice(&data[..n]);
+            self.consume(n);
+            Ok(n)
+        }
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        // The future can't capture the lifetime of the buffers, so reborrows
+        // them for the lifetime of `bufs`.
+        let mut bufs: Vec<IoSliceMut<'a>> = bufs.iter_mut().map(|b| IoSliceMut::new(b)).collect();
+        async move {
+            let len: usize = bufs.iter().map(|b| b.len()).sum();
+            if self.pos == self.filled && len >= self.capacity() {
+                self.discard_buffer();
+                return self.inner.read_vectored(&mut bufs).await;
+            }
+            let mut data = self.fill_buf().await?;
+            let mut n = 0;
+            for buf in bufs.iter_mut() {
+                let m = data.len().min(buf.len());
+                buf[..m].copy_from_slice(&data[..m]);
+                data = &data[m..];
+                n += m;
+            }
+            self.consume(n);
+            Ok(n)
+        }
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        self.inner.is_read_vectored()
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The buffered data is only copied into the buffer, and the other reads
+        // go to the underlying reader.
+        self.inner.initializer()
+    }
+
+    #[cfg(unix)]
+    fn splice_source(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        // The buffered data must be read first.
+        if self.pos == self.filled {
+            self.inner.splice_source()
+        } else {
+            None
+        }
+    }
+}
+
+impl<R: Read> BufRead for BufReader<R> {
+    type FillBuf<'a> = impl Future<Output = Result<&'a [u8]>> + 'a where Self: 'a;
+
+    fn fill_buf(&mut self) -> Self::FillBuf<'_> {
+        async move {
+            if self.pos == self.filled {
+                // Leaves the buffer empty if the read fails or is cancelled.
+                self.discard_buffer();
+                let mut buf = ReadBuf::uninit(&mut self.buf);
+                unsafe { buf.assume_init(self.initialized) };
+                let result = self.inner.read_buf(&mut buf).await;
+                self.initialized = buf.initialized().len();
+                self.filled = result?;
+            }
+            Ok(self.buffer())
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+impl<R: fmt::Debug> fmt::Debug for BufReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufReader")
+            .field("reader", &self.inner)
+            .field(
+                "buffer",
+                &format_args!("{}/{}", self.filled - self.pos, self.capacity()),
+            )
+            .finish()
+    }
+}
+
+/// Adds buffering to a writer.
+///
+/// Small writes are collected in an internal buffer, which is written to the
+/// underlying writer when it is full or flushed. Writes that don't fit in the
+/// buffer flush it and then go to the underlying writer directly.
+///
+/// The buffer can't be flushed when this is dropped, since that would need to
+/// wait, so the buffered data is lost unless [`Write::flush`],
+/// [`Write::shutdown`], or [`Self::into_inner`] is awaited before. With the
+/// `check-unflushed` feature, dropping a writer with buffered data panics in
+/// debug builds.
+///
+/// This type is an async version of [`std::io::BufWriter`].
+pub struct BufWriter<W> {
+    inner: W,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> BufWriter<W> {
+    /// Creates a writer with the default capacity, which is currently 8 KiB.
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, inner)
+    }
+
+    /// Creates a writer with a buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self {
+            inner,
+            buf: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Flushes the buffered data and returns the underlying writer.
+    ///
+    /// The underlying writer itself is not flushed.
+    pub async fn into_inner(mut self) -> Result<W> {
+        self.flush_buf().await?;
+        Ok(self.into_parts().0)
+    }
+
+    async fn flush_buf(&mut self) -> Result<()> {
+        // Removes the written data after each write, so that nothing is
+        // written twice if this is cancelled.
+        while !self.buf.is_empty() {
+            match self.inner.write(&self.buf).await {
+                Ok(0) => {
+                    return Err(Error::new(
+                        ErrorKind::WriteZero,
+                        "failed to write the buffered data",
+                    ))
+                }
+                Ok(n) => {
+                    self.buf.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W> BufWriter<W> {
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    ///
+    /// Writing to the underlying writer directly goes before the buffered
+    /// data.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the buffered data that is not written yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Returns the capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Returns true if some data is buffered and not written yet.
+    pub fn has_unflushed_data(&self) -> bool {
+        !self.buf.is_empty()
+    }
+
+    /// Returns the underlying writer and the buffered data that is not
+    /// written yet, without flushing.
+    pub fn into_parts(self) -> (W, Vec<u8>) {
+        let mut this = ManuallyDrop::new(self);
+        let buf = mem::take(&mut this.buf);
+        // The writer is moved out once, and `this` is never dropped.
+        let inner = unsafe { ptr::read(&this.inner) };
+        (inner, buf)
+    }
+}
+
+impl<W: Write> Write for BufWriter<W> {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        async move {
+            if self.buf.len() + buf.len() > self.capacity() {
+                self.flush_buf().await?;
+            }
+            if buf.len() >= self.capacity() {
+                return self.inner.write(buf).await;
+            }
+            self.buf.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        async move {
+            let len: usize = bufs.iter().map(|b| b.len()).sum();
+            if self.buf.len() + len > self.capacity() {
+                self.flush_buf().await?;
+            }
+            if len >= self.capacity() {
+                if self.inner.is_write_vectored() {
+                    return self.inner.write_vectored(bufs).await;
+                }
+                // Coalesces as many buffers as possible.
+                let mut n = 0;
+                for buf in bufs {
+                    if n + buf.len() > self.capacity() {
+                        break;
+                    }
+                    self.buf.extend_from_slice(buf);
+                    n += buf.len();
+                }
+                if n > 0 {
+                    return Ok(n);
+                }
+                let buf = bufs.iter().find(|b| !b.is_empty()).map_or(&[][..], |b| b);
+                return self.inner.write(buf).await;
+            }
+            for buf in bufs {
+                self.buf.extend_from_slice(buf);
+            }
+            Ok(len)
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    #[cfg(unix)]
+    fn splice_sink(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        // The buffered data must be written first.
+        if self.buf.is_empty() {
+            self.inner.splice_sink()
+        } else {
+            None
+        }
+    }
+
+    type Flush<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        async move {
+            self.flush_buf().await?;
+            self.inner.flush().await
+        }
+    }
+
+    type Shutdown<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        async move {
+            self.flush_buf().await?;
+            self.inner.shutdown().await
+        }
+    }
+}
+
+impl<W> Drop for BufWriter<W> {
+    fn drop(&mut self) {
+        #[cfg(feature = "check-unflushed")]
+        debug_assert!(
+            self.buf.is_empty() || std::thread::panicking(),
+            "BufWriter is dropped with {} bytes of unflushed data",
+            self.buf.len()
+        );
+    }
+}
+
+impl<W: fmt::Debug> fmt::Debug for BufWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufWriter")
+            .field("writer", &self.inner)
+            .field(
+                "buffer",
+                &format_args!("{}/{}", self.buf.len(), self.capacity()),
+            )
+            .finish()
+    }
+}
+
+/// Adds buffering to both directions of a stream.
+///
+/// This combines a [`BufReader`] and a [`BufWriter`] over one stream, with
+/// independent capacities. Reads that need data from the stream flush the
+/// buffered writes first, so that a request is always sent before waiting
+/// for its response. Reads served from the read buffer don't flush.
+///
+/// Like [`BufWriter`], the buffered writes are lost if this is dropped
+/// without flushing.
+pub struct BufStream<T> {
+    inner: BufReader<FlushOnRead<T>>,
+}
+
+impl<T: Read + Write> BufStream<T> {
+    /// Creates a stream with the default capacities, which are currently 8 KiB
+    /// each.
+    pub fn new(inner: T) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, DEFAULT_CAPACITY, inner)
+    }
+
+    /// Creates a stream with a read buffer of `read_capacity` bytes and a
+    /// write buffer of `write_capacity` bytes.
+    pub fn with_capacity(read_capacity: usize, write_capacity: usize, inner: T) -> Self {
+        let writer = BufWriter::with_capacity(write_capacity, inner);
+        Self {
+            inner: BufReader::with_capacity(read_capacity, FlushOnRead(writer)),
+        }
+    }
+
+    /// Flushes the buffered writes and returns the underlying stream.
+    ///
+    /// The buffered reads are lost, and the underlying stream itself is not
+    /// flushed.
+    pub async fn into_inner(self) -> Result<T> {
+        self.inner.into_inner().0.into_inner().await
+    }
+}
+
+impl<T> BufStream<T> {
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &T {
+        self.inner.get_ref().0.get_ref()
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Reading from or writing to the underlying stream directly skips the
+    /// buffered data.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut().0.get_mut()
+    }
+
+    fn writer(&mut self) -> &mut BufWriter<T> {
+        &mut self.inner.get_mut().0
+    }
+}
+
+impl<T: Read + Write> Read for BufStream<T> {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        self.inner.read(buf)
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        self.inner.read_vectored(bufs)
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        self.inner.is_read_vectored()
+    }
+
+    fn initializer(&self) -> Initializer {
+        self.inner.initializer()
+    }
+}
+
+impl<T: Read + Write> BufRead for BufStream<T> {
+    type FillBuf<'a> = impl Future<Output = Result<&'a [u8]>> + 'a where Self: 'a;
+
+    fn fill_buf(&mut self) -> Self::FillBuf<'_> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+impl<T: Read + Write> Write for BufStream<T> {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        self.writer().write(buf)
+    }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        self.writer().write_vectored(bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    type Flush<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        self.writer().flush()
+    }
+
+    type Shutdown<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        self.writer().shutdown()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for BufStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reader = &self.inner;
+        let writer = &reader.get_ref().0;
+        f.debug_struct("BufStream")
+            .field("stream", writer.get_ref())
+            .field(
+                "read_buffer",
+                &format_args!("{}/{}", reader.buffer().len(), reader.capacity()),
+            )
+            .field(
+                "write_buffer",
+                &format_args!("{}/{}", writer.buffer().len(), writer.capacity()),
+            )
+            .finish()
+    }
+}
+
+/// Reads from the stream of a writer after flushing it.
+struct FlushOnRead<T>(BufWriter<T>);
+
+impl<T: Read + Write> FlushOnRead<T> {
+    async fn flush(&mut self) -> Result<()> {
+        if self.0.has_unflushed_data() {
+            self.0.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Read + Write> Read for FlushOnRead<T> {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        async move {
+            self.flush().await?;
+            self.0.get_mut().read(buf).await
+        }
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        // The future can't capture the lifetime of the buffers, so reborrows
+        // them for the lifetime of `bufs`.
+        let mut bufs: Vec<IoSliceMut<'a>> = bufs.iter_mut().map(|b| IoSliceMut::new(b)).collect();
+        async move {
+            self.flush().await?;
+            self.0.get_mut().read_vectored(&mut bufs).await
+        }
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        self.0.get_ref().is_read_vectored()
+    }
+
+    fn initializer(&self) -> Initializer {
+        self.0.get_ref().initializer()
+    }
+}
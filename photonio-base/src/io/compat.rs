@@ -0,0 +1,575 @@
+//! Compatibility with the traits of [`futures_io`].
+//!
+//! [`Compat`] wraps an object that implements the I/O traits of this crate to
+//! implement [`futures_io::AsyncRead`] and [`futures_io::AsyncWrite`], and
+//! [`FromFutures`] does the reverse. The adapters for the traits of Tokio need
+//! the `tokio-compat` feature.
+//!
+//! This module needs the `futures-io` feature.
+
+use std::{
+    fmt,
+    future::{poll_fn, Future},
+    io::{ErrorKind, IoSlice, IoSliceMut, Result},
+    mem,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use super::{Read, Write};
+
+/// The default capacity of the buffers of [`Compat`].
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+type OpFuture<T> = Pin<Box<dyn Future<Output = Done<T>> + Send>>;
+
+/// Adapts an object to [`futures_io::AsyncRead`] and
+/// [`futures_io::AsyncWrite`].
+///
+/// The futures of this crate borrow their buffers until they complete, which
+/// doesn't fit the poll-based traits, since the caller can pass another buffer
+/// to each poll, or stop polling at all. So this type moves the object into a
+/// future that owns an intermediate buffer, and copies the data between that
+/// buffer and the buffers of the caller:
+///
+/// - Reads fill the buffer, and then serve the reads of the caller from it.
+/// - Writes copy the data into the buffer and report it as written at once. The
+///   write completes in the background, and its error, if any, is returned by
+///   the next operation. Use [`AsyncWrite::poll_flush`] to wait for the writes.
+///
+/// Each operation costs a copy of the data, and a boxed future. The capacity
+/// of the buffers bounds the data of each operation, and can be set with
+/// [`Self::with_capacity`].
+///
+/// Only one operation is submitted at a time, so a pending read delays writes
+/// until it completes, and vice versa.
+pub struct Compat<T> {
+    io: Option<T>,
+    op: Option<OpFuture<T>>,
+    capacity: usize,
+    // The data read but not yet returned is `rbuf[rpos..rlen]`.
+    rbuf: Vec<u8>,
+    rpos: usize,
+    rlen: usize,
+    wbuf: Vec<u8>,
+}
+
+impl<T> Compat<T> {
+    /// Creates a `Compat` with buffers of a default capacity, which is
+    /// currently 8 KiB.
+    pub fn new(io: T) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, io)
+    }
+
+    /// Creates a `Compat` with buffers of `capacity` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn with_capacity(capacity: usize, io: T) -> Self {
+        assert!(capacity > 0, "the capacity of Compat must not be 0");
+        Self {
+            io: Some(io),
+            op: None,
+            capacity,
+            rbuf: Vec::new(),
+            rpos: 0,
+            rlen: 0,
+            wbuf: Vec::new(),
+        }
+    }
+
+    /// Returns the capacity of the buffers.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Waits for the pending operation, if any, and returns the underlying
+    /// object.
+    ///
+    /// Returns the error of the pending operation instead. The data that is
+    /// read but not yet returned is discarded.
+    pub async fn into_inner(mut self) -> Result<T> {
+        poll_fn(|cx| self.poll_op(cx)).await?;
+        Ok(self.io.take().unwrap())
+    }
+
+    /// Polls the pending operation, if any.
+    ///
+    /// Returns the kind of the completed operation, or `None` if there is no
+    /// pending operation.
+    fn poll_op(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<Kind>>> {
+        let op = match &mut self.op {
+            Some(op) => op,
+            None => return Poll::Ready(Ok(None)),
+        };
+        let done = ready!(op.as_mut().poll(cx));
+        self.op = None;
+        self.io = Some(done.io);
+        match done.kind {
+            Kind::Read => {
+                self.rbuf = done.buf;
+                // Leaves nothing to return if the read fails.
+                self.rpos = 0;
+                self.rlen = 0;
+                self.rlen = done.result?;
+            }
+            Kind::Write => {
+                self.wbuf = done.buf;
+                self.wbuf.clear();
+                done.result?;
+            }
+            Kind::Flush | Kind::Shutdown => {
+                done.result?;
+            }
+        }
+        Poll::Ready(Ok(Some(done.kind)))
+    }
+
+    /// Moves the object and `buf` into the future returned by `f`.
+    fn start<F>(&mut self, kind: Kind, buf: Vec<u8>, f: impl FnOnce(T, Vec<u8>) -> F)
+    where
+        F: Future<Output = (T, Vec<u8>, Result<usize>)> + Send + 'static,
+    {
+        let io = self.io.take().unwrap();
+        let op = f(io, buf);
+        self.op = Some(Box::pin(async move {
+            let (io, buf, result) = op.await;
+            Done {
+                io,
+                buf,
+                kind,
+                result,
+            }
+        }));
+    }
+}
+
+impl<T> Compat<T>
+where
+    T: Write + Send + 'static,
+    for<'a> T::Write<'a>: Send,
+    for<'a> T::Flush<'a>: Send,
+    for<'a> T::Shutdown<'a>: Send,
+{
+    /// Waits for the pending operation, and then runs the operation of `kind`
+    /// until it completes.
+    fn poll_write_op(&mut self, cx: &mut Context<'_>, kind: Kind) -> Poll<Result<()>> {
+        loop {
+            // A completed operation of `kind` is the one started here, since
+            // only this function starts them.
+            if ready!(self.poll_op(cx))? == Some(kind) {
+                return Poll::Ready(Ok(()));
+            }
+            match kind {
+                Kind::Flush => self.start(kind, Vec::new(), |mut io, buf| async move {
+                    let result = io.flush().await.map(|_| 0);
+                    (io, buf, result)
+                }),
+                _ => self.start(kind, Vec::new(), |mut io, buf| async move {
+                    let result = io.shutdown().await.map(|_| 0);
+                    (io, buf, result)
+                }),
+            }
+        }
+    }
+}
+
+impl<T> Compat<T>
+where
+    T: Read + Send + 'static,
+    for<'a> T::Read<'a>: Send,
+{
+    /// Returns the data read but not yet returned, reading more if there is
+    /// none.
+    ///
+    /// An empty slice means EOF.
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<Result<&[u8]>> {
+        loop {
+            if self.rpos < self.rlen {
+                return Poll::Ready(Ok(&self.rbuf[self.rpos..self.rlen]));
+            }
+            match ready!(self.poll_op(cx))? {
+                // A read that returns nothing is EOF.
+                Some(Kind::Read) if self.rlen == 0 => return Poll::Ready(Ok(&[])),
+                Some(Kind::Read) => continue,
+                _ => {}
+            }
+            let mut rbuf = mem::take(&mut self.rbuf);
+            rbuf.resize(self.capacity, 0);
+            self.start(Kind::Read, rbuf, |mut io, mut buf| async move {
+                let result = io.read(&mut buf).await;
+                (io, buf, result)
+            });
+        }
+    }
+
+    /// Marks `amt` bytes of the data from [`Self::poll_fill`] as returned.
+    fn consume(&mut self, amt: usize) {
+        self.rpos = (self.rpos + amt).min(self.rlen);
+    }
+}
+
+impl<T> AsyncRead for Compat<T>
+where
+    T: Read + Send + 'static,
+    for<'a> T::Read<'a>: Send,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let this = self.get_mut();
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let data = ready!(this.poll_fill(cx))?;
+        let n = data.len().min(buf.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        this.consume(n);
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<T> AsyncWrite for Compat<T>
+where
+    T: Write + Send + 'static,
+    for<'a> T::Write<'a>: Send,
+    for<'a> T::Flush<'a>: Send,
+    for<'a> T::Shutdown<'a>: Send,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        let this = self.get_mut();
+        // Waits for the previous write, and returns its error if it fails.
+        ready!(this.poll_op(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let n = buf.len().min(this.capacity);
+        let mut wbuf = mem::take(&mut this.wbuf);
+        wbuf.extend_from_slice(&buf[..n]);
+        this.start(Kind::Write, wbuf, |mut io, buf| async move {
+            let mut written = 0;
+            while written < buf.len() {
+                match io.write(&buf[written..]).await {
+                    Ok(0) => return (io, buf, Err(ErrorKind::WriteZero.into())),
+                    Ok(n) => written += n,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return (io, buf, Err(e)),
+                }
+            }
+            (io, buf, Ok(written))
+        });
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.get_mut().poll_write_op(cx, Kind::Flush)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.get_mut().poll_write_op(cx, Kind::Shutdown)
+    }
+}
+
+// The object is never pinned, since it is moved into the boxed futures.
+impl<T> Unpin for Compat<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for Compat<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Compat")
+            .field("io", &self.io)
+            .field("capacity", &self.capacity)
+            .field("busy", &self.op.is_some())
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Read,
+    Write,
+    Flush,
+    Shutdown,
+}
+
+struct Done<T> {
+    io: T,
+    buf: Vec<u8>,
+    kind: Kind,
+    result: Result<usize>,
+}
+
+/// Adapts a [`futures_io::AsyncRead`] or [`futures_io::AsyncWrite`] to the
+/// I/O traits of this crate.
+///
+/// The futures poll the underlying object until the operation completes, so
+/// they don't need an intermediate buffer.
+#[derive(Debug)]
+pub struct FromFutures<T>(T);
+
+impl<T> FromFutures<T> {
+    /// Wraps `io`.
+    pub fn new(io: T) -> Self {
+        Self(io)
+    }
+
+    /// Gets a reference to the underlying object.
+    pub fn get_ref(&self) -> &T {
+        &self.0
+    }
+
+    /// Gets a mutable reference to the underlying object.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+
+    /// Unwraps this `FromFutures`, returning the underlying object.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: AsyncRead + Unpin> Read for FromFutures<T> {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        poll_fn(move |cx| Pin::new(&mut self.0).poll_read(cx, buf))
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        // The future can't capture the lifetime of the buffers, so reborrows
+        // them for the lifetime of `bufs`.
+        let mut bufs: Vec<IoSliceMut<'a>> = bufs.iter_mut().map(|b| IoSliceMut::new(b)).collect();
+        poll_fn(move |cx| Pin::new(&mut self.0).poll_read_vectored(cx, &mut bufs))
+    }
+}
+
+impl<T: AsyncWrite + Unpin> Write for FromFutures<T> {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        poll_fn(move |cx| Pin::new(&mut self.0).poll_write(cx, buf))
+    }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        poll_fn(move |cx| Pin::new(&mut self.0).poll_write_vectored(cx, bufs))
+    }
+
+    type Flush<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        poll_fn(move |cx| Pin::new(&mut self.0).poll_flush(cx))
+    }
+
+    type Shutdown<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        poll_fn(move |cx| Pin::new(&mut self.0).poll_close(cx))
+    }
+}
+
+#[cfg(feature = "tokio-compat")]
+pub use self::tokio::{FromTokio, TokioCompat};
+
+#[cfg(feature = "tokio-compat")]
+mod tokio {
+    use std::{
+        future::{poll_fn, Future},
+        io::{IoSlice, IoSliceMut, Result},
+        pin::Pin,
+        task::{ready, Context, Poll},
+    };
+
+    use ::tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+    use super::Compat;
+    use crate::io::{Read, Write};
+
+    /// Adapts an object to [`tokio::io::AsyncRead`] and
+    /// [`tokio::io::AsyncWrite`].
+    ///
+    /// This copies the data through intermediate buffers like [`Compat`], so
+    /// the same costs and limitations apply. The data is copied into the
+    /// unfilled part of the [`ReadBuf`] of the caller, which doesn't need to be
+    /// initialized.
+    ///
+    /// [`AsyncWrite::poll_shutdown`] waits for the pending writes, and then
+    /// calls [`Write::shutdown`], which shuts down the write half of sockets.
+    #[derive(Debug)]
+    pub struct TokioCompat<T>(Compat<T>);
+
+    impl<T> TokioCompat<T> {
+        /// Creates a `TokioCompat` with buffers of a default capacity, which
+        /// is currently 8 KiB.
+        pub fn new(io: T) -> Self {
+            Self(Compat::new(io))
+        }
+
+        /// Creates a `TokioCompat` with buffers of `capacity` bytes.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `capacity` is 0.
+        pub fn with_capacity(capacity: usize, io: T) -> Self {
+            Self(Compat::with_capacity(capacity, io))
+        }
+
+        /// Returns the capacity of the buffers.
+        pub fn capacity(&self) -> usize {
+            self.0.capacity()
+        }
+
+        /// Waits for the pending operation, if any, and returns the underlying
+        /// object.
+        ///
+        /// See also [`Compat::into_inner`].
+        pub async fn into_inner(self) -> Result<T> {
+            self.0.into_inner().await
+        }
+    }
+
+    impl<T> AsyncRead for TokioCompat<T>
+    where
+        T: Read + Send + 'static,
+        for<'a> T::Read<'a>: Send,
+    {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<Result<()>> {
+            let this = &mut self.get_mut().0;
+            if buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+            let data = ready!(this.poll_fill(cx))?;
+            let n = data.len().min(buf.remaining());
+            buf.put_slice(&data[..n]);
+            this.consume(n);
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl<T> AsyncWrite for TokioCompat<T>
+    where
+        T: Write + Send + 'static,
+        for<'a> T::Write<'a>: Send,
+        for<'a> T::Flush<'a>: Send,
+        for<'a> T::Shutdown<'a>: Send,
+    {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize>> {
+            futures_io::AsyncWrite::poll_write(Pin::new(&mut self.get_mut().0), cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+            futures_io::AsyncWrite::poll_flush(Pin::new(&mut self.get_mut().0), cx)
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+            futures_io::AsyncWrite::poll_close(Pin::new(&mut self.get_mut().0), cx)
+        }
+    }
+
+    /// Adapts a [`tokio::io::AsyncRead`] or [`tokio::io::AsyncWrite`] to the
+    /// I/O traits of this crate.
+    ///
+    /// Like [`super::FromFutures`], this doesn't need an intermediate buffer.
+    #[derive(Debug)]
+    pub struct FromTokio<T>(T);
+
+    impl<T> FromTokio<T> {
+        /// Wraps `io`.
+        pub fn new(io: T) -> Self {
+            Self(io)
+        }
+
+        /// Gets a reference to the underlying object.
+        pub fn get_ref(&self) -> &T {
+            &self.0
+        }
+
+        /// Gets a mutable reference to the underlying object.
+        pub fn get_mut(&mut self) -> &mut T {
+            &mut self.0
+        }
+
+        /// Unwraps this `FromTokio`, returning the underlying object.
+        pub fn into_inner(self) -> T {
+            self.0
+        }
+    }
+
+    impl<T: AsyncRead + Unpin> Read for FromTokio<T> {
+        type Read<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+        fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+            poll_fn(move |cx| poll_read(&mut self.0, cx, buf))
+        }
+
+        type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+        fn read_vectored<'a>(
+            &'a mut self,
+            bufs: &'a mut [IoSliceMut<'_>],
+        ) -> Self::ReadVectored<'a> {
+            // Tokio doesn't support vectored reads.
+            let buf = bufs
+                .iter_mut()
+                .find(|b| !b.is_empty())
+                .map_or(&mut [][..], |b| &mut **b);
+            poll_fn(move |cx| poll_read(&mut self.0, cx, buf))
+        }
+    }
+
+    fn poll_read<T: AsyncRead + Unpin>(
+        io: &mut T,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let mut buf = ReadBuf::new(buf);
+        ready!(Pin::new(io).poll_read(cx, &mut buf))?;
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+
+    impl<T: AsyncWrite + Unpin> Write for FromTokio<T> {
+        type Write<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+        fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+            poll_fn(move |cx| Pin::new(&mut self.0).poll_write(cx, buf))
+        }
+
+        type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+        fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+            poll_fn(move |cx| Pin::new(&mut self.0).poll_write_vectored(cx, bufs))
+        }
+
+        fn is_write_vectored(&self) -> bool {
+            self.0.is_write_vectored()
+        }
+
+        type Flush<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+        fn flush(&mut self) -> Self::Flush<'_> {
+            poll_fn(move |cx| Pin::new(&mut self.0).poll_flush(cx))
+        }
+
+        type Shutdown<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+        fn shutdown(&mut self) -> Self::Shutdown<'_> {
+            poll_fn(move |cx| Pin::new(&mut self.0).poll_shutdown(cx))
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Utilities to copy data between objects.
+
+use std::io::{Error, ErrorKind, Result};
+
+use super::{Read, Write, WriteExt};
+
+/// The default buffer size of [`copy`].
+const DEFAULT_BUF_SIZE: usize = 64 << 10;
+
+/// Copies all bytes from `reader` to `writer` until EOF.
+///
+/// Reads that fail with [`ErrorKind::Interrupted`] are retried. The data is
+/// copied through a buffer of 64 KiB, see [`copy_with_buf_size`] to change it.
+///
+/// Returns the number of bytes copied. If an error occurs, the error is
+/// returned instead, so the number of bytes copied before, including those
+/// of a partial write, is not reported.
+///
+/// See also [`std::io::copy`].
+pub async fn copy<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> Result<u64> {
+    copy_with_buf_size(reader, writer, DEFAULT_BUF_SIZE).await
+}
+
+/// Copies all bytes from `reader` to `writer` through a buffer of `buf_size`
+/// bytes.
+///
+/// Returns an error of [`ErrorKind::InvalidInput`] if `buf_size` is zero.
+///
+/// See also [`copy`].
+pub async fn copy_with_buf_size<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    buf_size: usize,
+) -> Result<u64> {
+    if buf_size == 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "the buffer size must be positive",
+        ));
+    }
+    let mut buf = vec![0; buf_size];
+    let mut copied = 0;
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) => return Ok(copied),
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n]).await?;
+        copied += n as u64;
+    }
+}
@@ -0,0 +1,235 @@
+//! An in-memory object with a position.
+
+use std::{
+    future::{ready, Ready},
+    io::{Error, ErrorKind, IoSlice, IoSliceMut, Result, SeekFrom},
+};
+
+use super::{BufRead, Initializer, Read, ReadAt, Seek, Write};
+
+/// Wraps an in-memory buffer to provide it with a position.
+///
+/// This is an async version of [`std::io::Cursor`]. The operations complete
+/// immediately, but they still return futures of the I/O traits, so that code
+/// written against the traits can be tested without a file or a socket.
+///
+/// Writes to a `Vec<u8>` grow it as needed, while writes to a fixed size
+/// buffer stop at the end of the buffer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cursor<T> {
+    inner: T,
+    pos: u64,
+}
+
+impl<T> Cursor<T> {
+    /// Creates a cursor at the start of `inner`.
+    pub fn new(inner: T) -> Self {
+        Self { inner, pos: 0 }
+    }
+
+    /// Returns the position of this cursor.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Sets the position of this cursor.
+    ///
+    /// The position can be beyond the end of the buffer, in which case reads
+    /// return EOF, and writes to a `Vec<u8>` fill the gap with zeros.
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    /// Gets a reference to the underlying buffer.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying buffer.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwraps this cursor, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsRef<[u8]>> Cursor<T> {
+    /// Returns the data from the position to the end of the buffer.
+    fn remaining(&self) -> &[u8] {
+        let data = self.inner.as_ref();
+        &data[clamp(self.pos, data.len())..]
+    }
+}
+
+impl<T: AsRef<[u8]>> Read for Cursor<T> {
+    type Read<'a> = Ready<Result<usize>> where Self: 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        let n = copy_to(buf, self.remaining());
+        self.pos += n as u64;
+        ready(Ok(n))
+    }
+
+    type ReadVectored<'a> = Ready<Result<usize>> where Self: 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        let mut total = 0;
+        for buf in bufs {
+            let n = copy_to(buf, self.remaining());
+            self.pos += n as u64;
+            total += n;
+        }
+        ready(Ok(total))
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The bytes are only copied into the buffer.
+        unsafe { Initializer::nop() }
+    }
+}
+
+impl<T: AsRef<[u8]>> ReadAt for Cursor<T> {
+    type ReadAt<'a> = Ready<Result<usize>> where Self: 'a;
+
+    fn read_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadAt<'a> {
+        let data = self.inner.as_ref();
+        ready(Ok(copy_to(buf, &data[clamp(pos, data.len())..])))
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The bytes are only copied into the buffer.
+        unsafe { Initializer::nop() }
+    }
+}
+
+impl<T: AsRef<[u8]>> BufRead for Cursor<T> {
+    type FillBuf<'a> = Ready<Result<&'a [u8]>> where Self: 'a;
+
+    fn fill_buf(&mut self) -> Self::FillBuf<'_> {
+        ready(Ok(self.remaining()))
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt.min(self.remaining().len()) as u64;
+    }
+}
+
+impl<T: AsRef<[u8]>> Seek for Cursor<T> {
+    type Seek<'a> = Ready<Result<u64>> where Self: 'a;
+
+    fn seek(&mut self, pos: SeekFrom) -> Self::Seek<'_> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return ready(Ok(pos));
+            }
+            SeekFrom::End(offset) => (self.inner.as_ref().len() as u64, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        ready(match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        })
+    }
+}
+
+macro_rules! impl_write {
+    ($ty:ty, $write:ident) => {
+        impl Write for Cursor<$ty> {
+            type Write<'a> = Ready<Result<usize>> where Self: 'a;
+
+            fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+                ready($write(&mut self.pos, &mut self.inner, buf))
+            }
+
+            type WriteVectored<'a> = Ready<Result<usize>> where Self: 'a;
+
+            fn write_vectored<'a>(
+                &'a mut self,
+                bufs: &'a [IoSlice<'_>],
+            ) -> Self::WriteVectored<'a> {
+                let mut total = 0;
+                for buf in bufs {
+                    match $write(&mut self.pos, &mut self.inner, buf) {
+                        Ok(n) if n < buf.len() => return ready(Ok(total + n)),
+                        Ok(n) => total += n,
+                        Err(e) if total == 0 => return ready(Err(e)),
+                        Err(_) => break,
+                    }
+                }
+                ready(Ok(total))
+            }
+
+            fn is_write_vectored(&self) -> bool {
+                true
+            }
+
+            type Flush<'a> = Ready<Result<()>> where Self: 'a;
+
+            fn flush(&mut self) -> Self::Flush<'_> {
+                ready(Ok(()))
+            }
+
+            type Shutdown<'a> = Ready<Result<()>> where Self: 'a;
+
+            fn shutdown(&mut self) -> Self::Shutdown<'_> {
+                ready(Ok(()))
+            }
+        }
+    };
+}
+
+impl_write!(Vec<u8>, vec_write);
+impl_write!(&mut Vec<u8>, vec_write);
+impl_write!(&mut [u8], slice_write);
+impl_write!(Box<[u8]>, slice_write);
+
+/// Copies as many bytes as possible from `src` to `dst`.
+fn copy_to(dst: &mut [u8], src: &[u8]) -> usize {
+    let n = dst.len().min(src.len());
+    dst[..n].copy_from_slice(&src[..n]);
+    n
+}
+
+/// Returns `pos` as an index into a buffer of `len` bytes.
+fn clamp(pos: u64, len: usize) -> usize {
+    pos.min(len as u64) as usize
+}
+
+/// Writes `buf` into `slice` at `pos`, stopping at the end of the slice.
+fn slice_write(pos: &mut u64, slice: &mut [u8], buf: &[u8]) -> Result<usize> {
+    let start = clamp(*pos, slice.len());
+    let n = copy_to(&mut slice[start..], buf);
+    *pos += n as u64;
+    Ok(n)
+}
+
+/// Writes `buf` into `vec` at `pos`, growing it as needed.
+fn vec_write(pos: &mut u64, vec: &mut Vec<u8>, buf: &[u8]) -> Result<usize> {
+    let start = usize::try_from(*pos).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "cursor position exceeds the maximum length of a vector",
+        )
+    })?;
+    if vec.len() < start {
+        vec.resize(start, 0);
+    }
+    let n = copy_to(&mut vec[start..], buf);
+    vec.extend_from_slice(&buf[n..]);
+    *pos = (start + buf.len()) as u64;
+    Ok(buf.len())
+}
@@ -0,0 +1,78 @@
+use std::{
+    error, fmt,
+    io::{Error, ErrorKind},
+};
+
+/// The error payload of an operation that stops before it transfers all
+/// bytes.
+///
+/// [`super::ReadExt::read_exact`] and [`super::ReadAtExt::read_exact_at`]
+/// return an error of [`ErrorKind::UnexpectedEof`] with this as its payload if
+/// they reach EOF, and the `write_all` methods of [`super::WriteExt`] and
+/// [`super::WriteAtExt`] return one of [`ErrorKind::WriteZero`] if the object
+/// stops accepting data. Other errors are returned as they are.
+///
+/// The payload can be extracted from the error with [`Error::get_ref`] and
+/// [`error::Error::downcast_ref`], or with [`Self::from_error`].
+#[derive(Debug)]
+pub struct IncompleteIo {
+    done: usize,
+    pos: Option<u64>,
+    is_read: bool,
+}
+
+impl IncompleteIo {
+    /// Returns the number of bytes transferred before the operation stops.
+    pub fn done(&self) -> usize {
+        self.done
+    }
+
+    /// Returns the position the operation stops at, if it is positional.
+    pub fn position(&self) -> Option<u64> {
+        self.pos
+    }
+
+    /// Returns the payload of `err` if it is an [`IncompleteIo`].
+    pub fn from_error(err: &Error) -> Option<&Self> {
+        err.get_ref().and_then(|e| e.downcast_ref())
+    }
+
+    /// Returns an error of [`ErrorKind::UnexpectedEof`] for a read that
+    /// reaches EOF after `done` bytes, at `pos` if it is positional.
+    pub fn eof(done: usize, pos: Option<u64>) -> Error {
+        let payload = Self {
+            done,
+            pos,
+            is_read: true,
+        };
+        Error::new(ErrorKind::UnexpectedEof, payload)
+    }
+
+    /// Returns an error of [`ErrorKind::WriteZero`] for a write that stops
+    /// accepting data after `done` bytes, at `pos` if it is positional.
+    pub fn write_zero(done: usize, pos: Option<u64>) -> Error {
+        let payload = Self {
+            done,
+            pos,
+            is_read: false,
+        };
+        Error::new(ErrorKind::WriteZero, payload)
+    }
+}
+
+impl fmt::Display for IncompleteIo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = if self.is_read { "fill" } else { "write" };
+        write!(
+            f,
+            "failed to {action} whole buffer after {} bytes",
+            self.done
+        )?;
+        if let Some(pos) = self.pos {
+            write!(f, " at position {pos}")?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for IncompleteIo {}
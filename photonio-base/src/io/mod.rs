@@ -1,12 +1,48 @@
 //! Primitives for asynchronous I/O.
 
-pub use std::io::{Error, Result, SeekFrom};
+pub use std::io::{Error, IoSlice, IoSliceMut, Result, SeekFrom};
+
+mod error;
+pub use error::IncompleteIo;
 
 mod read;
 pub use read::{Read, ReadAt, ReadAtExt, ReadExt};
 
+mod read_buf;
+pub use read_buf::{Initializer, ReadBuf, ReadBufFuture};
+
+mod aligned_buf;
+pub use aligned_buf::AlignedBuf;
+
+mod buf_read;
+pub use buf_read::{BufRead, BufReadExt, Lines};
+
+mod adapters;
+pub use adapters::{Chain, Take};
+
+mod buffered;
+pub use buffered::{BufReader, BufStream, BufWriter};
+
 mod seek;
 pub use seek::Seek;
 
+mod cursor;
+pub use cursor::Cursor;
+
 mod write;
 pub use write::{Write, WriteAt, WriteAtExt, WriteExt};
+
+mod boxed;
+pub use boxed::{BoxedReader, BoxedStream, BoxedWriter};
+
+#[cfg(feature = "futures-io")]
+pub mod compat;
+
+mod copy;
+pub use copy::{copy, copy_with_buf_size};
+
+mod split;
+pub use split::{split, ReadHalf, WriteHalf};
+
+mod util;
+pub use util::{empty, repeat, sink, Empty, Repeat, Sink};
@@ -1,10 +1,16 @@
 //! Primitives for asynchronous reads.
 
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
 use std::{
-    future::Future,
-    io::{ErrorKind, Result},
+    future::{ready, Future, Ready},
+    io::{Error, ErrorKind, IoSliceMut, Result},
+    str,
+    sync::Arc,
 };
 
+use super::{Chain, IncompleteIo, Initializer, ReadBuf, ReadBufFuture, Take};
+
 /// Reads some bytes from an object.
 pub trait Read {
     /// A future that resolves to the result of [`Self::read`].
@@ -16,6 +22,58 @@ pub trait Read {
     ///
     /// Returns the number of bytes read.
     fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a>;
+
+    /// A future that resolves to the result of [`Self::read_vectored`].
+    type ReadVectored<'a>: Future<Output = Result<usize>> + 'a
+    where
+        Self: 'a;
+
+    /// Reads some bytes from this object into `bufs`.
+    ///
+    /// Data is copied to fill each buffer in order. Implementations that don't
+    /// support vectored reads can read into the first non-empty buffer.
+    ///
+    /// Returns the number of bytes read.
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a>;
+
+    /// Returns true if [`Self::read_vectored`] reads into multiple buffers at
+    /// once.
+    ///
+    /// Callers can use this to decide whether it is worth passing multiple
+    /// buffers. The default implementation returns false.
+    fn is_read_vectored(&self) -> bool {
+        false
+    }
+
+    /// Returns whether [`Self::read`] needs the buffer it reads into to be
+    /// initialized.
+    ///
+    /// The default implementation returns [`Initializer::zeroing`].
+    fn initializer(&self) -> Initializer {
+        Initializer::zeroing()
+    }
+
+    /// Reads some bytes from this object into the unfilled part of `buf`.
+    ///
+    /// The unfilled part is zeroed before the read, unless
+    /// [`Self::initializer`] allows to read into uninitialized memory.
+    ///
+    /// Returns the number of bytes read, which are added to the filled part.
+    fn read_buf<'a>(&'a mut self, buf: &'a mut ReadBuf<'_>) -> ReadBufFuture<'a, Self::Read<'a>> {
+        let (unfilled, progress) = buf.prepare(self.initializer());
+        ReadBufFuture::new(self.read(unfilled), progress)
+    }
+
+    /// Returns the descriptor to read from in the kernel, and the position to
+    /// read at if it is tracked in user space.
+    ///
+    /// This allows copies to move data without going through user space, and
+    /// timeouts to be enforced by the kernel.
+    #[doc(hidden)]
+    #[cfg(unix)]
+    fn splice_source(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        None
+    }
 }
 
 /// Provides extension methods for [`Read`].
@@ -26,7 +84,63 @@ pub trait ReadExt {
         Self: 'a;
 
     /// Reads the exact number of bytes from this object to fill `buf`.
+    ///
+    /// Returns an error of [`ErrorKind::UnexpectedEof`] if EOF is reached
+    /// first, with an [`IncompleteIo`] payload that tells how many
+    /// bytes at the start of `buf` are read.
     fn read_exact<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::ReadExact<'a>;
+
+    /// A future that resolves to the result of [`Self::read_to_end`].
+    type ReadToEnd<'a>: Future<Output = Result<usize>> + 'a
+    where
+        Self: 'a;
+
+    /// Reads all bytes until EOF from this object, appending them to `buf`.
+    ///
+    /// The buffer grows as needed, starting with a small probe, so that small
+    /// objects don't allocate much. If an error occurs, the bytes read so
+    /// far are kept in `buf`.
+    ///
+    /// Returns the number of bytes read.
+    ///
+    /// See also [`std::io::Read::read_to_end`].
+    fn read_to_end<'a>(&'a mut self, buf: &'a mut Vec<u8>) -> Self::ReadToEnd<'a>;
+
+    /// A future that resolves to the result of [`Self::read_to_string`].
+    type ReadToString<'a>: Future<Output = Result<usize>> + 'a
+    where
+        Self: 'a;
+
+    /// Reads all bytes until EOF from this object, appending them to `buf`.
+    ///
+    /// Returns an error of [`ErrorKind::InvalidData`] if the bytes are not
+    /// valid UTF-8, in which case `buf` is left unchanged.
+    ///
+    /// Returns the number of bytes read.
+    ///
+    /// See also [`std::io::Read::read_to_string`].
+    fn read_to_string<'a>(&'a mut self, buf: &'a mut String) -> Self::ReadToString<'a>;
+
+    /// Creates an adapter that reads at most `limit` bytes from this object.
+    ///
+    /// The adapter returns EOF once `limit` bytes are read. Reads are clamped
+    /// to the remaining limit before they reach this object, so it never reads
+    /// more than `limit` bytes.
+    ///
+    /// See also [`std::io::Read::take`].
+    fn take(self, limit: u64) -> Take<Self>
+    where
+        Self: Sized;
+
+    /// Creates an adapter that reads from this object until EOF, and then
+    /// from `next`.
+    ///
+    /// A single read never spans both objects.
+    ///
+    /// See also [`std::io::Read::chain`].
+    fn chain<R: Read>(self, next: R) -> Chain<Self, R>
+    where
+        Self: Sized;
 }
 
 impl<T> ReadExt for T
@@ -35,12 +149,13 @@ where
 {
     type ReadExact<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
 
-    fn read_exact<'a>(&'a mut self, mut buf: &'a mut [u8]) -> Self::ReadExact<'a> {
+    fn read_exact<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::ReadExact<'a> {
         async move {
-            while !buf.is_empty() {
-                match self.read(buf).await {
-                    Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
-                    Ok(n) => buf = &mut buf[n..],
+            let mut done = 0;
+            while done < buf.len() {
+                match self.read(&mut buf[done..]).await {
+                    Ok(0) => return Err(IncompleteIo::eof(done, None)),
+                    Ok(n) => done += n,
                     Err(e) if e.kind() == ErrorKind::Interrupted => {}
                     Err(e) => return Err(e),
                 }
@@ -48,6 +163,149 @@ where
             Ok(())
         }
     }
+
+    type ReadToEnd<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_to_end<'a>(&'a mut self, buf: &'a mut Vec<u8>) -> Self::ReadToEnd<'a> {
+        read_to_end(self, buf)
+    }
+
+    type ReadToString<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_to_string<'a>(&'a mut self, buf: &'a mut String) -> Self::ReadToString<'a> {
+        async move {
+            let mut guard = Utf8Guard::new(buf);
+            let result = read_to_end(self, guard.bytes()).await;
+            guard.finish(result)
+        }
+    }
+
+    fn take(self, limit: u64) -> Take<Self> {
+        Take::new(self, limit)
+    }
+
+    fn chain<R: Read>(self, next: R) -> Chain<Self, R> {
+        Chain::new(self, next)
+    }
+}
+
+/// Reads from the slice, advancing it past the bytes read.
+impl Read for &[u8] {
+    type Read<'a> = Ready<Result<usize>> where Self: 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        let n = buf.len().min(self.len());
+        let (head, tail) = self.split_at(n);
+        buf[..n].copy_from_slice(head);
+        *self = tail;
+        ready(Ok(n))
+    }
+
+    type ReadVectored<'a> = Ready<Result<usize>> where Self: 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        let mut total = 0;
+        for buf in bufs {
+            let n = buf.len().min(self.len());
+            let (head, tail) = self.split_at(n);
+            buf[..n].copy_from_slice(head);
+            *self = tail;
+            total += n;
+        }
+        ready(Ok(total))
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The bytes are only copied into the buffer.
+        unsafe { Initializer::nop() }
+    }
+}
+
+/// The size of the first read of [`ReadExt::read_to_end`].
+const PROBE_LEN: usize = 32;
+
+/// Reads all bytes until EOF from `reader`, appending them to `buf`.
+async fn read_to_end<T: Read>(reader: &mut T, buf: &mut Vec<u8>) -> Result<usize> {
+    let start = buf.len();
+    let mut chunk = PROBE_LEN;
+    // The number of spare bytes that are initialized by previous reads.
+    let mut initialized = 0;
+    loop {
+        let len = buf.len();
+        // Fills the spare capacity first, which might be reserved by
+        // the caller for the expected size.
+        let spare = chunk.max(buf.capacity() - len);
+        let capacity = buf.capacity();
+        buf.reserve(spare);
+        if buf.capacity() != capacity {
+            initialized = 0;
+        }
+        let mut read_buf = ReadBuf::uninit(&mut buf.spare_capacity_mut()[..spare]);
+        unsafe { read_buf.assume_init(initialized.min(spare)) };
+        let n = match reader.read_buf(&mut read_buf).await {
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        initialized = read_buf.initialized().len() - n;
+        // The bytes read are initialized by the reader.
+        unsafe { buf.set_len(len + n) };
+        if n == 0 {
+            return Ok(len - start);
+        }
+        // Grows faster if the reads keep filling the buffer.
+        if n == spare {
+            chunk = spare.saturating_mul(2);
+        }
+    }
+}
+
+/// Appends bytes to a string, keeping it valid UTF-8.
+///
+/// The guard restores the original length if the appended bytes are invalid or
+/// if it is dropped before [`Self::finish`], like when a future is cancelled.
+pub(super) struct Utf8Guard<'a> {
+    buf: &'a mut Vec<u8>,
+    len: usize,
+}
+
+impl<'a> Utf8Guard<'a> {
+    pub(super) fn new(buf: &'a mut String) -> Self {
+        let len = buf.len();
+        Self {
+            buf: unsafe { buf.as_mut_vec() },
+            len,
+        }
+    }
+
+    /// Returns the bytes of the string to append to.
+    pub(super) fn bytes(&mut self) -> &mut Vec<u8> {
+        self.buf
+    }
+
+    /// Keeps the appended bytes if they are valid UTF-8, and returns `result`.
+    ///
+    /// Returns an error of [`ErrorKind::InvalidData`] otherwise.
+    pub(super) fn finish(mut self, result: Result<usize>) -> Result<usize> {
+        if str::from_utf8(&self.buf[self.len..]).is_err() {
+            return result.and(Err(Error::new(
+                ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )));
+        }
+        self.len = self.buf.len();
+        result
+    }
+}
+
+impl Drop for Utf8Guard<'_> {
+    fn drop(&mut self) {
+        self.buf.truncate(self.len);
+    }
 }
 
 /// Reads some bytes from an object at a given position.
@@ -61,6 +319,51 @@ pub trait ReadAt {
     ///
     /// Returns the number of bytes read.
     fn read_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadAt<'a>;
+
+    /// Returns whether [`Self::read_at`] needs the buffer it reads into to be
+    /// initialized.
+    ///
+    /// The default implementation returns [`Initializer::zeroing`].
+    fn initializer(&self) -> Initializer {
+        Initializer::zeroing()
+    }
+
+    /// Reads some bytes from this object at `pos` into the unfilled part of
+    /// `buf`.
+    ///
+    /// See also [`Read::read_buf`].
+    fn read_at_buf<'a>(
+        &'a self,
+        buf: &'a mut ReadBuf<'_>,
+        pos: u64,
+    ) -> ReadBufFuture<'a, Self::ReadAt<'a>> {
+        let (unfilled, progress) = buf.prepare(self.initializer());
+        ReadBufFuture::new(self.read_at(unfilled, pos), progress)
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for &T {
+    type ReadAt<'a> = T::ReadAt<'a> where Self: 'a;
+
+    fn read_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadAt<'a> {
+        (**self).read_at(buf, pos)
+    }
+
+    fn initializer(&self) -> Initializer {
+        (**self).initializer()
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for Arc<T> {
+    type ReadAt<'a> = T::ReadAt<'a> where Self: 'a;
+
+    fn read_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadAt<'a> {
+        (**self).read_at(buf, pos)
+    }
+
+    fn initializer(&self) -> Initializer {
+        (**self).initializer()
+    }
 }
 
 /// Provides extension methods for [`ReadAt`].
@@ -71,6 +374,10 @@ pub trait ReadAtExt {
         Self: 'a;
 
     /// Reads the exact number of bytes from this object at `pos` to fill `buf`.
+    ///
+    /// Returns an error of [`ErrorKind::UnexpectedEof`] if EOF is reached
+    /// first, with an [`IncompleteIo`] payload that tells how many
+    /// bytes are read and the position of EOF.
     fn read_exact_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadExactAt<'a>;
 }
 
@@ -80,15 +387,14 @@ where
 {
     type ReadExactAt<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
 
-    fn read_exact_at<'a>(&'a self, mut buf: &'a mut [u8], mut pos: u64) -> Self::ReadExactAt<'a> {
+    fn read_exact_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadExactAt<'a> {
         async move {
-            while !buf.is_empty() {
-                match self.read_at(buf, pos).await {
-                    Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
-                    Ok(n) => {
-                        buf = &mut buf[n..];
-                        pos += n as u64;
-                    }
+            let mut done = 0;
+            while done < buf.len() {
+                let at = pos + done as u64;
+                match self.read_at(&mut buf[done..], at).await {
+                    Ok(0) => return Err(IncompleteIo::eof(done, Some(at))),
+                    Ok(n) => done += n,
                     Err(e) if e.kind() == ErrorKind::Interrupted => {}
                     Err(e) => return Err(e),
                 }
@@ -0,0 +1,288 @@
+//! Reads into uninitialized memory.
+
+use std::{
+    fmt,
+    future::Future,
+    io::Result,
+    mem::MaybeUninit,
+    pin::Pin,
+    ptr,
+    task::{ready, Context, Poll},
+};
+
+/// A buffer that is filled incrementally and may be partially uninitialized.
+///
+/// The buffer is divided into three regions: the filled part, which holds the
+/// bytes read so far, the initialized part that is not filled yet, and the
+/// uninitialized rest. Readers only see the parts after the filled one.
+///
+/// ```text
+/// [             capacity              ]
+/// [ filled |         unfilled         ]
+/// [    initialized    | uninitialized ]
+/// ```
+///
+/// See also [`super::Read::read_buf`].
+pub struct ReadBuf<'a> {
+    buf: &'a mut [MaybeUninit<u8>],
+    filled: usize,
+    initialized: usize,
+}
+
+impl<'a> ReadBuf<'a> {
+    /// Creates a buffer over initialized memory.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        let initialized = buf.len();
+        // Initialized bytes are valid uninitialized bytes.
+        let buf = unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) };
+        Self {
+            buf,
+            filled: 0,
+            initialized,
+        }
+    }
+
+    /// Creates a buffer over uninitialized memory.
+    pub fn uninit(buf: &'a mut [MaybeUninit<u8>]) -> Self {
+        Self {
+            buf,
+            filled: 0,
+            initialized: 0,
+        }
+    }
+
+    /// Returns the total size of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the number of bytes that can still be filled.
+    pub fn remaining(&self) -> usize {
+        self.capacity() - self.filled
+    }
+
+    /// Returns the filled part of the buffer.
+    pub fn filled(&self) -> &[u8] {
+        unsafe { slice_assume_init(&self.buf[..self.filled]) }
+    }
+
+    /// Returns the filled part of the buffer mutably.
+    pub fn filled_mut(&mut self) -> &mut [u8] {
+        unsafe { slice_assume_init_mut(&mut self.buf[..self.filled]) }
+    }
+
+    /// Returns the initialized part of the buffer, which includes the filled
+    /// part.
+    pub fn initialized(&self) -> &[u8] {
+        unsafe { slice_assume_init(&self.buf[..self.initialized]) }
+    }
+
+    /// Returns the unfilled part of the buffer, which may be uninitialized.
+    ///
+    /// # Safety
+    ///
+    /// The caller must not write uninitialized bytes to the returned slice,
+    /// since that would de-initialize bytes that are tracked as initialized.
+    pub unsafe fn unfilled_mut(&mut self) -> &mut [MaybeUninit<u8>] {
+        &mut self.buf[self.filled..]
+    }
+
+    /// Initializes the unfilled part of the buffer with zeros if it is not
+    /// initialized yet, and returns it.
+    pub fn initialize_unfilled(&mut self) -> &mut [u8] {
+        self.initialize_unfilled_to(self.remaining())
+    }
+
+    /// Initializes the first `n` unfilled bytes of the buffer with zeros if
+    /// they are not initialized yet, and returns them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than [`Self::remaining`].
+    pub fn initialize_unfilled_to(&mut self, n: usize) -> &mut [u8] {
+        assert!(n <= self.remaining(), "n overflows the remaining buffer");
+        let end = self.filled + n;
+        if self.initialized < end {
+            let uninit = &mut self.buf[self.initialized..end];
+            unsafe { ptr::write_bytes(uninit.as_mut_ptr(), 0, uninit.len()) };
+            self.initialized = end;
+        }
+        unsafe { slice_assume_init_mut(&mut self.buf[self.filled..end]) }
+    }
+
+    /// Clears the filled part of the buffer.
+    ///
+    /// The initialized part is kept, so that it is not initialized again.
+    pub fn clear(&mut self) {
+        self.filled = 0;
+    }
+
+    /// Sets the size of the filled part of the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than the size of the initialized part.
+    pub fn set_filled(&mut self, n: usize) {
+        assert!(n <= self.initialized, "the filled part must be initialized");
+        self.filled = n;
+    }
+
+    /// Advances the filled part of the buffer by `n` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the filled part would be larger than the initialized part.
+    pub fn advance(&mut self, n: usize) {
+        let filled = self.filled.checked_add(n).expect("filled overflow");
+        self.set_filled(filled);
+    }
+
+    /// Asserts that the first `n` unfilled bytes of the buffer are
+    /// initialized.
+    ///
+    /// This does nothing for bytes that are already tracked as initialized.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the first `n` unfilled bytes are
+    /// initialized.
+    pub unsafe fn assume_init(&mut self, n: usize) {
+        self.initialized = self.initialized.max(self.filled + n);
+    }
+
+    /// Appends `data` to the filled part of the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is larger than [`Self::remaining`].
+    pub fn put_slice(&mut self, data: &[u8]) {
+        assert!(
+            data.len() <= self.remaining(),
+            "data overflows the remaining buffer"
+        );
+        let end = self.filled + data.len();
+        let dst = &mut self.buf[self.filled..end];
+        unsafe { ptr::copy_nonoverlapping(data.as_ptr(), dst.as_mut_ptr().cast(), data.len()) };
+        self.initialized = self.initialized.max(end);
+        self.filled = end;
+    }
+
+    /// Returns the parts of the buffer for a read that writes to the unfilled
+    /// part, initializing it first unless `initializer` allows otherwise.
+    pub(super) fn prepare(&mut self, initializer: Initializer) -> (&mut [u8], Progress<'_>) {
+        if initializer.should_initialize() {
+            self.initialize_unfilled();
+        }
+        let unfilled = &mut self.buf[self.filled..];
+        // The reader promises not to read the bytes if they are uninitialized.
+        let unfilled = unsafe { slice_assume_init_mut(unfilled) };
+        let progress = Progress {
+            len: unfilled.len(),
+            filled: &mut self.filled,
+            initialized: &mut self.initialized,
+        };
+        (unfilled, progress)
+    }
+}
+
+impl fmt::Debug for ReadBuf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadBuf")
+            .field("filled", &self.filled)
+            .field("initialized", &self.initialized)
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+unsafe fn slice_assume_init(buf: &[MaybeUninit<u8>]) -> &[u8] {
+    &*(buf as *const [MaybeUninit<u8>] as *const [u8])
+}
+
+unsafe fn slice_assume_init_mut(buf: &mut [MaybeUninit<u8>]) -> &mut [u8] {
+    &mut *(buf as *mut [MaybeUninit<u8>] as *mut [u8])
+}
+
+/// Describes whether a reader needs the buffers it reads into to be
+/// initialized.
+///
+/// Reading into a buffer that is never read from, like one that is only
+/// passed to the kernel, doesn't need to initialize it first. Readers opt
+/// into this with [`Self::nop`].
+///
+/// See also [`super::Read::initializer`].
+#[derive(Clone, Copy, Debug)]
+pub struct Initializer(bool);
+
+impl Initializer {
+    /// Returns an initializer that zeroes buffers before reads.
+    pub fn zeroing() -> Self {
+        Self(true)
+    }
+
+    /// Returns an initializer that leaves buffers uninitialized.
+    ///
+    /// # Safety
+    ///
+    /// The reader must never read from the buffers it reads into, and must
+    /// initialize the bytes it reports as read.
+    pub unsafe fn nop() -> Self {
+        Self(false)
+    }
+
+    /// Returns true if buffers must be initialized before reads.
+    pub fn should_initialize(&self) -> bool {
+        self.0
+    }
+}
+
+/// The filled and initialized sizes of a [`ReadBuf`] that is being read into.
+pub(super) struct Progress<'a> {
+    len: usize,
+    filled: &'a mut usize,
+    initialized: &'a mut usize,
+}
+
+impl Progress<'_> {
+    fn update(&mut self, n: usize) {
+        assert!(
+            n <= self.len,
+            "the reader reports more bytes than requested"
+        );
+        *self.filled += n;
+        *self.initialized = (*self.initialized).max(*self.filled);
+    }
+}
+
+/// A future that reads into a [`ReadBuf`].
+///
+/// This is returned by [`super::Read::read_buf`] and
+/// [`super::ReadAt::read_at_buf`].
+pub struct ReadBufFuture<'a, F> {
+    read: F,
+    progress: Progress<'a>,
+}
+
+impl<'a, F> ReadBufFuture<'a, F> {
+    pub(super) fn new(read: F, progress: Progress<'a>) -> Self {
+        Self { read, progress }
+    }
+}
+
+impl<F: Future<Output = Result<usize>>> Future for ReadBufFuture<'_, F> {
+    type Output = Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        let read = unsafe { Pin::new_unchecked(&mut this.read) };
+        let n = ready!(read.poll(cx))?;
+        this.progress.update(n);
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<F> fmt::Debug for ReadBufFuture<'_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadBufFuture").finish_non_exhaustive()
+    }
+}
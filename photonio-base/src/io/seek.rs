@@ -8,10 +8,19 @@ use std::{
 /// Seeks to a position in an object.
 pub trait Seek {
     /// A future that resolves to the result of [`Self::seek`].
-    type Seek: Future<Output = Result<u64>>;
+    type Seek<'a>: Future<Output = Result<u64>> + 'a
+    where
+        Self: 'a;
 
     /// Seeks to a given position in this object.
     ///
     /// Returns the new position from the start of this object.
-    fn seek(&mut self, pos: SeekFrom) -> Self::Seek;
+    fn seek(&mut self, pos: SeekFrom) -> Self::Seek<'_>;
+
+    /// Returns the current position from the start of this object.
+    ///
+    /// This is equivalent to `self.seek(SeekFrom::Current(0))`.
+    fn stream_position(&mut self) -> Self::Seek<'_> {
+        self.seek(SeekFrom::Current(0))
+    }
 }
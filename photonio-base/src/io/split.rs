@@ -0,0 +1,190 @@
+//! Splitting of objects into read and write halves.
+
+use std::{
+    cell::UnsafeCell,
+    fmt,
+    future::{poll_fn, Future},
+    io::{IoSlice, IoSliceMut, Result},
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex},
+    task::{Poll, Waker},
+};
+
+use super::{Read, Write};
+
+/// Splits `stream` into a read half and a write half.
+///
+/// The halves can be moved to different tasks. They share the stream through
+/// a lock that is held for the whole duration of each operation, so a pending
+/// read blocks writes until it completes, and vice versa. This is fine for
+/// request-response protocols, but full-duplex protocols should use the
+/// lock-free halves of the stream type if it has them, like
+/// `TcpStream::into_split`.
+///
+/// Use [`ReadHalf::unsplit`] to get the stream back.
+pub fn split<T: Read + Write>(stream: T) -> (ReadHalf<T>, WriteHalf<T>) {
+    let inner = Arc::new(Inner {
+        stream: UnsafeCell::new(stream),
+        state: Mutex::new(State {
+            locked: false,
+            waiters: Vec::new(),
+        }),
+    });
+    (
+        ReadHalf {
+            inner: inner.clone(),
+        },
+        WriteHalf { inner },
+    )
+}
+
+/// The read half of a stream, created by [`split`].
+pub struct ReadHalf<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// The write half of a stream, created by [`split`].
+pub struct WriteHalf<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> ReadHalf<T> {
+    /// Returns true if this half and `other` come from the same stream.
+    pub fn is_pair_of(&self, other: &WriteHalf<T>) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// Reunites this half with `other` to get the stream back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the halves don't come from the same stream.
+    pub fn unsplit(self, other: WriteHalf<T>) -> T {
+        assert!(
+            self.is_pair_of(&other),
+            "unsplit halves that don't come from the same stream"
+        );
+        drop(other);
+        // No guard is alive, since both halves are owned here.
+        match Arc::try_unwrap(self.inner) {
+            Ok(inner) => inner.stream.into_inner(),
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+impl<T: Read> Read for ReadHalf<T> {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        async move { self.inner.lock().await.read(buf).await }
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        // The future can't capture the lifetime of the buffers, so reborrows
+        // them for the lifetime of `bufs`.
+        let mut bufs: Vec<IoSliceMut<'a>> = bufs.iter_mut().map(|b| IoSliceMut::new(b)).collect();
+        async move { self.inner.lock().await.read_vectored(&mut bufs).await }
+    }
+}
+
+impl<T: Write> Write for WriteHalf<T> {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        async move { self.inner.lock().await.write(buf).await }
+    }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        async move { self.inner.lock().await.write_vectored(bufs).await }
+    }
+
+    type Flush<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        async move { self.inner.lock().await.flush().await }
+    }
+
+    type Shutdown<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        async move { self.inner.lock().await.shutdown().await }
+    }
+}
+
+impl<T> fmt::Debug for ReadHalf<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadHalf").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Debug for WriteHalf<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteHalf").finish_non_exhaustive()
+    }
+}
+
+struct Inner<T> {
+    stream: UnsafeCell<T>,
+    state: Mutex<State>,
+}
+
+struct State {
+    locked: bool,
+    waiters: Vec<Waker>,
+}
+
+// The stream is only accessed through a guard.
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+impl<T> Inner<T> {
+    /// Waits until the stream is not used by the other half.
+    async fn lock(&self) -> Guard<'_, T> {
+        poll_fn(|cx| {
+            let mut state = self.state.lock().unwrap();
+            if state.locked {
+                if !state.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                    state.waiters.push(cx.waker().clone());
+                }
+                return Poll::Pending;
+            }
+            state.locked = true;
+            Poll::Ready(())
+        })
+        .await;
+        Guard { inner: self }
+    }
+}
+
+struct Guard<'a, T> {
+    inner: &'a Inner<T>,
+}
+
+impl<T> Deref for Guard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.inner.stream.get() }
+    }
+}
+
+impl<T> DerefMut for Guard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.inner.stream.get() }
+    }
+}
+
+impl<T> Drop for Guard<'_, T> {
+    fn drop(&mut self) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.locked = false;
+        for waker in state.waiters.drain(..) {
+            waker.wake();
+        }
+    }
+}
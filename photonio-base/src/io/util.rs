@@ -0,0 +1,179 @@
+//! Readers and writers that don't have an underlying object.
+
+use std::{
+    future::{ready, Ready},
+    io::{IoSlice, IoSliceMut, Result},
+};
+
+use super::{BufRead, Initializer, Read, ReadAt, Write, WriteAt};
+
+/// A reader that is always at EOF.
+///
+/// This is created by [`empty`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Empty;
+
+/// Creates a reader that is always at EOF.
+///
+/// See also [`std::io::empty`].
+pub fn empty() -> Empty {
+    Empty
+}
+
+impl Read for Empty {
+    type Read<'a> = Ready<Result<usize>>;
+
+    fn read<'a>(&'a mut self, _: &'a mut [u8]) -> Self::Read<'a> {
+        ready(Ok(0))
+    }
+
+    type ReadVectored<'a> = Ready<Result<usize>>;
+
+    fn read_vectored<'a>(&'a mut self, _: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        ready(Ok(0))
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The buffer is never touched.
+        unsafe { Initializer::nop() }
+    }
+}
+
+impl ReadAt for Empty {
+    type ReadAt<'a> = Ready<Result<usize>>;
+
+    fn read_at<'a>(&'a self, _: &'a mut [u8], _: u64) -> Self::ReadAt<'a> {
+        ready(Ok(0))
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The buffer is never touched.
+        unsafe { Initializer::nop() }
+    }
+}
+
+impl BufRead for Empty {
+    type FillBuf<'a> = Ready<Result<&'a [u8]>>;
+
+    fn fill_buf(&mut self) -> Self::FillBuf<'_> {
+        ready(Ok(&[]))
+    }
+
+    fn consume(&mut self, _: usize) {}
+}
+
+/// A writer that discards all data.
+///
+/// This is created by [`sink`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sink;
+
+/// Creates a writer that discards all data, reporting that it is written.
+///
+/// See also [`std::io::sink`].
+pub fn sink() -> Sink {
+    Sink
+}
+
+impl Write for Sink {
+    type Write<'a> = Ready<Result<usize>>;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        ready(Ok(buf.len()))
+    }
+
+    type WriteVectored<'a> = Ready<Result<usize>>;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        ready(Ok(bufs.iter().map(|b| b.len()).sum()))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    type Flush<'a> = Ready<Result<()>>;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        ready(Ok(()))
+    }
+
+    type Shutdown<'a> = Ready<Result<()>>;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        ready(Ok(()))
+    }
+}
+
+impl WriteAt for Sink {
+    type WriteAt<'a> = Ready<Result<usize>>;
+
+    fn write_at<'a>(&'a self, buf: &'a [u8], _: u64) -> Self::WriteAt<'a> {
+        ready(Ok(buf.len()))
+    }
+}
+
+/// A reader that yields one byte over and over.
+///
+/// This is created by [`repeat`].
+#[derive(Clone, Copy, Debug)]
+pub struct Repeat {
+    byte: u8,
+}
+
+/// Creates a reader that yields `byte` over and over.
+///
+/// The reader never reaches EOF, so it is usually limited with
+/// [`super::ReadExt::take`].
+///
+/// See also [`std::io::repeat`].
+pub fn repeat(byte: u8) -> Repeat {
+    Repeat { byte }
+}
+
+impl Read for Repeat {
+    type Read<'a> = Ready<Result<usize>>;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        buf.fill(self.byte);
+        ready(Ok(buf.len()))
+    }
+
+    type ReadVectored<'a> = Ready<Result<usize>>;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        let mut total = 0;
+        for buf in bufs {
+            buf.fill(self.byte);
+            total += buf.len();
+        }
+        ready(Ok(total))
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The buffer is only written to.
+        unsafe { Initializer::nop() }
+    }
+}
+
+impl ReadAt for Repeat {
+    type ReadAt<'a> = Ready<Result<usize>>;
+
+    fn read_at<'a>(&'a self, buf: &'a mut [u8], _: u64) -> Self::ReadAt<'a> {
+        buf.fill(self.byte);
+        ready(Ok(buf.len()))
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The buffer is only written to.
+        unsafe { Initializer::nop() }
+    }
+}
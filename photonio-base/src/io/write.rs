@@ -1,10 +1,16 @@
 //! Primitives for asynchronous writes.
 
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
 use std::{
-    future::Future,
-    io::{ErrorKind, Result},
+    future::{ready, Future, Ready},
+    io::{ErrorKind, IoSlice, Result},
+    mem,
+    sync::Arc,
 };
 
+use super::IncompleteIo;
+
 /// Writes some bytes into an object.
 pub trait Write {
     /// A future that resolves to the result of [`Self::write`].
@@ -16,6 +22,62 @@ pub trait Write {
     ///
     /// Returns the number of bytes written.
     fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a>;
+
+    /// A future that resolves to the result of [`Self::write_vectored`].
+    type WriteVectored<'a>: Future<Output = Result<usize>> + 'a
+    where
+        Self: 'a;
+
+    /// Writes some bytes from `bufs` into this object.
+    ///
+    /// Data is copied from each buffer in order. Implementations that don't
+    /// support vectored writes can write from the first non-empty buffer.
+    ///
+    /// Returns the number of bytes written, which may be less than the total
+    /// length of `bufs`.
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a>;
+
+    /// Returns true if [`Self::write_vectored`] writes from multiple buffers
+    /// at once.
+    ///
+    /// Callers can use this to decide whether to pass multiple buffers or to
+    /// coalesce them first. The default implementation returns false.
+    fn is_write_vectored(&self) -> bool {
+        false
+    }
+
+    /// Returns the descriptor to write to in the kernel, and the position to
+    /// write at if it is tracked in user space.
+    ///
+    /// This allows copies to move data without going through user space, and
+    /// timeouts to be enforced by the kernel.
+    #[doc(hidden)]
+    #[cfg(unix)]
+    fn splice_sink(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        None
+    }
+
+    /// A future that resolves to the result of [`Self::flush`].
+    type Flush<'a>: Future<Output = Result<()>> + 'a
+    where
+        Self: 'a;
+
+    /// Flushes the data buffered by this object to its destination.
+    ///
+    /// Objects that don't buffer data return immediately.
+    fn flush(&mut self) -> Self::Flush<'_>;
+
+    /// A future that resolves to the result of [`Self::shutdown`].
+    type Shutdown<'a>: Future<Output = Result<()>> + 'a
+    where
+        Self: 'a;
+
+    /// Flushes this object and shuts down its write side.
+    ///
+    /// For a stream, the peer reads the end of the stream once it has read all
+    /// data written before. Objects without a write side to shut down, like
+    /// files, only flush.
+    fn shutdown(&mut self) -> Self::Shutdown<'_>;
 }
 
 /// Provides extension methods for [`Write`].
@@ -26,7 +88,31 @@ pub trait WriteExt {
         Self: 'a;
 
     /// Writes all bytes from `buf` into this object.
+    ///
+    /// Returns an error of [`ErrorKind::WriteZero`] if this object stops
+    /// accepting data, with an [`IncompleteIo`] payload that tells how
+    /// many bytes are written.
     fn write_all<'a>(&'a mut self, buf: &'a [u8]) -> Self::WriteAll<'a>;
+
+    /// A future that resolves to the result of [`Self::write_all_vectored`].
+    type WriteAllVectored<'a>: Future<Output = Result<()>> + 'a
+    where
+        Self: 'a;
+
+    /// Writes all bytes from `bufs` into this object.
+    ///
+    /// `bufs` is advanced in place as bytes are written, so its content is
+    /// unspecified after this function returns.
+    ///
+    /// If this object doesn't support vectored writes, as reported by
+    /// [`Write::is_write_vectored`], the buffers are coalesced into one to
+    /// avoid a write for each buffer.
+    ///
+    /// Errors are reported like [`Self::write_all`].
+    fn write_all_vectored<'a>(
+        &'a mut self,
+        bufs: &'a mut [IoSlice<'a>],
+    ) -> Self::WriteAllVectored<'a>;
 }
 
 impl<T> WriteExt for T
@@ -37,12 +123,36 @@ where
     where
         Self: 'a;
 
-    fn write_all<'a>(&'a mut self, mut buf: &'a [u8]) -> Self::WriteAll<'a> {
+    fn write_all<'a>(&'a mut self, buf: &'a [u8]) -> Self::WriteAll<'a> {
+        write_all(self, buf)
+    }
+
+    type WriteAllVectored<'a> = impl Future<Output = Result<()>> + 'a
+    where
+        Self: 'a;
+
+    fn write_all_vectored<'a>(
+        &'a mut self,
+        mut bufs: &'a mut [IoSlice<'a>],
+    ) -> Self::WriteAllVectored<'a> {
         async move {
-            while !buf.is_empty() {
-                match self.write(buf).await {
-                    Ok(0) => return Err(ErrorKind::WriteZero.into()),
-                    Ok(n) => buf = &buf[n..],
+            // Skips leading empty buffers.
+            IoSlice::advance_slices(&mut bufs, 0);
+            if !self.is_write_vectored() && bufs.len() > 1 {
+                let mut buf = Vec::with_capacity(bufs.iter().map(|b| b.len()).sum());
+                for b in bufs.iter() {
+                    buf.extend_from_slice(b);
+                }
+                return write_all(self, &buf).await;
+            }
+            let mut done = 0;
+            while !bufs.is_empty() {
+                match self.write_vectored(bufs).await {
+                    Ok(0) => return Err(IncompleteIo::write_zero(done, None)),
+                    Ok(n) => {
+                        IoSlice::advance_slices(&mut bufs, n);
+                        done += n;
+                    }
                     Err(e) if e.kind() == ErrorKind::Interrupted => {}
                     Err(e) => return Err(e),
                 }
@@ -52,6 +162,107 @@ where
     }
 }
 
+/// Writes into the slice, advancing it past the bytes written.
+///
+/// Writes are short once the slice is full, so [`WriteExt::write_all`] returns
+/// an error of [`ErrorKind::WriteZero`] if the data doesn't fit.
+impl Write for &mut [u8] {
+    type Write<'a> = Ready<Result<usize>> where Self: 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        ready(Ok(write_slice(self, buf)))
+    }
+
+    type WriteVectored<'a> = Ready<Result<usize>> where Self: 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        let mut total = 0;
+        for buf in bufs {
+            let n = write_slice(self, buf);
+            total += n;
+            if n < buf.len() {
+                break;
+            }
+        }
+        ready(Ok(total))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    type Flush<'a> = Ready<Result<()>> where Self: 'a;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        ready(Ok(()))
+    }
+
+    type Shutdown<'a> = Ready<Result<()>> where Self: 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        ready(Ok(()))
+    }
+}
+
+fn write_slice(slice: &mut &mut [u8], buf: &[u8]) -> usize {
+    let n = buf.len().min(slice.len());
+    let (head, tail) = mem::take(slice).split_at_mut(n);
+    head.copy_from_slice(&buf[..n]);
+    *slice = tail;
+    n
+}
+
+/// Appends to the vector, growing it as needed.
+impl Write for Vec<u8> {
+    type Write<'a> = Ready<Result<usize>>;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        self.extend_from_slice(buf);
+        ready(Ok(buf.len()))
+    }
+
+    type WriteVectored<'a> = Ready<Result<usize>>;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        let len = bufs.iter().map(|b| b.len()).sum();
+        self.reserve(len);
+        for buf in bufs {
+            self.extend_from_slice(buf);
+        }
+        ready(Ok(len))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    type Flush<'a> = Ready<Result<()>>;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        ready(Ok(()))
+    }
+
+    type Shutdown<'a> = Ready<Result<()>>;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        ready(Ok(()))
+    }
+}
+
+/// Writes all bytes from `buf` into `writer`.
+async fn write_all<T: Write>(writer: &mut T, buf: &[u8]) -> Result<()> {
+    let mut done = 0;
+    while done < buf.len() {
+        match writer.write(&buf[done..]).await {
+            Ok(0) => return Err(IncompleteIo::write_zero(done, None)),
+            Ok(n) => done += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
 /// Writes some bytes into an object at a given position.
 pub trait WriteAt {
     /// A future that resolves to the result of [`Self::write_at`].
@@ -65,6 +276,22 @@ pub trait WriteAt {
     fn write_at<'a>(&'a self, buf: &'a [u8], pos: u64) -> Self::WriteAt<'a>;
 }
 
+impl<T: WriteAt + ?Sized> WriteAt for &T {
+    type WriteAt<'a> = T::WriteAt<'a> where Self: 'a;
+
+    fn write_at<'a>(&'a self, buf: &'a [u8], pos: u64) -> Self::WriteAt<'a> {
+        (**self).write_at(buf, pos)
+    }
+}
+
+impl<T: WriteAt + ?Sized> WriteAt for Arc<T> {
+    type WriteAt<'a> = T::WriteAt<'a> where Self: 'a;
+
+    fn write_at<'a>(&'a self, buf: &'a [u8], pos: u64) -> Self::WriteAt<'a> {
+        (**self).write_at(buf, pos)
+    }
+}
+
 /// Provides extension methods for [`WriteAt`].
 pub trait WriteAtExt {
     /// A future that resolves to the result of [`Self::write_all_at`].
@@ -73,6 +300,10 @@ pub trait WriteAtExt {
         Self: 'a;
 
     /// Writes all bytes from `buf` into this object at `pos`.
+    ///
+    /// Returns an error of [`ErrorKind::WriteZero`] if this object stops
+    /// accepting data, with an [`IncompleteIo`] payload that tells how
+    /// many bytes are written and the position it stops at.
     fn write_all_at<'a>(&'a self, buf: &'a [u8], pos: u64) -> Self::WriteAllAt<'a>;
 }
 
@@ -84,15 +315,14 @@ where
     where
         Self: 'a;
 
-    fn write_all_at<'a>(&'a self, mut buf: &'a [u8], mut pos: u64) -> Self::WriteAllAt<'a> {
+    fn write_all_at<'a>(&'a self, buf: &'a [u8], pos: u64) -> Self::WriteAllAt<'a> {
         async move {
-            while !buf.is_empty() {
-                match self.write_at(buf, pos).await {
-                    Ok(0) => return Err(ErrorKind::WriteZero.into()),
-                    Ok(n) => {
-                        buf = &buf[n..];
-                        pos += n as u64;
-                    }
+            let mut done = 0;
+            while done < buf.len() {
+                let at = pos + done as u64;
+                match self.write_at(&buf[done..], at).await {
+                    Ok(0) => return Err(IncompleteIo::write_zero(done, Some(at))),
+                    Ok(n) => done += n,
                     Err(e) if e.kind() == ErrorKind::Interrupted => {}
                     Err(e) => return Err(e),
                 }
@@ -1,7 +1,7 @@
 //! The base of PhotonIO.
 
 #![warn(missing_docs, unreachable_pub)]
-#![feature(pin_macro, io_error_more, type_alias_impl_trait)]
+#![feature(pin_macro, io_error_more, io_slice_advance, type_alias_impl_trait)]
 
 pub mod io;
 pub mod net;
@@ -0,0 +1,35 @@
+use std::{io::Result, path::Path};
+
+use tokio::fs;
+
+#[derive(Debug)]
+pub struct DirBuilder(fs::DirBuilder);
+
+impl DirBuilder {
+    pub fn new() -> Self {
+        Self(fs::DirBuilder::new())
+    }
+
+    pub fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.0.recursive(recursive);
+        self
+    }
+
+    pub async fn create<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.0.create(path).await
+    }
+}
+
+impl Default for DirBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(unix)]
+impl std::os::unix::fs::DirBuilderExt for DirBuilder {
+    fn mode(&mut self, mode: u32) -> &mut Self {
+        self.0.mode(mode);
+        self
+    }
+}
@@ -2,32 +2,40 @@ use std::{future::Future, io::Result, path::Path};
 
 use tokio::{
     fs,
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncReadExt, AsyncWrite, AsyncWriteExt},
 };
 
-use super::Metadata;
-use crate::io::{Read, Write};
+use super::{temp::TempPath, Metadata, Permissions};
+use crate::io::{first_non_empty_mut, Initializer, IoSlice, IoSliceMut, Read, Write};
 
 #[derive(Debug)]
-pub struct File(fs::File);
+pub struct File(fs::File, Option<TempPath>);
 
 impl File {
     pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        fs::File::open(path).await.map(Self)
+        fs::File::open(path).await.map(Self::from)
     }
 
     pub async fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
-        fs::File::create(path).await.map(Self)
+        fs::File::create(path).await.map(Self::from)
     }
 
     pub async fn metadata(&self) -> Result<Metadata> {
         self.0.metadata().await.map(Metadata::from)
     }
 
+    pub async fn set_permissions(&self, perm: Permissions) -> Result<()> {
+        self.0.set_permissions(perm).await
+    }
+
     pub async fn set_len(&self, size: u64) -> Result<()> {
         self.0.set_len(size).await
     }
 
+    pub async fn try_clone(&self) -> Result<Self> {
+        self.0.try_clone().await.map(Self::from)
+    }
+
     pub async fn sync_all(&self) -> Result<()> {
         self.0.sync_all().await
     }
@@ -37,9 +45,19 @@ impl File {
     }
 }
 
+impl File {
+    pub(super) fn set_temp_path(&mut self, temp: TempPath) {
+        self.1 = Some(temp);
+    }
+
+    pub(super) fn take_temp_path(&mut self) -> Option<TempPath> {
+        self.1.take()
+    }
+}
+
 impl From<fs::File> for File {
     fn from(file: fs::File) -> Self {
-        Self(file)
+        Self(file, None)
     }
 }
 
@@ -49,6 +67,18 @@ impl Read for File {
     fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
         self.0.read(buf)
     }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        // Tokio doesn't support vectored reads.
+        self.0.read(first_non_empty_mut(bufs))
+    }
+
+    fn initializer(&self) -> Initializer {
+        // Tokio only writes to the buffer.
+        unsafe { Initializer::nop() }
+    }
 }
 
 impl Write for File {
@@ -57,22 +87,77 @@ impl Write for File {
     fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
         self.0.write(buf)
     }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        let bufs: &'a [IoSlice<'a>] = bufs;
+        self.0.write_vectored(bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        AsyncWrite::is_write_vectored(&self.0)
+    }
+
+    type Flush<'a> = impl Future<Output = Result<()>> + 'a;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        self.0.flush()
+    }
+
+    type Shutdown<'a> = impl Future<Output = Result<()>> + 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        self.0.shutdown()
+    }
 }
 
+#[cfg(unix)]
+pub use unix::FileExt;
+
 #[cfg(unix)]
 mod unix {
     use std::{
-        future::Future,
-        io::Result,
+        future::{ready, Future, Ready},
+        io::{self, Error, Result},
         mem::ManuallyDrop,
         os::{
             fd::{AsRawFd, FromRawFd, RawFd},
-            unix::fs::FileExt,
+            unix::fs::FileExt as _,
         },
     };
 
     use super::File;
-    use crate::io::{ReadAt, WriteAt};
+    use crate::io::{Initializer, IoSlice, IoSliceMut, Read, ReadAt, Write, WriteAt};
+
+    pub trait FileExt {
+        type SetOwner<'a>: Future<Output = Result<()>> + 'a
+        where
+            Self: 'a;
+
+        fn set_owner(&self, uid: Option<u32>, gid: Option<u32>) -> Self::SetOwner<'_>;
+    }
+
+    impl FileExt for File {
+        type SetOwner<'a> = impl Future<Output = Result<()>> + 'a;
+
+        fn set_owner(&self, uid: Option<u32>, gid: Option<u32>) -> Self::SetOwner<'_> {
+            async move {
+                // Owns a duplicate so that the descriptor outlives the blocking task.
+                let file = self.0.try_clone().await?.into_std().await;
+                let uid = uid.unwrap_or(u32::MAX);
+                let gid = gid.unwrap_or(u32::MAX);
+                tokio::task::spawn_blocking(move || {
+                    if unsafe { libc::fchown(file.as_raw_fd(), uid, gid) } == 0 {
+                        Ok(())
+                    } else {
+                        Err(Error::last_os_error())
+                    }
+                })
+                .await?
+            }
+        }
+    }
 
     impl AsRawFd for File {
         fn as_raw_fd(&self) -> RawFd {
@@ -82,7 +167,7 @@ mod unix {
 
     impl FromRawFd for File {
         unsafe fn from_raw_fd(fd: RawFd) -> Self {
-            Self(tokio::fs::File::from_raw_fd(fd))
+            Self(tokio::fs::File::from_raw_fd(fd), None)
         }
     }
 
@@ -94,6 +179,10 @@ mod unix {
             let file = unsafe { ManuallyDrop::new(std::fs::File::from_raw_fd(self.0.as_raw_fd())) };
             async move { file.read_at(buf, pos) }
         }
+
+        fn initializer(&self) -> Initializer {
+            unsafe { Initializer::nop() }
+        }
     }
 
     impl WriteAt for File {
@@ -102,7 +191,79 @@ mod unix {
         // FIXME: Make it asynchronous when Tokio supports positional writes.
         fn write_at<'a>(&'a self, buf: &'a [u8], pos: u64) -> Self::WriteAt<'a> {
             let file = unsafe { ManuallyDrop::new(std::fs::File::from_raw_fd(self.0.as_raw_fd())) };
-            async move { file.write_at(buf, pos) }
+            async move {
+                let flags = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETFL) };
+                if flags >= 0 && flags & libc::O_APPEND != 0 {
+                    return Err(Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "positional writes are not supported in append mode",
+                    ));
+                }
+                file.write_at(buf, pos)
+            }
+        }
+    }
+
+    impl Read for &File {
+        type Read<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+        // FIXME: Make it asynchronous when Tokio supports reads on shared files.
+        fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+            let file = unsafe { ManuallyDrop::new(std::fs::File::from_raw_fd(self.0.as_raw_fd())) };
+            async move { io::Read::read(&mut &*file, buf) }
+        }
+
+        type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+        fn read_vectored<'a>(
+            &'a mut self,
+            bufs: &'a mut [IoSliceMut<'_>],
+        ) -> Self::ReadVectored<'a> {
+            // Reads before the future, which can't capture the lifetime of the
+            // buffers.
+            let file = unsafe { ManuallyDrop::new(std::fs::File::from_raw_fd(self.0.as_raw_fd())) };
+            ready(io::Read::read_vectored(&mut &*file, bufs))
+        }
+
+        fn is_read_vectored(&self) -> bool {
+            true
+        }
+
+        fn initializer(&self) -> Initializer {
+            unsafe { Initializer::nop() }
+        }
+    }
+
+    impl Write for &File {
+        type Write<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+        // FIXME: Make it asynchronous when Tokio supports writes on shared files.
+        fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+            let file = unsafe { ManuallyDrop::new(std::fs::File::from_raw_fd(self.0.as_raw_fd())) };
+            async move { io::Write::write(&mut &*file, buf) }
+        }
+
+        type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+        fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+            let file = unsafe { ManuallyDrop::new(std::fs::File::from_raw_fd(self.0.as_raw_fd())) };
+            async move { io::Write::write_vectored(&mut &*file, bufs) }
+        }
+
+        fn is_write_vectored(&self) -> bool {
+            true
+        }
+
+        type Flush<'a> = Ready<Result<()>> where Self: 'a;
+
+        fn flush(&mut self) -> Self::Flush<'_> {
+            ready(Ok(()))
+        }
+
+        type Shutdown<'a> = Ready<Result<()>> where Self: 'a;
+
+        fn shutdown(&mut self) -> Self::Shutdown<'_> {
+            ready(Ok(()))
         }
     }
 }
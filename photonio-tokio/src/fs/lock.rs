@@ -0,0 +1,90 @@
+use std::{
+    io::{Error, Result},
+    mem,
+    os::unix::io::{AsRawFd, RawFd},
+    time::Duration,
+};
+
+use super::File;
+
+const MIN_BACKOFF: Duration = Duration::from_millis(1);
+const MAX_BACKOFF: Duration = Duration::from_millis(64);
+
+impl File {
+    pub async fn lock_exclusive(&self) -> Result<LockGuard<'_>> {
+        self.lock(libc::F_WRLCK).await
+    }
+
+    pub async fn lock_shared(&self) -> Result<LockGuard<'_>> {
+        self.lock(libc::F_RDLCK).await
+    }
+
+    pub fn try_lock_exclusive(&self) -> Result<Option<LockGuard<'_>>> {
+        self.try_lock(libc::F_WRLCK)
+    }
+
+    pub fn try_lock_shared(&self) -> Result<Option<LockGuard<'_>>> {
+        self.try_lock(libc::F_RDLCK)
+    }
+
+    pub fn unlock(&self) -> Result<()> {
+        set_lock(self.as_raw_fd(), libc::F_UNLCK).map(|_| ())
+    }
+
+    async fn lock(&self, kind: libc::c_int) -> Result<LockGuard<'_>> {
+        let mut backoff = MIN_BACKOFF;
+        loop {
+            if let Some(guard) = self.try_lock(kind)? {
+                return Ok(guard);
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    fn try_lock(&self, kind: libc::c_int) -> Result<Option<LockGuard<'_>>> {
+        if set_lock(self.as_raw_fd(), kind)? {
+            Ok(Some(LockGuard { file: self }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+fn set_lock(fd: RawFd, kind: libc::c_int) -> Result<bool> {
+    let mut lock = unsafe { mem::zeroed::<libc::flock>() };
+    lock.l_type = kind as _;
+    lock.l_whence = libc::SEEK_SET as _;
+    if unsafe { libc::fcntl(fd, libc::F_OFD_SETLK, &lock) } == 0 {
+        return Ok(true);
+    }
+    let err = Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EAGAIN | libc::EACCES) => Ok(false),
+        _ => Err(err),
+    }
+}
+
+#[derive(Debug)]
+#[must_use = "the lock is released immediately if the guard is unused"]
+pub struct LockGuard<'a> {
+    file: &'a File,
+}
+
+impl<'a> LockGuard<'a> {
+    pub fn file(&self) -> &'a File {
+        self.file
+    }
+
+    pub fn unlock(self) -> Result<()> {
+        let file = self.file;
+        mem::forget(self);
+        file.unlock()
+    }
+}
+
+impl Drop for LockGuard<'_> {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
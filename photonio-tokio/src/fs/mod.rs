@@ -1,18 +1,115 @@
-use std::{io::Result, path::Path};
+pub use std::fs::Permissions;
+use std::{
+    io::{ErrorKind, Result},
+    path::{Path, PathBuf},
+};
 
 mod open;
 pub use open::OpenOptions;
 
 mod file;
 pub use file::File;
+#[cfg(unix)]
+pub use file::FileExt;
+
+#[cfg(target_os = "linux")]
+mod lock;
+#[cfg(target_os = "linux")]
+pub use lock::LockGuard;
+
+mod temp;
+pub use temp::tempfile_in;
+
+#[cfg(unix)]
+mod times;
+#[cfg(unix)]
+pub use times::FileTimes;
+
+#[cfg(target_os = "linux")]
+mod statfs;
+#[cfg(target_os = "linux")]
+pub use statfs::{statfs, FsStats};
 
 mod metadata;
-pub use metadata::Metadata;
+pub use metadata::{FileType, Metadata};
+
+mod dir_builder;
+pub use dir_builder::DirBuilder;
+
+mod read_dir;
+pub use read_dir::{read_dir, DirEntry, ReadDir};
+
+pub async fn metadata<P: AsRef<Path>>(path: P) -> Result<Metadata> {
+    tokio::fs::metadata(path).await.map(Metadata::from)
+}
+
+pub async fn symlink_metadata<P: AsRef<Path>>(path: P) -> Result<Metadata> {
+    tokio::fs::symlink_metadata(path).await.map(Metadata::from)
+}
+
+pub async fn try_exists<P: AsRef<Path>>(path: P) -> Result<bool> {
+    exists(tokio::fs::metadata(path).await)
+}
+
+pub async fn symlink_exists<P: AsRef<Path>>(path: P) -> Result<bool> {
+    exists(tokio::fs::symlink_metadata(path).await)
+}
+
+fn exists(result: Result<std::fs::Metadata>) -> Result<bool> {
+    match result {
+        Ok(_) => Ok(true),
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn read<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
+    tokio::fs::read(path).await
+}
+
+pub async fn read_to_string<P: AsRef<Path>>(path: P) -> Result<String> {
+    tokio::fs::read_to_string(path).await
+}
+
+pub async fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<()> {
+    tokio::fs::write(path, contents).await
+}
 
 pub async fn rename<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<()> {
     tokio::fs::rename(from, to).await
 }
 
+pub async fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> Result<()> {
+    tokio::fs::hard_link(original, link).await
+}
+
+#[cfg(unix)]
+pub async fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> Result<()> {
+    tokio::fs::symlink(original, link).await
+}
+
+pub async fn read_link<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
+    tokio::fs::read_link(path).await
+}
+
+pub async fn canonicalize<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
+    tokio::fs::canonicalize(path).await
+}
+
+pub async fn set_permissions<P: AsRef<Path>>(path: P, perm: Permissions) -> Result<()> {
+    tokio::fs::set_permissions(path, perm).await
+}
+
+#[cfg(unix)]
+pub async fn set_file_times<P: AsRef<Path>>(path: P, times: FileTimes) -> Result<()> {
+    times::utimensat(path.as_ref(), times, 0).await
+}
+
+#[cfg(unix)]
+pub async fn set_symlink_file_times<P: AsRef<Path>>(path: P, times: FileTimes) -> Result<()> {
+    times::utimensat(path.as_ref(), times, libc::AT_SYMLINK_NOFOLLOW).await
+}
+
 pub async fn remove_file<P: AsRef<Path>>(path: P) -> Result<()> {
     tokio::fs::remove_file(path).await
 }
@@ -21,6 +118,18 @@ pub async fn create_dir<P: AsRef<Path>>(path: P) -> Result<()> {
     tokio::fs::create_dir(path).await
 }
 
+pub async fn create_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
+    tokio::fs::create_dir_all(path).await
+}
+
+pub async fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<u64> {
+    tokio::fs::copy(from, to).await
+}
+
 pub async fn remove_dir<P: AsRef<Path>>(path: P) -> Result<()> {
     tokio::fs::remove_dir(path).await
 }
+
+pub async fn remove_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
+    tokio::fs::remove_dir_all(path).await
+}
@@ -0,0 +1,53 @@
+use std::{
+    ffi::OsString,
+    io::Result,
+    path::{Path, PathBuf},
+};
+
+use tokio::fs;
+
+use super::{FileType, Metadata};
+
+pub async fn read_dir<P: AsRef<Path>>(path: P) -> Result<ReadDir> {
+    fs::read_dir(path).await.map(ReadDir)
+}
+
+#[derive(Debug)]
+pub struct ReadDir(fs::ReadDir);
+
+impl ReadDir {
+    // Tokio reads entries in its own batches.
+    pub fn set_batch_size(&mut self, _size: usize) {}
+
+    pub async fn next_entry(&mut self) -> Result<Option<DirEntry>> {
+        self.0.next_entry().await.map(|entry| entry.map(DirEntry))
+    }
+}
+
+#[derive(Debug)]
+pub struct DirEntry(fs::DirEntry);
+
+impl DirEntry {
+    pub fn file_name(&self) -> OsString {
+        self.0.file_name()
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.0.path()
+    }
+
+    pub async fn file_type(&self) -> Result<FileType> {
+        self.0.file_type().await.map(FileType::from)
+    }
+
+    pub async fn metadata(&self) -> Result<Metadata> {
+        self.0.metadata().await.map(Metadata::from)
+    }
+}
+
+#[cfg(unix)]
+impl std::os::unix::fs::DirEntryExt for DirEntry {
+    fn ino(&self) -> u64 {
+        self.0.ino()
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    ffi::CString,
+    fmt,
+    io::{Error, Result},
+    mem,
+    os::unix::{ffi::OsStrExt, io::AsRawFd},
+    path::Path,
+};
+
+use super::File;
+
+#[derive(Clone)]
+pub struct FsStats(libc::statfs64);
+
+impl FsStats {
+    pub fn total_bytes(&self) -> u64 {
+        self.0.f_blocks.saturating_mul(self.fragment_size())
+    }
+
+    pub fn free_bytes(&self) -> u64 {
+        self.0.f_bfree.saturating_mul(self.fragment_size())
+    }
+
+    pub fn available_bytes(&self) -> u64 {
+        self.0.f_bavail.saturating_mul(self.fragment_size())
+    }
+
+    pub fn total_inodes(&self) -> u64 {
+        self.0.f_files
+    }
+
+    pub fn free_inodes(&self) -> u64 {
+        self.0.f_ffree
+    }
+
+    pub fn block_size(&self) -> u64 {
+        self.0.f_bsize as u64
+    }
+
+    pub fn fs_type(&self) -> u64 {
+        self.0.f_type as u64
+    }
+
+    pub fn max_name_len(&self) -> u64 {
+        self.0.f_namelen as u64
+    }
+
+    fn fragment_size(&self) -> u64 {
+        match self.0.f_frsize {
+            0 => self.block_size(),
+            size => size as u64,
+        }
+    }
+}
+
+impl fmt::Debug for FsStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FsStats")
+            .field("fs_type", &format_args!("{:#x}", self.fs_type()))
+            .field("total_bytes", &self.total_bytes())
+            .field("available_bytes", &self.available_bytes())
+            .field("total_inodes", &self.total_inodes())
+            .field("free_inodes", &self.free_inodes())
+            .finish()
+    }
+}
+
+impl File {
+    pub async fn statfs(&self) -> Result<FsStats> {
+        let file = self.try_clone().await?;
+        tokio::task::spawn_blocking(move || {
+            let mut stat = unsafe { mem::zeroed::<libc::statfs64>() };
+            if unsafe { libc::fstatfs64(file.as_raw_fd(), &mut stat) } == 0 {
+                Ok(FsStats(stat))
+            } else {
+                Err(Error::last_os_error())
+            }
+        })
+        .await?
+    }
+}
+
+pub async fn statfs<P: AsRef<Path>>(path: P) -> Result<FsStats> {
+    let path = CString::new(path.as_ref().as_os_str().as_bytes())?;
+    tokio::task::spawn_blocking(move || {
+        let mut stat = unsafe { mem::zeroed::<libc::statfs64>() };
+        if unsafe { libc::statfs64(path.as_ptr(), &mut stat) } == 0 {
+            Ok(FsStats(stat))
+        } else {
+            Err(Error::last_os_error())
+        }
+    })
+    .await?
+}
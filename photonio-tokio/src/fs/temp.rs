@@ -0,0 +1,184 @@
+use std::{
+    collections::hash_map::RandomState,
+    ffi::{CString, OsString},
+    hash::{BuildHasher, Hasher},
+    io::{Error, ErrorKind, Result},
+    mem::{self, ManuallyDrop},
+    os::unix::{ffi::OsStringExt, io::AsRawFd},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use tokio::fs;
+
+use super::File;
+
+const MAX_ATTEMPTS: usize = 64;
+
+pub async fn tempfile_in<P: AsRef<Path>>(dir: P) -> Result<File> {
+    let dir = dir.as_ref();
+    let mut options = fs::OpenOptions::new();
+    options.read(true).write(true).mode(0o600);
+    #[cfg(target_os = "linux")]
+    match options
+        .clone()
+        .custom_flags(libc::O_TMPFILE)
+        .open(dir)
+        .await
+    {
+        Ok(file) => return Ok(File::from(file)),
+        Err(e) if matches!(e.raw_os_error(), Some(libc::EOPNOTSUPP | libc::EISDIR)) => {}
+        Err(e) => return Err(e),
+    }
+    options.create_new(true);
+    for _ in 0..MAX_ATTEMPTS {
+        let path = dir.join(temp_name());
+        match options.open(&path).await {
+            Ok(file) => {
+                let mut file = File::from(file);
+                file.set_temp_path(TempPath(path));
+                return Ok(file);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Err(too_many_attempts())
+}
+
+impl File {
+    pub async fn persist<P: AsRef<Path>>(&mut self, path: P, sync_dir: bool) -> Result<()> {
+        let path = path.as_ref();
+        match self.take_temp_path() {
+            Some(temp) => {
+                if let Err(e) = fs::hard_link(&temp.0, path).await {
+                    self.set_temp_path(temp);
+                    return Err(e);
+                }
+                fs::remove_file(temp.keep()).await?;
+            }
+            None => link_follow(self.proc_path(), path.to_path_buf()).await?,
+        }
+        if sync_dir {
+            sync_parent(path).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn persist_overwrite<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        sync_dir: bool,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        match self.take_temp_path() {
+            Some(temp) => {
+                if let Err(e) = fs::rename(&temp.0, path).await {
+                    self.set_temp_path(temp);
+                    return Err(e);
+                }
+                temp.keep();
+            }
+            None => {
+                let temp = self.link_temp(parent(path)).await?;
+                if let Err(e) = fs::rename(&temp, path).await {
+                    let _ = fs::remove_file(&temp).await;
+                    return Err(e);
+                }
+            }
+        }
+        if sync_dir {
+            sync_parent(path).await?;
+        }
+        Ok(())
+    }
+
+    async fn link_temp(&self, dir: &Path) -> Result<PathBuf> {
+        for _ in 0..MAX_ATTEMPTS {
+            let path = dir.join(temp_name());
+            match link_follow(self.proc_path(), path.clone()).await {
+                Ok(()) => return Ok(path),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Err(too_many_attempts())
+    }
+
+    fn proc_path(&self) -> PathBuf {
+        PathBuf::from(format!("/proc/self/fd/{}", self.as_raw_fd()))
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct TempPath(PathBuf);
+
+impl TempPath {
+    fn keep(self) -> PathBuf {
+        let mut this = ManuallyDrop::new(self);
+        mem::take(&mut this.0)
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let path = mem::take(&mut self.0);
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(move || std::fs::remove_file(path));
+            }
+            Err(_) => {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// Links `original` into `link`, following `original` if it is a symbolic
+/// link, which `std::fs::hard_link` doesn't do on Linux.
+async fn link_follow(original: PathBuf, link: PathBuf) -> Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let original = CString::new(original.into_os_string().into_vec())?;
+        let link = CString::new(link.into_os_string().into_vec())?;
+        let res = unsafe {
+            libc::linkat(
+                libc::AT_FDCWD,
+                original.as_ptr(),
+                libc::AT_FDCWD,
+                link.as_ptr(),
+                libc::AT_SYMLINK_FOLLOW,
+            )
+        };
+        if res == 0 {
+            Ok(())
+        } else {
+            Err(Error::last_os_error())
+        }
+    })
+    .await?
+}
+
+fn temp_name() -> OsString {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!(".tmp{:016x}", hasher.finish()).into()
+}
+
+fn too_many_attempts() -> Error {
+    Error::new(
+        ErrorKind::AlreadyExists,
+        "failed to find an unused name for a temporary file",
+    )
+}
+
+fn parent(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+async fn sync_parent(path: &Path) -> Result<()> {
+    fs::File::open(parent(path)).await?.sync_all().await
+}
@@ -0,0 +1,96 @@
+use std::{
+    ffi::CString,
+    io::{Error, ErrorKind, Result},
+    os::unix::{ffi::OsStrExt, io::AsRawFd},
+    path::Path,
+    time::SystemTime,
+};
+
+use super::File;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileTimes {
+    accessed: Option<SystemTime>,
+    modified: Option<SystemTime>,
+}
+
+impl FileTimes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_accessed(mut self, t: SystemTime) -> Self {
+        self.accessed = Some(t);
+        self
+    }
+
+    pub fn set_modified(mut self, t: SystemTime) -> Self {
+        self.modified = Some(t);
+        self
+    }
+
+    fn to_timespecs(self) -> Result<[libc::timespec; 2]> {
+        Ok([to_timespec(self.accessed)?, to_timespec(self.modified)?])
+    }
+}
+
+fn to_timespec(time: Option<SystemTime>) -> Result<libc::timespec> {
+    let out_of_range = || Error::new(ErrorKind::InvalidInput, "the time is out of range");
+    let (sec, nsec) = match time {
+        None => (0, libc::UTIME_OMIT),
+        Some(time) => match time.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(d) => {
+                let sec = d.as_secs().try_into().map_err(|_| out_of_range())?;
+                (sec, d.subsec_nanos().into())
+            }
+            // Times before the epoch have a negative second and a positive
+            // nanosecond part.
+            Err(e) => {
+                let d = e.duration();
+                let sec: libc::time_t = d.as_secs().try_into().map_err(|_| out_of_range())?;
+                match d.subsec_nanos() {
+                    0 => (-sec, 0),
+                    nsec => (-sec - 1, (1_000_000_000 - nsec).into()),
+                }
+            }
+        },
+    };
+    Ok(libc::timespec {
+        tv_sec: sec,
+        tv_nsec: nsec,
+    })
+}
+
+impl File {
+    pub async fn set_times(&self, times: FileTimes) -> Result<()> {
+        let times = times.to_timespecs()?;
+        // The blocking task might outlive this future, so it gets its own descriptor.
+        let file = self.try_clone().await?;
+        tokio::task::spawn_blocking(move || {
+            if unsafe { libc::futimens(file.as_raw_fd(), times.as_ptr()) } == 0 {
+                Ok(())
+            } else {
+                Err(Error::last_os_error())
+            }
+        })
+        .await?
+    }
+
+    pub async fn set_modified(&self, t: SystemTime) -> Result<()> {
+        self.set_times(FileTimes::new().set_modified(t)).await
+    }
+}
+
+pub(super) async fn utimensat(path: &Path, times: FileTimes, flags: libc::c_int) -> Result<()> {
+    let times = times.to_timespecs()?;
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    tokio::task::spawn_blocking(move || {
+        let res = unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), flags) };
+        if res == 0 {
+            Ok(())
+        } else {
+            Err(Error::last_os_error())
+        }
+    })
+    .await?
+}
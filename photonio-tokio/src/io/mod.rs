@@ -1 +1,13 @@
+use std::io::IoSliceMut;
+
 pub use photonio_base::io::*;
+
+mod timeout;
+pub use timeout::{ReadTimeoutExt, WriteTimeoutExt};
+
+/// Returns the first non-empty buffer in `bufs`, or an empty buffer.
+pub(crate) fn first_non_empty_mut<'a>(bufs: &'a mut [IoSliceMut<'_>]) -> &'a mut [u8] {
+    bufs.iter_mut()
+        .find(|b| !b.is_empty())
+        .map_or(&mut [][..], |b| &mut **b)
+}
@@ -0,0 +1,140 @@
+use std::{
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    time::{Duration, Instant},
+};
+
+use super::{IncompleteIo, Read, Write};
+
+/// Provides extension methods with timeouts for [`Read`].
+///
+/// The read is polled before the timer, so data that is ready is returned even
+/// if the timeout is zero. A read that is still pending when the timer fires is
+/// dropped.
+pub trait ReadTimeoutExt {
+    /// A future that resolves to the result of [`Self::read_timeout`].
+    type ReadTimeout<'a>: Future<Output = Result<usize>> + 'a
+    where
+        Self: 'a;
+
+    /// Reads some bytes from this object into `buf`, failing with
+    /// [`ErrorKind::TimedOut`] if no data arrives in `timeout`.
+    fn read_timeout<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        timeout: Duration,
+    ) -> Self::ReadTimeout<'a>;
+
+    /// A future that resolves to the result of [`Self::read_exact_timeout`].
+    type ReadExactTimeout<'a>: Future<Output = Result<()>> + 'a
+    where
+        Self: 'a;
+
+    /// Reads the exact number of bytes from this object to fill `buf`,
+    /// failing with [`ErrorKind::TimedOut`] if they don't arrive in `timeout`.
+    ///
+    /// The timeout applies to the whole operation. EOF is reported like
+    /// [`super::ReadExt::read_exact`].
+    fn read_exact_timeout<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        timeout: Duration,
+    ) -> Self::ReadExactTimeout<'a>;
+}
+
+impl<T: Read> ReadTimeoutExt for T {
+    type ReadTimeout<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_timeout<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        timeout: Duration,
+    ) -> Self::ReadTimeout<'a> {
+        read_timeout(self, buf, timeout)
+    }
+
+    type ReadExactTimeout<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn read_exact_timeout<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        timeout: Duration,
+    ) -> Self::ReadExactTimeout<'a> {
+        async move {
+            let deadline = Instant::now() + timeout;
+            let mut done = 0;
+            while done < buf.len() {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match read_timeout(self, &mut buf[done..], timeout).await {
+                    Ok(0) => return Err(IncompleteIo::eof(done, None)),
+                    Ok(n) => done += n,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Provides extension methods with timeouts for [`Write`].
+///
+/// See also [`ReadTimeoutExt`] for how the timeouts are implemented.
+pub trait WriteTimeoutExt {
+    /// A future that resolves to the result of [`Self::write_all_timeout`].
+    type WriteAllTimeout<'a>: Future<Output = Result<()>> + 'a
+    where
+        Self: 'a;
+
+    /// Writes all bytes from `buf` into this object, failing with
+    /// [`ErrorKind::TimedOut`] if they are not written in `timeout`.
+    ///
+    /// The timeout applies to the whole operation. Some bytes might be written
+    /// when the timeout fires.
+    fn write_all_timeout<'a>(
+        &'a mut self,
+        buf: &'a [u8],
+        timeout: Duration,
+    ) -> Self::WriteAllTimeout<'a>;
+}
+
+impl<T: Write> WriteTimeoutExt for T {
+    type WriteAllTimeout<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn write_all_timeout<'a>(
+        &'a mut self,
+        buf: &'a [u8],
+        timeout: Duration,
+    ) -> Self::WriteAllTimeout<'a> {
+        async move {
+            let deadline = Instant::now() + timeout;
+            let mut done = 0;
+            while done < buf.len() {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match write_timeout(self, &buf[done..], timeout).await {
+                    Ok(0) => return Err(IncompleteIo::write_zero(done, None)),
+                    Ok(n) => done += n,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn read_timeout<T: Read>(reader: &mut T, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+    race(reader.read(buf), timeout).await
+}
+
+async fn write_timeout<T: Write>(writer: &mut T, buf: &[u8], timeout: Duration) -> Result<usize> {
+    race(writer.write(buf), timeout).await
+}
+
+/// Runs `op` until it completes or `timeout` passes.
+async fn race<F: Future<Output = Result<usize>>>(op: F, timeout: Duration) -> Result<usize> {
+    // The timeout polls `op` first, so its result is kept if both are ready.
+    tokio::time::timeout(timeout, op)
+        .await
+        .unwrap_or_else(|_| Err(Error::new(ErrorKind::TimedOut, "operation timed out")))
+}
@@ -2,3 +2,6 @@ pub use photonio_base::net::*;
 
 mod tcp;
 pub use tcp::{TcpListener, TcpStream};
+
+mod split;
+pub use split::{OwnedReadHalf, OwnedWriteHalf, ReuniteError};
@@ -0,0 +1,112 @@
+use std::{error, fmt, future::Future, io::Result, net::SocketAddr};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::tcp,
+};
+
+use super::TcpStream;
+use crate::io::{first_non_empty_mut, Initializer, IoSlice, IoSliceMut, Read, Write};
+
+#[derive(Debug)]
+pub struct OwnedReadHalf(tcp::OwnedReadHalf);
+
+#[derive(Debug)]
+pub struct OwnedWriteHalf(tcp::OwnedWriteHalf);
+
+pub(super) fn split(stream: tokio::net::TcpStream) -> (OwnedReadHalf, OwnedWriteHalf) {
+    let (r, w) = stream.into_split();
+    (OwnedReadHalf(r), OwnedWriteHalf(w))
+}
+
+impl OwnedReadHalf {
+    pub fn reunite(self, other: OwnedWriteHalf) -> std::result::Result<TcpStream, ReuniteError> {
+        self.0
+            .reunite(other.0)
+            .map(TcpStream)
+            .map_err(|e| ReuniteError(OwnedReadHalf(e.0), OwnedWriteHalf(e.1)))
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.0.local_addr()
+    }
+
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.0.peer_addr()
+    }
+}
+
+impl OwnedWriteHalf {
+    pub fn reunite(self, other: OwnedReadHalf) -> std::result::Result<TcpStream, ReuniteError> {
+        other.reunite(self)
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.0.local_addr()
+    }
+
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.0.peer_addr()
+    }
+}
+
+impl Read for OwnedReadHalf {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        self.0.read(buf)
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        // Tokio doesn't support vectored reads.
+        self.0.read(first_non_empty_mut(bufs))
+    }
+
+    fn initializer(&self) -> Initializer {
+        unsafe { Initializer::nop() }
+    }
+}
+
+impl Write for OwnedWriteHalf {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        self.0.write(buf)
+    }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        let bufs: &'a [IoSlice<'a>] = bufs;
+        self.0.write_vectored(bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        AsyncWrite::is_write_vectored(&self.0)
+    }
+
+    type Flush<'a> = impl Future<Output = Result<()>> + 'a;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        self.0.flush()
+    }
+
+    type Shutdown<'a> = impl Future<Output = Result<()>> + 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        self.0.shutdown()
+    }
+}
+
+#[derive(Debug)]
+pub struct ReuniteError(pub OwnedReadHalf, pub OwnedWriteHalf);
+
+impl fmt::Display for ReuniteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("tried to reunite halves that are not from the same stream")
+    }
+}
+
+impl error::Error for ReuniteError {}
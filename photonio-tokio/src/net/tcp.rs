@@ -1,12 +1,12 @@
 use std::{future::Future, io::Result, net::SocketAddr};
 
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncReadExt, AsyncWrite, AsyncWriteExt},
     net,
 };
 
-use super::ToSocketAddrs;
-use crate::io::{Read, Write};
+use super::{split, OwnedReadHalf, OwnedWriteHalf, ToSocketAddrs};
+use crate::io::{first_non_empty_mut, Initializer, IoSlice, IoSliceMut, Read, Write};
 
 #[derive(Debug)]
 pub struct TcpListener(net::TcpListener);
@@ -66,6 +66,10 @@ impl TcpStream {
     pub fn set_nodelay(&self, nodelay: bool) -> Result<()> {
         self.0.set_nodelay(nodelay)
     }
+
+    pub fn into_split(self) -> (OwnedReadHalf, OwnedWriteHalf) {
+        split::split(self.0)
+    }
 }
 
 impl Read for TcpStream {
@@ -74,6 +78,18 @@ impl Read for TcpStream {
     fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
         self.0.read(buf)
     }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        // Tokio doesn't support vectored reads.
+        self.0.read(first_non_empty_mut(bufs))
+    }
+
+    fn initializer(&self) -> Initializer {
+        // Tokio only writes to the buffer.
+        unsafe { Initializer::nop() }
+    }
 }
 
 impl Write for TcpStream {
@@ -82,4 +98,27 @@ impl Write for TcpStream {
     fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
         self.0.write(buf)
     }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        let bufs: &'a [IoSlice<'a>] = bufs;
+        self.0.write_vectored(bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        AsyncWrite::is_write_vectored(&self.0)
+    }
+
+    type Flush<'a> = impl Future<Output = Result<()>> + 'a;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        self.0.flush()
+    }
+
+    type Shutdown<'a> = impl Future<Output = Result<()>> + 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        self.0.shutdown()
+    }
 }
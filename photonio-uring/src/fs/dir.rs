@@ -0,0 +1,140 @@
+use std::{
+    io::Result,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+    path::Path,
+};
+
+use super::{File, Metadata, OpenOptions};
+use crate::runtime::syscall;
+
+/// A reference to an open directory.
+///
+/// Operations on a directory resolve relative paths against the directory
+/// instead of the current working directory. Absolute paths are resolved as
+/// they are.
+///
+/// See also `man openat.2`.
+#[derive(Debug)]
+pub struct Dir(OwnedFd);
+
+impl Dir {
+    /// Opens a directory.
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let flags = libc::O_RDONLY | libc::O_DIRECTORY;
+        syscall::open(path, flags, 0).await.map(Self)
+    }
+
+    /// Opens a file relative to this directory in read-only mode.
+    ///
+    /// See also [`File::open`].
+    pub async fn open_file_at<P: AsRef<Path>>(&self, path: P) -> Result<File> {
+        OpenOptions::new().read(true).open_at_dir(self, path).await
+    }
+
+    /// Opens a file relative to this directory in write-only mode.
+    ///
+    /// See also [`File::create`].
+    pub async fn create_file_at<P: AsRef<Path>>(&self, path: P) -> Result<File> {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open_at_dir(self, path)
+            .await
+    }
+
+    /// Opens a file beneath this directory in read-only mode.
+    ///
+    /// The path must not resolve outside of this directory.
+    ///
+    /// See also [`OpenOptions::resolve_beneath`].
+    pub async fn open_beneath<P: AsRef<Path>>(&self, path: P) -> Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .resolve_beneath(true)
+            .open_at_dir(self, path)
+            .await
+    }
+
+    /// Opens a file relative to this directory with the given options.
+    ///
+    /// See also [`OpenOptions::open`].
+    pub async fn open_with<P: AsRef<Path>>(&self, path: P, options: &OpenOptions) -> Result<File> {
+        options.open_at_dir(self, path).await
+    }
+
+    /// Removes a file relative to this directory.
+    ///
+    /// See also [`super::remove_file`].
+    pub async fn remove_file_at<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        syscall::unlinkat(Some(self.as_fd()), path, 0).await
+    }
+
+    /// Removes an empty directory relative to this directory.
+    ///
+    /// See also [`super::remove_dir`].
+    pub async fn remove_dir_at<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        syscall::unlinkat(Some(self.as_fd()), path, libc::AT_REMOVEDIR).await
+    }
+
+    /// Renames a file or directory relative to this directory.
+    ///
+    /// See also [`super::rename`].
+    pub async fn rename_at<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
+        let from = from.as_ref();
+        let to = to.as_ref();
+        let fd = Some(self.as_fd());
+        syscall::renameat2(fd, from, fd, to, 0).await
+    }
+
+    /// Returns the metadata about a path relative to this directory.
+    ///
+    /// See also [`super::metadata`].
+    pub async fn metadata_at<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        let path = path.as_ref();
+        syscall::statx(Some(self.as_fd()), path, 0, libc::STATX_ALL)
+            .await
+            .map(Metadata::from)
+    }
+
+    /// Returns the metadata about this directory.
+    pub async fn metadata(&self) -> Result<Metadata> {
+        syscall::fstat(self.as_fd()).await.map(Metadata::from)
+    }
+
+    /// Synchronizes this directory to disk.
+    ///
+    /// This makes previous creations, removals, and renames in this directory
+    /// durable.
+    pub async fn sync_all(&self) -> Result<()> {
+        syscall::fsync(self.as_fd()).await
+    }
+}
+
+impl OpenOptions {
+    async fn open_at_dir<P: AsRef<Path>>(&self, dir: &Dir, path: P) -> Result<File> {
+        let path = path.as_ref();
+        self.open_at(Some(dir.as_fd()), path).await
+    }
+}
+
+impl AsFd for Dir {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl AsRawFd for Dir {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl FromRawFd for Dir {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self(OwnedFd::from_raw_fd(fd))
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+use super::Metadata;
+use crate::runtime::syscall;
+
+/// A builder to create directories with options.
+///
+/// This type is an async version of [`std::fs::DirBuilder`].
+#[derive(Debug)]
+pub struct DirBuilder {
+    recursive: bool,
+    mode: u32,
+}
+
+impl DirBuilder {
+    /// See also [`std::fs::DirBuilder::new`].
+    pub fn new() -> Self {
+        Self {
+            recursive: false,
+            mode: 0o777,
+        }
+    }
+
+    /// Sets the option to create all missing parent directories, and to treat
+    /// an existing directory at the path as success.
+    ///
+    /// See also [`std::fs::DirBuilder::recursive`].
+    pub fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Creates a directory at `path` with these options.
+    ///
+    /// The mode applies to every directory that is created, including missing
+    /// parents, and is masked by the umask of the process like `mkdir`.
+    /// Existing directories are left untouched.
+    ///
+    /// See also [`std::fs::DirBuilder::create`].
+    pub async fn create<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if !self.recursive {
+            return syscall::mkdir(path, self.mode).await;
+        }
+        // Walks up until a directory is created or found, then creates the
+        // missing directories from the top down.
+        let mut missing = Vec::new();
+        let mut current = Some(path);
+        while let Some(dir) = current {
+            if dir.as_os_str().is_empty() {
+                break;
+            }
+            match self.create_if_missing(dir).await {
+                Ok(()) => break,
+                Err(e) if e.kind() == ErrorKind::NotFound => {
+                    missing.push(dir);
+                    current = dir.parent();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        for dir in missing.into_iter().rev() {
+            self.create_if_missing(dir).await?;
+        }
+        Ok(())
+    }
+
+    /// Creates a directory, treating an existing directory as success.
+    ///
+    /// A `NotFound` error is returned as it is so that the caller can create
+    /// the parent directory first. Other errors carry the failing path in the
+    /// message.
+    async fn create_if_missing(&self, dir: &Path) -> Result<()> {
+        let err = match syscall::mkdir(dir, self.mode).await {
+            Ok(()) => return Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(e),
+            // Another creator might have won the race on this directory.
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                match syscall::statx(None, dir, 0, libc::STATX_TYPE).await {
+                    Ok(stat) if Metadata::from(stat).is_dir() => return Ok(()),
+                    _ => e,
+                }
+            }
+            Err(e) => e,
+        };
+        Err(Error::new(
+            err.kind(),
+            format!("failed to create directory {}: {}", dir.display(), err),
+        ))
+    }
+}
+
+impl Default for DirBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::os::unix::fs::DirBuilderExt for DirBuilder {
+    fn mode(&mut self, mode: u32) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+}
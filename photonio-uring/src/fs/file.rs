@@ -1,24 +1,74 @@
 use std::{
-    future::{ready, Future},
-    io::{Error, ErrorKind, Result, Seek as _},
-    mem::ManuallyDrop,
-    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+    ffi::OsString,
+    future::{ready, Future, Ready},
+    io::{Error, ErrorKind, IoSlice, IoSliceMut, Result},
+    ops::{BitOr, BitOrAssign},
+    os::{
+        fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+        unix::fs::PermissionsExt,
+    },
     path::Path,
+    time::Duration,
 };
 
-use super::{Metadata, OpenOptions};
+use super::{
+    temp::TempPath, xattr, FixedFile, FsStats, Metadata, OpenOptions, Permissions, PrioritizedFile,
+};
 use crate::{
-    io::{Read, ReadAt, Seek, SeekFrom, Write, WriteAt},
+    io::{
+        self, raw_os_error, CloseOnDrop, FixedBuf, Initializer, IoBufMut, IoPriority,
+        IoPriorityClass, Read, ReadAt, ReadBuf, Seek, SeekFrom, Write, WriteAt,
+    },
     runtime::syscall,
 };
 
 /// A reference to an open file.
 ///
 /// This type is an async version of [`std::fs::File`].
+///
+/// The position of [`Read`], [`Write`], and [`Seek`] is kept by this type
+/// instead of the kernel, so it is not shared with other descriptors of the
+/// same open file. Positional operations like [`ReadAt`] don't change it.
+///
+/// In append mode, each write through [`Write`] goes to the end of the file
+/// atomically, even with other writers of the same file, and moves the
+/// position there. Seeking only moves the position of later reads, and
+/// positional writes like [`WriteAt`] fail with [`ErrorKind::InvalidInput`].
 #[derive(Debug)]
-pub struct File(OwnedFd);
+pub struct File {
+    fd: CloseOnDrop<OwnedFd>,
+    pos: u64,
+    // Writes go to the end of the file regardless of the position.
+    append: bool,
+    // The alignment of direct I/O, if the file is opened with `O_DIRECT`.
+    align: Option<usize>,
+    // The name of a temporary file that is removed unless it is persisted.
+    temp: Option<TempPath>,
+}
 
 impl File {
+    /// Creates a file from a descriptor that is just opened with `flags`.
+    pub(super) fn opened(fd: OwnedFd, flags: libc::c_int) -> Self {
+        let align = direct_alignment(&fd, flags);
+        Self {
+            fd: CloseOnDrop::new(fd),
+            pos: 0,
+            append: flags & libc::O_APPEND != 0,
+            align,
+            temp: None,
+        }
+    }
+
+    /// Attaches the name of a temporary file, which is removed when the file
+    /// is dropped unless it is taken back.
+    pub(super) fn set_temp_path(&mut self, temp: TempPath) {
+        self.temp = Some(temp);
+    }
+
+    pub(super) fn take_temp_path(&mut self) -> Option<TempPath> {
+        self.temp.take()
+    }
+
     /// Opens a file in read-only mode.
     ///
     /// See also [`std::fs::File::open`].
@@ -49,11 +99,401 @@ impl File {
         syscall::fstat(self.as_fd()).await.map(Metadata::from)
     }
 
+    /// Returns statistics about the filesystem that contains this file.
+    ///
+    /// See also [`super::statfs`].
+    pub async fn statfs(&self) -> Result<FsStats> {
+        syscall::fstatfs(self.as_fd()).await.map(FsStats::from)
+    }
+
+    /// Changes the permissions of this file.
+    ///
+    /// See also [`std::fs::File::set_permissions`].
+    pub async fn set_permissions(&self, perm: Permissions) -> Result<()> {
+        syscall::fchmod(self.as_fd(), perm.mode()).await
+    }
+
     /// Truncates or extends the size of this file.
     ///
+    /// Reads of the removed range that are in flight return short counts.
+    ///
+    /// io_uring doesn't support truncating files before Linux 6.9, so this
+    /// method runs on a blocking thread pool there. It doesn't block the
+    /// current worker thread.
+    ///
     /// See also [`std::fs::File::set_len`].
     pub async fn set_len(&self, size: u64) -> Result<()> {
-        self.as_std(|file| file.set_len(size))
+        let size = size
+            .try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        syscall::ftruncate(self.as_fd(), size)
+            .await
+            .map_err(|e| match raw_os_error(&e) {
+                Some(libc::EBADF | libc::EINVAL) if !self.is_writable() => Error::new(
+                    ErrorKind::PermissionDenied,
+                    "file is not opened for writing",
+                ),
+                _ => e,
+            })
+    }
+
+    /// Allocates disk space for the range `[offset, offset + len)` of this
+    /// file.
+    ///
+    /// The file size is extended if the range goes beyond the end of the file.
+    ///
+    /// See also `man fallocate.2`.
+    pub async fn allocate(&self, offset: u64, len: u64) -> Result<()> {
+        self.fallocate(offset, len, 0).await
+    }
+
+    /// Deallocates disk space for the range `[offset, offset + len)` of this
+    /// file.
+    ///
+    /// Reads from the range return zeros afterwards. The file size is not
+    /// changed.
+    ///
+    /// Returns an error of [`ErrorKind::Unsupported`] if the filesystem doesn't
+    /// support punching holes, in which case callers can write zeros instead.
+    ///
+    /// See also `man fallocate.2`.
+    pub async fn punch_hole(&self, offset: u64, len: u64) -> Result<()> {
+        self.fallocate(
+            offset,
+            len,
+            libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+        )
+        .await
+    }
+
+    /// Announces an intention to access the range `[offset, offset + len)` of
+    /// this file in a specific pattern.
+    ///
+    /// A `len` of zero means until the end of the file.
+    ///
+    /// The advice is only a hint, so this function succeeds without doing
+    /// anything if the kernel doesn't support the operation. Other errors are
+    /// returned. Use [`Self::advise_strict`] to observe the former.
+    ///
+    /// See also `man posix_fadvise.2`.
+    pub async fn advise(&self, offset: u64, len: u64, advice: Advice) -> Result<()> {
+        match self.advise_strict(offset, len, advice).await {
+            Err(e) if e.kind() == ErrorKind::Unsupported => Ok(()),
+            r => r,
+        }
+    }
+
+    /// This function is similar to [`Self::advise`], except that it returns an
+    /// error if the operation is not supported.
+    ///
+    /// Returns an error of [`ErrorKind::Unsupported`] if the kernel doesn't
+    /// support the operation.
+    pub async fn advise_strict(&self, offset: u64, len: u64, advice: Advice) -> Result<()> {
+        let offset = offset
+            .try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        let len = len
+            .try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        syscall::fadvise(self.as_fd(), offset, len, advice.into()).await
+    }
+
+    /// Starts reading the range `[offset, offset + len)` of this file into the
+    /// page cache, so that following reads of the range don't wait for the
+    /// disk.
+    ///
+    /// This function returns once the readahead is initiated, not once the
+    /// pages are resident. A `len` of zero means until the end of the file.
+    ///
+    /// See also `man readahead.2`.
+    pub async fn readahead(&self, offset: u64, len: u64) -> Result<()> {
+        let offset = offset
+            .try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        let len = len
+            .try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        syscall::readahead(self.as_fd(), offset, len).await
+    }
+
+    /// Reads some bytes at `pos` into `buf`, failing with
+    /// [`ErrorKind::TimedOut`] if the read doesn't complete in `timeout`.
+    ///
+    /// The read is cancelled in the kernel when the timeout fires. A zero
+    /// `timeout` fails immediately.
+    pub async fn read_at_timeout(
+        &self,
+        buf: &mut [u8],
+        pos: u64,
+        timeout: Duration,
+    ) -> Result<usize> {
+        let pos = pos
+            .try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        syscall::pread_timeout(self.as_fd(), buf, pos, timeout).await
+    }
+
+    /// Creates a new handle to the same open file.
+    ///
+    /// The descriptor is duplicated with close-on-exec set, so both handles
+    /// share the file status flags like `O_APPEND`, and either can be closed
+    /// without affecting the other. The new handle tracks its own position,
+    /// starting at the position of this one. The name of a file from
+    /// [`super::tempfile_in`] stays owned by this handle.
+    ///
+    /// See also [`std::fs::File::try_clone`].
+    pub async fn try_clone(&self) -> Result<Self> {
+        // Duplicating a descriptor doesn't block, so it runs on the current
+        // worker.
+        let fd = self.fd.try_clone()?;
+        Ok(Self {
+            fd: CloseOnDrop::new(fd),
+            pos: self.pos,
+            append: self.append,
+            align: self.align,
+            temp: None,
+        })
+    }
+
+    /// Closes this file and waits for the result.
+    ///
+    /// Dropping a file closes it in the background and ignores errors, but
+    /// some filesystems like NFS report errors of previous writes on close.
+    /// The descriptor is released even if this method fails.
+    ///
+    /// See also `man close.2`.
+    pub async fn close(self) -> Result<()> {
+        self.fd.close().await
+    }
+
+    /// Returns the alignment that direct I/O on this file requires, or `None`
+    /// if the file is not opened with `O_DIRECT`.
+    ///
+    /// Buffer addresses, lengths, and positions of reads and writes must be
+    /// multiples of the alignment. Reads and writes that are not aligned fail
+    /// with an error of [`ErrorKind::InvalidInput`] before they are submitted.
+    ///
+    /// The alignment is the logical block size of block devices, and the block
+    /// size of the filesystem for other files, which is a safe upper bound.
+    ///
+    /// See also [`crate::io::AlignedBuf`] and [`OpenOptions::direct`].
+    pub fn alignment(&self) -> Option<usize> {
+        self.align
+    }
+
+    /// Registers this file in the fixed file table of the current worker.
+    ///
+    /// See also [`crate::io::register_files`].
+    pub fn register(self) -> Result<FixedFile> {
+        let mut fds = io::register_files(&[self.as_fd()])?;
+        Ok(FixedFile::new(self, fds.pop().unwrap()))
+    }
+
+    /// Reads some bytes into the owned buffer `buf` from this file at `pos`,
+    /// and returns the result with the buffer.
+    ///
+    /// The read starts at the beginning of the buffer, up to its total
+    /// capacity. Unlike [`ReadAt::read_at`], the kernel reads into the buffer
+    /// directly, instead of into an intermediate one that is copied out. If
+    /// the returned future is dropped, the buffer is kept alive until the
+    /// kernel is done with it.
+    pub async fn read_at_owned<B: IoBufMut>(&self, buf: B, pos: u64) -> (Result<usize>, B) {
+        let pos = match pos.try_into() {
+            Ok(pos) => pos,
+            Err(e) => return (Err(Error::new(ErrorKind::InvalidInput, e)), buf),
+        };
+        syscall::pread_owned(self.as_fd(), buf, pos).await
+    }
+
+    /// Reads all bytes until EOF from this file, appending them to `buf`.
+    ///
+    /// This method is similar to [`io::ReadExt::read_to_end`], except that it
+    /// reserves the remaining size of the file up front, so reading a file of
+    /// a stable size allocates at most once. The spare capacity is read into
+    /// without being zeroed. If the file grows after its size is queried, the
+    /// buffer grows as needed until EOF.
+    ///
+    /// Returns the number of bytes read.
+    pub async fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        // A little more than the size, so that EOF is detected without growing
+        // the buffer, and small appends fit.
+        const SLACK: usize = 32;
+
+        let start = buf.len();
+        let hint = self
+            .metadata()
+            .await
+            .map_or(0, |meta| meta.len().saturating_sub(self.pos));
+        let hint = usize::try_from(hint).unwrap_or(usize::MAX);
+        // The size of a sparse file might not fit in memory, in which case the
+        // buffer grows as the data is read.
+        let _ = buf.try_reserve_exact(hint.saturating_add(SLACK));
+        loop {
+            if buf.len() == buf.capacity() {
+                // The file has grown.
+                buf.reserve(SLACK);
+            }
+            let len = buf.len();
+            let mut read_buf = ReadBuf::uninit(buf.spare_capacity_mut());
+            let n = match self.read_buf(&mut read_buf).await {
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            // The bytes read are initialized by the kernel.
+            unsafe { buf.set_len(len + n) };
+            if n == 0 {
+                return Ok(len - start);
+            }
+        }
+    }
+
+    /// Reads the whole fixed buffer `buf` from this file at `pos`, and returns
+    /// the number of bytes read with the buffer.
+    ///
+    /// The buffer must be used on the worker that registers its pool. Like
+    /// [`Self::read_at_owned`], the buffer is kept alive until the kernel is
+    /// done with it if the returned future is dropped.
+    pub async fn read_at_fixed(&self, buf: FixedBuf, pos: u64) -> (Result<usize>, FixedBuf) {
+        let pos = match pos.try_into() {
+            Ok(pos) => pos,
+            Err(e) => return (Err(Error::new(ErrorKind::InvalidInput, e)), buf),
+        };
+        syscall::read_fixed(self.as_fd(), buf, pos).await
+    }
+
+    /// Writes the whole fixed buffer `buf` to this file at `pos`, and returns
+    /// the number of bytes written with the buffer.
+    ///
+    /// The buffer must be used on the worker that registers its pool. Like
+    /// [`Self::read_at_owned`], the buffer is kept alive until the kernel is
+    /// done with it if the returned future is dropped.
+    pub async fn write_at_fixed(&self, buf: FixedBuf, pos: u64) -> (Result<usize>, FixedBuf) {
+        let pos = match self.write_at_pos(pos) {
+            Ok(pos) => pos,
+            Err(e) => return (Err(e), buf),
+        };
+        syscall::write_fixed(self.as_fd(), buf, pos).await
+    }
+
+    /// Writes `buf` at `pos` and synchronizes the data of this file to disk.
+    ///
+    /// This function is similar to [`WriteAt::write_at`] followed by
+    /// [`Self::sync_data`], except that both operations are submitted together
+    /// as a linked chain.
+    ///
+    /// Returns the number of bytes written, which have been synchronized.
+    pub async fn write_at_sync(&self, buf: &[u8], pos: u64) -> Result<usize> {
+        let pos = self.write_at_pos(pos)?;
+        match syscall::pwrite_fdatasync(self.as_fd(), buf, pos).await? {
+            (Err(e), _) => Err(e),
+            (Ok(n), Ok(())) => Ok(n),
+            // A short write breaks the chain, so the data is synchronized
+            // separately.
+            (Ok(n), Err(e)) if raw_os_error(&e) == Some(libc::ECANCELED) => {
+                self.sync_data().await?;
+                Ok(n)
+            }
+            (Ok(_), Err(e)) => Err(e),
+        }
+    }
+
+    /// Reads from this file at `pos` into `bufs`, with `flags` for this read
+    /// only.
+    ///
+    /// Returns the number of bytes read. With [`RwFlags::NOWAIT`], this
+    /// returns an error of [`ErrorKind::WouldBlock`] if the data is not
+    /// cached, so that the caller can fall back to a read without the flag.
+    ///
+    /// See also `man preadv2.2`.
+    pub async fn read_vectored_at_flags(
+        &self,
+        bufs: &mut [IoSliceMut<'_>],
+        pos: u64,
+        flags: RwFlags,
+    ) -> Result<usize> {
+        self.check_aligned_bufs(bufs.iter().map(|buf| &**buf), Some(pos))?;
+        let pos = pos
+            .try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        syscall::preadv2(self.as_fd(), bufs, pos, flags.0).await
+    }
+
+    /// Writes `bufs` to this file at `pos`, with `flags` for this write only.
+    ///
+    /// Returns the number of bytes written. With [`RwFlags::NOWAIT`], this
+    /// returns an error of [`ErrorKind::WouldBlock`] if the write would
+    /// block.
+    ///
+    /// See also `man pwritev2.2`.
+    pub async fn write_vectored_at_flags(
+        &self,
+        bufs: &[IoSlice<'_>],
+        pos: u64,
+        flags: RwFlags,
+    ) -> Result<usize> {
+        let pos = self.write_at_pos(pos)?;
+        self.check_aligned_bufs(bufs.iter().map(|buf| &**buf), Some(pos as u64))?;
+        syscall::pwritev2(self.as_fd(), bufs, pos, flags.0).await
+    }
+
+    /// This function is similar to [`Self::sync_data`], except that it also
+    /// waits for all operations submitted before it on the current worker.
+    ///
+    /// Operations on a worker can complete in any order, so a plain
+    /// [`Self::sync_data`] might run before writes submitted earlier. This
+    /// function acts as a barrier instead: it starts only after all previous
+    /// operations have completed, and no later operation starts until it
+    /// completes. This stalls the whole worker, including operations on other
+    /// files and sockets, so it should be used sparingly.
+    pub async fn barrier_sync(&self) -> Result<()> {
+        syscall::fdatasync_drained(self.as_fd()).await
+    }
+
+    /// Gets the value of the extended attribute `name` of this file.
+    ///
+    /// Returns `None` if the attribute doesn't exist.
+    ///
+    /// See also `man fgetxattr.2`.
+    pub async fn get_xattr(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let fd = self.as_fd();
+        xattr::get_with(|len| syscall::fgetxattr(fd, name, len)).await
+    }
+
+    /// Sets the value of the extended attribute `name` of this file.
+    ///
+    /// The attribute is created if it doesn't exist, and its value is replaced
+    /// if it does.
+    ///
+    /// See also `man fsetxattr.2`.
+    pub async fn set_xattr(&self, name: &str, value: &[u8]) -> Result<()> {
+        syscall::fsetxattr(self.as_fd(), name, value, 0).await
+    }
+
+    /// Returns the names of the extended attributes of this file.
+    ///
+    /// See also `man flistxattr.2`.
+    pub async fn list_xattr(&self) -> Result<Vec<OsString>> {
+        let fd = self.as_fd();
+        xattr::list_with(|len| syscall::flistxattr(fd, len)).await
+    }
+
+    /// Returns a handle to submit I/O on this file with the priority of `level`
+    /// in `class`.
+    ///
+    /// This is useful to keep background I/O, like compactions, from starving
+    /// foreground I/O.
+    ///
+    /// Returns an error of [`ErrorKind::InvalidInput`] if `level` is greater
+    /// than 7.
+    ///
+    /// See also [`IoPriority`] and [`io::with_io_priority`].
+    pub fn with_io_priority(
+        &self,
+        class: IoPriorityClass,
+        level: u8,
+    ) -> Result<PrioritizedFile<'_>> {
+        IoPriority::new(class, level).map(|priority| PrioritizedFile::new(self, priority))
     }
 
     /// Synchronizes all modified data of this file to disk.
@@ -70,51 +510,371 @@ impl File {
     pub async fn sync_data(&self) -> Result<()> {
         syscall::fdatasync(self.as_fd()).await
     }
+
+    /// Synchronizes the modified data in the range `[offset, offset + len)` of
+    /// this file with the given flags.
+    ///
+    /// A `len` of zero means until the end of the file.
+    ///
+    /// Unlike [`Self::sync_data`], this function neither writes out metadata
+    /// nor flushes the disk write cache, so it provides no durability guarantee
+    /// on its own. It is useful to start writeback of a range early, so that a
+    /// following [`Self::sync_data`] has less work to do.
+    ///
+    /// If the kernel doesn't support the operation, this function falls back
+    /// to [`Self::sync_data`].
+    ///
+    /// See also `man sync_file_range.2`.
+    pub async fn sync_range(&self, offset: u64, len: u64, flags: SyncRangeFlags) -> Result<()> {
+        let end = offset
+            .checked_add(len)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "range overflow"))?;
+        let mut offset = offset;
+        loop {
+            // The operation takes a 32-bit length, so syncs large ranges in
+            // chunks.
+            let nbytes = (end - offset).min(u32::MAX as u64);
+            syscall::sync_file_range(self.as_fd(), offset, nbytes as u32, flags.0).await?;
+            offset += nbytes;
+            if offset == end {
+                return Ok(());
+            }
+        }
+    }
 }
 
 impl File {
-    fn as_std<F, R>(&self, f: F) -> R
+    async fn fallocate(&self, offset: u64, len: u64, mode: libc::c_int) -> Result<()> {
+        let offset = offset
+            .try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        let len = len
+            .try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        syscall::fallocate(self.as_fd(), offset, len, mode)
+            .await
+            .map_err(|e| match raw_os_error(&e) {
+                Some(libc::EOPNOTSUPP) => Error::new(ErrorKind::Unsupported, e),
+                _ => e,
+            })
+    }
+
+    fn is_writable(&self) -> bool {
+        let flags = unsafe { libc::fcntl(self.as_raw_fd(), libc::F_GETFL) };
+        flags >= 0 && (flags & libc::O_ACCMODE) != libc::O_RDONLY
+    }
+
+    /// Checks that a direct I/O on `len` bytes at `addr` is aligned, at `pos`
+    /// if the I/O is positional.
+    fn check_aligned(&self, addr: usize, len: usize, pos: Option<u64>) -> Result<()> {
+        let align = match self.align {
+            Some(align) => align,
+            None => return Ok(()),
+        };
+        let what = if addr % align != 0 {
+            "buffer address"
+        } else if len % align != 0 {
+            "buffer length"
+        } else if pos.map_or(false, |pos| pos % align as u64 != 0) {
+            "file position"
+        } else {
+            return Ok(());
+        };
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("the {what} is not aligned to {align} bytes for direct I/O"),
+        ))
+    }
+
+    /// Checks that a direct I/O on `bufs` at `pos` is aligned.
+    fn check_aligned_bufs<'a>(
+        &self,
+        mut bufs: impl Iterator<Item = &'a [u8]>,
+        pos: Option<u64>,
+    ) -> Result<()> {
+        bufs.try_for_each(|buf| self.check_aligned(buf.as_ptr() as usize, buf.len(), pos))
+    }
+
+    /// The position of a write through [`Write`], which the kernel chooses in
+    /// append mode.
+    fn write_pos(&self) -> Option<u64> {
+        (!self.append).then_some(self.pos)
+    }
+
+    /// Checks the position of a positional write, which the kernel ignores in
+    /// append mode.
+    pub(super) fn write_at_pos(&self, pos: u64) -> Result<libc::off64_t> {
+        if self.append {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "positional writes are not supported in append mode",
+            ));
+        }
+        pos.try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+    }
+
+    /// Updates the position after an append, which the kernel has moved to
+    /// the end of the file.
+    fn sync_pos(&mut self) {
+        let pos = unsafe { libc::lseek64(self.as_raw_fd(), 0, libc::SEEK_CUR) };
+        if pos >= 0 {
+            self.pos = pos as u64;
+        }
+    }
+}
+
+/// Advice about the access pattern of a file.
+///
+/// See also `man posix_fadvise.2`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Advice {
+    /// No advice, which is the default.
+    Normal,
+    /// The data will be accessed sequentially.
+    Sequential,
+    /// The data will be accessed in random order.
+    Random,
+    /// The data will be accessed in the near future.
+    WillNeed,
+    /// The data will not be accessed in the near future.
+    DontNeed,
+    /// The data will only be accessed once.
+    NoReuse,
+}
+
+impl From<Advice> for libc::c_int {
+    fn from(advice: Advice) -> Self {
+        match advice {
+            Advice::Normal => libc::POSIX_FADV_NORMAL,
+            Advice::Sequential => libc::POSIX_FADV_SEQUENTIAL,
+            Advice::Random => libc::POSIX_FADV_RANDOM,
+            Advice::WillNeed => libc::POSIX_FADV_WILLNEED,
+            Advice::DontNeed => libc::POSIX_FADV_DONTNEED,
+            Advice::NoReuse => libc::POSIX_FADV_NOREUSE,
+        }
+    }
+}
+
+/// Flags for [`File::sync_range`].
+///
+/// Flags can be combined with the `|` operator.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct SyncRangeFlags(libc::c_uint);
+
+impl SyncRangeFlags {
+    /// Waits for writeback of pages in the range that have already been
+    /// submitted before writing.
+    pub const WAIT_BEFORE: Self = Self(libc::SYNC_FILE_RANGE_WAIT_BEFORE as _);
+    /// Starts writeback of dirty pages in the range that are not already
+    /// submitted.
+    pub const WRITE: Self = Self(libc::SYNC_FILE_RANGE_WRITE as _);
+    /// Waits for writeback of pages in the range after writing.
+    pub const WAIT_AFTER: Self = Self(libc::SYNC_FILE_RANGE_WAIT_AFTER as _);
+
+    /// Returns an empty set of flags.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns true if all flags in `other` are contained in `self`.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for SyncRangeFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for SyncRangeFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Flags for the reads and writes of [`File::read_vectored_at_flags`] and
+/// [`File::write_vectored_at_flags`].
+///
+/// Flags can be combined with the `|` operator.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RwFlags(libc::c_int);
+
+// The values of these flags are missing from libc. See also `linux/fs.h`.
+impl RwFlags {
+    /// Polls for the completion instead of waiting for an interrupt, which
+    /// only works for direct I/O on devices with polling queues.
+    pub const HIPRI: Self = Self(0x01);
+    /// Synchronizes the written data like `O_DSYNC`.
+    pub const DSYNC: Self = Self(0x02);
+    /// Synchronizes the written data and metadata like `O_SYNC`.
+    pub const SYNC: Self = Self(0x04);
+    /// Fails instead of waiting, for example for data that is not cached.
+    pub const NOWAIT: Self = Self(0x08);
+    /// Appends the written data to the end of the file like `O_APPEND`.
+    pub const APPEND: Self = Self(0x10);
+
+    /// Returns an empty set of flags.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns true if all flags in `other` are contained in `self`.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for RwFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for RwFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Provides unix-specific extension methods for [`File`].
+pub trait FileExt {
+    /// A future that resolves to the result of [`Self::set_owner`].
+    type SetOwner<'a>: Future<Output = Result<()>> + 'a
     where
-        F: Fn(&mut std::fs::File) -> R,
-    {
-        // Convert the file to a `std::fs::File` without taking its ownership.
-        let fd = self.0.as_raw_fd();
-        let mut file = unsafe { ManuallyDrop::new(std::fs::File::from_raw_fd(fd)) };
-        f(&mut file)
+        Self: 'a;
+
+    /// Changes the owner and the group of this file.
+    ///
+    /// An id of `None` leaves the owner or the group unchanged.
+    ///
+    /// See also `man fchown.2`.
+    fn set_owner(&self, uid: Option<u32>, gid: Option<u32>) -> Self::SetOwner<'_>;
+}
+
+impl FileExt for File {
+    type SetOwner<'a> = impl Future<Output = Result<()>> + 'a;
+
+    fn set_owner(&self, uid: Option<u32>, gid: Option<u32>) -> Self::SetOwner<'_> {
+        syscall::fchown(self.as_fd(), uid, gid)
     }
 }
 
-#[doc(hidden)]
 impl From<OwnedFd> for File {
+    /// Takes over the descriptor, starting from its current position.
     fn from(fd: OwnedFd) -> Self {
-        Self(fd)
+        let pos = unsafe { libc::lseek64(fd.as_raw_fd(), 0, libc::SEEK_CUR) };
+        let flags = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFL) }.max(0);
+        let align = direct_alignment(&fd, flags);
+        Self {
+            fd: CloseOnDrop::new(fd),
+            pos: pos.max(0) as u64,
+            append: flags & libc::O_APPEND != 0,
+            align,
+            temp: None,
+        }
+    }
+}
+
+/// Returns the alignment of direct I/O on `fd`, if `flags` has `O_DIRECT`.
+fn direct_alignment(fd: &OwnedFd, flags: libc::c_int) -> Option<usize> {
+    // See also `linux/fs.h`.
+    const BLKSSZGET: libc::c_ulong = 0x1268;
+    // The smallest logical block size of devices.
+    const MIN_ALIGN: usize = 512;
+
+    if flags & libc::O_DIRECT == 0 {
+        return None;
+    }
+    let fd = fd.as_raw_fd();
+    let mut stat = unsafe { std::mem::zeroed::<libc::stat64>() };
+    if unsafe { libc::fstat64(fd, &mut stat) } < 0 {
+        return Some(MIN_ALIGN);
+    }
+    let align = if stat.st_mode & libc::S_IFMT == libc::S_IFBLK {
+        let mut size: libc::c_int = 0;
+        if unsafe { libc::ioctl(fd, BLKSSZGET as _, &mut size) } < 0 {
+            0
+        } else {
+            size as usize
+        }
+    } else {
+        stat.st_blksize as usize
+    };
+    // Falls back to a common size if the reported one is not usable.
+    if align.is_power_of_two() {
+        Some(align.max(MIN_ALIGN))
+    } else {
+        Some(MIN_ALIGN)
+    }
+}
+
+impl From<File> for OwnedFd {
+    /// Returns the descriptor, moving its position to that of the file.
+    ///
+    /// A temporary file that is not persisted is still removed from its
+    /// directory, but the descriptor keeps it open.
+    fn from(file: File) -> Self {
+        unsafe { libc::lseek64(file.as_raw_fd(), file.pos as _, libc::SEEK_SET) };
+        file.fd.into_inner()
     }
 }
 
-#[doc(hidden)]
 impl AsFd for File {
     fn as_fd(&self) -> BorrowedFd<'_> {
-        self.0.as_fd()
+        self.fd.as_fd()
     }
 }
 
 impl AsRawFd for File {
     fn as_raw_fd(&self) -> RawFd {
-        self.0.as_raw_fd()
+        self.fd.as_raw_fd()
     }
 }
 
 impl FromRawFd for File {
     unsafe fn from_raw_fd(fd: RawFd) -> Self {
-        Self(OwnedFd::from_raw_fd(fd))
+        Self::from(OwnedFd::from_raw_fd(fd))
+    }
+}
+
+impl IntoRawFd for File {
+    fn into_raw_fd(self) -> RawFd {
+        OwnedFd::from(self).into_raw_fd()
     }
 }
 
 impl Seek for File {
-    type Seek = impl Future<Output = Result<u64>>;
+    type Seek<'a> = impl Future<Output = Result<u64>> + 'a;
 
-    fn seek(&mut self, pos: SeekFrom) -> Self::Seek {
-        ready(self.as_std(|file| file.seek(pos)))
+    /// Seeks to a position in this file.
+    ///
+    /// Seeking from the end of the file queries its size. Returns an error of
+    /// [`ErrorKind::InvalidInput`] if the new position is negative or
+    /// overflows.
+    fn seek(&mut self, pos: SeekFrom) -> Self::Seek<'_> {
+        async move {
+            let (base, offset) = match pos {
+                SeekFrom::Start(offset) => (0, offset as i128),
+                SeekFrom::Current(offset) => (self.pos as i128, offset as i128),
+                SeekFrom::End(offset) => (self.metadata().await?.len() as i128, offset as i128),
+            };
+            let pos = base + offset;
+            if pos < 0 || pos > i64::MAX as i128 {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "invalid seek to a negative or overflowing position",
+                ));
+            }
+            self.pos = pos as u64;
+            Ok(self.pos)
+        }
     }
 }
 
@@ -122,7 +882,41 @@ impl Read for File {
     type Read<'a> = impl Future<Output = Result<usize>> + 'a;
 
     fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
-        syscall::read(self.0.as_fd(), buf)
+        async move {
+            self.check_aligned(buf.as_ptr() as usize, buf.len(), Some(self.pos))?;
+            let n = syscall::pread(self.fd.as_fd(), buf, self.pos as _).await?;
+            self.pos += n as u64;
+            Ok(n)
+        }
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        let check = self.check_aligned_bufs(bufs.iter().map(|buf| &**buf), Some(self.pos));
+        // The op is built before the future, which can't capture the lifetime
+        // of the buffers.
+        let Self { fd, pos, .. } = self;
+        let readv = syscall::preadv(fd.as_fd(), bufs, *pos as _);
+        async move {
+            check?;
+            let n = readv.await?;
+            *pos += n as u64;
+            Ok(n)
+        }
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+
+    fn splice_source(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        Some((self.fd.as_raw_fd(), Some(&mut self.pos)))
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The kernel only writes to the buffer.
+        unsafe { Initializer::nop() }
     }
 }
 
@@ -131,19 +925,75 @@ impl ReadAt for File {
 
     fn read_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadAt<'a> {
         async move {
+            self.check_aligned(buf.as_ptr() as usize, buf.len(), Some(pos))?;
             let pos = pos
                 .try_into()
                 .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
-            syscall::pread(self.0.as_fd(), buf, pos).await
+            syscall::pread(self.fd.as_fd(), buf, pos).await
         }
     }
+
+    fn initializer(&self) -> Initializer {
+        unsafe { Initializer::nop() }
+    }
 }
 
 impl Write for File {
     type Write<'a> = impl Future<Output = Result<usize>> + 'a;
 
     fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
-        syscall::write(self.0.as_fd(), buf)
+        async move {
+            self.check_aligned(buf.as_ptr() as usize, buf.len(), self.write_pos())?;
+            if self.append {
+                let n = syscall::write(self.fd.as_fd(), buf).await?;
+                self.sync_pos();
+                return Ok(n);
+            }
+            let n = syscall::pwrite(self.fd.as_fd(), buf, self.pos as _).await?;
+            self.pos += n as u64;
+            Ok(n)
+        }
+    }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        async move {
+            self.check_aligned_bufs(bufs.iter().map(|buf| &**buf), self.write_pos())?;
+            if self.append {
+                let n = syscall::writev(self.fd.as_fd(), bufs).await?;
+                self.sync_pos();
+                return Ok(n);
+            }
+            let n = syscall::pwritev(self.fd.as_fd(), bufs, self.pos as _).await?;
+            self.pos += n as u64;
+            Ok(n)
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn splice_sink(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        // Splicing to a file in append mode is not supported.
+        if self.append {
+            return None;
+        }
+        Some((self.fd.as_raw_fd(), Some(&mut self.pos)))
+    }
+
+    // Writes are not buffered, and there is nothing to shut down.
+    type Flush<'a> = Ready<Result<()>>;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        ready(Ok(()))
+    }
+
+    type Shutdown<'a> = Ready<Result<()>>;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        ready(Ok(()))
     }
 }
 
@@ -152,10 +1002,69 @@ impl WriteAt for File {
 
     fn write_at<'a>(&'a self, buf: &'a [u8], pos: u64) -> Self::WriteAt<'a> {
         async move {
-            let pos = pos
-                .try_into()
-                .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
-            syscall::pwrite(self.0.as_fd(), buf, pos).await
+            let pos = self.write_at_pos(pos)?;
+            self.check_aligned(buf.as_ptr() as usize, buf.len(), Some(pos as u64))?;
+            syscall::pwrite(self.fd.as_fd(), buf, pos).await
         }
     }
 }
+
+/// Reads from the position of the open file in the kernel.
+///
+/// Unlike reads through [`File`], this position is shared by all references to
+/// the file, and by other descriptors of the same open file, like
+/// [`std::fs::File`] does. It is separate from the position of [`File`].
+impl Read for &File {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        syscall::read(self.fd.as_fd(), buf)
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        syscall::readv(self.fd.as_fd(), bufs)
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+
+    fn initializer(&self) -> Initializer {
+        unsafe { Initializer::nop() }
+    }
+}
+
+/// Writes at the position of the open file in the kernel.
+///
+/// See also the [`Read`] implementation for `&File`.
+impl Write for &File {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        syscall::write(self.fd.as_fd(), buf)
+    }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        syscall::writev(self.fd.as_fd(), bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    type Flush<'a> = Ready<Result<()>> where Self: 'a;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        ready(Ok(()))
+    }
+
+    type Shutdown<'a> = Ready<Result<()>> where Self: 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        ready(Ok(()))
+    }
+}
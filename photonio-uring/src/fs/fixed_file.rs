@@ -0,0 +1,164 @@
+use std::{
+    future::Future,
+    io::{Error, ErrorKind, Result},
+};
+
+use super::File;
+use crate::{
+    io::{FixedFd, Initializer, ReadAt, WriteAt},
+    runtime::syscall::{self, Target},
+};
+
+/// A file registered in the fixed file table of a worker.
+///
+/// I/O on this file refers to its fixed slot, and it can only be done by tasks
+/// running on the worker that registers it.
+///
+/// See also [`File::register`] and [`crate::io::register_files`].
+#[derive(Debug)]
+pub struct FixedFile {
+    file: File,
+    fd: FixedFd,
+}
+
+impl FixedFile {
+    pub(super) fn new(file: File, fd: FixedFd) -> Self {
+        Self { file, fd }
+    }
+
+    /// Returns the underlying file.
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Unregisters the file and returns the underlying file.
+    pub fn into_file(self) -> File {
+        self.file
+    }
+
+    /// Synchronizes all modified data of this file to disk.
+    ///
+    /// See also [`File::sync_all`].
+    pub async fn sync_all(&self) -> Result<()> {
+        syscall::fsync(self.target()?).await
+    }
+
+    /// This function is similiar to [`Self::sync_all`], except that it might
+    /// not synchronize metadata.
+    ///
+    /// See also [`File::sync_data`].
+    pub async fn sync_data(&self) -> Result<()> {
+        syscall::fdatasync(self.target()?).await
+    }
+}
+
+impl FixedFile {
+    fn target(&self) -> Result<Target<'static>> {
+        self.fd.slot().map(Target::Fixed)
+    }
+}
+
+impl ReadAt for FixedFile {
+    type ReadAt<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadAt<'a> {
+        async move {
+            let pos = pos
+                .try_into()
+                .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+            syscall::pread(self.target()?, buf, pos).await
+        }
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The kernel only writes to the buffer.
+        unsafe { Initializer::nop() }
+    }
+}
+
+impl WriteAt for FixedFile {
+    type WriteAt<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write_at<'a>(&'a self, buf: &'a [u8], pos: u64) -> Self::WriteAt<'a> {
+        async move {
+            let pos = self.file.write_at_pos(pos)?;
+            syscall::pwrite(self.target()?, buf, pos).await
+        }
+    }
+}
+
+/// A file opened as a direct descriptor.
+///
+/// A direct descriptor only lives in the fixed file table of a worker, so it
+/// can't be passed to code that expects a [`std::os::unix::io::RawFd`], and
+/// it can only be used by tasks running on that worker. The slot is closed
+/// when this is dropped.
+///
+/// See also [`super::OpenOptions::open_direct`].
+#[derive(Debug)]
+pub struct DirectFile(FixedFd);
+
+impl DirectFile {
+    pub(super) fn new(fd: FixedFd) -> Self {
+        Self(fd)
+    }
+
+    /// Synchronizes all modified data of this file to disk.
+    ///
+    /// See also [`File::sync_all`].
+    pub async fn sync_all(&self) -> Result<()> {
+        syscall::fsync(self.target()?).await
+    }
+
+    /// This function is similiar to [`Self::sync_all`], except that it might
+    /// not synchronize metadata.
+    ///
+    /// See also [`File::sync_data`].
+    pub async fn sync_data(&self) -> Result<()> {
+        syscall::fdatasync(self.target()?).await
+    }
+
+    /// Closes this file.
+    ///
+    /// This function is similar to dropping the file, except that it returns
+    /// the error if any.
+    pub fn close(self) -> Result<()> {
+        self.0.unregister()
+    }
+}
+
+impl DirectFile {
+    fn target(&self) -> Result<Target<'static>> {
+        self.0.slot().map(Target::Fixed)
+    }
+}
+
+impl ReadAt for DirectFile {
+    type ReadAt<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadAt<'a> {
+        async move {
+            let pos = pos
+                .try_into()
+                .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+            syscall::pread(self.target()?, buf, pos).await
+        }
+    }
+
+    fn initializer(&self) -> Initializer {
+        unsafe { Initializer::nop() }
+    }
+}
+
+impl WriteAt for DirectFile {
+    type WriteAt<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write_at<'a>(&'a self, buf: &'a [u8], pos: u64) -> Self::WriteAt<'a> {
+        async move {
+            let pos = pos
+                .try_into()
+                .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+            syscall::pwrite(self.target()?, buf, pos).await
+        }
+    }
+}
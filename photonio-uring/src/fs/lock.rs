@@ -0,0 +1,137 @@
+use std::{
+    io::{Error, Result},
+    mem,
+    os::fd::{AsFd, AsRawFd, BorrowedFd},
+    time::Duration,
+};
+
+use super::File;
+use crate::runtime::syscall;
+
+// The interval to retry a contended lock, which doubles up to the maximum.
+const MIN_BACKOFF: Duration = Duration::from_millis(1);
+const MAX_BACKOFF: Duration = Duration::from_millis(64);
+
+impl File {
+    /// Acquires an exclusive advisory lock on this file, waiting until it is
+    /// available.
+    ///
+    /// The lock is an open file description lock (`F_OFD_SETLK`) on the whole
+    /// file. Unlike `flock`, it interoperates with `fcntl` record locks and
+    /// works on NFS. Unlike traditional `fcntl` locks, it is owned by the open
+    /// file description instead of the process: it is not released when
+    /// another descriptor of the file is closed, and files opened separately
+    /// contend even in the same process. Files from [`File::try_clone`] share
+    /// the open file description, so they share the lock.
+    ///
+    /// The kernel can't wait for the lock without blocking a thread, so this
+    /// method retries [`File::try_lock_exclusive`] with a backoff of up to 64
+    /// milliseconds. Dropping the returned future stops waiting. Locking a
+    /// file that already holds a lock converts it.
+    ///
+    /// See also `F_OFD_SETLK` in `man fcntl.2`.
+    pub async fn lock_exclusive(&self) -> Result<LockGuard<'_>> {
+        self.lock(libc::F_WRLCK).await
+    }
+
+    /// Acquires a shared advisory lock on this file, waiting until it is
+    /// available.
+    ///
+    /// See also [`File::lock_exclusive`].
+    pub async fn lock_shared(&self) -> Result<LockGuard<'_>> {
+        self.lock(libc::F_RDLCK).await
+    }
+
+    /// Tries to acquire an exclusive advisory lock on this file, returning
+    /// `None` if a conflicting lock is held.
+    ///
+    /// See also [`File::lock_exclusive`].
+    pub fn try_lock_exclusive(&self) -> Result<Option<LockGuard<'_>>> {
+        self.try_lock(libc::F_WRLCK)
+    }
+
+    /// Tries to acquire a shared advisory lock on this file, returning `None`
+    /// if a conflicting lock is held.
+    ///
+    /// See also [`File::lock_exclusive`].
+    pub fn try_lock_shared(&self) -> Result<Option<LockGuard<'_>>> {
+        self.try_lock(libc::F_RDLCK)
+    }
+
+    /// Releases the advisory lock on this file, if any.
+    ///
+    /// This also releases locks held through guards or files that share the
+    /// open file description.
+    pub fn unlock(&self) -> Result<()> {
+        set_lock(self.as_fd(), libc::F_UNLCK).map(|_| ())
+    }
+
+    async fn lock(&self, kind: libc::c_int) -> Result<LockGuard<'_>> {
+        let mut backoff = MIN_BACKOFF;
+        loop {
+            if let Some(guard) = self.try_lock(kind)? {
+                return Ok(guard);
+            }
+            syscall::sleep(backoff).await?;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    fn try_lock(&self, kind: libc::c_int) -> Result<Option<LockGuard<'_>>> {
+        if set_lock(self.as_fd(), kind)? {
+            Ok(Some(LockGuard { file: self }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Sets a lock of `kind` on the whole file, returning `false` if a
+/// conflicting lock is held.
+fn set_lock(fd: BorrowedFd<'_>, kind: libc::c_int) -> Result<bool> {
+    // A zero length covers the whole file, even if it grows.
+    let mut lock = unsafe { mem::zeroed::<libc::flock>() };
+    lock.l_type = kind as _;
+    lock.l_whence = libc::SEEK_SET as _;
+    if unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_OFD_SETLK, &lock) } == 0 {
+        return Ok(true);
+    }
+    let err = Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EAGAIN | libc::EACCES) => Ok(false),
+        _ => Err(err),
+    }
+}
+
+/// An advisory lock on a file, which is released when this guard is dropped.
+///
+/// Releasing a lock never blocks, so it is done immediately on drop instead of
+/// being scheduled on the runtime.
+///
+/// This is returned by [`File::lock_exclusive`] and similar methods.
+#[derive(Debug)]
+#[must_use = "the lock is released immediately if the guard is unused"]
+pub struct LockGuard<'a> {
+    file: &'a File,
+}
+
+impl<'a> LockGuard<'a> {
+    /// Returns the locked file.
+    pub fn file(&self) -> &'a File {
+        self.file
+    }
+
+    /// Releases the lock, returning the error that dropping the guard would
+    /// ignore.
+    pub fn unlock(self) -> Result<()> {
+        let file = self.file;
+        mem::forget(self);
+        file.unlock()
+    }
+}
+
+impl Drop for LockGuard<'_> {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
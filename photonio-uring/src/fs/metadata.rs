@@ -1,4 +1,10 @@
-use std::fmt;
+use std::{
+    fmt,
+    fs::Permissions,
+    io::{Error, ErrorKind, Result},
+    os::unix::fs::PermissionsExt,
+    time::{Duration, SystemTime},
+};
 
 /// Metadata information about a file.
 ///
@@ -15,31 +21,80 @@ impl Metadata {
         self.0.stx_size
     }
 
+    /// Returns the type of the file this metadata is for.
+    ///
+    /// See also [`std::fs::Metadata::file_type`].
+    pub fn file_type(&self) -> FileType {
+        FileType::from_mode(self.0.stx_mode.into())
+    }
+
     /// Returns true if this metadata is for a directory.
     ///
     /// See also [`std::fs::Metadata::is_dir`].
     pub fn is_dir(&self) -> bool {
-        self.is_type(libc::S_IFDIR)
+        self.file_type().is_dir()
     }
 
     /// Returns true if this metadata is for a regular file.
     ///
     /// See also [`std::fs::Metadata::is_file`].
     pub fn is_file(&self) -> bool {
-        self.is_type(libc::S_IFREG)
+        self.file_type().is_file()
     }
 
     /// Returns true if this metadata is for a symbolic link.
     ///
     /// See also [`std::fs::Metadata::is_symlink`].
     pub fn is_symlink(&self) -> bool {
-        self.is_type(libc::S_IFLNK)
+        self.file_type().is_symlink()
+    }
+
+    /// Returns the permissions of the file this metadata is for.
+    ///
+    /// See also [`std::fs::Metadata::permissions`].
+    pub fn permissions(&self) -> Permissions {
+        Permissions::from_mode(self.0.stx_mode.into())
+    }
+
+    /// Returns the last modification time of the file.
+    ///
+    /// See also [`std::fs::Metadata::modified`].
+    pub fn modified(&self) -> Result<SystemTime> {
+        self.time(libc::STATX_MTIME, self.0.stx_mtime)
+    }
+
+    /// Returns the last access time of the file.
+    ///
+    /// See also [`std::fs::Metadata::accessed`].
+    pub fn accessed(&self) -> Result<SystemTime> {
+        self.time(libc::STATX_ATIME, self.0.stx_atime)
+    }
+
+    /// Returns the creation time of the file.
+    ///
+    /// See also [`std::fs::Metadata::created`].
+    pub fn created(&self) -> Result<SystemTime> {
+        self.time(libc::STATX_BTIME, self.0.stx_btime)
     }
 }
 
 impl Metadata {
-    fn is_type(&self, ty: libc::mode_t) -> bool {
-        (self.0.stx_mode as u32 & libc::S_IFMT) == ty
+    fn time(&self, mask: libc::c_uint, ts: libc::statx_timestamp) -> Result<SystemTime> {
+        if self.0.stx_mask & mask == 0 {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "the time is not available on this platform or filesystem",
+            ));
+        }
+        let nsec = Duration::from_nanos(ts.tv_nsec.into());
+        let time = if ts.tv_sec >= 0 {
+            SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(ts.tv_sec as u64) + nsec)
+        } else {
+            SystemTime::UNIX_EPOCH
+                .checked_sub(Duration::from_secs(ts.tv_sec.unsigned_abs()))
+                .and_then(|t| t.checked_add(nsec))
+        };
+        time.ok_or_else(|| Error::new(ErrorKind::InvalidData, "the time is out of range"))
     }
 }
 
@@ -126,3 +181,65 @@ impl std::os::unix::fs::MetadataExt for Metadata {
         self.0.stx_blocks
     }
 }
+
+/// The type of a file.
+///
+/// See also [`std::fs::FileType`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileType(libc::mode_t);
+
+impl FileType {
+    /// Creates a file type from the `st_mode` of a file.
+    pub(super) fn from_mode(mode: libc::mode_t) -> Self {
+        Self(mode & libc::S_IFMT)
+    }
+
+    /// Returns true if this is a directory.
+    ///
+    /// See also [`std::fs::FileType::is_dir`].
+    pub fn is_dir(&self) -> bool {
+        self.0 == libc::S_IFDIR
+    }
+
+    /// Returns true if this is a regular file.
+    ///
+    /// See also [`std::fs::FileType::is_file`].
+    pub fn is_file(&self) -> bool {
+        self.0 == libc::S_IFREG
+    }
+
+    /// Returns true if this is a symbolic link.
+    ///
+    /// See also [`std::fs::FileType::is_symlink`].
+    pub fn is_symlink(&self) -> bool {
+        self.0 == libc::S_IFLNK
+    }
+}
+
+impl fmt::Debug for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileType")
+            .field("is_file", &self.is_file())
+            .field("is_dir", &self.is_dir())
+            .field("is_symlink", &self.is_symlink())
+            .finish_non_exhaustive()
+    }
+}
+
+impl std::os::unix::fs::FileTypeExt for FileType {
+    fn is_block_device(&self) -> bool {
+        self.0 == libc::S_IFBLK
+    }
+
+    fn is_char_device(&self) -> bool {
+        self.0 == libc::S_IFCHR
+    }
+
+    fn is_fifo(&self) -> bool {
+        self.0 == libc::S_IFIFO
+    }
+
+    fn is_socket(&self) -> bool {
+        self.0 == libc::S_IFSOCK
+    }
+}
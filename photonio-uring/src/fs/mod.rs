@@ -1,19 +1,164 @@
 //! Primitives for asynchronous filesystem operations.
 //!
 //! This module is an async version of [`std::fs`].
+//!
+//! # Blocking operations
+//!
+//! io_uring has no opcodes for some filesystem operations, so they run on a
+//! blocking thread pool instead. They don't block the current worker thread,
+//! but each of them hands its work to another thread and back. These are:
+//!
+//! - Changing the permissions, the owner or the timestamps of a file, like
+//!   [`set_permissions`], [`File::set_permissions`], [`FileExt::set_owner`],
+//!   [`set_file_times`] and [`File::set_times`].
+//! - Querying filesystems with [`statfs`] and [`File::statfs`].
+//! - Resolving paths with [`read_link`] and [`canonicalize`].
+//! - Adding watches with [`Watcher::watch`].
+//! - Reading directories with [`read_dir`], which reads the entries in batches.
+//! - Truncating files with [`File::set_len`] before Linux 6.9.
 
-use std::{io::Result, path::Path};
+#[doc(no_inline)]
+pub use std::fs::Permissions;
+use std::{
+    io::{Error, ErrorKind, Result},
+    os::unix::{
+        fs::{MetadataExt, PermissionsExt},
+        io::AsFd,
+    },
+    path::{Path, PathBuf},
+};
 
-use crate::runtime::syscall;
+use futures::{stream, StreamExt, TryStreamExt};
+
+use crate::{
+    io::{raw_os_error, ReadAt, ReadExt, WriteAtExt, WriteExt},
+    runtime::syscall,
+};
 
 mod open;
 pub use open::OpenOptions;
 
 mod file;
-pub use file::File;
+pub use file::{Advice, File, FileExt, RwFlags, SyncRangeFlags};
+
+mod lock;
+pub use lock::LockGuard;
+
+mod fixed_file;
+pub use fixed_file::{DirectFile, FixedFile};
 
 mod metadata;
-pub use metadata::Metadata;
+pub use metadata::{FileType, Metadata};
+
+mod dir;
+pub use dir::Dir;
+
+mod dir_builder;
+pub use dir_builder::DirBuilder;
+
+mod read_dir;
+pub use read_dir::{read_dir, DirEntry, ReadDir};
+
+mod temp;
+pub use temp::tempfile_in;
+
+mod statfs;
+pub use statfs::FsStats;
+
+mod times;
+pub use times::FileTimes;
+
+mod priority;
+pub use priority::PrioritizedFile;
+
+mod xattr;
+pub use xattr::{get_xattr, set_xattr};
+
+mod watcher;
+pub use watcher::{Event, EventMask, WatchDescriptor, Watcher};
+
+/// An async version of [`std::fs::metadata`].
+pub async fn metadata<P: AsRef<Path>>(path: P) -> Result<Metadata> {
+    let path = path.as_ref();
+    syscall::statx(None, path, 0, libc::STATX_ALL)
+        .await
+        .map(Metadata::from)
+}
+
+/// An async version of [`std::fs::symlink_metadata`].
+pub async fn symlink_metadata<P: AsRef<Path>>(path: P) -> Result<Metadata> {
+    let path = path.as_ref();
+    syscall::statx(None, path, libc::AT_SYMLINK_NOFOLLOW, libc::STATX_ALL)
+        .await
+        .map(Metadata::from)
+}
+
+/// Returns `Ok(true)` if `path` points to an existing entity, following
+/// symbolic links.
+///
+/// Unlike `metadata(path).is_ok()`, this function returns `Ok(false)` only if
+/// the path is known not to exist, like a missing component or a component
+/// that is not a directory. Other errors, like a parent directory that can't
+/// be searched, are returned since the existence can't be told.
+///
+/// See also [`std::path::Path::try_exists`].
+pub async fn try_exists<P: AsRef<Path>>(path: P) -> Result<bool> {
+    exists_at(path.as_ref(), 0).await
+}
+
+/// Returns `Ok(true)` if `path` points to an existing entity, without
+/// following a symbolic link at the end of the path.
+///
+/// A dangling symbolic link exists for this function but not for
+/// [`try_exists`].
+pub async fn symlink_exists<P: AsRef<Path>>(path: P) -> Result<bool> {
+    exists_at(path.as_ref(), libc::AT_SYMLINK_NOFOLLOW).await
+}
+
+async fn exists_at(path: &Path, flags: libc::c_int) -> Result<bool> {
+    match syscall::statx(None, path, flags, 0).await {
+        Ok(_) => Ok(true),
+        Err(e) if matches!(raw_os_error(&e), Some(libc::ENOENT | libc::ENOTDIR)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns statistics about the filesystem that contains `path`.
+///
+/// See also `man statfs.2`.
+pub async fn statfs<P: AsRef<Path>>(path: P) -> Result<FsStats> {
+    let path = path.as_ref();
+    syscall::statfs(path).await.map(FsStats::from)
+}
+
+/// An async version of [`std::fs::read`].
+pub async fn read<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    File::open(path).await?.read_to_end(&mut buf).await?;
+    Ok(buf)
+}
+
+/// An async version of [`std::fs::read_to_string`].
+pub async fn read_to_string<P: AsRef<Path>>(path: P) -> Result<String> {
+    let mut file = File::open(path).await?;
+    let mut buf = String::with_capacity(size_hint(&file).await);
+    file.read_to_string(&mut buf).await?;
+    Ok(buf)
+}
+
+/// Returns the expected number of bytes to read from `file`.
+async fn size_hint(file: &File) -> usize {
+    // One more byte to detect EOF without growing the buffer.
+    file.metadata()
+        .await
+        .map_or(0, |meta| meta.len() as usize + 1)
+}
+
+/// An async version of [`std::fs::write`].
+pub async fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<()> {
+    let mut file = File::create(path).await?;
+    file.write_all(contents.as_ref()).await
+}
 
 /// An async version of [`std::fs::rename`].
 pub async fn rename<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<()> {
@@ -22,6 +167,69 @@ pub async fn rename<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<()
     syscall::rename(from, to).await
 }
 
+/// Renames a file or directory to a new name, failing if the new name already
+/// exists.
+///
+/// This function is similar to [`rename`], except that it returns an error of
+/// [`std::io::ErrorKind::AlreadyExists`] instead of replacing `to`.
+///
+/// See also `RENAME_NOREPLACE` in `man renameat2.2`.
+pub async fn rename_noreplace<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<()> {
+    let from = from.as_ref();
+    let to = to.as_ref();
+    syscall::renameat2(None, from, None, to, libc::RENAME_NOREPLACE as _).await
+}
+
+/// An async version of [`std::fs::hard_link`].
+pub async fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> Result<()> {
+    let original = original.as_ref();
+    let link = link.as_ref();
+    syscall::linkat(None, original, None, link, 0).await
+}
+
+/// An async version of [`std::os::unix::fs::symlink`].
+pub async fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(original: P, link: Q) -> Result<()> {
+    let original = original.as_ref();
+    let link = link.as_ref();
+    syscall::symlinkat(original, None, link).await
+}
+
+/// An async version of [`std::fs::read_link`].
+pub async fn read_link<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
+    let path = path.as_ref();
+    syscall::readlinkat(None, path).await
+}
+
+/// An async version of [`std::fs::canonicalize`].
+pub async fn canonicalize<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
+    let path = path.as_ref();
+    syscall::realpath(path).await
+}
+
+/// An async version of [`std::fs::set_permissions`].
+pub async fn set_permissions<P: AsRef<Path>>(path: P, perm: Permissions) -> Result<()> {
+    let path = path.as_ref();
+    syscall::fchmodat(None, path, perm.mode()).await
+}
+
+/// Changes the timestamps of the file at `path`, following symbolic links.
+///
+/// See also `man utimensat.2`.
+pub async fn set_file_times<P: AsRef<Path>>(path: P, times: FileTimes) -> Result<()> {
+    let path = path.as_ref();
+    syscall::utimensat(None, path, times.to_timespecs()?, 0).await
+}
+
+/// Changes the timestamps of the file at `path`, without following symbolic
+/// links.
+///
+/// See also [`set_file_times`].
+pub async fn set_symlink_file_times<P: AsRef<Path>>(path: P, times: FileTimes) -> Result<()> {
+    let path = path.as_ref();
+    let flags = libc::AT_SYMLINK_NOFOLLOW;
+    syscall::utimensat(None, path, times.to_timespecs()?, flags).await
+}
+
 /// An async version of [`std::fs::remove_file`].
 pub async fn remove_file<P: AsRef<Path>>(path: P) -> Result<()> {
     let path = path.as_ref();
@@ -30,8 +238,67 @@ pub async fn remove_file<P: AsRef<Path>>(path: P) -> Result<()> {
 
 /// An async version of [`std::fs::create_dir`].
 pub async fn create_dir<P: AsRef<Path>>(path: P) -> Result<()> {
-    let path = path.as_ref();
-    syscall::mkdir(path, 0o777).await
+    DirBuilder::new().create(path).await
+}
+
+/// An async version of [`std::fs::create_dir_all`].
+pub async fn create_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
+    DirBuilder::new().recursive(true).create(path).await
+}
+
+/// An async version of [`std::fs::copy`].
+///
+/// The data is copied within the kernel with `copy_file_range` if possible,
+/// falling back to reading and writing through user space otherwise, for
+/// example when the files are on different filesystems. The permission bits of
+/// the source file are copied to the destination file.
+///
+/// Returns the number of bytes copied.
+pub async fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<u64> {
+    let from = from.as_ref();
+    let to = to.as_ref();
+    let src = File::open(from).await?;
+    let meta = src.metadata().await?;
+    if !meta.is_file() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "the source path is not a regular file",
+        ));
+    }
+    let dst = File::create(to).await?;
+    syscall::fchmod(dst.as_fd(), meta.mode() & 0o7777).await?;
+    copy_file(&src, &dst).await
+}
+
+/// Copies all data from `src` to `dst`, starting at the beginning of both.
+async fn copy_file(src: &File, dst: &File) -> Result<u64> {
+    const CHUNK_LEN: usize = 1 << 30;
+
+    let mut pos = 0;
+    loop {
+        let off = pos as libc::off64_t;
+        match syscall::copy_file_range(src.as_fd(), off, dst.as_fd(), off, CHUNK_LEN).await {
+            Ok(0) => return Ok(pos),
+            Ok(n) => pos += n as u64,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => match raw_os_error(&e) {
+                Some(libc::EXDEV | libc::EOPNOTSUPP | libc::ENOSYS | libc::EINVAL) => break,
+                _ => return Err(e),
+            },
+        }
+    }
+
+    let mut buf = vec![0; 64 << 10];
+    loop {
+        let n = match src.read_at(&mut buf, pos).await {
+            Ok(0) => return Ok(pos),
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        dst.write_all_at(&buf[..n], pos).await?;
+        pos += n as u64;
+    }
 }
 
 /// An async version of [`std::fs::remove_dir`].
@@ -39,3 +306,73 @@ pub async fn remove_dir<P: AsRef<Path>>(path: P) -> Result<()> {
     let path = path.as_ref();
     syscall::rmdir(path).await
 }
+
+/// An async version of [`std::fs::remove_dir_all`].
+///
+/// The tree is walked iteratively, so deep trees don't overflow the stack.
+/// Symbolic links are removed instead of followed, including `path` itself.
+/// Entries that disappear during the walk, for example because another process
+/// removes them too, are skipped.
+pub async fn remove_dir_all<P: AsRef<Path>>(path: P) -> Result<()> {
+    // The number of entries of a directory to unlink concurrently.
+    const CONCURRENCY: usize = 32;
+
+    let path = path.as_ref();
+    if symlink_metadata(path).await?.is_symlink() {
+        return remove_file(path).await;
+    }
+    // Directories are removed after their entries, in the reverse order that
+    // they are listed.
+    let mut stack = vec![(path.to_path_buf(), false)];
+    while let Some((dir, listed)) = stack.pop() {
+        if listed {
+            ignore_not_found(syscall::rmdir(&dir).await)?;
+            continue;
+        }
+        let entries = match list_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        stack.push((dir, true));
+        let mut files = Vec::new();
+        for (path, is_dir) in entries {
+            if is_dir {
+                stack.push((path, false));
+            } else {
+                files.push(path);
+            }
+        }
+        stream::iter(files)
+            .map(|file| async move { ignore_not_found(syscall::unlink(&file).await) })
+            .buffer_unordered(CONCURRENCY)
+            .try_collect::<()>()
+            .await?;
+    }
+    Ok(())
+}
+
+/// Returns the paths of the entries in `dir`, and whether they are
+/// directories.
+///
+/// Symbolic links are not followed, so they are never directories.
+async fn list_dir(dir: &Path) -> Result<Vec<(PathBuf, bool)>> {
+    let mut entries = Vec::new();
+    let mut iter = read_dir(dir).await?;
+    while let Some(entry) = iter.next_entry().await? {
+        let is_dir = match entry.file_type().await {
+            Ok(ty) => ty.is_dir(),
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        entries.push((entry.path(), is_dir));
+    }
+    Ok(entries)
+}
+
+fn ignore_not_found(result: Result<()>) -> Result<()> {
+    match result {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
@@ -1,7 +1,14 @@
-use std::{io::Result, os::unix::fs::OpenOptionsExt, path::Path};
+use std::{
+    io::{Error, ErrorKind, Result},
+    os::{fd::BorrowedFd, unix::fs::OpenOptionsExt},
+    path::Path,
+};
 
-use super::File;
-use crate::runtime::syscall;
+use super::{DirectFile, File};
+use crate::{
+    io::{raw_os_error, FixedFd},
+    runtime::syscall,
+};
 
 /// Options to configure how a file is opened.
 ///
@@ -14,10 +21,17 @@ pub struct OpenOptions {
     truncate: bool,
     create: bool,
     create_new: bool,
+    direct: bool,
     mode: u32,
     custom_flags: i32,
+    resolve: u64,
 }
 
+// See also `linux/openat2.h`.
+const RESOLVE_NO_XDEV: u64 = 0x01;
+const RESOLVE_NO_SYMLINKS: u64 = 0x04;
+const RESOLVE_BENEATH: u64 = 0x08;
+
 impl OpenOptions {
     /// See also [`std::fs::OpenOptions::new`].
     pub fn new() -> Self {
@@ -28,8 +42,10 @@ impl OpenOptions {
             truncate: false,
             create: false,
             create_new: false,
+            direct: false,
             mode: 0o666,
             custom_flags: 0,
+            resolve: 0,
         }
     }
 
@@ -69,25 +85,156 @@ impl OpenOptions {
         self
     }
 
+    /// Sets the option to transfer data directly between user buffers and the
+    /// device, bypassing the page cache.
+    ///
+    /// Reads and writes of a file opened with this option must be aligned to
+    /// [`File::alignment`], for example with buffers of
+    /// [`crate::io::AlignedBuf`]. Some filesystems like tmpfs don't support
+    /// direct I/O, and opening a file on them with this option returns an
+    /// error of [`std::io::ErrorKind::InvalidInput`].
+    ///
+    /// See also `O_DIRECT` in `man open.2`.
+    pub fn direct(&mut self, direct: bool) -> &mut Self {
+        self.direct = direct;
+        self
+    }
+
+    /// Sets the option to reject paths that resolve outside of the directory
+    /// the file is opened relative to.
+    ///
+    /// This rejects absolute paths, `..` components, and symbolic links that
+    /// escape the directory. Opening a rejected path returns an error of
+    /// [`std::io::ErrorKind::CrossesDevices`].
+    ///
+    /// Options that restrict path resolution require kernel 5.6 or later. On
+    /// older kernels, opening a file with them returns an error of
+    /// [`std::io::ErrorKind::Unsupported`].
+    ///
+    /// See also `RESOLVE_BENEATH` in `man openat2.2`.
+    pub fn resolve_beneath(&mut self, resolve_beneath: bool) -> &mut Self {
+        self.set_resolve(RESOLVE_BENEATH, resolve_beneath)
+    }
+
+    /// Sets the option to reject paths that contain symbolic links.
+    ///
+    /// Opening a rejected path returns an error of
+    /// [`std::io::ErrorKind::FilesystemLoop`].
+    ///
+    /// See also `RESOLVE_NO_SYMLINKS` in `man openat2.2`.
+    pub fn resolve_no_symlinks(&mut self, resolve_no_symlinks: bool) -> &mut Self {
+        self.set_resolve(RESOLVE_NO_SYMLINKS, resolve_no_symlinks)
+    }
+
+    /// Sets the option to reject paths that cross mount points.
+    ///
+    /// Opening a rejected path returns an error of
+    /// [`std::io::ErrorKind::CrossesDevices`].
+    ///
+    /// See also `RESOLVE_NO_XDEV` in `man openat2.2`.
+    pub fn resolve_no_xdev(&mut self, resolve_no_xdev: bool) -> &mut Self {
+        self.set_resolve(RESOLVE_NO_XDEV, resolve_no_xdev)
+    }
+
+    /// Opens a file at `path` with these options.
+    ///
+    /// Returns an error of [`ErrorKind::InvalidInput`] if the options
+    /// contradict each other, like truncating a file without write access.
+    ///
     /// See also [`std::fs::OpenOptions::open`].
     pub async fn open<P: AsRef<Path>>(&self, path: P) -> Result<File> {
         let path = path.as_ref();
-        syscall::open(path, self.flags(), self.mode)
-            .await
-            .map(File::from)
+        self.open_at(None, path).await
+    }
+}
+
+impl OpenOptions {
+    /// Opens a file as a direct descriptor in the fixed file table of the
+    /// current worker.
+    ///
+    /// The options to restrict path resolution are not supported.
+    ///
+    /// See also [`DirectFile`].
+    pub async fn open_direct<P: AsRef<Path>>(&self, path: P) -> Result<DirectFile> {
+        if self.resolve != 0 {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "direct descriptors don't support restricted path resolution",
+            ));
+        }
+        let flags = self.flags()?;
+        let fd = FixedFd::alloc()?;
+        let slot = fd.slot()?;
+        syscall::openat_direct(None, path.as_ref(), flags, self.mode, slot).await?;
+        Ok(DirectFile::new(fd))
     }
 }
 
 impl OpenOptions {
-    fn flags(&self) -> libc::c_int {
+    /// Opens a file relative to `dirfd`, or the current working directory if
+    /// `dirfd` is `None`.
+    pub(super) async fn open_at(&self, dirfd: Option<BorrowedFd<'_>>, path: &Path) -> Result<File> {
+        let flags = self.flags()?;
+        if self.resolve == 0 {
+            return syscall::openat(dirfd, path, flags, self.mode)
+                .await
+                .map(|fd| File::opened(fd, flags));
+        }
+        // openat2 rejects a mode if no file is created.
+        let mode = if flags & (libc::O_CREAT | libc::O_TMPFILE) != 0 {
+            self.mode
+        } else {
+            0
+        };
+        syscall::openat2(dirfd, path, flags, mode, self.resolve)
+            .await
+            .map(|fd| File::opened(fd, flags))
+            .map_err(|e| match raw_os_error(&e) {
+                Some(libc::EXDEV) => Error::new(
+                    e.kind(),
+                    format!("path {} escapes the restricted resolution", path.display()),
+                ),
+                Some(libc::ELOOP) => Error::new(
+                    e.kind(),
+                    format!("path {} contains a forbidden symbolic link", path.display()),
+                ),
+                _ => e,
+            })
+    }
+
+    fn set_resolve(&mut self, flag: u64, enable: bool) -> &mut Self {
+        if enable {
+            self.resolve |= flag;
+        } else {
+            self.resolve &= !flag;
+        }
+        self
+    }
+
+    /// Returns the flags to open a file with, or an error of
+    /// [`ErrorKind::InvalidInput`] if the options contradict each other.
+    ///
+    /// The options are validated like [`std::fs::OpenOptions::open`].
+    fn flags(&self) -> Result<libc::c_int> {
         let mut flags = match (self.read, self.write, self.append) {
             (true, _, true) => libc::O_RDWR | libc::O_APPEND,
             (true, true, false) => libc::O_RDWR,
             (true, false, false) => libc::O_RDONLY,
             (false, _, true) => libc::O_WRONLY | libc::O_APPEND,
             (false, true, false) => libc::O_WRONLY,
-            (false, false, false) => 0,
+            (false, false, false) => return Err(invalid_options("no access mode is set")),
         };
+        match (self.write, self.append) {
+            (false, false) if self.truncate || self.create || self.create_new => {
+                return Err(invalid_options(
+                    "creating or truncating a file requires write access",
+                ));
+            }
+            (_, true) if self.truncate && !self.create_new => {
+                return Err(invalid_options("a file can't be truncated in append mode"));
+            }
+            _ => {}
+        }
         if self.create_new {
             flags |= libc::O_CREAT | libc::O_EXCL;
         } else {
@@ -98,11 +245,21 @@ impl OpenOptions {
                 flags |= libc::O_TRUNC;
             }
         }
+        if self.direct {
+            flags |= libc::O_DIRECT;
+        }
         flags |= self.custom_flags & !libc::O_ACCMODE;
-        flags
+        Ok(flags)
     }
 }
 
+fn invalid_options(msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid open options: {msg}"),
+    )
+}
+
 impl Default for OpenOptions {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,68 @@
+use std::{future::Future, io::Result};
+
+use super::File;
+use crate::io::{with_io_priority, Initializer, IoPriority, ReadAt, WriteAt};
+
+/// A handle to submit I/O on a file with a specific priority.
+///
+/// Reads and writes through this handle carry the priority, while
+/// synchronizations are submitted with the default priority, since the kernel
+/// doesn't support priorities for them.
+///
+/// See also [`File::with_io_priority`].
+#[derive(Debug)]
+pub struct PrioritizedFile<'a> {
+    file: &'a File,
+    priority: IoPriority,
+}
+
+impl<'a> PrioritizedFile<'a> {
+    pub(super) fn new(file: &'a File, priority: IoPriority) -> Self {
+        Self { file, priority }
+    }
+
+    /// Returns the underlying file.
+    pub fn file(&self) -> &'a File {
+        self.file
+    }
+
+    /// Returns the priority of this handle.
+    pub fn priority(&self) -> IoPriority {
+        self.priority
+    }
+
+    /// Synchronizes all modified data of this file to disk.
+    ///
+    /// See also [`File::sync_all`].
+    pub async fn sync_all(&self) -> Result<()> {
+        self.file.sync_all().await
+    }
+
+    /// This function is similiar to [`Self::sync_all`], except that it might
+    /// not synchronize metadata.
+    ///
+    /// See also [`File::sync_data`].
+    pub async fn sync_data(&self) -> Result<()> {
+        self.file.sync_data().await
+    }
+}
+
+impl ReadAt for PrioritizedFile<'_> {
+    type ReadAt<'b> = impl Future<Output = Result<usize>> + 'b where Self: 'b;
+
+    fn read_at<'b>(&'b self, buf: &'b mut [u8], pos: u64) -> Self::ReadAt<'b> {
+        with_io_priority(self.priority, self.file.read_at(buf, pos))
+    }
+
+    fn initializer(&self) -> Initializer {
+        ReadAt::initializer(self.file)
+    }
+}
+
+impl WriteAt for PrioritizedFile<'_> {
+    type WriteAt<'b> = impl Future<Output = Result<usize>> + 'b where Self: 'b;
+
+    fn write_at<'b>(&'b self, buf: &'b [u8], pos: u64) -> Self::WriteAt<'b> {
+        with_io_priority(self.priority, self.file.write_at(buf, pos))
+    }
+}
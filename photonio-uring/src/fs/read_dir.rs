@@ -0,0 +1,211 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fmt,
+    io::{Error, ErrorKind, Result},
+    mem,
+    os::{fd::OwnedFd, unix::ffi::OsStrExt},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use super::{FileType, Metadata};
+use crate::runtime::syscall;
+
+/// Returns the entries within a directory.
+///
+/// The entries are read in batches on a blocking thread pool, since io_uring
+/// doesn't support reading directories. It doesn't block the current worker
+/// thread. The entries `.` and `..` are skipped.
+///
+/// See also [`std::fs::read_dir`].
+pub async fn read_dir<P: AsRef<Path>>(path: P) -> Result<ReadDir> {
+    let path = path.as_ref();
+    let flags = libc::O_RDONLY | libc::O_DIRECTORY;
+    let fd = syscall::open(path, flags, 0).await?;
+    Ok(ReadDir {
+        fd: Arc::new(fd),
+        dir: Arc::new(path.to_path_buf()),
+        buf: Vec::new(),
+        pos: 0,
+        batch_size: ReadDir::DEFAULT_BATCH_SIZE,
+        done: false,
+    })
+}
+
+/// An iterator over the entries within a directory.
+///
+/// This is returned by [`read_dir`].
+///
+/// See also [`std::fs::ReadDir`].
+pub struct ReadDir {
+    fd: Arc<OwnedFd>,
+    dir: Arc<PathBuf>,
+    // The entries of the last batch, and the position of the next one in it.
+    buf: Vec<u8>,
+    pos: usize,
+    batch_size: usize,
+    done: bool,
+}
+
+impl ReadDir {
+    const DEFAULT_BATCH_SIZE: usize = 32 << 10;
+    // Large enough for an entry with the longest name.
+    const MIN_BATCH_SIZE: usize = 1 << 10;
+
+    /// Sets the size of the buffer to read entries into in one batch.
+    ///
+    /// This applies to the next batch. The default size is 32 KiB. Sizes less
+    /// than 1 KiB are rounded up.
+    pub fn set_batch_size(&mut self, size: usize) {
+        self.batch_size = size.max(Self::MIN_BATCH_SIZE);
+    }
+
+    /// Returns the next entry within the directory, or `None` if there are no
+    /// more entries.
+    ///
+    /// Entries that are removed or added during the iteration might or might
+    /// not be returned. Dropping the returned future before it completes might
+    /// skip the entries of the pending batch.
+    pub async fn next_entry(&mut self) -> Result<Option<DirEntry>> {
+        loop {
+            if let Some(entry) = self.next_in_batch()? {
+                if entry.name == "." || entry.name == ".." {
+                    continue;
+                }
+                return Ok(Some(entry));
+            }
+            if self.done {
+                return Ok(None);
+            }
+            let mut buf = mem::take(&mut self.buf);
+            buf.clear();
+            buf.reserve(self.batch_size);
+            self.buf = syscall::getdents64(self.fd.clone(), buf).await?;
+            self.pos = 0;
+            self.done = self.buf.is_empty();
+        }
+    }
+
+    /// Parses the next entry in the current batch, if any.
+    ///
+    /// See also `struct linux_dirent64` in `man getdents64.2`.
+    fn next_in_batch(&mut self) -> Result<Option<DirEntry>> {
+        const NAME_OFFSET: usize = 19;
+
+        let rest = &self.buf[self.pos..];
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        if rest.len() < NAME_OFFSET {
+            return Err(invalid_entry());
+        }
+        let ino = u64::from_ne_bytes(rest[0..8].try_into().unwrap());
+        let reclen = u16::from_ne_bytes(rest[16..18].try_into().unwrap()) as usize;
+        let d_type = rest[18];
+        if reclen < NAME_OFFSET || reclen > rest.len() {
+            return Err(invalid_entry());
+        }
+        let name = &rest[NAME_OFFSET..reclen];
+        let len = name
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(invalid_entry)?;
+        let name = OsStr::from_bytes(&name[..len]).to_owned();
+        self.pos += reclen;
+        Ok(Some(DirEntry {
+            dir: self.dir.clone(),
+            name,
+            ino,
+            file_type: file_type_of(d_type),
+        }))
+    }
+}
+
+impl fmt::Debug for ReadDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReadDir").field(&self.dir).finish()
+    }
+}
+
+fn invalid_entry() -> Error {
+    Error::new(ErrorKind::InvalidData, "invalid directory entry")
+}
+
+/// Returns the file type of a `d_type`, or `None` if it is unknown.
+fn file_type_of(d_type: u8) -> Option<FileType> {
+    let mode = match d_type {
+        libc::DT_DIR => libc::S_IFDIR,
+        libc::DT_REG => libc::S_IFREG,
+        libc::DT_LNK => libc::S_IFLNK,
+        libc::DT_BLK => libc::S_IFBLK,
+        libc::DT_CHR => libc::S_IFCHR,
+        libc::DT_FIFO => libc::S_IFIFO,
+        libc::DT_SOCK => libc::S_IFSOCK,
+        _ => return None,
+    };
+    Some(FileType::from_mode(mode))
+}
+
+/// An entry within a directory.
+///
+/// This is returned by [`ReadDir::next_entry`].
+///
+/// See also [`std::fs::DirEntry`].
+pub struct DirEntry {
+    dir: Arc<PathBuf>,
+    name: OsString,
+    ino: u64,
+    file_type: Option<FileType>,
+}
+
+impl DirEntry {
+    /// Returns the file name of this entry.
+    ///
+    /// See also [`std::fs::DirEntry::file_name`].
+    pub fn file_name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    /// Returns the full path of this entry, which is the path passed to
+    /// [`read_dir`] joined with the file name.
+    ///
+    /// See also [`std::fs::DirEntry::path`].
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(&self.name)
+    }
+
+    /// Returns the type of the file this entry is for.
+    ///
+    /// The type comes from the directory entry if the filesystem provides it,
+    /// and from the metadata of the file otherwise. Symbolic links are not
+    /// followed.
+    ///
+    /// See also [`std::fs::DirEntry::file_type`].
+    pub async fn file_type(&self) -> Result<FileType> {
+        match self.file_type {
+            Some(file_type) => Ok(file_type),
+            None => self.metadata().await.map(|meta| meta.file_type()),
+        }
+    }
+
+    /// Returns the metadata of the file this entry is for.
+    ///
+    /// Symbolic links are not followed.
+    ///
+    /// See also [`std::fs::DirEntry::metadata`].
+    pub async fn metadata(&self) -> Result<Metadata> {
+        super::symlink_metadata(self.path()).await
+    }
+}
+
+impl fmt::Debug for DirEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DirEntry").field(&self.path()).finish()
+    }
+}
+
+impl std::os::unix::fs::DirEntryExt for DirEntry {
+    fn ino(&self) -> u64 {
+        self.ino
+    }
+}
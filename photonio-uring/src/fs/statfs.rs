@@ -0,0 +1,86 @@
+use std::fmt;
+
+/// Statistics about a mounted filesystem.
+///
+/// This is returned by [`super::statfs`] and [`super::File::statfs`].
+///
+/// See also `man statfs.2`.
+#[derive(Clone)]
+pub struct FsStats(libc::statfs64);
+
+impl FsStats {
+    /// Returns the size of the filesystem in bytes.
+    pub fn total_bytes(&self) -> u64 {
+        self.0.f_blocks.saturating_mul(self.fragment_size())
+    }
+
+    /// Returns the number of free bytes in the filesystem.
+    pub fn free_bytes(&self) -> u64 {
+        self.0.f_bfree.saturating_mul(self.fragment_size())
+    }
+
+    /// Returns the number of free bytes that unprivileged users can use.
+    ///
+    /// This is less than [`Self::free_bytes`] if some blocks are reserved for
+    /// the superuser.
+    pub fn available_bytes(&self) -> u64 {
+        self.0.f_bavail.saturating_mul(self.fragment_size())
+    }
+
+    /// Returns the number of inodes in the filesystem.
+    pub fn total_inodes(&self) -> u64 {
+        self.0.f_files
+    }
+
+    /// Returns the number of free inodes in the filesystem.
+    pub fn free_inodes(&self) -> u64 {
+        self.0.f_ffree
+    }
+
+    /// Returns the optimal block size for transfers.
+    pub fn block_size(&self) -> u64 {
+        self.0.f_bsize as u64
+    }
+
+    /// Returns the type of the filesystem, like `TMPFS_MAGIC`.
+    ///
+    /// See also `linux/magic.h`.
+    pub fn fs_type(&self) -> u64 {
+        self.0.f_type as u64
+    }
+
+    /// Returns the maximum length of file names.
+    pub fn max_name_len(&self) -> u64 {
+        self.0.f_namelen as u64
+    }
+
+    /// Returns the unit of the block counts.
+    ///
+    /// The counts are in fragments, which might be smaller than the block
+    /// size. Old kernels don't report the fragment size.
+    fn fragment_size(&self) -> u64 {
+        match self.0.f_frsize {
+            0 => self.block_size(),
+            size => size as u64,
+        }
+    }
+}
+
+#[doc(hidden)]
+impl From<libc::statfs64> for FsStats {
+    fn from(stat: libc::statfs64) -> Self {
+        Self(stat)
+    }
+}
+
+impl fmt::Debug for FsStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FsStats")
+            .field("fs_type", &format_args!("{:#x}", self.fs_type()))
+            .field("total_bytes", &self.total_bytes())
+            .field("available_bytes", &self.available_bytes())
+            .field("total_inodes", &self.total_inodes())
+            .field("free_inodes", &self.free_inodes())
+            .finish()
+    }
+}
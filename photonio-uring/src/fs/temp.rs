@@ -0,0 +1,197 @@
+use std::{
+    collections::hash_map::RandomState,
+    ffi::OsString,
+    hash::{BuildHasher, Hasher},
+    io::{Error, ErrorKind, Result},
+    mem::{self, ManuallyDrop},
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::{Dir, File};
+use crate::{
+    io::raw_os_error,
+    runtime::{syscall, unblock_detached},
+};
+
+/// The number of random names to try before giving up.
+const MAX_ATTEMPTS: usize = 64;
+
+/// Creates an unnamed temporary file in `dir` for reading and writing.
+///
+/// The file is created with `O_TMPFILE`, so it doesn't appear in `dir` and is
+/// removed when it is closed, unless it is given a name with
+/// [`File::persist`]. Writing a temporary file, syncing it, and persisting it
+/// over the old one replaces a file without exposing partial contents, even
+/// if the process crashes.
+///
+/// On filesystems that don't support `O_TMPFILE`, this function falls back to
+/// creating a file with a random name in `dir`, which is removed when the
+/// returned handle is dropped, unless it is persisted.
+///
+/// See also `O_TMPFILE` in `man open.2`.
+pub async fn tempfile_in<P: AsRef<Path>>(dir: P) -> Result<File> {
+    let dir = dir.as_ref();
+    let flags = libc::O_RDWR | libc::O_TMPFILE;
+    match syscall::open(dir, flags, 0o600).await {
+        Ok(fd) => return Ok(File::opened(fd, flags)),
+        // Kernels without `O_TMPFILE` take it as `O_DIRECTORY`.
+        Err(e) if matches!(raw_os_error(&e), Some(libc::EOPNOTSUPP | libc::EISDIR)) => {}
+        Err(e) => return Err(e),
+    }
+    let flags = libc::O_RDWR | libc::O_CREAT | libc::O_EXCL;
+    for _ in 0..MAX_ATTEMPTS {
+        let path = dir.join(temp_name());
+        match syscall::open(&path, flags, 0o600).await {
+            Ok(fd) => {
+                let mut file = File::opened(fd, flags);
+                file.set_temp_path(TempPath(path));
+                return Ok(file);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Err(too_many_attempts())
+}
+
+impl File {
+    /// Gives this file the name `path`, failing with an error of
+    /// [`ErrorKind::AlreadyExists`] if `path` exists.
+    ///
+    /// This is meant for files from [`tempfile_in`], which are linked into
+    /// `path` through `/proc/self/fd`, or renamed if they already have a
+    /// temporary name. For other files, this creates another hard link.
+    ///
+    /// Sync the file before persisting it if its contents must survive a
+    /// crash. If `sync_dir` is true, the parent directory of `path` is synced
+    /// afterwards so that the new name survives a crash too.
+    pub async fn persist<P: AsRef<Path>>(&mut self, path: P, sync_dir: bool) -> Result<()> {
+        let path = path.as_ref();
+        match self.take_temp_path() {
+            Some(temp) => {
+                // Renaming would replace an existing `path`.
+                if let Err(e) = syscall::linkat(None, &temp.0, None, path, 0).await {
+                    self.set_temp_path(temp);
+                    return Err(e);
+                }
+                syscall::unlink(&temp.keep()).await?;
+            }
+            None => {
+                let proc_path = self.proc_path();
+                syscall::linkat(None, &proc_path, None, path, libc::AT_SYMLINK_FOLLOW).await?;
+            }
+        }
+        if sync_dir {
+            sync_parent(path).await?;
+        }
+        Ok(())
+    }
+
+    /// Gives this file the name `path`, atomically replacing the file at
+    /// `path` if it exists.
+    ///
+    /// An unnamed file is linked into a random name next to `path` first,
+    /// which is then renamed over `path`.
+    ///
+    /// See also [`File::persist`].
+    pub async fn persist_overwrite<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        sync_dir: bool,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        match self.take_temp_path() {
+            Some(temp) => {
+                if let Err(e) = syscall::rename(&temp.0, path).await {
+                    self.set_temp_path(temp);
+                    return Err(e);
+                }
+                temp.keep();
+            }
+            None => {
+                let temp = self.link_temp(parent(path)).await?;
+                if let Err(e) = syscall::rename(&temp, path).await {
+                    let _ = syscall::unlink(&temp).await;
+                    return Err(e);
+                }
+            }
+        }
+        if sync_dir {
+            sync_parent(path).await?;
+        }
+        Ok(())
+    }
+
+    /// Links this file into a random name in `dir`.
+    async fn link_temp(&self, dir: &Path) -> Result<PathBuf> {
+        let proc_path = self.proc_path();
+        for _ in 0..MAX_ATTEMPTS {
+            let path = dir.join(temp_name());
+            match syscall::linkat(None, &proc_path, None, &path, libc::AT_SYMLINK_FOLLOW).await {
+                Ok(()) => return Ok(path),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Err(too_many_attempts())
+    }
+
+    /// Returns a path that refers to this file even if it has no name.
+    ///
+    /// Linking with `AT_EMPTY_PATH` would avoid `/proc`, but it requires
+    /// `CAP_DAC_READ_SEARCH`.
+    fn proc_path(&self) -> PathBuf {
+        PathBuf::from(format!("/proc/self/fd/{}", self.as_raw_fd()))
+    }
+}
+
+/// The name of a temporary file, which is removed when this is dropped.
+#[derive(Debug)]
+pub(super) struct TempPath(PathBuf);
+
+impl TempPath {
+    /// Returns the name without removing it.
+    fn keep(self) -> PathBuf {
+        let mut this = ManuallyDrop::new(self);
+        mem::take(&mut this.0)
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        // Drop can't wait for the removal, so it is left to the blocking
+        // thread pool.
+        let path = mem::take(&mut self.0);
+        unblock_detached(move || {
+            let _ = std::fs::remove_file(path);
+        });
+    }
+}
+
+/// Returns a random name that is hidden in directory listings by convention.
+fn temp_name() -> OsString {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!(".tmp{:016x}", hasher.finish()).into()
+}
+
+fn too_many_attempts() -> Error {
+    Error::new(
+        ErrorKind::AlreadyExists,
+        "failed to find an unused name for a temporary file",
+    )
+}
+
+fn parent(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+async fn sync_parent(path: &Path) -> Result<()> {
+    Dir::open(parent(path)).await?.sync_all().await
+}
@@ -0,0 +1,87 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    os::fd::AsFd,
+    time::SystemTime,
+};
+
+use super::File;
+use crate::runtime::syscall;
+
+/// Timestamps to set on a file.
+///
+/// Timestamps that are not set are left unchanged.
+///
+/// See also [`File::set_times`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileTimes {
+    accessed: Option<SystemTime>,
+    modified: Option<SystemTime>,
+}
+
+impl FileTimes {
+    /// Creates a new set of timestamps that leaves both unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the last access time.
+    pub fn set_accessed(mut self, t: SystemTime) -> Self {
+        self.accessed = Some(t);
+        self
+    }
+
+    /// Sets the last modification time.
+    pub fn set_modified(mut self, t: SystemTime) -> Self {
+        self.modified = Some(t);
+        self
+    }
+
+    /// Returns the timestamps for `utimensat`, with `UTIME_OMIT` for the
+    /// unset ones.
+    pub(super) fn to_timespecs(self) -> Result<[libc::timespec; 2]> {
+        Ok([to_timespec(self.accessed)?, to_timespec(self.modified)?])
+    }
+}
+
+fn to_timespec(time: Option<SystemTime>) -> Result<libc::timespec> {
+    let out_of_range = || Error::new(ErrorKind::InvalidInput, "the time is out of range");
+    let (sec, nsec) = match time {
+        None => (0, libc::UTIME_OMIT),
+        Some(time) => match time.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(d) => {
+                let sec = d.as_secs().try_into().map_err(|_| out_of_range())?;
+                (sec, d.subsec_nanos().into())
+            }
+            // Times before the epoch have a negative second and a positive
+            // nanosecond part.
+            Err(e) => {
+                let d = e.duration();
+                let sec: libc::time_t = d.as_secs().try_into().map_err(|_| out_of_range())?;
+                match d.subsec_nanos() {
+                    0 => (-sec, 0),
+                    nsec => (-sec - 1, (1_000_000_000 - nsec).into()),
+                }
+            }
+        },
+    };
+    Ok(libc::timespec {
+        tv_sec: sec,
+        tv_nsec: nsec,
+    })
+}
+
+impl File {
+    /// Changes the timestamps of this file.
+    ///
+    /// See also `man futimens.3`.
+    pub async fn set_times(&self, times: FileTimes) -> Result<()> {
+        syscall::futimens(self.as_fd(), times.to_timespecs()?).await
+    }
+
+    /// Changes the last modification time of this file.
+    ///
+    /// See also [`File::set_times`].
+    pub async fn set_modified(&self, t: SystemTime) -> Result<()> {
+        self.set_times(FileTimes::new().set_modified(t)).await
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    ffi::OsString,
+    io::{Error, ErrorKind, Result},
+    mem,
+    ops::{BitOr, BitOrAssign},
+    os::unix::{
+        ffi::OsStringExt,
+        io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+    },
+    path::Path,
+    ptr,
+};
+
+use crate::{io::CloseOnDrop, runtime::syscall};
+
+/// The size of the header of an event, which is followed by its name.
+const HEADER_LEN: usize = mem::size_of::<libc::inotify_event>();
+
+/// The size of the event buffer, which fits at least one event with the
+/// longest name.
+const BUF_LEN: usize = 4096;
+
+/// A watcher of filesystem events.
+///
+/// Watches are added with [`Watcher::watch`], and their events are read in
+/// order with [`Watcher::next_event`]. Dropping the watcher removes all its
+/// watches.
+///
+/// See also `man inotify.7`.
+#[derive(Debug)]
+pub struct Watcher {
+    fd: CloseOnDrop<OwnedFd>,
+    buf: Box<[u8]>,
+    // The range of the buffer that holds events that are not returned yet.
+    start: usize,
+    end: usize,
+}
+
+impl Watcher {
+    /// Creates a watcher without watches.
+    ///
+    /// See also `man inotify_init.2`.
+    pub fn new() -> Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(Self {
+            fd: CloseOnDrop::new(unsafe { OwnedFd::from_raw_fd(fd) }),
+            buf: vec![0; BUF_LEN].into_boxed_slice(),
+            start: 0,
+            end: 0,
+        })
+    }
+
+    /// Watches `path` for the events in `mask`.
+    ///
+    /// Watching a path that is already watched replaces its mask, unless
+    /// [`EventMask::MASK_ADD`] is set, and returns the same descriptor.
+    ///
+    /// See also `man inotify_add_watch.2`.
+    pub async fn watch<P: AsRef<Path>>(&self, path: P, mask: EventMask) -> Result<WatchDescriptor> {
+        syscall::inotify_add_watch(self.fd.as_fd(), path.as_ref(), mask.0)
+            .await
+            .map(WatchDescriptor)
+    }
+
+    /// Removes the watch `wd`.
+    ///
+    /// An event with [`EventMask::IGNORED`] follows the last event of the
+    /// watch.
+    ///
+    /// See also `man inotify_rm_watch.2`.
+    pub fn unwatch(&self, wd: WatchDescriptor) -> Result<()> {
+        if unsafe { libc::inotify_rm_watch(self.fd.as_raw_fd(), wd.0) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Waits for the next event.
+    ///
+    /// If the kernel runs out of space for events, later events are dropped,
+    /// and an event with [`EventMask::Q_OVERFLOW`] is returned in their place.
+    /// The watched paths should be rescanned then.
+    ///
+    /// This method is not cancel safe, dropping the returned future before
+    /// completion might lose events.
+    pub async fn next_event(&mut self) -> Result<Event> {
+        loop {
+            if let Some(event) = self.parse_event() {
+                return Ok(event);
+            }
+            self.fill_buf().await?;
+        }
+    }
+}
+
+impl Watcher {
+    /// Reads more events into the buffer, which must be consumed.
+    async fn fill_buf(&mut self) -> Result<()> {
+        loop {
+            match syscall::read(self.fd.as_fd(), &mut self.buf).await {
+                Ok(n) => {
+                    self.start = 0;
+                    self.end = n;
+                    return Ok(());
+                }
+                // The descriptor is non-blocking, so the read doesn't wait for
+                // events.
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    syscall::poll_add(self.fd.as_fd(), libc::POLLIN).await?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Returns the next event in the buffer.
+    ///
+    /// The kernel only returns whole events, each with a name padded with
+    /// zeros to the alignment of the header.
+    fn parse_event(&mut self) -> Option<Event> {
+        let buf = &self.buf[self.start..self.end];
+        if buf.len() < HEADER_LEN {
+            return None;
+        }
+        let raw = unsafe { ptr::read_unaligned(buf.as_ptr() as *const libc::inotify_event) };
+        let len = HEADER_LEN + raw.len as usize;
+        let name = &buf[HEADER_LEN..len];
+        let name = match name.iter().position(|&b| b == 0).unwrap_or(name.len()) {
+            0 => None,
+            n => Some(OsString::from_vec(name[..n].to_vec())),
+        };
+        self.start += len;
+        Some(Event {
+            wd: WatchDescriptor(raw.wd),
+            mask: EventMask(raw.mask),
+            cookie: raw.cookie,
+            name,
+        })
+    }
+}
+
+impl AsFd for Watcher {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for Watcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// A watch added by [`Watcher::watch`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct WatchDescriptor(libc::c_int);
+
+/// An event returned by [`Watcher::next_event`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Event {
+    /// The watch of the event.
+    ///
+    /// This doesn't refer to any watch if the mask has
+    /// [`EventMask::Q_OVERFLOW`].
+    pub wd: WatchDescriptor,
+    /// The kind of the event, with some flags about it.
+    pub mask: EventMask,
+    /// A number that pairs the events of a rename.
+    ///
+    /// An event with [`EventMask::MOVED_FROM`] and one with
+    /// [`EventMask::MOVED_TO`] have the same cookie if they come from the same
+    /// rename, and the cookie is zero for other events.
+    pub cookie: u32,
+    /// The name of the file in the watched directory, or `None` if the event
+    /// is about the watched path itself.
+    pub name: Option<OsString>,
+}
+
+/// Kinds of events and options for [`Watcher::watch`].
+///
+/// Masks can be combined with the `|` operator.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct EventMask(u32);
+
+impl EventMask {
+    /// A file is read.
+    pub const ACCESS: Self = Self(libc::IN_ACCESS);
+    /// A file is written or truncated.
+    pub const MODIFY: Self = Self(libc::IN_MODIFY);
+    /// The metadata of a file changes.
+    pub const ATTRIB: Self = Self(libc::IN_ATTRIB);
+    /// A file opened for writing is closed.
+    pub const CLOSE_WRITE: Self = Self(libc::IN_CLOSE_WRITE);
+    /// A file not opened for writing is closed.
+    pub const CLOSE_NOWRITE: Self = Self(libc::IN_CLOSE_NOWRITE);
+    /// A file is closed.
+    pub const CLOSE: Self = Self(libc::IN_CLOSE);
+    /// A file is opened.
+    pub const OPEN: Self = Self(libc::IN_OPEN);
+    /// A file is renamed out of the watched directory.
+    pub const MOVED_FROM: Self = Self(libc::IN_MOVED_FROM);
+    /// A file is renamed into the watched directory.
+    pub const MOVED_TO: Self = Self(libc::IN_MOVED_TO);
+    /// A file is renamed out of or into the watched directory.
+    pub const MOVE: Self = Self(libc::IN_MOVE);
+    /// A file is created in the watched directory.
+    pub const CREATE: Self = Self(libc::IN_CREATE);
+    /// A file is removed from the watched directory.
+    pub const DELETE: Self = Self(libc::IN_DELETE);
+    /// The watched path itself is removed.
+    pub const DELETE_SELF: Self = Self(libc::IN_DELETE_SELF);
+    /// The watched path itself is renamed.
+    pub const MOVE_SELF: Self = Self(libc::IN_MOVE_SELF);
+    /// All the kinds of events above.
+    pub const ALL_EVENTS: Self = Self(libc::IN_ALL_EVENTS);
+
+    /// The filesystem of the watched path is unmounted.
+    pub const UNMOUNT: Self = Self(libc::IN_UNMOUNT);
+    /// Events are dropped because the event queue is full.
+    pub const Q_OVERFLOW: Self = Self(libc::IN_Q_OVERFLOW);
+    /// The watch is removed.
+    pub const IGNORED: Self = Self(libc::IN_IGNORED);
+    /// The event is about a directory.
+    pub const ISDIR: Self = Self(libc::IN_ISDIR);
+
+    /// Only watches the path if it is a directory.
+    pub const ONLYDIR: Self = Self(libc::IN_ONLYDIR);
+    /// Doesn't follow the path if it is a symbolic link.
+    pub const DONT_FOLLOW: Self = Self(libc::IN_DONT_FOLLOW);
+    // The values of `EXCL_UNLINK` and `MASK_ADD` are missing from libc. See
+    // also `linux/inotify.h`.
+    /// Stops watching files once they are removed from the watched directory.
+    pub const EXCL_UNLINK: Self = Self(0x0400_0000);
+    /// Adds to the mask of an existing watch instead of replacing it.
+    pub const MASK_ADD: Self = Self(0x2000_0000);
+    /// Removes the watch after its first event.
+    pub const ONESHOT: Self = Self(libc::IN_ONESHOT);
+
+    /// Returns an empty mask.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns true if all bits in `other` are contained in `self`.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for EventMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for EventMask {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
@@ -0,0 +1,76 @@
+use std::{ffi::OsString, future::Future, io::Result, os::unix::ffi::OsStringExt, path::Path};
+
+use crate::{io::raw_os_error, runtime::syscall};
+
+/// The initial buffer size to get a value or a list of names.
+const INITIAL_LEN: usize = 256;
+
+/// The maximum size of a value or a list of names.
+///
+/// See also `XATTR_SIZE_MAX` and `XATTR_LIST_MAX` in `linux/limits.h`.
+const MAX_LEN: usize = 65536;
+
+/// Gets the value of the extended attribute `name` of the file at `path`.
+///
+/// Returns `None` if the attribute doesn't exist.
+///
+/// See also `man getxattr.2`.
+pub async fn get_xattr<P: AsRef<Path>>(path: P, name: &str) -> Result<Option<Vec<u8>>> {
+    let path = path.as_ref();
+    get_with(|len| syscall::getxattr(path, name, len)).await
+}
+
+/// Sets the value of the extended attribute `name` of the file at `path`.
+///
+/// The attribute is created if it doesn't exist, and its value is replaced if
+/// it does.
+///
+/// See also `man setxattr.2`.
+pub async fn set_xattr<P: AsRef<Path>>(path: P, name: &str, value: &[u8]) -> Result<()> {
+    let path = path.as_ref();
+    syscall::setxattr(path, name, value, 0).await
+}
+
+/// Gets a value with `get`, growing the buffer if it is too small.
+pub(super) async fn get_with<F, Fut>(mut get: F) -> Result<Option<Vec<u8>>>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<Vec<u8>>>,
+{
+    match grow(&mut get).await {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if raw_os_error(&e) == Some(libc::ENODATA) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Lists names with `list`, growing the buffer if it is too small.
+pub(super) async fn list_with<F, Fut>(mut list: F) -> Result<Vec<OsString>>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<Vec<u8>>>,
+{
+    let list = grow(&mut list).await?;
+    // The names are separated by nul bytes.
+    Ok(list
+        .split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| OsString::from_vec(name.to_vec()))
+        .collect())
+}
+
+async fn grow<F, Fut>(f: &mut F) -> Result<Vec<u8>>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<Vec<u8>>>,
+{
+    let mut len = INITIAL_LEN;
+    loop {
+        match f(len).await {
+            Err(e) if raw_os_error(&e) == Some(libc::ERANGE) && len < MAX_LEN => {
+                len = (len * 4).min(MAX_LEN);
+            }
+            r => return r,
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use super::AlignedBuf;
+
+/// A buffer that can be owned by an operation.
+///
+/// The buffer is moved into the operation and handed back once the kernel is
+/// done with it, so it stays valid even if the operation is dropped before
+/// completion.
+///
+/// # Safety
+///
+/// The memory returned by [`Self::stable_ptr`] must stay valid and not move
+/// while the buffer is moved around.
+pub unsafe trait IoBuf: Send + 'static {
+    /// Returns a pointer to the start of the buffer.
+    fn stable_ptr(&self) -> *const u8;
+
+    /// Returns the number of initialized bytes of the buffer.
+    fn bytes_init(&self) -> usize;
+}
+
+/// A mutable buffer that can be owned by an operation.
+///
+/// # Safety
+///
+/// The memory returned by [`Self::stable_mut_ptr`] must be valid for
+/// [`Self::bytes_total`] bytes, and it must not move while the buffer is moved
+/// around.
+pub unsafe trait IoBufMut: IoBuf {
+    /// Returns a mutable pointer to the start of the buffer.
+    fn stable_mut_ptr(&mut self) -> *mut u8;
+
+    /// Returns the total capacity of the buffer.
+    fn bytes_total(&mut self) -> usize;
+
+    /// Marks the first `n` bytes of the buffer as initialized.
+    ///
+    /// # Safety
+    ///
+    /// The first `n` bytes must have been initialized.
+    unsafe fn set_init(&mut self, n: usize);
+}
+
+unsafe impl IoBuf for Vec<u8> {
+    fn stable_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len()
+    }
+}
+
+unsafe impl IoBufMut for Vec<u8> {
+    fn stable_mut_ptr(&mut self) -> *mut u8 {
+        self.as_mut_ptr()
+    }
+
+    fn bytes_total(&mut self) -> usize {
+        self.capacity()
+    }
+
+    unsafe fn set_init(&mut self, n: usize) {
+        if self.len() < n {
+            self.set_len(n);
+        }
+    }
+}
+
+unsafe impl IoBuf for Box<[u8]> {
+    fn stable_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len()
+    }
+}
+
+unsafe impl IoBufMut for Box<[u8]> {
+    fn stable_mut_ptr(&mut self) -> *mut u8 {
+        self.as_mut_ptr()
+    }
+
+    fn bytes_total(&mut self) -> usize {
+        self.len()
+    }
+
+    unsafe fn set_init(&mut self, _: usize) {}
+}
+
+unsafe impl IoBuf for AlignedBuf {
+    fn stable_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len()
+    }
+}
+
+unsafe impl IoBufMut for AlignedBuf {
+    fn stable_mut_ptr(&mut self) -> *mut u8 {
+        self.as_mut_ptr()
+    }
+
+    fn bytes_total(&mut self) -> usize {
+        self.len()
+    }
+
+    unsafe fn set_init(&mut self, _: usize) {}
+}
+
+unsafe impl IoBuf for &'static [u8] {
+    fn stable_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn bytes_init(&self) -> usize {
+        self.len()
+    }
+}
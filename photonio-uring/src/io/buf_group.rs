@@ -0,0 +1,225 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+    ops::Deref,
+    os::unix::io::BorrowedFd,
+    ptr, slice,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use super::{buf_ring::next_bgid, raw_os_error};
+use crate::runtime::syscall;
+
+/// A group of buffers provided to the kernel.
+///
+/// Reads with [`Self::read`] select a buffer from the group only when data is
+/// ready, so pending reads on idle descriptors don't hold any buffer. A
+/// selected buffer is provided to the kernel again when the [`SelectedBuf`] is
+/// dropped.
+///
+/// A group is registered with the worker that creates it, and it can only be
+/// used by tasks running on that worker.
+///
+/// See also `IORING_OP_PROVIDE_BUFFERS` in `man io_uring_enter.2`.
+pub struct BufGroup(Arc<Inner>);
+
+impl BufGroup {
+    /// Creates a group of `count` buffers of `buf_len` bytes each, and provides
+    /// them to the kernel through the current worker.
+    ///
+    /// Groups share their ids with [`crate::io::BufRing`]s, see
+    /// [`crate::io::BufRing::new`] for the limit.
+    pub async fn new(count: u16, buf_len: usize) -> Result<Self> {
+        if count == 0 || buf_len == 0 || buf_len > i32::MAX as usize {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid number of buffers or buffer length",
+            ));
+        }
+        let bgid = next_bgid()?;
+        let bufs = vec![0u8; count as usize * buf_len].into_boxed_slice();
+        let inner = Inner {
+            bufs: Box::into_raw(bufs) as *mut u8,
+            count,
+            buf_len,
+            bgid,
+            driver_id: syscall::driver_id()?,
+            returned: Mutex::new(Vec::new()),
+            is_closed: AtomicBool::new(false),
+        };
+        syscall::provide_buffers(inner.bufs as usize, buf_len, count, inner.bgid, 0).await?;
+        Ok(Self(Arc::new(inner)))
+    }
+
+    /// Reads from `fd` into a buffer selected from this group.
+    ///
+    /// An empty buffer means EOF.
+    ///
+    /// Returns an error of [`ErrorKind::WouldBlock`] if all buffers of this
+    /// group are in use. Buffers are provided again when they are dropped on
+    /// this worker. Buffers dropped elsewhere are provided by
+    /// [`Self::replenish`]. If the returned future is dropped, the buffer
+    /// selected by the read is provided again in the same way.
+    pub async fn read(&self, fd: BorrowedFd<'_>) -> Result<SelectedBuf> {
+        self.check_worker()?;
+        // The dropped read keeps the group alive, since the kernel might still
+        // write to the selected buffer.
+        let group = self.0.clone();
+        let discard = move |bid| group.recycle(bid);
+        match syscall::read_select(fd, self.0.buf_len, self.0.bgid, discard).await {
+            Ok((len, bid)) => Ok(SelectedBuf {
+                group: self.0.clone(),
+                bid,
+                len,
+            }),
+            Err(e) if raw_os_error(&e) == Some(libc::ENOBUFS) => Err(Error::new(
+                ErrorKind::WouldBlock,
+                "all buffers of the group are in use",
+            )),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Provides the buffers that have been returned to this group but not
+    /// provided to the kernel yet.
+    ///
+    /// Returns the number of buffers provided.
+    pub async fn replenish(&self) -> Result<usize> {
+        self.check_worker()?;
+        let returned = std::mem::take(&mut *self.0.returned.lock().unwrap());
+        for (i, &bid) in returned.iter().enumerate() {
+            let addr = self.0.buf_ptr(bid) as usize;
+            if let Err(e) =
+                syscall::provide_buffers(addr, self.0.buf_len, 1, self.0.bgid, bid).await
+            {
+                self.0.returned.lock().unwrap().extend(&returned[i..]);
+                return Err(e);
+            }
+        }
+        Ok(returned.len())
+    }
+
+    /// Returns the number of buffers in this group.
+    pub fn count(&self) -> usize {
+        self.0.count as usize
+    }
+
+    /// Returns the length of each buffer in this group.
+    pub fn buf_len(&self) -> usize {
+        self.0.buf_len
+    }
+}
+
+impl BufGroup {
+    fn check_worker(&self) -> Result<()> {
+        if self.0.driver_id != syscall::driver_id()? {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "the buffer group is registered with another worker",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BufGroup {
+    fn drop(&mut self) {
+        self.0.is_closed.store(true, Ordering::Release);
+        // If this is dropped on another worker, the buffers stay provided to
+        // the kernel, which is harmless since the group id is never used
+        // again.
+        let _ = syscall::remove_buffers_detached(self.0.driver_id, self.0.count, self.0.bgid);
+    }
+}
+
+impl fmt::Debug for BufGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufGroup")
+            .field("bgid", &self.0.bgid)
+            .field("count", &self.count())
+            .field("buf_len", &self.buf_len())
+            .finish()
+    }
+}
+
+/// A buffer selected from a [`BufGroup`].
+///
+/// The buffer is provided to the kernel again when dropped.
+pub struct SelectedBuf {
+    group: Arc<Inner>,
+    bid: Option<u16>,
+    len: usize,
+}
+
+impl Deref for SelectedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self.bid {
+            Some(bid) => unsafe { slice::from_raw_parts(self.group.buf_ptr(bid), self.len) },
+            None => &[],
+        }
+    }
+}
+
+impl Drop for SelectedBuf {
+    fn drop(&mut self) {
+        if let Some(bid) = self.bid {
+            self.group.recycle(bid);
+        }
+    }
+}
+
+impl fmt::Debug for SelectedBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SelectedBuf")
+            .field("bid", &self.bid)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+struct Inner {
+    bufs: *mut u8,
+    count: u16,
+    buf_len: usize,
+    bgid: u16,
+    driver_id: u64,
+    /// Buffers that have been dropped outside of the worker.
+    returned: Mutex<Vec<u16>>,
+    is_closed: AtomicBool,
+}
+
+// The kernel and the owner of each selected buffer access disjoint buffers.
+unsafe impl Send for Inner {}
+unsafe impl Sync for Inner {}
+
+impl Inner {
+    fn buf_ptr(&self, bid: u16) -> *mut u8 {
+        unsafe { self.bufs.add(bid as usize * self.buf_len) }
+    }
+
+    fn recycle(&self, bid: u16) {
+        if self.is_closed.load(Ordering::Acquire) {
+            return;
+        }
+        let addr = self.buf_ptr(bid) as usize;
+        if syscall::provide_buffers_detached(self.driver_id, addr, self.buf_len, 1, self.bgid, bid)
+            .is_err()
+        {
+            self.returned.lock().unwrap().push(bid);
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let len = self.count as usize * self.buf_len;
+        unsafe {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(self.bufs, len)));
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+    mem,
+    ops::Deref,
+    ptr, slice,
+    sync::{
+        atomic::{AtomicU16, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::runtime::syscall;
+
+/// A ring of buffers provided to the kernel.
+///
+/// Operations like [`crate::net::TcpStream::recv_multi`] select buffers from
+/// the ring only when data arrives, so idle connections don't hold any buffer.
+/// A selected buffer is returned to the ring when the [`RingBuf`] is dropped.
+///
+/// A ring is registered with the worker that creates it, and it can only be
+/// used by tasks running on that worker.
+pub struct BufRing(Arc<Inner>);
+
+impl BufRing {
+    /// Creates a ring of `entries` buffers of `buf_len` bytes each, and
+    /// registers it with the current worker.
+    ///
+    /// `entries` must be a power of two no more than 32768.
+    ///
+    /// Buffer group ids are never reused, so a process can create at most
+    /// 65536 rings and [`crate::io::BufGroup`]s in total.
+    ///
+    /// See also `IORING_REGISTER_PBUF_RING` in `man io_uring_register.2`.
+    pub fn new(entries: u16, buf_len: usize) -> Result<Self> {
+        if !entries.is_power_of_two() || entries > 1 << 15 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the number of entries must be a power of two no more than 32768",
+            ));
+        }
+        if buf_len == 0 || buf_len > u32::MAX as usize {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid buffer length"));
+        }
+        let mut inner = Inner::new(entries, buf_len)?;
+        inner.driver_id = Some(syscall::register_buf_ring(
+            inner.map as u64,
+            entries,
+            inner.bgid,
+        )?);
+        Ok(Self(Arc::new(inner)))
+    }
+
+    /// Returns the number of buffers in this ring.
+    pub fn entries(&self) -> usize {
+        self.0.mask as usize + 1
+    }
+
+    /// Returns the length of each buffer in this ring.
+    pub fn buf_len(&self) -> usize {
+        self.0.buf_len
+    }
+}
+
+impl BufRing {
+    /// Returns the buffer group id of this ring.
+    ///
+    /// Returns an error if the current worker is not the one that registers
+    /// this ring.
+    pub(crate) fn bgid(&self) -> Result<u16> {
+        if self.0.driver_id != Some(syscall::driver_id()?) {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "the buffer ring is registered with another worker",
+            ));
+        }
+        Ok(self.0.bgid)
+    }
+
+    /// Takes the buffer `bid` that has been selected by the kernel.
+    pub(crate) fn take(&self, bid: u16, len: usize) -> RingBuf {
+        debug_assert!(len <= self.0.buf_len);
+        RingBuf {
+            ring: self.0.clone(),
+            bid: Some(bid),
+            len,
+        }
+    }
+
+    /// Returns an empty buffer that doesn't belong to the ring.
+    pub(crate) fn empty(&self) -> RingBuf {
+        RingBuf {
+            ring: self.0.clone(),
+            bid: None,
+            len: 0,
+        }
+    }
+
+    /// Returns a function that returns a buffer to this ring.
+    pub(crate) fn recycler(&self) -> impl FnMut(u16) + Send + 'static {
+        let ring = self.0.clone();
+        move |bid| ring.recycle(bid)
+    }
+}
+
+impl fmt::Debug for BufRing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufRing")
+            .field("bgid", &self.0.bgid)
+            .field("entries", &self.entries())
+            .field("buf_len", &self.buf_len())
+            .finish()
+    }
+}
+
+/// A buffer selected from a [`BufRing`].
+///
+/// The buffer is returned to the ring when dropped.
+pub struct RingBuf {
+    ring: Arc<Inner>,
+    bid: Option<u16>,
+    len: usize,
+}
+
+impl Deref for RingBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self.bid {
+            Some(bid) => unsafe { slice::from_raw_parts(self.ring.buf_ptr(bid), self.len) },
+            None => &[],
+        }
+    }
+}
+
+impl Drop for RingBuf {
+    fn drop(&mut self) {
+        if let Some(bid) = self.bid {
+            self.ring.recycle(bid);
+        }
+    }
+}
+
+impl fmt::Debug for RingBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RingBuf")
+            .field("bid", &self.bid)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+/// Returns a new buffer group id.
+///
+/// Buffer group ids are shared by buffer rings and buffer groups, and they are
+/// not reused, so that a stale registration can't be confused with a new one.
+/// Returns an error once all 65536 ids have been handed out.
+pub(super) fn next_bgid() -> Result<u16> {
+    static NEXT_BGID: AtomicU32 = AtomicU32::new(0);
+    NEXT_BGID
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bgid| {
+            (bgid <= u16::MAX as u32).then_some(bgid + 1)
+        })
+        .map(|bgid| bgid as u16)
+        .map_err(|_| Error::new(ErrorKind::Other, "buffer group ids are exhausted"))
+}
+
+/// See also `struct io_uring_buf` in `man io_uring_register.2`.
+#[repr(C)]
+struct RawBuf {
+    addr: u64,
+    len: u32,
+    bid: u16,
+    // The `resv` field of the first entry is the tail of the ring.
+    resv: u16,
+}
+
+struct Inner {
+    // The ring entries followed by the buffers, mapped at once.
+    map: *mut u8,
+    map_len: usize,
+    bufs: *mut u8,
+    buf_len: usize,
+    mask: u16,
+    bgid: u16,
+    driver_id: Option<u64>,
+    tail: Mutex<u16>,
+}
+
+// The kernel and the owner of each selected buffer access disjoint parts of the
+// mapping, and the tail is protected by the mutex.
+unsafe impl Send for Inner {}
+unsafe impl Sync for Inner {}
+
+impl Inner {
+    fn new(entries: u16, buf_len: usize) -> Result<Self> {
+        let bgid = next_bgid()?;
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let ring_len = entries as usize * mem::size_of::<RawBuf>();
+        let ring_len = (ring_len + page_size - 1) / page_size * page_size;
+        let map_len = ring_len + entries as usize * buf_len;
+        let map = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                map_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(Error::last_os_error());
+        }
+        let inner = Self {
+            map: map as *mut u8,
+            map_len,
+            bufs: unsafe { (map as *mut u8).add(ring_len) },
+            buf_len,
+            mask: entries - 1,
+            bgid,
+            driver_id: None,
+            tail: Mutex::new(0),
+        };
+        for bid in 0..entries {
+            inner.recycle(bid);
+        }
+        Ok(inner)
+    }
+
+    fn buf_ptr(&self, bid: u16) -> *mut u8 {
+        unsafe { self.bufs.add(bid as usize * self.buf_len) }
+    }
+
+    /// Returns the buffer `bid` to the ring.
+    fn recycle(&self, bid: u16) {
+        let mut tail = self.tail.lock().unwrap();
+        unsafe {
+            let ring = self.map as *mut RawBuf;
+            let entry = ring.add((*tail & self.mask) as usize);
+            // Don't touch `resv`, which might be the tail of the ring.
+            ptr::addr_of_mut!((*entry).addr).write(self.buf_ptr(bid) as u64);
+            ptr::addr_of_mut!((*entry).len).write(self.buf_len as u32);
+            ptr::addr_of_mut!((*entry).bid).write(bid);
+            *tail = tail.wrapping_add(1);
+            // Publishes the entry to the kernel.
+            let ring_tail = &*(ptr::addr_of!((*ring).resv) as *const AtomicU16);
+            ring_tail.store(*tail, Ordering::Release);
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(driver_id) = self.driver_id {
+            // If this is dropped on another worker, or while the driver is in
+            // use, the registration can't be removed and the kernel may still
+            // write to the ring. The mapping is leaked then, instead of being
+            // unmapped under the kernel.
+            if syscall::unregister_buf_ring(driver_id, self.bgid).is_err() {
+                return;
+            }
+        }
+        unsafe {
+            libc::munmap(self.map as _, self.map_len);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    io::Result,
+    mem::ManuallyDrop,
+    ops::Deref,
+    os::fd::{FromRawFd, IntoRawFd, OwnedFd},
+};
+
+use crate::runtime::syscall;
+
+/// An owned descriptor that is closed by the runtime when it is dropped.
+///
+/// Dropping this on a worker thread submits a close to the worker without
+/// waiting for it, so the worker doesn't block on a slow close, like the last
+/// close of a file on NFS that flushes its data. Elsewhere, for example after
+/// the runtime is gone, the descriptor is closed synchronously. Use
+/// [`Self::close`] to observe the errors of the close.
+#[derive(Debug)]
+pub(crate) struct CloseOnDrop<T: IntoRawFd>(ManuallyDrop<T>);
+
+impl<T: IntoRawFd> CloseOnDrop<T> {
+    pub(crate) fn new(fd: T) -> Self {
+        Self(ManuallyDrop::new(fd))
+    }
+
+    /// Returns the descriptor without closing it.
+    pub(crate) fn into_inner(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+        unsafe { ManuallyDrop::take(&mut this.0) }
+    }
+
+    /// Closes the descriptor and waits for the result.
+    pub(crate) async fn close(self) -> Result<()> {
+        let fd = unsafe { OwnedFd::from_raw_fd(self.into_inner().into_raw_fd()) };
+        syscall::close(fd).await
+    }
+}
+
+impl<T: IntoRawFd> Deref for CloseOnDrop<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: IntoRawFd> Drop for CloseOnDrop<T> {
+    fn drop(&mut self) {
+        let fd = unsafe { ManuallyDrop::take(&mut self.0) }.into_raw_fd();
+        syscall::close_detached(unsafe { OwnedFd::from_raw_fd(fd) });
+    }
+}
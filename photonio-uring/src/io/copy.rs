@@ -0,0 +1,362 @@
+use std::{
+    future::{poll_fn, Future},
+    io::{ErrorKind, Result},
+    mem,
+    ops::Range,
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::future::try_join;
+use photonio_base::io as base;
+
+use super::{is_socket, pipe, raw_os_error, Opcode, Read, Write};
+use crate::runtime::syscall;
+
+/// The default buffer size of [`copy`].
+const DEFAULT_BUF_SIZE: usize = 64 << 10;
+
+/// Copies all bytes from `reader` to `writer` until EOF.
+///
+/// If both objects are backed by descriptors, like [`crate::fs::File`] and
+/// [`crate::net::TcpStream`], the data is moved within the kernel through a
+/// pipe with `splice`. Otherwise, or if the descriptors don't support
+/// splicing, the data is copied through a buffer of 64 KiB. See
+/// [`copy_with_buf_size`] to change it.
+///
+/// Returns the number of bytes copied. If an error occurs, the error is
+/// returned instead, so the number of bytes copied before, including those
+/// of a partial write, is not reported.
+///
+/// See also [`std::io::copy`].
+pub async fn copy<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> Result<u64> {
+    copy_with_buf_size(reader, writer, DEFAULT_BUF_SIZE).await
+}
+
+/// Copies all bytes from `reader` to `writer` in chunks of at most
+/// `buf_size` bytes.
+///
+/// Returns an error of [`ErrorKind::InvalidInput`] if `buf_size` is zero.
+///
+/// See also [`copy`].
+pub async fn copy_with_buf_size<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    buf_size: usize,
+) -> Result<u64> {
+    if buf_size > 0 {
+        if let Some(copied) = splice_all(reader, writer, buf_size).await? {
+            return Ok(copied);
+        }
+    }
+    base::copy_with_buf_size(reader, writer, buf_size).await
+}
+
+/// Moves all bytes from `reader` to `writer` through a pipe.
+///
+/// Returns `None` before anything is moved if splicing is not possible.
+async fn splice_all<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    len: usize,
+) -> Result<Option<u64>> {
+    if !syscall::is_supported(Opcode::Splice) {
+        return Ok(None);
+    }
+    let (fd_in, mut pos_in) = match reader.splice_source() {
+        Some(source) => source,
+        None => return Ok(None),
+    };
+    let (fd_out, mut pos_out) = match writer.splice_sink() {
+        Some(sink) => sink,
+        None => return Ok(None),
+    };
+    // The descriptors are borrowed from the objects until this returns.
+    let fd_in = unsafe { BorrowedFd::borrow_raw(fd_in) };
+    let fd_out = unsafe { BorrowedFd::borrow_raw(fd_out) };
+    let (pipe_in, pipe_out) = pipe()?;
+    let len = len.min(u32::MAX as usize) as u32;
+
+    let mut copied = 0;
+    loop {
+        let off_in = pos_in.as_deref().map_or(-1, |&pos| pos as _);
+        let n = match syscall::splice(fd_in, off_in, pipe_out.as_fd(), -1, len, 0).await {
+            Ok(0) => return Ok(Some(copied)),
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            // Some files can't be spliced from, like those of some virtual
+            // filesystems.
+            Err(e) if copied == 0 && raw_os_error(&e) == Some(libc::EINVAL) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if let Some(pos) = pos_in.as_deref_mut() {
+            *pos += n as u64;
+        }
+        let mut rem = n;
+        while rem > 0 {
+            let off_out = pos_out.as_deref().map_or(-1, |&pos| pos as _);
+            match syscall::splice(pipe_in.as_fd(), -1, fd_out, off_out, rem as u32, 0).await {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(m) => {
+                    if let Some(pos) = pos_out.as_deref_mut() {
+                        *pos += m as u64;
+                    }
+                    rem -= m;
+                    copied += m as u64;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Copies data in both directions between `a` and `b` until both directions
+/// reach EOF.
+///
+/// Both directions are copied concurrently within the returned future. Once
+/// one side reaches EOF, the write side of the other is shut down, so that its
+/// peer reads EOF too, while the other direction goes on. The future completes
+/// when both directions are done, or as soon as either fails.
+///
+/// The future can be dropped at any time, which cancels the pending
+/// operations without waiting. Data that is read but not written yet is lost
+/// in that case.
+///
+/// If both objects are streams backed by descriptors, like
+/// [`crate::net::TcpStream`] and pipes, the descriptors are read and written
+/// directly. Otherwise, the data is copied through the [`Read`] and [`Write`]
+/// methods of the objects, which can only run one at a time on each object.
+/// Writes to an object take precedence over reads from it, but a pending read
+/// delays the data for the object until the read completes.
+///
+/// Returns the number of bytes copied from `a` to `b` and from `b` to `a`.
+pub async fn copy_bidirectional<A, B>(a: &mut A, b: &mut B) -> Result<(u64, u64)>
+where
+    A: Read + Write,
+    B: Read + Write,
+{
+    if let (Some(fd_a), Some(fd_b)) = (stream_fd(a), stream_fd(b)) {
+        // The descriptors are borrowed from the objects until this returns.
+        let fd_a = unsafe { BorrowedFd::borrow_raw(fd_a) };
+        let fd_b = unsafe { BorrowedFd::borrow_raw(fd_b) };
+        return try_join(pump(fd_a, fd_b), pump(fd_b, fd_a)).await;
+    }
+    pump_objects(a, b).await
+}
+
+/// Returns the descriptor that `stream` reads from and writes to, if it has
+/// no position in user space.
+fn stream_fd<T: Read + Write>(stream: &mut T) -> Option<RawFd> {
+    let fd = match stream.splice_source() {
+        Some((fd, None)) => fd,
+        _ => return None,
+    };
+    match stream.splice_sink() {
+        Some((sink, None)) if sink == fd => Some(fd),
+        _ => None,
+    }
+}
+
+/// Copies all bytes from `from` to `to`, and then shuts down the write side of
+/// `to`.
+async fn pump(from: BorrowedFd<'_>, to: BorrowedFd<'_>) -> Result<u64> {
+    let to_socket = is_socket(to.as_raw_fd())?;
+    let mut buf = vec![0; DEFAULT_BUF_SIZE];
+    let mut copied = 0;
+    loop {
+        let n = match syscall::read(from, &mut buf).await {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let mut written = 0;
+        while written < n {
+            let rest = &buf[written..n];
+            // Returns `EPIPE` instead of raising `SIGPIPE` if the peer is closed.
+            let result = if to_socket {
+                syscall::send(to, rest, libc::MSG_NOSIGNAL).await
+            } else {
+                syscall::write(to, rest).await
+            };
+            match result {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(m) => written += m,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        copied += n as u64;
+    }
+    match syscall::shutdown(to, libc::SHUT_WR).await {
+        // Pipes can't be shut down, and the peer might be gone already.
+        Err(e) if !matches!(raw_os_error(&e), Some(libc::ENOTSOCK | libc::ENOTCONN)) => Err(e),
+        _ => Ok(copied),
+    }
+}
+
+/// Copies data in both directions between `a` and `b` through their [`Read`]
+/// and [`Write`] methods, see [`copy_bidirectional`].
+async fn pump_objects<A, B>(a: &mut A, b: &mut B) -> Result<(u64, u64)>
+where
+    A: Read + Write,
+    B: Read + Write,
+{
+    let mut a_to_b = Flow::new();
+    let mut b_to_a = Flow::new();
+    let mut a = Slot::Idle(a);
+    let mut b = Slot::Idle(b);
+    poll_fn(|cx| loop {
+        // A completion on one object might allow the other to go on.
+        let a_progress = a.poll(cx, |obj, step| run(obj, step), &mut b_to_a, &mut a_to_b)?;
+        let b_progress = b.poll(cx, |obj, step| run(obj, step), &mut a_to_b, &mut b_to_a)?;
+        if a_to_b.is_done && b_to_a.is_done {
+            return Poll::Ready(Ok((a_to_b.copied, b_to_a.copied)));
+        }
+        if !a_progress && !b_progress {
+            return Poll::Pending;
+        }
+    })
+    .await
+}
+
+/// The data in flight from one object to the other.
+struct Flow {
+    // The buffer, unless an operation owns it.
+    buf: Option<Vec<u8>>,
+    // The part of the buffer that is read but not written yet.
+    pending: Range<usize>,
+    is_eof: bool,
+    // Whether the write side of the destination is shut down.
+    is_done: bool,
+    copied: u64,
+}
+
+impl Flow {
+    fn new() -> Self {
+        Self {
+            buf: Some(vec![0; DEFAULT_BUF_SIZE]),
+            pending: 0..0,
+            is_eof: false,
+            is_done: false,
+            copied: 0,
+        }
+    }
+}
+
+/// An operation on one object, which owns the buffer it uses.
+enum Step {
+    Read(Vec<u8>),
+    Write(Vec<u8>, Range<usize>),
+    Shutdown,
+}
+
+impl Step {
+    /// Returns the next operation on an object that writes the data of `input`
+    /// and reads the data of `output`.
+    fn next(input: &mut Flow, output: &mut Flow) -> Option<Self> {
+        if !input.pending.is_empty() {
+            return input
+                .buf
+                .take()
+                .map(|buf| Self::Write(buf, input.pending.clone()));
+        }
+        if input.is_eof && !input.is_done {
+            return Some(Self::Shutdown);
+        }
+        if !output.is_eof && output.pending.is_empty() {
+            return output.buf.take().map(Self::Read);
+        }
+        None
+    }
+
+    /// Applies the result of this operation to the flows of its object.
+    fn finish(self, result: Result<usize>, input: &mut Flow, output: &mut Flow) -> Result<()> {
+        let (n, interrupted) = match result {
+            Ok(n) => (n, false),
+            Err(e) if e.kind() == ErrorKind::Interrupted => (0, true),
+            Err(e) => return Err(e),
+        };
+        match self {
+            Self::Read(buf) => {
+                output.buf = Some(buf);
+                output.pending = 0..n;
+                output.is_eof = n == 0 && !interrupted;
+            }
+            Self::Write(buf, pending) => {
+                if n == 0 && !interrupted {
+                    return Err(ErrorKind::WriteZero.into());
+                }
+                input.buf = Some(buf);
+                input.pending = pending.start + n..pending.end;
+                input.copied += n as u64;
+            }
+            Self::Shutdown => input.is_done = !interrupted,
+        }
+        Ok(())
+    }
+}
+
+/// Runs `step` on `obj`, and returns the object back with the step.
+async fn run<T: Read + Write>(obj: &mut T, mut step: Step) -> (&mut T, Step, Result<usize>) {
+    let result = match &mut step {
+        Step::Read(buf) => obj.read(buf).await,
+        Step::Write(buf, pending) => obj.write(&buf[pending.clone()]).await,
+        Step::Shutdown => match obj.flush().await {
+            Ok(()) => obj.shutdown().await.map(|_| 0),
+            Err(e) => Err(e),
+        },
+    };
+    (obj, step, result)
+}
+
+/// An object of [`pump_objects`], with the operation in flight on it, if any.
+enum Slot<'a, T, F> {
+    Idle(&'a mut T),
+    Busy(Pin<Box<F>>),
+    // Only seen while switching between the other states.
+    Empty,
+}
+
+impl<'a, T, F> Slot<'a, T, F>
+where
+    F: Future<Output = (&'a mut T, Step, Result<usize>)>,
+{
+    /// Runs operations on the object until it waits, with `start`.
+    ///
+    /// Returns true if any operation completes.
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+        start: impl Fn(&'a mut T, Step) -> F,
+        input: &mut Flow,
+        output: &mut Flow,
+    ) -> Result<bool> {
+        let mut progress = false;
+        loop {
+            match mem::replace(self, Self::Empty) {
+                Self::Busy(mut op) => match op.as_mut().poll(cx) {
+                    Poll::Ready((obj, step, result)) => {
+                        *self = Self::Idle(obj);
+                        step.finish(result, input, output)?;
+                        progress = true;
+                    }
+                    Poll::Pending => {
+                        *self = Self::Busy(op);
+                        return Ok(progress);
+                    }
+                },
+                Self::Idle(obj) => match Step::next(input, output) {
+                    Some(step) => *self = Self::Busy(Box::pin(start(obj, step))),
+                    None => {
+                        *self = Self::Idle(obj);
+                        return Ok(progress);
+                    }
+                },
+                Self::Empty => unreachable!(),
+            }
+        }
+    }
+}
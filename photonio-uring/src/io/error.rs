@@ -0,0 +1,139 @@
+use std::{error, fmt, io::Error, os::unix::io::RawFd};
+
+use io_uring::squeue;
+
+use super::Opcode;
+use crate::runtime::raw::RawSqe;
+
+/// The error of an operation that fails in the kernel.
+///
+/// Operations submitted to the ring return an [`Error`] with this as its
+/// payload, so that the error message shows what failed, like
+/// `write(fd=12, len=4096, off=81920): Input/output error (os error 5)`.
+/// The [`std::io::ErrorKind`] of the error is the same as that of the OS
+/// error.
+///
+/// Since the OS error is wrapped, [`Error::raw_os_error`] returns `None` for
+/// these errors. Use [`raw_os_error`] to get the code of either kind of error,
+/// or downcast the payload with [`Error::get_ref`] to get the context.
+#[derive(Debug)]
+pub struct OpError {
+    opcode: Opcode,
+    fd: Option<RawFd>,
+    is_fixed: bool,
+    len: Option<u32>,
+    offset: Option<u64>,
+    source: Error,
+}
+
+impl OpError {
+    /// Returns the operation that fails.
+    pub fn opcode(&self) -> Opcode {
+        self.opcode
+    }
+
+    /// Returns the file descriptor of the operation, if it operates on one.
+    ///
+    /// This is the slot in the fixed file table if [`Self::is_fixed_file`]
+    /// returns true.
+    pub fn fd(&self) -> Option<RawFd> {
+        self.fd
+    }
+
+    /// Returns true if the operation refers to a fixed file.
+    pub fn is_fixed_file(&self) -> bool {
+        self.is_fixed
+    }
+
+    /// Returns the number of bytes requested, if applicable.
+    pub fn byte_count(&self) -> Option<u32> {
+        self.len
+    }
+
+    /// Returns the file offset of the operation, if applicable.
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    /// Returns the OS error code of the operation.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.source.raw_os_error()
+    }
+}
+
+impl OpError {
+    /// Wraps the error of `sqe` with its context.
+    ///
+    /// Returns the error as it is for opcodes that are not known.
+    pub(crate) fn wrap(sqe: &squeue::Entry, err: Error) -> Error {
+        let raw = RawSqe::from_entry(sqe);
+        let opcode = match Opcode::from_code(raw.opcode) {
+            Some(opcode) => opcode,
+            None => return err,
+        };
+        let (fd, len, offset) = fields(opcode);
+        let kind = err.kind();
+        Error::new(
+            kind,
+            Self {
+                opcode,
+                fd: fd.then_some(raw.fd),
+                is_fixed: fd && raw.flags & squeue::Flags::FIXED_FILE.bits() != 0,
+                len: len.then_some(raw.len),
+                // An offset of -1 means the current file position.
+                offset: offset.then_some(raw.off).filter(|&off| off != u64::MAX),
+                source: err,
+            },
+        )
+    }
+}
+
+impl fmt::Display for OpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.opcode.name().trim_start_matches("IORING_OP_");
+        write!(f, "{}(", name.to_ascii_lowercase())?;
+        let mut sep = "";
+        if let Some(fd) = self.fd {
+            let key = if self.is_fixed { "fixed" } else { "fd" };
+            write!(f, "{key}={fd}")?;
+            sep = ", ";
+        }
+        if let Some(len) = self.len {
+            write!(f, "{sep}len={len}")?;
+            sep = ", ";
+        }
+        if let Some(off) = self.offset {
+            write!(f, "{sep}off={off}")?;
+        }
+        write!(f, "): {}", self.source)
+    }
+}
+
+impl error::Error for OpError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Returns the OS error code of `err`, looking through [`OpError`].
+pub fn raw_os_error(err: &Error) -> Option<i32> {
+    err.raw_os_error().or_else(|| {
+        err.get_ref()
+            .and_then(|e| e.downcast_ref::<OpError>())
+            .and_then(OpError::raw_os_error)
+    })
+}
+
+/// Returns whether the fd, length, and offset fields of `opcode` are
+/// meaningful.
+fn fields(opcode: Opcode) -> (bool, bool, bool) {
+    use Opcode::*;
+    match opcode {
+        Read | Write | ReadFixed | WriteFixed => (true, true, true),
+        Readv | Writev | Fallocate | Fadvise | SyncFileRange => (true, false, true),
+        Send | Recv | SendZc => (true, true, false),
+        Accept | Close | Connect | FGetXattr | FSetXattr | Fsync | MsgRing | PollAdd | RecvMsg
+        | SendMsg | Shutdown | Splice | Tee | UringCmd => (true, false, false),
+        _ => (false, false, false),
+    }
+}
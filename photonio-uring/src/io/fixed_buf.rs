@@ -0,0 +1,180 @@
+use std::{
+    alloc::{self, Layout},
+    fmt,
+    io::{Error, ErrorKind, Result},
+    ops::{Deref, DerefMut},
+    slice,
+    sync::{Arc, Mutex},
+};
+
+use crate::runtime::syscall;
+
+/// The alignment of fixed buffers, which is enough for `O_DIRECT`.
+const ALIGN: usize = 4096;
+
+/// A pool of buffers registered with the kernel.
+///
+/// Fixed operations like [`crate::fs::File::read_at_fixed`] use registered
+/// buffers, which saves the kernel from mapping the buffers on every
+/// operation.
+///
+/// A pool is registered with the worker that creates it, and its buffers can
+/// only be used by tasks running on that worker. A worker holds at most one
+/// pool at a time. If a pool is dropped on another worker, its registration is
+/// leaked until that worker exits.
+///
+/// Each buffer is aligned to 4 KiB, so it can be used with `O_DIRECT`.
+///
+/// See also `IORING_REGISTER_BUFFERS` in `man io_uring_register.2`.
+#[derive(Clone)]
+pub struct FixedBufPool(Arc<Inner>);
+
+impl FixedBufPool {
+    /// Creates a pool of `count` buffers of `buf_len` bytes each, and
+    /// registers it with the current worker.
+    pub fn new(count: u16, buf_len: usize) -> Result<Self> {
+        if count == 0 || buf_len == 0 || buf_len > u32::MAX as usize {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid number of buffers or buffer length",
+            ));
+        }
+        let layout = Layout::from_size_align(buf_len, ALIGN)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        let mut inner = Inner {
+            bufs: Vec::with_capacity(count as usize),
+            layout,
+            driver_id: None,
+            free: Mutex::new((0..count).rev().collect()),
+        };
+        for _ in 0..count {
+            let ptr = unsafe { alloc::alloc_zeroed(layout) };
+            if ptr.is_null() {
+                alloc::handle_alloc_error(layout);
+            }
+            inner.bufs.push(ptr);
+        }
+        let iovecs: Vec<_> = inner
+            .bufs
+            .iter()
+            .map(|&ptr| libc::iovec {
+                iov_base: ptr as _,
+                iov_len: buf_len,
+            })
+            .collect();
+        inner.driver_id = Some(syscall::register_buffers(&iovecs)?);
+        Ok(Self(Arc::new(inner)))
+    }
+
+    /// Takes a free buffer from this pool.
+    ///
+    /// Returns `None` if all buffers are in use. A buffer is returned to the
+    /// pool when the [`FixedBuf`] is dropped.
+    pub fn get(&self) -> Option<FixedBuf> {
+        let index = self.0.free.lock().unwrap().pop()?;
+        Some(FixedBuf {
+            pool: self.0.clone(),
+            index,
+        })
+    }
+
+    /// Returns the number of buffers in this pool.
+    pub fn count(&self) -> usize {
+        self.0.bufs.len()
+    }
+
+    /// Returns the length of each buffer in this pool.
+    pub fn buf_len(&self) -> usize {
+        self.0.layout.size()
+    }
+}
+
+impl fmt::Debug for FixedBufPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixedBufPool")
+            .field("count", &self.count())
+            .field("buf_len", &self.buf_len())
+            .finish()
+    }
+}
+
+/// A buffer taken from a [`FixedBufPool`].
+///
+/// Fixed operations always transfer the whole buffer.
+///
+/// The buffer is returned to the pool when dropped.
+pub struct FixedBuf {
+    pool: Arc<Inner>,
+    index: u16,
+}
+
+impl FixedBuf {
+    /// Returns the index of this buffer in the registration.
+    ///
+    /// Returns an error if the current worker is not the one that registers
+    /// the pool.
+    pub(crate) fn index(&self) -> Result<u16> {
+        if self.pool.driver_id != Some(syscall::driver_id()?) {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "the fixed buffer is registered with another worker",
+            ));
+        }
+        Ok(self.index)
+    }
+}
+
+impl Deref for FixedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        let ptr = self.pool.bufs[self.index as usize];
+        unsafe { slice::from_raw_parts(ptr, self.pool.layout.size()) }
+    }
+}
+
+impl DerefMut for FixedBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let ptr = self.pool.bufs[self.index as usize];
+        unsafe { slice::from_raw_parts_mut(ptr, self.pool.layout.size()) }
+    }
+}
+
+impl Drop for FixedBuf {
+    fn drop(&mut self) {
+        self.pool.free.lock().unwrap().push(self.index);
+    }
+}
+
+impl fmt::Debug for FixedBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixedBuf")
+            .field("index", &self.index)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+struct Inner {
+    bufs: Vec<*mut u8>,
+    layout: Layout,
+    driver_id: Option<u64>,
+    free: Mutex<Vec<u16>>,
+}
+
+// Each buffer is owned by at most one `FixedBuf` at a time.
+unsafe impl Send for Inner {}
+unsafe impl Sync for Inner {}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(driver_id) = self.driver_id {
+            // The kernel keeps its own references to the registered pages, so
+            // freeing the buffers is fine even if this fails.
+            let _ = syscall::unregister_buffers(driver_id);
+        }
+        for &ptr in &self.bufs {
+            unsafe { alloc::dealloc(ptr, self.layout) };
+        }
+    }
+}
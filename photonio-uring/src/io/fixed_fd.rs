@@ -0,0 +1,86 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    os::unix::io::{AsRawFd, BorrowedFd, RawFd},
+};
+
+use crate::runtime::syscall;
+
+/// Registers `fds` in the fixed file table of the current worker.
+///
+/// Operations on a fixed file refer to its slot in the table, which saves the
+/// kernel from looking up the descriptor on every operation. The table holds
+/// its own references to the files, so the descriptors can be closed after
+/// registration.
+///
+/// Fixed files are registered with the current worker, and they can only be
+/// used by tasks running on that worker. Each worker has a table of 1024 slots
+/// by default, see [`crate::runtime::Builder::fixed_files`]. Returns an error
+/// if there are not enough free slots for all `fds`, in which case none of them
+/// is registered.
+///
+/// See also `IORING_REGISTER_FILES` in `man io_uring_register.2`.
+pub fn register_files(fds: &[BorrowedFd<'_>]) -> Result<Vec<FixedFd>> {
+    let fds: Vec<RawFd> = fds.iter().map(|fd| fd.as_raw_fd()).collect();
+    let (driver_id, slots) = syscall::register_files(&fds)?;
+    Ok(slots
+        .into_iter()
+        .map(|slot| FixedFd { driver_id, slot })
+        .collect())
+}
+
+/// A file registered in the fixed file table of a worker.
+///
+/// The slot is freed when this is dropped on the worker that registers it.
+/// If this is dropped on another worker, the slot is leaked until that worker
+/// exits.
+#[derive(Debug)]
+pub struct FixedFd {
+    driver_id: u64,
+    slot: u32,
+}
+
+impl FixedFd {
+    /// Replaces the registered file with `fd`.
+    pub fn update(&self, fd: BorrowedFd<'_>) -> Result<()> {
+        syscall::update_file(self.driver_id, self.slot, fd.as_raw_fd())
+    }
+
+    /// Frees the slot of this file.
+    ///
+    /// This function is similar to dropping the file, except that it returns
+    /// the error if any.
+    pub fn unregister(self) -> Result<()> {
+        let result = syscall::unregister_file(self.driver_id, self.slot);
+        std::mem::forget(self);
+        result
+    }
+}
+
+impl FixedFd {
+    /// Allocates a free slot of the current worker, for an operation to
+    /// install a direct descriptor into.
+    pub(crate) fn alloc() -> Result<Self> {
+        let (driver_id, slot) = syscall::alloc_file()?;
+        Ok(Self { driver_id, slot })
+    }
+
+    /// Returns the slot of this file.
+    ///
+    /// Returns an error if the current worker is not the one that registers
+    /// this file.
+    pub(crate) fn slot(&self) -> Result<u32> {
+        if self.driver_id != syscall::driver_id()? {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "the fixed file is registered with another worker",
+            ));
+        }
+        Ok(self.slot)
+    }
+}
+
+impl Drop for FixedFd {
+    fn drop(&mut self) {
+        let _ = syscall::unregister_file(self.driver_id, self.slot);
+    }
+}
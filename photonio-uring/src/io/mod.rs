@@ -2,4 +2,234 @@
 //!
 //! This module is an async version of [`std::io`].
 
+use std::{
+    future::Future,
+    io::ErrorKind,
+    os::unix::io::{BorrowedFd, RawFd},
+    sync::atomic::AtomicU32,
+};
+
+#[doc(no_inline)]
+pub use io_uring;
+use io_uring::squeue;
 pub use photonio_base::io::*;
+
+use crate::runtime::syscall;
+
+mod buf;
+pub use buf::{IoBuf, IoBufMut};
+
+mod pipe;
+pub use pipe::{pipe, PipeReader, PipeWriter};
+
+mod copy;
+pub use copy::{copy, copy_bidirectional, copy_with_buf_size};
+
+mod buf_ring;
+pub use buf_ring::{BufRing, RingBuf};
+
+mod buf_group;
+pub use buf_group::{BufGroup, SelectedBuf};
+
+mod fixed_buf;
+pub use fixed_buf::{FixedBuf, FixedBufPool};
+
+mod fixed_fd;
+pub use fixed_fd::{register_files, FixedFd};
+
+mod close_on_drop;
+pub(crate) use close_on_drop::CloseOnDrop;
+
+mod opcode;
+pub use opcode::{is_supported, Opcode};
+
+mod error;
+pub use error::{raw_os_error, OpError};
+
+mod priority;
+pub(crate) use priority::apply as apply_io_priority;
+pub use priority::{with_io_priority, IoPriority, IoPriorityClass, WithIoPriority};
+
+mod timeout;
+pub use timeout::{ReadTimeoutExt, WriteTimeoutExt};
+
+/// Submits an operation that does nothing, and waits for its completion.
+///
+/// The operation goes through the ring like any other, so this measures the
+/// round trip latency of the current worker.
+pub async fn nop() -> Result<()> {
+    syscall::nop().await
+}
+
+/// Submits the pending operations of the current worker to the kernel now.
+///
+/// Operations are batched and submitted when the worker runs out of tasks to
+/// poll or after polling a number of tasks. This pushes them to the kernel
+/// immediately instead, without waiting for completions.
+///
+/// Returns the number of operations submitted.
+pub fn submit_now() -> Result<usize> {
+    syscall::submit_now()
+}
+
+/// Submits a custom entry to the current worker, and waits for its completion.
+///
+/// This is an escape hatch for operations that the runtime doesn't wrap yet.
+/// The entry is built with the re-exported [`io_uring`] crate. Returns the raw
+/// result of the completion, which is a negated `errno` on failure. Returns an
+/// error only if the entry can't be submitted, for example if this is not
+/// called on a worker.
+///
+/// Unlike other operations, the entry is not resubmitted if it is interrupted.
+///
+/// # Safety
+///
+/// The caller must guarantee that:
+///
+/// - The entry is valid and produces exactly one completion. Multishot entries
+///   are not allowed.
+/// - Any memory the entry refers to remains valid until the entry completes.
+///   Dropping the future cancels the entry without waiting for the
+///   cancellation, so the memory must outlive the future in that case.
+/// - The entry doesn't set `IOSQE_IO_LINK`, since the next entry in the queue
+///   is unrelated, and fixed files or buffers it refers to are registered with
+///   the current worker.
+///
+/// The `user_data` of the entry is overwritten by the runtime to track the
+/// completion.
+pub unsafe fn submit_raw(sqe: squeue::Entry) -> impl Future<Output = Result<i32>> {
+    async move { syscall::submit_raw(sqe).await.map(|(res, _)| res) }
+}
+
+/// This function is similar to [`submit_raw`], except that it also returns the
+/// `IORING_CQE_F_*` flags of the completion, like the id of the selected
+/// buffer.
+///
+/// # Safety
+///
+/// See [`submit_raw`].
+pub unsafe fn submit_raw_with_cqe_flags(
+    sqe: squeue::Entry,
+) -> impl Future<Output = Result<(i32, u32)>> {
+    async move { syscall::submit_raw(sqe).await }
+}
+
+/// Submits the passthrough command `cmd_op` to the driver of `fd`.
+///
+/// `cmd` is copied into the SQE, and it can be at most 80 bytes. Returns the
+/// result of the command and the extra 16 bytes of the CQE, which are zeros
+/// unless the runtime is set up with [`crate::runtime::Builder::cqe32`].
+///
+/// Returns an error of [`ErrorKind::InvalidInput`] if `cmd` is too long or if
+/// the runtime is not set up with [`crate::runtime::Builder::sqe128`].
+///
+/// See also `IORING_OP_URING_CMD` in `man io_uring_enter.2`.
+///
+/// # Safety
+///
+/// The caller must guarantee that `cmd` is a valid command for `cmd_op` of the
+/// driver, and that any memory `cmd` refers to remains valid until the
+/// command completes, even if the returned future is dropped before.
+pub unsafe fn uring_cmd<'a>(
+    fd: BorrowedFd<'a>,
+    cmd_op: u32,
+    cmd: &[u8],
+) -> impl Future<Output = Result<(u32, [u64; 2])>> + 'a {
+    let len = cmd.len();
+    let mut buf = [0; 80];
+    if len <= buf.len() {
+        buf[..len].copy_from_slice(cmd);
+    }
+    async move {
+        if len > buf.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the command is longer than 80 bytes",
+            ));
+        }
+        syscall::uring_cmd(fd, cmd_op, buf).await
+    }
+}
+
+/// Waits on the futex `word` if it still equals `expected`.
+///
+/// The wait ends when a [`futex_wake`] with an overlapping `mask` wakes it
+/// up, possibly from another process if `word` is in shared memory. Spurious
+/// wakeups are possible, so the caller should check the word again.
+///
+/// Returns an error of [`ErrorKind::WouldBlock`] if `word` doesn't equal
+/// `expected`.
+///
+/// See also `FUTEX_WAIT_BITSET` in `man futex.2`.
+pub async fn futex_wait(word: &AtomicU32, expected: u32, mask: u32) -> Result<()> {
+    syscall::futex_wait(word as *const AtomicU32 as usize, expected, mask).await
+}
+
+/// Wakes up at most `count` waiters on the futex `word` with an overlapping
+/// `mask`.
+///
+/// Returns the number of waiters woken up.
+///
+/// See also `FUTEX_WAKE_BITSET` in `man futex.2`.
+pub async fn futex_wake(word: &AtomicU32, count: u32, mask: u32) -> Result<usize> {
+    syscall::futex_wake(word as *const AtomicU32 as usize, count, mask).await
+}
+
+/// Gives advice about the use of the memory range `[addr, addr + len)`.
+///
+/// `advice` is one of the `MADV_*` constants, see also `man madvise.2`.
+///
+/// # Safety
+///
+/// The caller must guarantee that the range remains mapped until the advice is
+/// applied, even if the returned future is dropped before.
+pub unsafe fn madvise(
+    addr: *mut u8,
+    len: usize,
+    advice: libc::c_int,
+) -> impl Future<Output = Result<()>> + Send {
+    syscall::madvise(addr as usize, len, advice)
+}
+
+/// Moves up to `len` bytes from `fd_in` to `fd_out` without copying them
+/// between user space and kernel space.
+///
+/// At least one of the descriptors must refer to a pipe. An offset of `None`
+/// reads from or writes to the current position of the descriptor, which is
+/// required for pipes and sockets. For other descriptors, an explicit offset
+/// doesn't change the current position.
+///
+/// Returns the number of bytes moved, which might be less than `len`. Zero
+/// means that there is no more data to read from `fd_in`.
+///
+/// See also `man splice.2`.
+pub async fn splice(
+    fd_in: BorrowedFd<'_>,
+    off_in: Option<u64>,
+    fd_out: BorrowedFd<'_>,
+    off_out: Option<u64>,
+    len: usize,
+) -> Result<usize> {
+    let off_in = splice_offset(off_in)?;
+    let off_out = splice_offset(off_out)?;
+    let len = len.min(u32::MAX as usize) as u32;
+    syscall::splice(fd_in, off_in, fd_out, off_out, len, 0).await
+}
+
+/// Returns true if `fd` is a socket, which is written with `MSG_NOSIGNAL`.
+fn is_socket(fd: RawFd) -> Result<bool> {
+    let mut stat = unsafe { std::mem::zeroed::<libc::stat>() };
+    if unsafe { libc::fstat(fd, &mut stat) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(stat.st_mode & libc::S_IFMT == libc::S_IFSOCK)
+}
+
+fn splice_offset(off: Option<u64>) -> Result<libc::off64_t> {
+    match off {
+        Some(off) => off
+            .try_into()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e)),
+        None => Ok(-1),
+    }
+}
@@ -0,0 +1,164 @@
+use std::fmt;
+
+use crate::runtime::syscall;
+
+mod opcode {
+    pub(super) use io_uring::opcode::{UringCmd80 as UringCmd, *};
+
+    pub(super) use crate::runtime::raw::*;
+}
+
+macro_rules! opcodes {
+    ($($(#[$attr:meta])* $name:ident => $op:ident,)*) => {
+        /// An io_uring operation that the runtime might submit.
+        ///
+        /// See also [`is_supported`].
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum Opcode {
+            $($(#[$attr])* $name,)*
+        }
+
+        impl Opcode {
+            /// Returns the code of this operation in the kernel.
+            pub(crate) fn code(self) -> u8 {
+                match self {
+                    $(Self::$name => opcode::$name::CODE,)*
+                }
+            }
+
+            /// Returns the operation with `code` in the kernel.
+            pub(crate) fn from_code(code: u8) -> Option<Self> {
+                $(
+                    if code == opcode::$name::CODE {
+                        return Some(Self::$name);
+                    }
+                )*
+                None
+            }
+
+            /// Returns the name of this operation in the kernel.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Self::$name => concat!("IORING_OP_", stringify!($op)),)*
+                }
+            }
+        }
+    };
+}
+
+opcodes! {
+    /// `IORING_OP_ACCEPT`
+    Accept => ACCEPT,
+    /// `IORING_OP_ASYNC_CANCEL`
+    AsyncCancel => ASYNC_CANCEL,
+    /// `IORING_OP_CLOSE`
+    Close => CLOSE,
+    /// `IORING_OP_CONNECT`
+    Connect => CONNECT,
+    /// `IORING_OP_FADVISE`
+    Fadvise => FADVISE,
+    /// `IORING_OP_FALLOCATE`
+    Fallocate => FALLOCATE,
+    /// `IORING_OP_FGETXATTR`
+    FGetXattr => FGETXATTR,
+    /// `IORING_OP_FSETXATTR`
+    FSetXattr => FSETXATTR,
+    /// `IORING_OP_FSYNC`
+    Fsync => FSYNC,
+    /// `IORING_OP_FTRUNCATE`
+    Ftruncate => FTRUNCATE,
+    /// `IORING_OP_FUTEX_WAIT`
+    FutexWait => FUTEX_WAIT,
+    /// `IORING_OP_FUTEX_WAKE`
+    FutexWake => FUTEX_WAKE,
+    /// `IORING_OP_GETXATTR`
+    GetXattr => GETXATTR,
+    /// `IORING_OP_LINKAT`
+    LinkAt => LINKAT,
+    /// `IORING_OP_MADVISE`
+    Madvise => MADVISE,
+    /// `IORING_OP_MKDIRAT`
+    MkDirAt => MKDIRAT,
+    /// `IORING_OP_MSG_RING`
+    MsgRing => MSG_RING,
+    /// `IORING_OP_NOP`
+    Nop => NOP,
+    /// `IORING_OP_OPENAT`
+    OpenAt => OPENAT,
+    /// `IORING_OP_OPENAT2`
+    OpenAt2 => OPENAT2,
+    /// `IORING_OP_POLL_ADD`
+    PollAdd => POLL_ADD,
+    /// `IORING_OP_PROVIDE_BUFFERS`
+    ProvideBuffers => PROVIDE_BUFFERS,
+    /// `IORING_OP_READ`
+    Read => READ,
+    /// `IORING_OP_READ_FIXED`
+    ReadFixed => READ_FIXED,
+    /// `IORING_OP_READV`
+    Readv => READV,
+    /// `IORING_OP_RECV`
+    Recv => RECV,
+    /// `IORING_OP_RECVMSG`
+    RecvMsg => RECVMSG,
+    /// `IORING_OP_REMOVE_BUFFERS`
+    RemoveBuffers => REMOVE_BUFFERS,
+    /// `IORING_OP_RENAMEAT`
+    RenameAt => RENAMEAT,
+    /// `IORING_OP_SEND`
+    Send => SEND,
+    /// `IORING_OP_SENDMSG`
+    SendMsg => SENDMSG,
+    /// `IORING_OP_SEND_ZC`
+    SendZc => SEND_ZC,
+    /// `IORING_OP_SETXATTR`
+    SetXattr => SETXATTR,
+    /// `IORING_OP_SHUTDOWN`
+    Shutdown => SHUTDOWN,
+    /// `IORING_OP_SOCKET`
+    Socket => SOCKET,
+    /// `IORING_OP_SPLICE`
+    Splice => SPLICE,
+    /// `IORING_OP_STATX`
+    Statx => STATX,
+    /// `IORING_OP_SYMLINKAT`
+    SymlinkAt => SYMLINKAT,
+    /// `IORING_OP_SYNC_FILE_RANGE`
+    SyncFileRange => SYNC_FILE_RANGE,
+    /// `IORING_OP_TEE`
+    Tee => TEE,
+    /// `IORING_OP_UNLINKAT`
+    UnlinkAt => UNLINKAT,
+    /// `IORING_OP_URING_CMD`
+    UringCmd => URING_CMD,
+    /// `IORING_OP_WAITID`
+    WaitId => WAITID,
+    /// `IORING_OP_WRITE`
+    Write => WRITE,
+    /// `IORING_OP_WRITE_FIXED`
+    WriteFixed => WRITE_FIXED,
+    /// `IORING_OP_WRITEV`
+    Writev => WRITEV,
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Returns true if the kernel supports `opcode`.
+///
+/// The supported operations are probed when a worker starts. Returns false if
+/// the kernel doesn't support probing, which is the case before Linux 5.6, or
+/// if this is not called on a worker.
+///
+/// Operations with reasonable fallbacks, like [`Opcode::Socket`] and
+/// [`Opcode::Statx`], fall back transparently if they are not supported.
+/// Others return an error of [`std::io::ErrorKind::Unsupported`].
+///
+/// See also `IORING_REGISTER_PROBE` in `man io_uring_register.2`.
+pub fn is_supported(opcode: Opcode) -> bool {
+    syscall::is_supported(opcode)
+}
@@ -0,0 +1,174 @@
+use std::{
+    future::{ready, Future, Ready},
+    io::{Error, IoSlice, IoSliceMut, Result},
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+};
+
+use super::{Initializer, Read, Write};
+use crate::runtime::syscall;
+
+/// Creates an anonymous pipe.
+///
+/// Returns the read and write ends of the pipe.
+///
+/// See also `man pipe.2`.
+pub fn pipe() -> Result<(PipeReader, PipeWriter)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    unsafe {
+        let reader = PipeReader(OwnedFd::from_raw_fd(fds[0]));
+        let writer = PipeWriter(OwnedFd::from_raw_fd(fds[1]));
+        Ok((reader, writer))
+    }
+}
+
+/// The read end of a pipe.
+///
+/// Reading from a pipe returns zero bytes once all its write ends are closed.
+#[derive(Debug)]
+pub struct PipeReader(OwnedFd);
+
+impl PipeReader {
+    /// Duplicates up to `len` bytes from this pipe to `writer` without
+    /// consuming them.
+    ///
+    /// Returns the number of bytes duplicated, which might be less than `len`.
+    /// Zero means that this pipe is empty and all its write ends are closed.
+    ///
+    /// See also `man tee.2`.
+    pub async fn tee_to(&self, writer: &PipeWriter, len: usize) -> Result<usize> {
+        let len = len.min(u32::MAX as usize) as u32;
+        loop {
+            let n = syscall::tee(self.as_fd(), writer.as_fd(), len, 0).await?;
+            if n > 0 || len == 0 {
+                return Ok(n);
+            }
+            // The pipe is empty, waits for more data or for the write ends to
+            // be closed instead of spinning.
+            let events = syscall::poll_add(self.as_fd(), libc::POLLIN).await?;
+            if events & libc::POLLIN == 0 {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+/// The write end of a pipe.
+///
+/// Writing to a pipe returns an error of [`std::io::ErrorKind::BrokenPipe`]
+/// once all its read ends are closed.
+#[derive(Debug)]
+pub struct PipeWriter(OwnedFd);
+
+impl AsFd for PipeReader {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl AsRawFd for PipeReader {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl FromRawFd for PipeReader {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self(OwnedFd::from_raw_fd(fd))
+    }
+}
+
+impl IntoRawFd for PipeReader {
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_raw_fd()
+    }
+}
+
+impl AsFd for PipeWriter {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl AsRawFd for PipeWriter {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl FromRawFd for PipeWriter {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self(OwnedFd::from_raw_fd(fd))
+    }
+}
+
+impl IntoRawFd for PipeWriter {
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_raw_fd()
+    }
+}
+
+impl Read for PipeReader {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        syscall::read(self.as_fd(), buf)
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        syscall::readv(self.as_fd(), bufs)
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+
+    fn splice_source(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        Some((self.as_raw_fd(), None))
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The kernel only writes to the buffer.
+        unsafe { Initializer::nop() }
+    }
+}
+
+impl Write for PipeWriter {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        syscall::write(self.as_fd(), buf)
+    }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        syscall::writev(self.as_fd(), bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn splice_sink(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        Some((self.as_raw_fd(), None))
+    }
+
+    type Flush<'a> = Ready<Result<()>>;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        ready(Ok(()))
+    }
+
+    type Shutdown<'a> = Ready<Result<()>>;
+
+    // A pipe can only be shut down by closing the write end, which happens
+    // when this is dropped.
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        ready(Ok(()))
+    }
+}
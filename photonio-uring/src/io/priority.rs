@@ -0,0 +1,149 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use io_uring::squeue;
+
+use super::Opcode;
+use crate::runtime::raw::RawSqe;
+
+/// The scheduling class of an [`IoPriority`].
+///
+/// See also `man ioprio_set.2`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IoPriorityClass {
+    /// `IOPRIO_CLASS_RT`, which is served before the other classes. This
+    /// requires `CAP_SYS_ADMIN`.
+    RealTime,
+    /// `IOPRIO_CLASS_BE`, which is the default class.
+    BestEffort,
+    /// `IOPRIO_CLASS_IDLE`, which is only served when no other I/O is pending.
+    Idle,
+}
+
+/// The I/O priority of an operation.
+///
+/// Only reads and writes carry the priority, since the kernel ignores it for
+/// other operations. Synchronizations are submitted with the default priority.
+///
+/// See also [`with_io_priority`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IoPriority(u16);
+
+impl IoPriority {
+    const CLASS_SHIFT: u16 = 13;
+    const MAX_LEVEL: u8 = 7;
+
+    /// Creates a priority of `level` in `class`.
+    ///
+    /// A lower level means a higher priority within the class. The level of
+    /// [`IoPriorityClass::Idle`] is ignored by the kernel.
+    ///
+    /// Returns an error of [`ErrorKind::InvalidInput`] if `level` is greater
+    /// than 7.
+    pub fn new(class: IoPriorityClass, level: u8) -> Result<Self> {
+        if level > Self::MAX_LEVEL {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the I/O priority level must be in 0..=7",
+            ));
+        }
+        let class = match class {
+            IoPriorityClass::RealTime => 1,
+            IoPriorityClass::BestEffort => 2,
+            IoPriorityClass::Idle => 3,
+        };
+        Ok(Self(class << Self::CLASS_SHIFT | level as u16))
+    }
+
+    /// Returns the class of this priority.
+    pub fn class(self) -> IoPriorityClass {
+        match self.0 >> Self::CLASS_SHIFT {
+            1 => IoPriorityClass::RealTime,
+            2 => IoPriorityClass::BestEffort,
+            _ => IoPriorityClass::Idle,
+        }
+    }
+
+    /// Returns the level of this priority.
+    pub fn level(self) -> u8 {
+        (self.0 & ((1 << Self::CLASS_SHIFT) - 1)) as u8
+    }
+
+    /// Returns the value of this priority in the kernel.
+    pub fn value(self) -> u16 {
+        self.0
+    }
+}
+
+thread_local! {
+    static CURRENT: Cell<Option<IoPriority>> = Cell::new(None);
+}
+
+/// Runs `future` with `priority` as the default I/O priority of the reads and
+/// writes it submits.
+///
+/// The priority applies whenever the returned future is polled, so it can be
+/// set for a whole task by wrapping the future to spawn. Nested calls,
+/// including those of [`crate::fs::File::with_io_priority`], override the
+/// outer priority.
+pub fn with_io_priority<F: Future>(priority: IoPriority, future: F) -> WithIoPriority<F> {
+    WithIoPriority { priority, future }
+}
+
+/// A future returned by [`with_io_priority`].
+#[derive(Debug)]
+pub struct WithIoPriority<F> {
+    priority: IoPriority,
+    future: F,
+}
+
+impl<F: Future> Future for WithIoPriority<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let priority = self.priority;
+        let future = unsafe { self.map_unchecked_mut(|this| &mut this.future) };
+        let prev = CURRENT.with(|current| current.replace(Some(priority)));
+        // Restores the outer priority even if the future panics.
+        let _guard = Restore(prev);
+        future.poll(cx)
+    }
+}
+
+struct Restore(Option<IoPriority>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.0));
+    }
+}
+
+/// Sets the current I/O priority on `sqe`, if any and if it is a read or a
+/// write without a priority.
+pub(crate) fn apply(sqe: &mut squeue::Entry) {
+    let priority = match CURRENT.with(Cell::get) {
+        Some(priority) => priority,
+        None => return,
+    };
+    let raw = RawSqe::from_entry_mut(sqe);
+    // Other operations use the field for their own flags.
+    let is_rw = matches!(
+        Opcode::from_code(raw.opcode),
+        Some(
+            Opcode::Read
+                | Opcode::Write
+                | Opcode::ReadFixed
+                | Opcode::WriteFixed
+                | Opcode::Readv
+                | Opcode::Writev
+        )
+    );
+    if is_rw && raw.ioprio == 0 {
+        raw.ioprio = priority.value();
+    }
+}
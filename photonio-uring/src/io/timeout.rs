@@ -0,0 +1,189 @@
+use std::{
+    future::{poll_fn, Future},
+    io::{Error, ErrorKind, Result},
+    os::unix::io::BorrowedFd,
+    pin::pin,
+    task::Poll,
+    time::{Duration, Instant},
+};
+
+use super::{is_socket, splice_offset, IncompleteIo, Read, Write};
+use crate::runtime::syscall;
+
+/// Provides extension methods with timeouts for [`Read`].
+///
+/// Objects that expose their descriptor, like files, sockets, and pipes, are
+/// read with a linked timeout in the kernel: the read either completes with
+/// its data or is cancelled before the timeout error is returned, so no data
+/// is lost. Other objects race the read against a timer and drop it when the
+/// timer fires first. The read is polled before the timer, so data that is
+/// ready is returned, but readers that wrap a descriptor without exposing it
+/// might lose data that arrives while the read is being cancelled.
+///
+/// A zero timeout fails immediately for objects that expose their descriptor.
+pub trait ReadTimeoutExt {
+    /// A future that resolves to the result of [`Self::read_timeout`].
+    type ReadTimeout<'a>: Future<Output = Result<usize>> + 'a
+    where
+        Self: 'a;
+
+    /// Reads some bytes from this object into `buf`, failing with
+    /// [`ErrorKind::TimedOut`] if no data arrives in `timeout`.
+    fn read_timeout<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        timeout: Duration,
+    ) -> Self::ReadTimeout<'a>;
+
+    /// A future that resolves to the result of [`Self::read_exact_timeout`].
+    type ReadExactTimeout<'a>: Future<Output = Result<()>> + 'a
+    where
+        Self: 'a;
+
+    /// Reads the exact number of bytes from this object to fill `buf`,
+    /// failing with [`ErrorKind::TimedOut`] if they don't arrive in `timeout`.
+    ///
+    /// The timeout applies to the whole operation. EOF is reported like
+    /// [`super::ReadExt::read_exact`].
+    fn read_exact_timeout<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        timeout: Duration,
+    ) -> Self::ReadExactTimeout<'a>;
+}
+
+impl<T: Read> ReadTimeoutExt for T {
+    type ReadTimeout<'a> = impl Future<Output = Result<usize>> + 'a where Self: 'a;
+
+    fn read_timeout<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        timeout: Duration,
+    ) -> Self::ReadTimeout<'a> {
+        read_timeout(self, buf, timeout)
+    }
+
+    type ReadExactTimeout<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn read_exact_timeout<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        timeout: Duration,
+    ) -> Self::ReadExactTimeout<'a> {
+        async move {
+            let deadline = Instant::now() + timeout;
+            let mut done = 0;
+            while done < buf.len() {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match read_timeout(self, &mut buf[done..], timeout).await {
+                    Ok(0) => return Err(IncompleteIo::eof(done, None)),
+                    Ok(n) => done += n,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Provides extension methods with timeouts for [`Write`].
+///
+/// See also [`ReadTimeoutExt`] for how the timeouts are implemented.
+pub trait WriteTimeoutExt {
+    /// A future that resolves to the result of [`Self::write_all_timeout`].
+    type WriteAllTimeout<'a>: Future<Output = Result<()>> + 'a
+    where
+        Self: 'a;
+
+    /// Writes all bytes from `buf` into this object, failing with
+    /// [`ErrorKind::TimedOut`] if they are not written in `timeout`.
+    ///
+    /// The timeout applies to the whole operation. Some bytes might be written
+    /// when the timeout fires.
+    fn write_all_timeout<'a>(
+        &'a mut self,
+        buf: &'a [u8],
+        timeout: Duration,
+    ) -> Self::WriteAllTimeout<'a>;
+}
+
+impl<T: Write> WriteTimeoutExt for T {
+    type WriteAllTimeout<'a> = impl Future<Output = Result<()>> + 'a where Self: 'a;
+
+    fn write_all_timeout<'a>(
+        &'a mut self,
+        buf: &'a [u8],
+        timeout: Duration,
+    ) -> Self::WriteAllTimeout<'a> {
+        async move {
+            let deadline = Instant::now() + timeout;
+            let mut done = 0;
+            while done < buf.len() {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match write_timeout(self, &buf[done..], timeout).await {
+                    Ok(0) => return Err(IncompleteIo::write_zero(done, None)),
+                    Ok(n) => done += n,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn read_timeout<T: Read>(reader: &mut T, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+    match reader.splice_source() {
+        Some((fd, pos)) => {
+            let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+            let offset = splice_offset(pos.as_deref().copied())?;
+            let n = syscall::pread_timeout(fd, buf, offset, timeout).await?;
+            if let Some(pos) = pos {
+                *pos += n as u64;
+            }
+            Ok(n)
+        }
+        None => race(reader.read(buf), timeout).await,
+    }
+}
+
+async fn write_timeout<T: Write>(writer: &mut T, buf: &[u8], timeout: Duration) -> Result<usize> {
+    match writer.splice_sink() {
+        Some((fd, None)) if is_socket(fd)? => {
+            let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+            // Returns `EPIPE` instead of raising `SIGPIPE` if the peer is closed.
+            syscall::send_timeout(fd, buf, libc::MSG_NOSIGNAL, timeout).await
+        }
+        Some((fd, pos)) => {
+            let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+            let offset = splice_offset(pos.as_deref().copied())?;
+            let n = syscall::pwrite_timeout(fd, buf, offset, timeout).await?;
+            if let Some(pos) = pos {
+                *pos += n as u64;
+            }
+            Ok(n)
+        }
+        None => race(writer.write(buf), timeout).await,
+    }
+}
+
+/// Runs `op` until it completes or `timeout` passes.
+async fn race<F: Future<Output = Result<usize>>>(op: F, timeout: Duration) -> Result<usize> {
+    let mut op = pin!(op);
+    let mut sleep = pin!(syscall::sleep(timeout));
+    poll_fn(|cx| {
+        // Keeps the result of the op if both are ready.
+        if let Poll::Ready(result) = op.as_mut().poll(cx) {
+            return Poll::Ready(result);
+        }
+        match sleep.as_mut().poll(cx) {
+            Poll::Ready(Ok(())) => {
+                Poll::Ready(Err(Error::new(ErrorKind::TimedOut, "operation timed out")))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    })
+    .await
+}
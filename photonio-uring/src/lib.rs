@@ -1,7 +1,7 @@
 //! A PhotonIO implementation based on io_uring.
 
 #![warn(missing_docs, unreachable_pub)]
-#![feature(pin_macro, io_error_more, type_alias_impl_trait)]
+#![feature(pin_macro, io_error_more, once_cell, type_alias_impl_trait)]
 
 #[cfg(target_os = "linux")]
 pub mod fs;
@@ -10,6 +10,10 @@ pub mod io;
 #[cfg(target_os = "linux")]
 pub mod net;
 #[cfg(target_os = "linux")]
+pub mod process;
+#[cfg(target_os = "linux")]
 pub mod runtime;
 #[cfg(target_os = "linux")]
+pub mod sync;
+#[cfg(target_os = "linux")]
 pub mod task;
@@ -0,0 +1,142 @@
+use std::{mem, ptr, slice};
+
+/// A control message sent or received along with a socket message.
+///
+/// See also `man cmsg.3`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ControlMessage {
+    /// The originating protocol of this message, such as `SOL_SOCKET`.
+    pub level: libc::c_int,
+    /// The protocol-specific type of this message, such as `SCM_RIGHTS`.
+    pub ty: libc::c_int,
+    /// The data of this message.
+    pub data: Vec<u8>,
+}
+
+impl ControlMessage {
+    /// Creates a control message with the given level, type, and data.
+    pub fn new(level: libc::c_int, ty: libc::c_int, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            level,
+            ty,
+            data: data.into(),
+        }
+    }
+
+    /// Returns the number of bytes required to receive a control message with
+    /// `len` bytes of data.
+    ///
+    /// See also `CMSG_SPACE` in `man cmsg.3`.
+    pub fn space(len: usize) -> usize {
+        unsafe { libc::CMSG_SPACE(len as _) as _ }
+    }
+}
+
+/// The flags of a received socket message.
+///
+/// See also `MSG_TRUNC` and `MSG_CTRUNC` in `man recvmsg.2`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RecvFlags(libc::c_int);
+
+impl RecvFlags {
+    pub(super) fn new(flags: libc::c_int) -> Self {
+        Self(flags)
+    }
+
+    /// Returns true if the data of the message is truncated, since it doesn't
+    /// fit in the buffers.
+    pub fn is_truncated(self) -> bool {
+        self.0 & libc::MSG_TRUNC != 0
+    }
+
+    /// Returns true if the control messages are truncated, since they don't
+    /// fit in the control buffer.
+    pub fn is_control_truncated(self) -> bool {
+        self.0 & libc::MSG_CTRUNC != 0
+    }
+
+    /// Returns the raw `MSG_*` flags.
+    pub fn bits(self) -> libc::c_int {
+        self.0
+    }
+}
+
+/// A buffer of control messages aligned for `cmsghdr`.
+pub(super) struct ControlBuf {
+    buf: Vec<u64>,
+    len: usize,
+}
+
+impl ControlBuf {
+    /// Creates a zeroed buffer with `len` bytes.
+    pub(super) fn new(len: usize) -> Self {
+        let words = (len + mem::size_of::<u64>() - 1) / mem::size_of::<u64>();
+        Self {
+            buf: vec![0; words],
+            len,
+        }
+    }
+
+    /// Encodes `msgs` into a buffer.
+    pub(super) fn encode(msgs: &[ControlMessage]) -> Self {
+        let len = msgs
+            .iter()
+            .map(|m| ControlMessage::space(m.data.len()))
+            .sum();
+        let mut buf = Self::new(len);
+        let mut offset = 0;
+        for m in msgs {
+            unsafe {
+                let hdr = buf.as_mut_ptr().add(offset) as *mut libc::cmsghdr;
+                (*hdr).cmsg_level = m.level;
+                (*hdr).cmsg_type = m.ty;
+                (*hdr).cmsg_len = libc::CMSG_LEN(m.data.len() as _) as _;
+                ptr::copy_nonoverlapping(m.data.as_ptr(), libc::CMSG_DATA(hdr), m.data.len());
+            }
+            offset += ControlMessage::space(m.data.len());
+        }
+        buf
+    }
+
+    /// Decodes the first `len` bytes of this buffer.
+    pub(super) fn decode(&self, len: usize) -> Vec<ControlMessage> {
+        let len = len.min(self.len);
+        let hdr_len = unsafe { libc::CMSG_LEN(0) as usize };
+        let mut msgs = Vec::new();
+        let mut offset = 0;
+        while offset + mem::size_of::<libc::cmsghdr>() <= len {
+            unsafe {
+                let hdr = self.as_ptr().add(offset) as *const libc::cmsghdr;
+                let msg_len = (*hdr).cmsg_len as usize;
+                if msg_len < hdr_len || offset + msg_len > len {
+                    break;
+                }
+                let data_len = msg_len - hdr_len;
+                let data = slice::from_raw_parts(libc::CMSG_DATA(hdr), data_len);
+                msgs.push(ControlMessage::new(
+                    (*hdr).cmsg_level,
+                    (*hdr).cmsg_type,
+                    data,
+                ));
+                offset += ControlMessage::space(data_len);
+            }
+        }
+        msgs
+    }
+
+    pub(super) fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len) }
+    }
+
+    pub(super) fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+    }
+
+    fn as_ptr(&self) -> *const u8 {
+        self.buf.as_ptr() as _
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.buf.as_mut_ptr() as _
+    }
+}
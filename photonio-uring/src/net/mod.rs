@@ -2,7 +2,51 @@
 //!
 //! This module is an async version of [`std::net`].
 
+use std::{
+    io::{Error, ErrorKind, Result},
+    os::unix::io::{FromRawFd, IntoRawFd, OwnedFd},
+};
+
 pub use photonio_base::net::*;
+use socket2::{Domain, SockAddr, Socket, Type};
+
+use crate::runtime::syscall;
+
+mod cmsg;
+pub use cmsg::{ControlMessage, RecvFlags};
 
 mod tcp;
-pub use tcp::{TcpListener, TcpStream};
+pub use tcp::{AcceptMulti, RecvMulti, TcpListener, TcpStream};
+
+mod split;
+pub use split::{OwnedReadHalf, OwnedWriteHalf, ReuniteError};
+
+mod udp;
+pub use udp::UdpSocket;
+
+mod unix;
+pub use unix::{UnixDatagram, UnixListener, UnixListenerOptions, UnixSocketAddr, UnixStream};
+
+fn to_socket_addr(addr: SockAddr) -> Result<SocketAddr> {
+    addr.as_socket()
+        .ok_or_else(|| Error::new(ErrorKind::Other, "invalid socket address"))
+}
+
+/// Takes over a socket from another owner.
+///
+/// The sockets of this crate are in blocking mode, and io_uring waits for
+/// them to be ready. Some operations on a non-blocking socket fail with
+/// [`ErrorKind::WouldBlock`] instead of waiting, so the mode is cleared, which
+/// applies to the other descriptors of the same socket too.
+fn adopt_socket(fd: OwnedFd) -> Socket {
+    let socket = unsafe { Socket::from_raw_fd(fd.into_raw_fd()) };
+    // This only fails if the descriptor is invalid, which `OwnedFd` rules out.
+    let _ = socket.set_nonblocking(false);
+    socket
+}
+
+async fn new_socket(addr: SocketAddr, ty: Type) -> Result<Socket> {
+    let domain = Domain::for_address(addr);
+    let fd = syscall::socket(domain.into(), ty.into(), 0).await?;
+    Ok(unsafe { Socket::from_raw_fd(fd.into_raw_fd()) })
+}
@@ -0,0 +1,173 @@
+use std::{
+    error, fmt,
+    future::{ready, Future, Ready},
+    io::{IoSlice, IoSliceMut, Result},
+    net::SocketAddr,
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd},
+    sync::Arc,
+};
+
+use super::TcpStream;
+use crate::{
+    io::{Initializer, Read, Write},
+    runtime::syscall,
+};
+
+/// The read half of a [`TcpStream`], created by [`TcpStream::into_split`].
+///
+/// Reads on this half don't block writes on the other half.
+#[derive(Debug)]
+pub struct OwnedReadHalf {
+    stream: Arc<TcpStream>,
+}
+
+/// The write half of a [`TcpStream`], created by [`TcpStream::into_split`].
+///
+/// Dropping this half doesn't shut down the write side of the connection. Use
+/// [`Write::shutdown`] for that.
+#[derive(Debug)]
+pub struct OwnedWriteHalf {
+    stream: Arc<TcpStream>,
+}
+
+pub(super) fn split(stream: TcpStream) -> (OwnedReadHalf, OwnedWriteHalf) {
+    let stream = Arc::new(stream);
+    (
+        OwnedReadHalf {
+            stream: stream.clone(),
+        },
+        OwnedWriteHalf { stream },
+    )
+}
+
+impl OwnedReadHalf {
+    /// Reunites this half with `other` to get the stream back.
+    ///
+    /// Returns an error with both halves if they don't come from the same
+    /// stream.
+    pub fn reunite(self, other: OwnedWriteHalf) -> std::result::Result<TcpStream, ReuniteError> {
+        if !Arc::ptr_eq(&self.stream, &other.stream) {
+            return Err(ReuniteError(self, other));
+        }
+        drop(other);
+        // The other half is dropped, so this is the only reference.
+        Ok(Arc::try_unwrap(self.stream).expect("the stream must not be shared"))
+    }
+
+    /// Returns the local address of the stream.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.stream.local_addr()
+    }
+
+    /// Returns the remote address of the stream.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+}
+
+impl OwnedWriteHalf {
+    /// Reunites this half with `other` to get the stream back.
+    ///
+    /// See also [`OwnedReadHalf::reunite`].
+    pub fn reunite(self, other: OwnedReadHalf) -> std::result::Result<TcpStream, ReuniteError> {
+        other.reunite(self)
+    }
+
+    /// Returns the local address of the stream.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.stream.local_addr()
+    }
+
+    /// Returns the remote address of the stream.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+}
+
+impl AsFd for OwnedReadHalf {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.stream.as_fd()
+    }
+}
+
+impl AsFd for OwnedWriteHalf {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.stream.as_fd()
+    }
+}
+
+impl Read for OwnedReadHalf {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        syscall::recv(self.as_fd(), buf, 0)
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        syscall::readv(self.as_fd(), bufs)
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+
+    fn splice_source(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        Some((self.stream.as_raw_fd(), None))
+    }
+
+    fn initializer(&self) -> Initializer {
+        unsafe { Initializer::nop() }
+    }
+}
+
+impl Write for OwnedWriteHalf {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        syscall::send(self.as_fd(), buf, libc::MSG_NOSIGNAL)
+    }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        // `writev` can't pass `MSG_NOSIGNAL`, so this sends a message instead.
+        syscall::sendmsg(self.as_fd(), bufs, None, &[], libc::MSG_NOSIGNAL)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn splice_sink(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        Some((self.stream.as_raw_fd(), None))
+    }
+
+    type Flush<'a> = Ready<Result<()>>;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        ready(Ok(()))
+    }
+
+    type Shutdown<'a> = impl Future<Output = Result<()>> + 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        syscall::shutdown(self.as_fd(), libc::SHUT_WR)
+    }
+}
+
+/// The error returned by [`OwnedReadHalf::reunite`] if the halves don't come
+/// from the same stream.
+///
+/// The error contains the halves, so that they can be used again.
+#[derive(Debug)]
+pub struct ReuniteError(pub OwnedReadHalf, pub OwnedWriteHalf);
+
+impl fmt::Display for ReuniteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("tried to reunite halves that are not from the same stream")
+    }
+}
+
+impl error::Error for ReuniteError {}
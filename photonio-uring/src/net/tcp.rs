@@ -1,23 +1,28 @@
 use std::{
-    future::Future,
-    io::{Error, ErrorKind, Result},
+    future::{ready, Future, Ready},
+    io::{Error, ErrorKind, IoSlice, IoSliceMut, Result},
     net::{Shutdown, SocketAddr},
-    os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, RawFd},
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+    time::Duration,
 };
 
-use socket2::{Domain, SockAddr, Socket, Type};
+use socket2::{Socket, Type};
 
+use super::{adopt_socket, new_socket, split, to_socket_addr, OwnedReadHalf, OwnedWriteHalf};
 use crate::{
-    io::{Read, Write},
+    io::{
+        raw_os_error, BufRing, CloseOnDrop, FixedFd, Initializer, IoBuf, IoBufMut, Read, RingBuf,
+        Write,
+    },
     net::ToSocketAddrs,
-    runtime::syscall,
+    runtime::syscall::{self, Target},
 };
 
 /// A TCP socket listening for connections.
 ///
 /// This type is an async version of [`std::net::TcpListener`].
 #[derive(Debug)]
-pub struct TcpListener(Socket);
+pub struct TcpListener(CloseOnDrop<Socket>);
 
 impl TcpListener {
     /// Creates a listener bound to the specified address.
@@ -26,8 +31,8 @@ impl TcpListener {
     pub async fn bind<A: ToSocketAddrs>(addrs: A) -> Result<Self> {
         let mut last_err = None;
         for addr in addrs.to_socket_addrs().await? {
-            match listen_addr(addr) {
-                Ok(l) => return Ok(Self(l)),
+            match listen_addr(addr).await {
+                Ok(l) => return Ok(Self(CloseOnDrop::new(l))),
                 Err(e) => last_err = Some(e),
             }
         }
@@ -44,6 +49,32 @@ impl TcpListener {
         Ok((stream, socket_addr))
     }
 
+    /// Accepts a new connection as a direct descriptor in the fixed file table
+    /// of the current worker.
+    ///
+    /// This saves allocating a descriptor in the process, which is useful for
+    /// servers that accept many connections. See also [`DirectTcpStream`].
+    pub async fn accept_direct(&self) -> Result<(DirectTcpStream, SocketAddr)> {
+        let fd = FixedFd::alloc()?;
+        let addr = syscall::accept_direct(self.fd(), fd.slot()?).await?;
+        Ok((DirectTcpStream(fd), to_socket_addr(addr)?))
+    }
+
+    /// Returns a stream of connections accepted with a multishot operation.
+    ///
+    /// A single submission keeps accepting connections until it terminates,
+    /// which is cheaper than submitting one operation per connection. If the
+    /// kernel doesn't support multishot accept, this falls back to
+    /// [`Self::accept`].
+    pub fn accept_multi(&self) -> AcceptMulti<'_> {
+        AcceptMulti {
+            listener: self,
+            op: None,
+            is_supported: true,
+            has_accepted: false,
+        }
+    }
+
     /// Returns the local socket address of this listener.
     ///
     /// See also [`std::net::TcpListener::local_addr`].
@@ -87,13 +118,120 @@ impl AsRawFd for TcpListener {
 
 impl FromRawFd for TcpListener {
     unsafe fn from_raw_fd(fd: RawFd) -> Self {
-        Self(Socket::from_raw_fd(fd))
+        Self(CloseOnDrop::new(Socket::from_raw_fd(fd)))
     }
 }
 
 impl IntoRawFd for TcpListener {
     fn into_raw_fd(self) -> RawFd {
-        self.0.into_raw_fd()
+        self.0.into_inner().into_raw_fd()
+    }
+}
+
+impl From<OwnedFd> for TcpListener {
+    /// Takes over the socket, switching it to blocking mode.
+    ///
+    /// The descriptor is not checked to be a TCP socket. If it is not,
+    /// operations on the returned value fail, for example with `ENOTSOCK`.
+    fn from(fd: OwnedFd) -> Self {
+        Self(CloseOnDrop::new(adopt_socket(fd)))
+    }
+}
+
+impl From<TcpListener> for OwnedFd {
+    fn from(socket: TcpListener) -> Self {
+        unsafe { OwnedFd::from_raw_fd(socket.into_raw_fd()) }
+    }
+}
+
+/// A stream of connections accepted by [`TcpListener::accept_multi`].
+///
+/// Dropping this stream cancels the underlying operation.
+pub struct AcceptMulti<'a> {
+    listener: &'a TcpListener,
+    op: Option<syscall::AcceptMulti>,
+    is_supported: bool,
+    has_accepted: bool,
+}
+
+impl AcceptMulti<'_> {
+    /// Accepts the next connection.
+    ///
+    /// The multishot operation is re-armed transparently when it terminates.
+    /// After an error is returned, the next call re-arms it as well.
+    pub async fn next(&mut self) -> Result<(TcpStream, SocketAddr)> {
+        loop {
+            if !self.is_supported {
+                return self.listener.accept().await;
+            }
+            if self.op.is_none() {
+                self.op = Some(syscall::accept_multi(self.listener.fd())?);
+            }
+            match self.op.as_mut().unwrap().next().await {
+                Some(Ok(fd)) => {
+                    self.has_accepted = true;
+                    let stream = unsafe { TcpStream::from_raw_fd(fd.into_raw_fd()) };
+                    let addr = stream.peer_addr()?;
+                    return Ok((stream, addr));
+                }
+                Some(Err(e)) => {
+                    // The operation terminates after an error.
+                    self.op = None;
+                    // Kernels before 5.19 reject multishot accept.
+                    if raw_os_error(&e) == Some(libc::EINVAL) && !self.has_accepted {
+                        self.is_supported = false;
+                        continue;
+                    }
+                    return Err(e);
+                }
+                None => self.op = None,
+            }
+        }
+    }
+}
+
+/// A stream of data received by [`TcpStream::recv_multi`].
+///
+/// Dropping this stream cancels the underlying operation.
+pub struct RecvMulti<'a> {
+    stream: &'a TcpStream,
+    ring: &'a BufRing,
+    op: Option<syscall::RecvMulti>,
+}
+
+impl RecvMulti<'_> {
+    /// Receives the next buffer of data.
+    ///
+    /// An empty buffer means that the peer has shut down its write half.
+    ///
+    /// Returns an error of [`ErrorKind::WouldBlock`] if all buffers of the ring
+    /// are in use. The data is not lost in this case, so the caller can try
+    /// again after dropping some buffers. The multishot operation is re-armed
+    /// transparently when it terminates.
+    pub async fn next(&mut self) -> Result<RingBuf> {
+        loop {
+            if self.op.is_none() {
+                let bgid = self.ring.bgid()?;
+                let op = syscall::recv_multi(self.stream.fd(), bgid, self.ring.recycler())?;
+                self.op = Some(op);
+            }
+            match self.op.as_mut().unwrap().next().await {
+                Some(Ok((len, Some(bid)))) => return Ok(self.ring.take(bid, len)),
+                Some(Ok((_, None))) => return Ok(self.ring.empty()),
+                Some(Err(e)) => {
+                    // The operation terminates after an error.
+                    self.op = None;
+                    if raw_os_error(&e) == Some(libc::ENOBUFS) {
+                        return Err(Error::new(
+                            ErrorKind::WouldBlock,
+                            "all buffers of the ring are in use",
+                        ));
+                    }
+                    return Err(e);
+                }
+                None => self.op = None,
+            }
+        }
     }
 }
 
@@ -101,15 +239,15 @@ impl IntoRawFd for TcpListener {
 ///
 /// This type is an async version of [`std::net::TcpStream`].
 #[derive(Debug)]
-pub struct TcpStream(Socket);
+pub struct TcpStream(CloseOnDrop<Socket>);
 
 impl TcpStream {
     /// Opens a TCP connection to a remote host.
     ///
     /// See also [`std::net::TcpStream::connect`].
     pub async fn connect(addr: SocketAddr) -> Result<Self> {
-        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
-        let stream = Self(socket);
+        let socket = new_socket(addr, Type::STREAM).await?;
+        let stream = Self(CloseOnDrop::new(socket));
         syscall::connect(stream.fd(), addr.into()).await?;
         Ok(stream)
     }
@@ -126,6 +264,98 @@ impl TcpStream {
         syscall::shutdown(self.fd(), flags).await.map(|_| ())
     }
 
+    /// Closes this stream and waits for the result.
+    ///
+    /// Dropping a stream closes it in the background and ignores errors. The
+    /// descriptor is released even if this method fails.
+    ///
+    /// See also `man close.2`.
+    pub async fn close(self) -> Result<()> {
+        self.0.close().await
+    }
+
+    /// Waits until this stream is readable.
+    ///
+    /// This function is cancel safe, dropping the returned future before
+    /// completion cancels the wait.
+    pub async fn readable(&self) -> Result<()> {
+        syscall::poll_add(self.fd(), libc::POLLIN).await.map(|_| ())
+    }
+
+    /// Waits until this stream is writable.
+    ///
+    /// This function is cancel safe, dropping the returned future before
+    /// completion cancels the wait.
+    pub async fn writable(&self) -> Result<()> {
+        syscall::poll_add(self.fd(), libc::POLLOUT)
+            .await
+            .map(|_| ())
+    }
+
+    /// Returns a stream of data received with a multishot operation.
+    ///
+    /// Data is received into buffers selected from `ring` only when it
+    /// arrives, so an idle stream doesn't hold any buffer. The ring must be
+    /// registered with the current worker.
+    pub fn recv_multi<'a>(&'a self, ring: &'a BufRing) -> RecvMulti<'a> {
+        RecvMulti {
+            stream: self,
+            ring,
+            op: None,
+        }
+    }
+
+    /// Reads some bytes into `buf`, failing with [`ErrorKind::TimedOut`] if no
+    /// data arrives in `timeout`.
+    ///
+    /// Unlike a timeout on the caller side, the read is cancelled in the
+    /// kernel, so no data is lost when the timeout fires. A zero `timeout`
+    /// fails immediately.
+    pub async fn read_timeout(&self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        syscall::recv_timeout(self.fd(), buf, 0, timeout).await
+    }
+
+    /// Reads some bytes into the owned buffer `buf`, and returns the result
+    /// with the buffer.
+    ///
+    /// The read starts at the beginning of the buffer, up to its total
+    /// capacity. Unlike [`Read::read`], the kernel reads into the buffer
+    /// directly, instead of into an intermediate one that is copied out. If
+    /// the returned future is dropped, the buffer is kept alive until the
+    /// kernel is done with it.
+    pub async fn read_owned<B: IoBufMut>(&self, buf: B) -> (Result<usize>, B) {
+        syscall::pread_owned(self.fd(), buf, -1).await
+    }
+
+    /// Writes some bytes from the owned buffer `buf` without copying them into
+    /// the socket buffer, and returns the result with the buffer.
+    ///
+    /// The buffer is returned once the kernel doesn't reference it anymore.
+    /// This saves CPU for large writes, but it's usually slower than
+    /// [`Write::write`] for small ones. Falls back to a regular send if the
+    /// kernel doesn't support zero-copy sends.
+    pub async fn write_zc<B: IoBuf>(&self, buf: B) -> (Result<usize>, B) {
+        syscall::send_zc(self.fd(), buf, 0).await
+    }
+
+    /// Sends some bytes from `buf` with the given `MSG_*` flags.
+    ///
+    /// Returns the number of bytes sent.
+    ///
+    /// See also `man send.2`.
+    pub async fn send_with_flags(&self, buf: &[u8], flags: libc::c_int) -> Result<usize> {
+        syscall::send(self.fd(), buf, flags).await
+    }
+
+    /// Receives some bytes into `buf` with the given `MSG_*` flags.
+    ///
+    /// Returns the number of bytes received.
+    ///
+    /// See also `man recv.2`.
+    pub async fn recv_with_flags(&self, buf: &mut [u8], flags: libc::c_int) -> Result<usize> {
+        syscall::recv(self.fd(), buf, flags).await
+    }
+
     /// Returns the socket address of the local half of this connection.
     ///
     /// See also [`std::net::TcpStream::local_addr`].
@@ -169,6 +399,15 @@ impl TcpStream {
     pub fn set_nodelay(&self, nodelay: bool) -> Result<()> {
         self.0.set_nodelay(nodelay)
     }
+
+    /// Splits this stream into a read half and a write half.
+    ///
+    /// The halves can be moved to different tasks, and operations on one half
+    /// don't block those on the other. Use [`OwnedReadHalf::reunite`] to get
+    /// the stream back.
+    pub fn into_split(self) -> (OwnedReadHalf, OwnedWriteHalf) {
+        split::split(self)
+    }
 }
 
 impl TcpStream {
@@ -191,13 +430,29 @@ impl AsRawFd for TcpStream {
 
 impl FromRawFd for TcpStream {
     unsafe fn from_raw_fd(fd: RawFd) -> Self {
-        Self(Socket::from_raw_fd(fd))
+        Self(CloseOnDrop::new(Socket::from_raw_fd(fd)))
     }
 }
 
 impl IntoRawFd for TcpStream {
     fn into_raw_fd(self) -> RawFd {
-        self.0.into_raw_fd()
+        self.0.into_inner().into_raw_fd()
+    }
+}
+
+impl From<OwnedFd> for TcpStream {
+    /// Takes over the socket, switching it to blocking mode.
+    ///
+    /// The descriptor is not checked to be a TCP socket. If it is not,
+    /// operations on the returned value fail, for example with `ENOTSOCK`.
+    fn from(fd: OwnedFd) -> Self {
+        Self(CloseOnDrop::new(adopt_socket(fd)))
+    }
+}
+
+impl From<TcpStream> for OwnedFd {
+    fn from(socket: TcpStream) -> Self {
+        unsafe { OwnedFd::from_raw_fd(socket.into_raw_fd()) }
     }
 }
 
@@ -205,7 +460,110 @@ impl Read for TcpStream {
     type Read<'a> = impl Future<Output = Result<usize>> + 'a;
 
     fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
-        syscall::read(self.fd(), buf)
+        syscall::recv(self.fd(), buf, 0)
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        syscall::readv(self.fd(), bufs)
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+
+    fn splice_source(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        Some((self.as_raw_fd(), None))
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The kernel only writes to the buffer.
+        unsafe { Initializer::nop() }
+    }
+}
+
+/// A TCP stream accepted as a direct descriptor.
+///
+/// A direct descriptor only lives in the fixed file table of a worker, so it
+/// can't be passed to code that expects a [`RawFd`], and it can only be used
+/// by tasks running on that worker. The slot is closed when this is dropped.
+///
+/// See also [`TcpListener::accept_direct`].
+#[derive(Debug)]
+pub struct DirectTcpStream(FixedFd);
+
+impl DirectTcpStream {
+    /// Closes this stream.
+    ///
+    /// This function is similar to dropping the stream, except that it returns
+    /// the error if any.
+    pub fn close(self) -> Result<()> {
+        self.0.unregister()
+    }
+}
+
+impl DirectTcpStream {
+    fn target(&self) -> Result<Target<'static>> {
+        self.0.slot().map(Target::Fixed)
+    }
+}
+
+impl Read for DirectTcpStream {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        async move { syscall::pread(self.target()?, buf, -1).await }
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        // Reads into the first non-empty buffer.
+        let buf = bufs.iter_mut().find(|b| !b.is_empty()).map(|b| &mut **b);
+        async move {
+            match buf {
+                Some(buf) => syscall::pread(self.target()?, buf, -1).await,
+                None => Ok(0),
+            }
+        }
+    }
+
+    fn initializer(&self) -> Initializer {
+        unsafe { Initializer::nop() }
+    }
+}
+
+impl Write for DirectTcpStream {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        // Returns `EPIPE` instead of raising `SIGPIPE` if the peer is closed.
+        async move { syscall::send(self.target()?, buf, libc::MSG_NOSIGNAL).await }
+    }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        // Writes from the first non-empty buffer.
+        async move {
+            match bufs.iter().find(|b| !b.is_empty()) {
+                Some(buf) => syscall::send(self.target()?, buf, libc::MSG_NOSIGNAL).await,
+                None => Ok(0),
+            }
+        }
+    }
+
+    type Flush<'a> = Ready<Result<()>>;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        ready(Ok(()))
+    }
+
+    type Shutdown<'a> = impl Future<Output = Result<()>> + 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        async move { syscall::shutdown(self.target()?, libc::SHUT_WR).await }
     }
 }
 
@@ -213,12 +571,40 @@ impl Write for TcpStream {
     type Write<'a> = impl Future<Output = Result<usize>> + 'a;
 
     fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
-        syscall::write(self.fd(), buf)
+        // Returns `EPIPE` instead of raising `SIGPIPE` if the peer is closed.
+        syscall::send(self.fd(), buf, libc::MSG_NOSIGNAL)
+    }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        // `writev` can't pass `MSG_NOSIGNAL`, so this sends a message instead.
+        syscall::sendmsg(self.fd(), bufs, None, &[], libc::MSG_NOSIGNAL)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn splice_sink(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        Some((self.as_raw_fd(), None))
+    }
+
+    type Flush<'a> = Ready<Result<()>>;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        ready(Ok(()))
+    }
+
+    type Shutdown<'a> = impl Future<Output = Result<()>> + 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        syscall::shutdown(self.fd(), libc::SHUT_WR)
     }
 }
 
-fn listen_addr(addr: SocketAddr) -> Result<Socket> {
-    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+async fn listen_addr(addr: SocketAddr) -> Result<Socket> {
+    let socket = new_socket(addr, Type::STREAM).await?;
     socket.set_reuse_port(true)?;
     socket.set_reuse_address(true)?;
     let sock_addr = addr.into();
@@ -226,8 +612,3 @@ fn listen_addr(addr: SocketAddr) -> Result<Socket> {
     socket.listen(1024)?;
     Ok(socket)
 }
-
-fn to_socket_addr(addr: SockAddr) -> Result<SocketAddr> {
-    addr.as_socket()
-        .ok_or_else(|| Error::new(ErrorKind::Other, "invalid socket address"))
-}
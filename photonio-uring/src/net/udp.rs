@@ -0,0 +1,276 @@
+use std::{
+    io::{Error, ErrorKind, IoSlice, IoSliceMut, Result},
+    net::SocketAddr,
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+};
+
+use socket2::{SockAddr, Socket, Type};
+
+use super::{
+    adopt_socket, cmsg::ControlBuf, new_socket, to_socket_addr, ControlMessage, RecvFlags,
+};
+use crate::{
+    io::{CloseOnDrop, IoBufMut},
+    net::ToSocketAddrs,
+    runtime::syscall,
+};
+
+/// A UDP socket.
+///
+/// This type is an async version of [`std::net::UdpSocket`].
+#[derive(Debug)]
+pub struct UdpSocket(CloseOnDrop<Socket>);
+
+impl UdpSocket {
+    /// Creates a UDP socket bound to the specified address.
+    ///
+    /// See also [`std::net::UdpSocket::bind`].
+    pub async fn bind<A: ToSocketAddrs>(addrs: A) -> Result<Self> {
+        let mut last_err = None;
+        for addr in addrs.to_socket_addrs().await? {
+            match bind_addr(addr).await {
+                Ok(s) => return Ok(Self(CloseOnDrop::new(s))),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| ErrorKind::InvalidInput.into()))
+    }
+
+    /// Connects this socket to a remote address.
+    ///
+    /// A connected socket can use [`Self::send`] and [`Self::recv`], and only
+    /// receives datagrams from the remote address. Each address is tried in
+    /// turn until one succeeds.
+    ///
+    /// See also [`std::net::UdpSocket::connect`].
+    pub async fn connect<A: ToSocketAddrs>(&self, addrs: A) -> Result<()> {
+        let mut last_err = None;
+        for addr in addrs.to_socket_addrs().await? {
+            match syscall::connect(self.fd(), addr.into()).await {
+                Ok(()) => return Ok(()),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| ErrorKind::InvalidInput.into()))
+    }
+
+    /// Sends data from `buf` to the connected address.
+    ///
+    /// Returns the number of bytes sent.
+    ///
+    /// See also [`std::net::UdpSocket::send`].
+    pub async fn send(&self, buf: &[u8]) -> Result<usize> {
+        syscall::send(self.fd(), buf, 0).await
+    }
+
+    /// Receives a datagram from the connected address into `buf`.
+    ///
+    /// Returns the number of bytes received. If the datagram is longer than
+    /// `buf`, the rest of it is discarded.
+    ///
+    /// See also [`std::net::UdpSocket::recv`].
+    pub async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        syscall::recv(self.fd(), buf, 0).await
+    }
+
+    /// Waits until this socket is readable.
+    ///
+    /// This function is cancel safe, dropping the returned future before
+    /// completion cancels the wait.
+    pub async fn readable(&self) -> Result<()> {
+        syscall::poll_add(self.fd(), libc::POLLIN).await.map(|_| ())
+    }
+
+    /// Waits until this socket is writable.
+    ///
+    /// This function is cancel safe, dropping the returned future before
+    /// completion cancels the wait.
+    pub async fn writable(&self) -> Result<()> {
+        syscall::poll_add(self.fd(), libc::POLLOUT)
+            .await
+            .map(|_| ())
+    }
+
+    /// Sends data from `buf` to the given address.
+    ///
+    /// Returns the number of bytes sent.
+    ///
+    /// See also [`std::net::UdpSocket::send_to`].
+    pub async fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addrs: A) -> Result<usize> {
+        let addr =
+            addrs.to_socket_addrs().await?.next().ok_or_else(|| {
+                Error::new(ErrorKind::InvalidInput, "no addresses to send data to")
+            })?;
+        syscall::send_to(self.fd(), buf, addr.into(), 0).await
+    }
+
+    /// Receives a datagram into `buf`.
+    ///
+    /// Returns the number of bytes received and the source address. If the
+    /// datagram is longer than `buf`, the rest of it is discarded.
+    ///
+    /// See also [`std::net::UdpSocket::recv_from`].
+    pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        let (n, addr) = syscall::recv_from(self.fd(), buf, 0).await?;
+        to_socket_addr(addr).map(|addr| (n, addr))
+    }
+
+    /// Receives a batch of datagrams into `bufs`, and their source addresses
+    /// into `addrs`.
+    ///
+    /// This function waits for at least one datagram, and then receives as
+    /// many datagrams as are ready, up to the length of the shorter slice, in
+    /// one pass. Datagrams are received in order, the `i`-th into `bufs[i]`.
+    ///
+    /// Each datagram is received into the start of a buffer, and its bytes
+    /// are marked initialized with [`IoBufMut::set_init`], so buffers are
+    /// expected to be empty.
+    ///
+    /// Returns the number of datagrams received.
+    pub async fn recv_batch<B: IoBufMut>(
+        &self,
+        bufs: &mut [B],
+        addrs: &mut [SocketAddr],
+    ) -> Result<usize> {
+        let len = bufs.len().min(addrs.len());
+        if len == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "no buffers to receive datagrams into",
+            ));
+        }
+        let msgs = loop {
+            // The iovecs are not kept across the wait, so that the future is
+            // `Send`.
+            let iovecs: Vec<_> = bufs[..len]
+                .iter_mut()
+                .map(|buf| libc::iovec {
+                    iov_base: buf.stable_mut_ptr() as _,
+                    iov_len: buf.bytes_total(),
+                })
+                .collect();
+            match unsafe { syscall::try_recvmmsg(self.fd(), &iovecs) } {
+                Ok(msgs) => break msgs,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+            self.readable().await?;
+        };
+        let n = msgs.len();
+        for (i, (len, addr)) in msgs.into_iter().enumerate() {
+            unsafe { bufs[i].set_init(len) };
+            addrs[i] = to_socket_addr(addr)?;
+        }
+        Ok(n)
+    }
+
+    /// Sends a message with data from `bufs` and control messages from
+    /// `control` to `addr`.
+    ///
+    /// If `addr` is `None`, the socket must be connected.
+    ///
+    /// Returns the number of bytes sent.
+    ///
+    /// See also `man sendmsg.2`.
+    pub async fn send_msg(
+        &self,
+        bufs: &[IoSlice<'_>],
+        addr: Option<SocketAddr>,
+        control: &[ControlMessage],
+    ) -> Result<usize> {
+        let addr = addr.map(SockAddr::from);
+        let control = ControlBuf::encode(control);
+        syscall::sendmsg(self.fd(), bufs, addr, control.as_slice(), 0).await
+    }
+
+    /// Receives a message into `bufs` with up to `control_len` bytes of control
+    /// messages.
+    ///
+    /// Use [`ControlMessage::space`] to compute `control_len` for the expected
+    /// control messages.
+    ///
+    /// Returns the number of bytes received, the source address, the received
+    /// control messages, and the flags of the message, which tell if the data
+    /// or the control messages are truncated.
+    ///
+    /// See also `man recvmsg.2`.
+    pub async fn recv_msg(
+        &self,
+        bufs: &mut [IoSliceMut<'_>],
+        control_len: usize,
+    ) -> Result<(usize, SocketAddr, Vec<ControlMessage>, RecvFlags)> {
+        let mut control = ControlBuf::new(control_len);
+        let msg = syscall::recvmsg(self.fd(), bufs, control.as_mut_slice(), 0).await?;
+        let addr = to_socket_addr(msg.addr)?;
+        let control = control.decode(msg.control_len);
+        Ok((msg.len, addr, control, RecvFlags::new(msg.flags)))
+    }
+
+    /// Returns the local socket address of this socket.
+    ///
+    /// See also [`std::net::UdpSocket::local_addr`].
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        let addr = self.0.local_addr()?;
+        to_socket_addr(addr)
+    }
+
+    /// Returns the remote socket address of this socket if it is connected.
+    ///
+    /// See also [`std::net::UdpSocket::peer_addr`].
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        let addr = self.0.peer_addr()?;
+        to_socket_addr(addr)
+    }
+}
+
+impl UdpSocket {
+    fn fd(&self) -> BorrowedFd<'_> {
+        self.as_fd()
+    }
+}
+
+impl AsFd for UdpSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.0.as_raw_fd()) }
+    }
+}
+
+impl AsRawFd for UdpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl FromRawFd for UdpSocket {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self(CloseOnDrop::new(Socket::from_raw_fd(fd)))
+    }
+}
+
+impl IntoRawFd for UdpSocket {
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_inner().into_raw_fd()
+    }
+}
+
+impl From<OwnedFd> for UdpSocket {
+    /// Takes over the socket, switching it to blocking mode.
+    ///
+    /// The descriptor is not checked to be a UDP socket. If it is not,
+    /// operations on the returned value fail, for example with `ENOTSOCK`.
+    fn from(fd: OwnedFd) -> Self {
+        Self(CloseOnDrop::new(adopt_socket(fd)))
+    }
+}
+
+impl From<UdpSocket> for OwnedFd {
+    fn from(socket: UdpSocket) -> Self {
+        unsafe { OwnedFd::from_raw_fd(socket.into_raw_fd()) }
+    }
+}
+
+async fn bind_addr(addr: SocketAddr) -> Result<Socket> {
+    let socket = new_socket(addr, Type::DGRAM).await?;
+    socket.bind(&addr.into())?;
+    Ok(socket)
+}
@@ -0,0 +1,764 @@
+use std::{
+    ffi::OsStr,
+    fmt,
+    future::{ready, Future, Ready},
+    io::{Error, ErrorKind, IoSlice, IoSliceMut, Result},
+    mem::{self, ManuallyDrop},
+    net::Shutdown,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, MetadataExt},
+        io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+    },
+    path::{Path, PathBuf},
+    slice,
+};
+
+use socket2::{Domain, SockAddr, Socket, Type};
+
+use super::{adopt_socket, cmsg::ControlBuf, ControlMessage, RecvFlags};
+use crate::{
+    io::{CloseOnDrop, Initializer, Read, Write},
+    runtime::{syscall, unblock, unblock_detached},
+};
+
+/// The address of a Unix domain socket.
+///
+/// This type is similar to [`std::os::unix::net::SocketAddr`], which can't be
+/// created from a raw address.
+#[derive(Clone)]
+pub struct UnixSocketAddr(SockAddr);
+
+impl UnixSocketAddr {
+    /// Returns true if the address is unnamed, like the address of a socket
+    /// that is not bound, such as a peer accepted by a listener.
+    pub fn is_unnamed(&self) -> bool {
+        self.name().is_empty()
+    }
+
+    /// Returns the path of the address, if it is bound to a path.
+    pub fn as_pathname(&self) -> Option<&Path> {
+        match self.name() {
+            [] | [0, ..] => None,
+            name => {
+                // The path might be followed by a trailing zero.
+                let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+                Some(Path::new(OsStr::from_bytes(&name[..len])))
+            }
+        }
+    }
+
+    /// Returns the name of the address, if it is in the abstract namespace.
+    ///
+    /// See also `man unix.7`.
+    pub fn as_abstract_name(&self) -> Option<&[u8]> {
+        match self.name() {
+            [0, name @ ..] => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes of `sun_path` that are part of the address.
+    fn name(&self) -> &[u8] {
+        let offset = mem::size_of::<libc::sa_family_t>();
+        let len = (self.0.len() as usize).saturating_sub(offset);
+        unsafe {
+            let addr = self.0.as_ptr() as *const u8;
+            slice::from_raw_parts(addr.add(offset), len)
+        }
+    }
+}
+
+impl fmt::Debug for UnixSocketAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = self.as_pathname() {
+            write!(f, "{path:?} (pathname)")
+        } else if let Some(name) = self.as_abstract_name() {
+            write!(f, "{:?} (abstract)", String::from_utf8_lossy(name))
+        } else {
+            f.write_str("(unnamed)")
+        }
+    }
+}
+
+/// Options for binding a [`UnixListener`].
+#[derive(Debug, Default)]
+pub struct UnixListenerOptions {
+    unlink_existing: bool,
+    unlink_on_drop: bool,
+}
+
+impl UnixListenerOptions {
+    /// Creates options that bind like [`UnixListener::bind`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the option to remove a socket file that already exists at the
+    /// path before binding, like one left behind by a crashed process.
+    ///
+    /// Only sockets are removed. Binding still fails if another kind of file
+    /// exists at the path.
+    pub fn unlink_existing(&mut self, unlink: bool) -> &mut Self {
+        self.unlink_existing = unlink;
+        self
+    }
+
+    /// Sets the option to remove the socket file when the listener is
+    /// dropped.
+    ///
+    /// The file is only removed if the path still refers to the socket file
+    /// that is created on bind.
+    pub fn unlink_on_drop(&mut self, unlink: bool) -> &mut Self {
+        self.unlink_on_drop = unlink;
+        self
+    }
+
+    /// Creates a listener bound to `path` with these options.
+    ///
+    /// Binding creates a file in the filesystem, so it runs on a blocking
+    /// thread pool. It doesn't block the current worker thread.
+    pub async fn bind<P: AsRef<Path>>(&self, path: P) -> Result<UnixListener> {
+        let path = path.as_ref().to_path_buf();
+        let addr = SockAddr::unix(&path)?;
+        let socket = new_socket(libc::SOCK_STREAM).await?;
+        let Self {
+            unlink_existing,
+            unlink_on_drop,
+        } = *self;
+        let (socket, path) = unblock(move || {
+            if unlink_existing {
+                match std::fs::symlink_metadata(&path) {
+                    Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(&path)?,
+                    _ => {}
+                }
+            }
+            socket.bind(&addr)?;
+            socket.listen(1024)?;
+            let path = if unlink_on_drop {
+                SocketPath::new(path)
+            } else {
+                None
+            };
+            Ok::<_, std::io::Error>((socket, path))
+        })
+        .await?;
+        Ok(UnixListener {
+            socket: CloseOnDrop::new(socket),
+            path,
+        })
+    }
+}
+
+/// A Unix domain socket listening for connections.
+///
+/// This type is an async version of [`std::os::unix::net::UnixListener`].
+#[derive(Debug)]
+pub struct UnixListener {
+    socket: CloseOnDrop<Socket>,
+    // The socket file to remove on drop.
+    path: Option<SocketPath>,
+}
+
+impl UnixListener {
+    /// Creates a listener bound to `path`.
+    ///
+    /// This fails if a file already exists at `path`, and the socket file is
+    /// left behind when the listener is dropped. See [`UnixListenerOptions`]
+    /// to change these.
+    ///
+    /// See also [`std::os::unix::net::UnixListener::bind`].
+    pub async fn bind<P: AsRef<Path>>(path: P) -> Result<Self> {
+        UnixListenerOptions::new().bind(path).await
+    }
+
+    /// Accepts a new connection from this listener.
+    ///
+    /// The address of the peer is usually unnamed, since clients rarely bind
+    /// their sockets.
+    ///
+    /// See also [`std::os::unix::net::UnixListener::accept`].
+    pub async fn accept(&self) -> Result<(UnixStream, UnixSocketAddr)> {
+        let (fd, addr) = syscall::accept(self.fd()).await?;
+        let stream = unsafe { UnixStream::from_raw_fd(fd.into_raw_fd()) };
+        Ok((stream, UnixSocketAddr(addr)))
+    }
+
+    /// Returns the local socket address of this listener.
+    ///
+    /// See also [`std::os::unix::net::UnixListener::local_addr`].
+    pub fn local_addr(&self) -> Result<UnixSocketAddr> {
+        self.socket.local_addr().map(UnixSocketAddr)
+    }
+}
+
+impl UnixListener {
+    fn fd(&self) -> BorrowedFd<'_> {
+        self.as_fd()
+    }
+}
+
+impl AsFd for UnixListener {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.socket.as_raw_fd()) }
+    }
+}
+
+impl AsRawFd for UnixListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl FromRawFd for UnixListener {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self {
+            socket: CloseOnDrop::new(Socket::from_raw_fd(fd)),
+            path: None,
+        }
+    }
+}
+
+impl IntoRawFd for UnixListener {
+    /// Returns the descriptor, leaving the socket file in place.
+    fn into_raw_fd(self) -> RawFd {
+        if let Some(path) = self.path {
+            path.keep();
+        }
+        self.socket.into_inner().into_raw_fd()
+    }
+}
+
+impl From<OwnedFd> for UnixListener {
+    /// Takes over the socket, switching it to blocking mode.
+    ///
+    /// The descriptor is not checked to be a Unix domain socket. If it is not,
+    /// operations on the returned value fail, for example with `ENOTSOCK`.
+    fn from(fd: OwnedFd) -> Self {
+        Self {
+            socket: CloseOnDrop::new(adopt_socket(fd)),
+            path: None,
+        }
+    }
+}
+
+impl From<UnixListener> for OwnedFd {
+    fn from(listener: UnixListener) -> Self {
+        unsafe { OwnedFd::from_raw_fd(listener.into_raw_fd()) }
+    }
+}
+
+/// The socket file of a listener, which is removed when this is dropped.
+#[derive(Debug)]
+struct SocketPath {
+    path: PathBuf,
+    // Identifies the socket file, so that a file that replaces it is kept.
+    dev: u64,
+    ino: u64,
+}
+
+impl SocketPath {
+    /// Records the socket file at `path`, which must be just bound.
+    fn new(path: PathBuf) -> Option<Self> {
+        let meta = std::fs::symlink_metadata(&path).ok()?;
+        Some(Self {
+            path,
+            dev: meta.dev(),
+            ino: meta.ino(),
+        })
+    }
+
+    /// Leaves the socket file in place.
+    fn keep(self) {
+        let mut this = ManuallyDrop::new(self);
+        drop(mem::take(&mut this.path));
+    }
+}
+
+impl Drop for SocketPath {
+    fn drop(&mut self) {
+        // Drop can't wait for the removal, so it is left to the blocking
+        // thread pool.
+        let path = mem::take(&mut self.path);
+        let (dev, ino) = (self.dev, self.ino);
+        unblock_detached(move || match std::fs::symlink_metadata(&path) {
+            Ok(meta) if meta.dev() == dev && meta.ino() == ino => {
+                let _ = std::fs::remove_file(&path);
+            }
+            _ => {}
+        });
+    }
+}
+
+/// A Unix domain stream socket.
+///
+/// This type is an async version of [`std::os::unix::net::UnixStream`].
+#[derive(Debug)]
+pub struct UnixStream(CloseOnDrop<Socket>);
+
+impl UnixStream {
+    /// Connects to the socket at `path`.
+    ///
+    /// Returns an error of [`std::io::ErrorKind::NotFound`] if nothing exists
+    /// at `path`, or of [`std::io::ErrorKind::ConnectionRefused`] if nothing
+    /// listens on the socket there.
+    ///
+    /// See also [`std::os::unix::net::UnixStream::connect`].
+    pub async fn connect<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let addr = SockAddr::unix(path)?;
+        let stream = Self(CloseOnDrop::new(new_socket(libc::SOCK_STREAM).await?));
+        syscall::connect(stream.fd(), addr).await?;
+        Ok(stream)
+    }
+
+    /// Creates a pair of connected sockets.
+    ///
+    /// See also [`std::os::unix::net::UnixStream::pair`].
+    pub fn pair() -> Result<(Self, Self)> {
+        let (a, b) = Socket::pair(Domain::UNIX, Type::STREAM, None)?;
+        Ok((Self(CloseOnDrop::new(a)), Self(CloseOnDrop::new(b))))
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    ///
+    /// See also [`std::os::unix::net::UnixStream::shutdown`].
+    pub async fn shutdown(&self, how: Shutdown) -> Result<()> {
+        let flags = match how {
+            Shutdown::Both => libc::SHUT_RDWR,
+            Shutdown::Read => libc::SHUT_RD,
+            Shutdown::Write => libc::SHUT_WR,
+        };
+        syscall::shutdown(self.fd(), flags).await.map(|_| ())
+    }
+
+    /// Sends data from `buf` along with the descriptors in `fds`.
+    ///
+    /// The receiver gets new descriptors that refer to the same open files.
+    /// The descriptors are attached to the first byte sent, so `buf` must not
+    /// be empty. Up to 253 descriptors can be sent at once.
+    ///
+    /// Returns the number of bytes sent.
+    ///
+    /// See also `SCM_RIGHTS` in `man unix.7`.
+    pub async fn send_with_fds(&self, buf: &[u8], fds: &[BorrowedFd<'_>]) -> Result<usize> {
+        if buf.is_empty() && !fds.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "descriptors can't be sent without data",
+            ));
+        }
+        send_with_fds(self.fd(), buf, fds).await
+    }
+
+    /// Receives data into `buf` along with the descriptors sent with it,
+    /// which are appended to `fds`.
+    ///
+    /// The received descriptors are created with `O_CLOEXEC`.
+    ///
+    /// Returns the number of bytes received, the number of descriptors
+    /// appended, and the flags of the message. If the kernel can't deliver all
+    /// descriptors, like when the process runs out of descriptors, the
+    /// delivered ones are still appended, and
+    /// [`RecvFlags::is_control_truncated`] returns true.
+    ///
+    /// See also `SCM_RIGHTS` in `man unix.7`.
+    pub async fn recv_with_fds(
+        &self,
+        buf: &mut [u8],
+        fds: &mut Vec<OwnedFd>,
+    ) -> Result<(usize, usize, RecvFlags)> {
+        recv_with_fds(self.fd(), buf, fds).await
+    }
+
+    /// Returns the socket address of the local half of this connection.
+    ///
+    /// See also [`std::os::unix::net::UnixStream::local_addr`].
+    pub fn local_addr(&self) -> Result<UnixSocketAddr> {
+        self.0.local_addr().map(UnixSocketAddr)
+    }
+
+    /// Returns the socket address of the remote peer of this connection.
+    ///
+    /// See also [`std::os::unix::net::UnixStream::peer_addr`].
+    pub fn peer_addr(&self) -> Result<UnixSocketAddr> {
+        self.0.peer_addr().map(UnixSocketAddr)
+    }
+}
+
+impl UnixStream {
+    fn fd(&self) -> BorrowedFd<'_> {
+        self.as_fd()
+    }
+}
+
+impl AsFd for UnixStream {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.0.as_raw_fd()) }
+    }
+}
+
+impl AsRawFd for UnixStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl FromRawFd for UnixStream {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self(CloseOnDrop::new(Socket::from_raw_fd(fd)))
+    }
+}
+
+impl IntoRawFd for UnixStream {
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_inner().into_raw_fd()
+    }
+}
+
+impl From<OwnedFd> for UnixStream {
+    /// Takes over the socket, switching it to blocking mode.
+    ///
+    /// The descriptor is not checked to be a Unix domain socket. If it is not,
+    /// operations on the returned value fail, for example with `ENOTSOCK`.
+    fn from(fd: OwnedFd) -> Self {
+        Self(CloseOnDrop::new(adopt_socket(fd)))
+    }
+}
+
+impl From<UnixStream> for OwnedFd {
+    fn from(stream: UnixStream) -> Self {
+        unsafe { OwnedFd::from_raw_fd(stream.into_raw_fd()) }
+    }
+}
+
+impl Read for UnixStream {
+    type Read<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        syscall::recv(self.fd(), buf, 0)
+    }
+
+    type ReadVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        syscall::readv(self.fd(), bufs)
+    }
+
+    fn is_read_vectored(&self) -> bool {
+        true
+    }
+
+    fn splice_source(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        Some((self.as_raw_fd(), None))
+    }
+
+    fn initializer(&self) -> Initializer {
+        // The kernel only writes to the buffer.
+        unsafe { Initializer::nop() }
+    }
+}
+
+impl Write for UnixStream {
+    type Write<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        // Returns `EPIPE` instead of raising `SIGPIPE` if the peer is closed.
+        syscall::send(self.fd(), buf, libc::MSG_NOSIGNAL)
+    }
+
+    type WriteVectored<'a> = impl Future<Output = Result<usize>> + 'a;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        // `writev` can't pass `MSG_NOSIGNAL`, so this sends a message instead.
+        syscall::sendmsg(self.fd(), bufs, None, &[], libc::MSG_NOSIGNAL)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn splice_sink(&mut self) -> Option<(RawFd, Option<&mut u64>)> {
+        Some((self.as_raw_fd(), None))
+    }
+
+    type Flush<'a> = Ready<Result<()>>;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        ready(Ok(()))
+    }
+
+    type Shutdown<'a> = impl Future<Output = Result<()>> + 'a;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        syscall::shutdown(self.fd(), libc::SHUT_WR)
+    }
+}
+
+/// A Unix domain datagram socket.
+///
+/// This type is an async version of [`std::os::unix::net::UnixDatagram`].
+#[derive(Debug)]
+pub struct UnixDatagram(CloseOnDrop<Socket>);
+
+impl UnixDatagram {
+    /// Creates a socket bound to `path`.
+    ///
+    /// Paths that start with a zero byte are names in the abstract namespace,
+    /// which don't create a file.
+    ///
+    /// Binding creates a file in the filesystem, so it runs on a blocking
+    /// thread pool. It doesn't block the current worker thread.
+    ///
+    /// See also [`std::os::unix::net::UnixDatagram::bind`].
+    pub async fn bind<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let addr = SockAddr::unix(path)?;
+        let socket = new_socket(libc::SOCK_DGRAM).await?;
+        let socket = unblock(move || socket.bind(&addr).map(|_| socket)).await?;
+        Ok(Self(CloseOnDrop::new(socket)))
+    }
+
+    /// Creates a socket that is not bound to any address.
+    ///
+    /// An unbound socket can send datagrams, but the receivers see an unnamed
+    /// source address that they can't reply to.
+    ///
+    /// See also [`std::os::unix::net::UnixDatagram::unbound`].
+    pub async fn unbound() -> Result<Self> {
+        let socket = new_socket(libc::SOCK_DGRAM).await?;
+        Ok(Self(CloseOnDrop::new(socket)))
+    }
+
+    /// Creates a pair of connected sockets.
+    ///
+    /// See also [`std::os::unix::net::UnixDatagram::pair`].
+    pub fn pair() -> Result<(Self, Self)> {
+        let (a, b) = Socket::pair(Domain::UNIX, Type::DGRAM, None)?;
+        Ok((Self(CloseOnDrop::new(a)), Self(CloseOnDrop::new(b))))
+    }
+
+    /// Connects this socket to the socket at `path`.
+    ///
+    /// A connected socket can use [`Self::send`] and [`Self::recv`], and only
+    /// receives datagrams from the socket at `path`.
+    ///
+    /// See also [`std::os::unix::net::UnixDatagram::connect`].
+    pub async fn connect<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let addr = SockAddr::unix(path)?;
+        syscall::connect(self.fd(), addr).await
+    }
+
+    /// Sends data from `buf` to the connected socket.
+    ///
+    /// Returns the number of bytes sent.
+    ///
+    /// See also [`std::os::unix::net::UnixDatagram::send`].
+    pub async fn send(&self, buf: &[u8]) -> Result<usize> {
+        syscall::send(self.fd(), buf, libc::MSG_NOSIGNAL).await
+    }
+
+    /// Receives a datagram from the connected socket into `buf`.
+    ///
+    /// Returns the number of bytes received. If the datagram is longer than
+    /// `buf`, the rest of it is discarded.
+    ///
+    /// See also [`std::os::unix::net::UnixDatagram::recv`].
+    pub async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        syscall::recv(self.fd(), buf, 0).await
+    }
+
+    /// Sends data from `buf` to the socket at `path`.
+    ///
+    /// Returns the number of bytes sent.
+    ///
+    /// See also [`std::os::unix::net::UnixDatagram::send_to`].
+    pub async fn send_to<P: AsRef<Path>>(&self, buf: &[u8], path: P) -> Result<usize> {
+        let addr = SockAddr::unix(path)?;
+        syscall::send_to(self.fd(), buf, addr, libc::MSG_NOSIGNAL).await
+    }
+
+    /// Sends data from `buf` to the socket at `addr`.
+    ///
+    /// This is useful to reply to the source of a datagram, which might be
+    /// bound in the abstract namespace.
+    ///
+    /// Returns the number of bytes sent.
+    pub async fn send_to_addr(&self, buf: &[u8], addr: &UnixSocketAddr) -> Result<usize> {
+        syscall::send_to(self.fd(), buf, addr.0.clone(), libc::MSG_NOSIGNAL).await
+    }
+
+    /// Receives a datagram into `buf`.
+    ///
+    /// Returns the number of bytes received and the source address, which is
+    /// unnamed if the source is not bound. If the datagram is longer than
+    /// `buf`, the rest of it is discarded.
+    ///
+    /// See also [`std::os::unix::net::UnixDatagram::recv_from`].
+    pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, UnixSocketAddr)> {
+        let (n, addr) = syscall::recv_from(self.fd(), buf, 0).await?;
+        Ok((n, UnixSocketAddr(addr)))
+    }
+
+    /// Sends a datagram with data from `buf` along with the descriptors in
+    /// `fds` to the connected socket.
+    ///
+    /// The receiver gets new descriptors that refer to the same open files. Up
+    /// to 253 descriptors can be sent at once.
+    ///
+    /// Returns the number of bytes sent.
+    ///
+    /// See also `SCM_RIGHTS` in `man unix.7`.
+    pub async fn send_with_fds(&self, buf: &[u8], fds: &[BorrowedFd<'_>]) -> Result<usize> {
+        send_with_fds(self.fd(), buf, fds).await
+    }
+
+    /// Receives a datagram into `buf` along with the descriptors sent with it,
+    /// which are appended to `fds`.
+    ///
+    /// The received descriptors are created with `O_CLOEXEC`.
+    ///
+    /// Returns the number of bytes received, the number of descriptors
+    /// appended, and the flags of the message. If the datagram is longer than
+    /// `buf`, the rest of it is discarded, and [`RecvFlags::is_truncated`]
+    /// returns true. If the kernel can't deliver all descriptors, like when the
+    /// process runs out of descriptors, the delivered ones are still appended,
+    /// and [`RecvFlags::is_control_truncated`] returns true.
+    ///
+    /// See also `SCM_RIGHTS` in `man unix.7`.
+    pub async fn recv_with_fds(
+        &self,
+        buf: &mut [u8],
+        fds: &mut Vec<OwnedFd>,
+    ) -> Result<(usize, usize, RecvFlags)> {
+        recv_with_fds(self.fd(), buf, fds).await
+    }
+
+    /// Shuts down the read, write, or both halves of this socket.
+    ///
+    /// See also [`std::os::unix::net::UnixDatagram::shutdown`].
+    pub async fn shutdown(&self, how: Shutdown) -> Result<()> {
+        let flags = match how {
+            Shutdown::Both => libc::SHUT_RDWR,
+            Shutdown::Read => libc::SHUT_RD,
+            Shutdown::Write => libc::SHUT_WR,
+        };
+        syscall::shutdown(self.fd(), flags).await.map(|_| ())
+    }
+
+    /// Returns the local socket address of this socket.
+    ///
+    /// See also [`std::os::unix::net::UnixDatagram::local_addr`].
+    pub fn local_addr(&self) -> Result<UnixSocketAddr> {
+        self.0.local_addr().map(UnixSocketAddr)
+    }
+
+    /// Returns the remote socket address of this socket if it is connected.
+    ///
+    /// See also [`std::os::unix::net::UnixDatagram::peer_addr`].
+    pub fn peer_addr(&self) -> Result<UnixSocketAddr> {
+        self.0.peer_addr().map(UnixSocketAddr)
+    }
+}
+
+impl UnixDatagram {
+    fn fd(&self) -> BorrowedFd<'_> {
+        self.as_fd()
+    }
+}
+
+impl AsFd for UnixDatagram {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.0.as_raw_fd()) }
+    }
+}
+
+impl AsRawFd for UnixDatagram {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl FromRawFd for UnixDatagram {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self(CloseOnDrop::new(Socket::from_raw_fd(fd)))
+    }
+}
+
+impl IntoRawFd for UnixDatagram {
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_inner().into_raw_fd()
+    }
+}
+
+impl From<OwnedFd> for UnixDatagram {
+    /// Takes over the socket, switching it to blocking mode.
+    ///
+    /// The descriptor is not checked to be a Unix domain socket. If it is not,
+    /// operations on the returned value fail, for example with `ENOTSOCK`.
+    fn from(fd: OwnedFd) -> Self {
+        Self(CloseOnDrop::new(adopt_socket(fd)))
+    }
+}
+
+impl From<UnixDatagram> for OwnedFd {
+    fn from(socket: UnixDatagram) -> Self {
+        unsafe { OwnedFd::from_raw_fd(socket.into_raw_fd()) }
+    }
+}
+
+/// The maximum number of descriptors in a message.
+///
+/// See also `SCM_MAX_FD` in `net/scm.h`.
+const MAX_FDS: usize = 253;
+
+async fn send_with_fds(fd: BorrowedFd<'_>, buf: &[u8], fds: &[BorrowedFd<'_>]) -> Result<usize> {
+    let bufs = [IoSlice::new(buf)];
+    let control = if fds.is_empty() {
+        ControlBuf::new(0)
+    } else {
+        let data: Vec<u8> = fds
+            .iter()
+            .flat_map(|fd| fd.as_raw_fd().to_ne_bytes())
+            .collect();
+        ControlBuf::encode(&[ControlMessage::new(
+            libc::SOL_SOCKET,
+            libc::SCM_RIGHTS,
+            data,
+        )])
+    };
+    // Returns `EPIPE` instead of raising `SIGPIPE` if the peer is closed.
+    syscall::sendmsg(fd, &bufs, None, control.as_slice(), libc::MSG_NOSIGNAL).await
+}
+
+async fn recv_with_fds(
+    fd: BorrowedFd<'_>,
+    buf: &mut [u8],
+    fds: &mut Vec<OwnedFd>,
+) -> Result<(usize, usize, RecvFlags)> {
+    let mut bufs = [IoSliceMut::new(buf)];
+    let mut control = ControlBuf::new(ControlMessage::space(MAX_FDS * mem::size_of::<RawFd>()));
+    let msg = syscall::recvmsg(
+        fd,
+        &mut bufs,
+        control.as_mut_slice(),
+        libc::MSG_CMSG_CLOEXEC,
+    )
+    .await?;
+    // The kernel installs the descriptors before the message is returned, so
+    // they are owned here even if the control messages are truncated.
+    let start = fds.len();
+    for m in control.decode(msg.control_len) {
+        if m.level != libc::SOL_SOCKET || m.ty != libc::SCM_RIGHTS {
+            continue;
+        }
+        for raw in m.data.chunks_exact(mem::size_of::<RawFd>()) {
+            let raw = RawFd::from_ne_bytes(raw.try_into().unwrap());
+            fds.push(unsafe { OwnedFd::from_raw_fd(raw) });
+        }
+    }
+    Ok((msg.len, fds.len() - start, RecvFlags::new(msg.flags)))
+}
+
+async fn new_socket(ty: libc::c_int) -> Result<Socket> {
+    let fd = syscall::socket(libc::AF_UNIX, ty, 0).await?;
+    Ok(unsafe { Socket::from_raw_fd(fd.into_raw_fd()) })
+}
@@ -0,0 +1,65 @@
+//! Primitives for child processes.
+//!
+//! This module is an async version of [`std::process`]. Processes are spawned
+//! with [`std::process::Command`], and then converted into a [`Child`] to be
+//! waited asynchronously.
+
+use std::{
+    io::Result,
+    os::unix::process::ExitStatusExt,
+    process::{self, ExitStatus},
+};
+
+use crate::runtime::syscall;
+
+/// A child process.
+#[derive(Debug)]
+pub struct Child {
+    inner: process::Child,
+    status: Option<ExitStatus>,
+}
+
+impl Child {
+    /// Returns the OS-assigned process identifier of the child.
+    pub fn id(&self) -> u32 {
+        self.inner.id()
+    }
+
+    /// Waits for the child to exit.
+    ///
+    /// The child is reaped without a signal handler or a blocking thread. The
+    /// exit status is cached, so this can be called again after the child
+    /// exits.
+    ///
+    /// Returns an error of `ECHILD` if the child has been reaped by someone
+    /// else, for example, by a concurrent `waitpid` on the same pid.
+    pub async fn wait(&mut self) -> Result<ExitStatus> {
+        if let Some(status) = self.status {
+            return Ok(status);
+        }
+        let info = syscall::waitid(self.inner.id() as _, libc::WEXITED).await?;
+        let status = exit_status(&info);
+        self.status = Some(status);
+        Ok(status)
+    }
+}
+
+impl From<process::Child> for Child {
+    fn from(inner: process::Child) -> Self {
+        Self {
+            inner,
+            status: None,
+        }
+    }
+}
+
+/// Converts the state change in `info` to a wait status.
+fn exit_status(info: &libc::siginfo_t) -> ExitStatus {
+    let status = unsafe { info.si_status() };
+    let raw = match info.si_code {
+        libc::CLD_EXITED => (status & 0xff) << 8,
+        libc::CLD_DUMPED => status | 0x80,
+        _ => status,
+    };
+    ExitStatus::from_raw(raw)
+}
@@ -0,0 +1,116 @@
+//! A thread pool to run blocking system calls off the worker threads.
+
+use std::{
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{Condvar, Mutex, OnceLock},
+    thread,
+    time::Duration,
+};
+
+use futures::channel::oneshot;
+use log::trace;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The maximum number of blocking threads.
+const MAX_THREADS: usize = 512;
+/// The time an idle thread waits for a new job before it exits.
+const KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+struct Pool {
+    state: Mutex<State>,
+    cond: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    jobs: VecDeque<Job>,
+    num_idle: usize,
+    num_threads: usize,
+}
+
+impl Pool {
+    fn get() -> &'static Pool {
+        static POOL: OnceLock<Pool> = OnceLock::new();
+        POOL.get_or_init(|| Pool {
+            state: Mutex::new(State::default()),
+            cond: Condvar::new(),
+        })
+    }
+
+    fn execute(&'static self, job: Job) {
+        let mut state = self.state.lock().unwrap();
+        state.jobs.push_back(job);
+        // Idle threads that are notified but not awake yet still count as
+        // idle, and each of them takes one of the queued jobs. A job that no
+        // idle thread will take gets a new thread, so that it isn't delayed
+        // behind another blocking job.
+        if state.jobs.len() <= state.num_idle {
+            self.cond.notify_one();
+        } else if state.num_threads < MAX_THREADS {
+            let id = state.num_threads;
+            let spawned = thread::Builder::new()
+                .name(format!("photonio-blocking/{}", id))
+                .spawn(move || self.run());
+            match spawned {
+                Ok(_) => state.num_threads += 1,
+                // The job will be picked up by one of the existing threads.
+                Err(e) if state.num_threads > 0 => trace!("failed to spawn blocking thread: {}", e),
+                Err(e) => panic!("failed to spawn blocking thread: {}", e),
+            }
+        }
+    }
+
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(job) = state.jobs.pop_front() {
+                drop(state);
+                job();
+                state = self.state.lock().unwrap();
+                continue;
+            }
+            state.num_idle += 1;
+            let (next, timeout) = self.cond.wait_timeout(state, KEEP_ALIVE).unwrap();
+            state = next;
+            state.num_idle -= 1;
+            if timeout.timed_out() && state.jobs.is_empty() {
+                state.num_threads -= 1;
+                return;
+            }
+        }
+    }
+}
+
+/// Runs a blocking function on the blocking thread pool.
+///
+/// If the function panics, the panic is propagated to the caller.
+pub(crate) async fn unblock<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    Pool::get().execute(Box::new(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(f));
+        let _ = tx.send(result);
+    }));
+    match rx.await.expect("blocking job was dropped") {
+        Ok(v) => v,
+        Err(e) => panic::resume_unwind(e),
+    }
+}
+
+/// Runs a blocking function on the blocking thread pool without waiting for
+/// it, like cleanups in `Drop` that can't be awaited.
+///
+/// If the function panics, the panic is ignored.
+pub(crate) fn unblock_detached<F>(f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    Pool::get().execute(Box::new(move || {
+        let _ = panic::catch_unwind(AssertUnwindSafe(f));
+    }));
+}
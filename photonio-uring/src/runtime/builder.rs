@@ -1,12 +1,17 @@
 use std::io::Result;
 
 use super::{Runtime, Shared};
+use crate::io::Opcode;
 
 /// Builds a [`Runtime`] with custom options.
 pub struct Builder {
     pub(super) num_threads: usize,
     pub(super) thread_stack_size: usize,
     pub(super) event_interval: usize,
+    pub(super) disabled_opcodes: Vec<Opcode>,
+    pub(super) sqe128: bool,
+    pub(super) cqe32: bool,
+    pub(super) fixed_files: u32,
 }
 
 impl Builder {
@@ -16,6 +21,10 @@ impl Builder {
             num_threads: num_cpus::get(),
             thread_stack_size: 2 << 20,
             event_interval: 3,
+            disabled_opcodes: Vec::new(),
+            sqe128: false,
+            cqe32: false,
+            fixed_files: 1024,
         }
     }
 
@@ -43,6 +52,53 @@ impl Builder {
         self
     }
 
+    /// Sets up the rings of workers with 128-byte SQEs.
+    ///
+    /// This is required by [`crate::io::uring_cmd`], and it doubles the memory
+    /// of submission queues. The default value is false.
+    ///
+    /// See also `IORING_SETUP_SQE128` in `man io_uring_setup.2`.
+    pub fn sqe128(mut self, sqe128: bool) -> Self {
+        self.sqe128 = sqe128;
+        self
+    }
+
+    /// Sets up the rings of workers with 32-byte CQEs.
+    ///
+    /// The extra bytes are returned by [`crate::io::uring_cmd`]. The default
+    /// value is false.
+    ///
+    /// See also `IORING_SETUP_CQE32` in `man io_uring_setup.2`.
+    pub fn cqe32(mut self, cqe32: bool) -> Self {
+        self.cqe32 = cqe32;
+        self
+    }
+
+    /// Sets the number of slots in the fixed file table of each worker.
+    ///
+    /// This bounds the files that can be registered with
+    /// [`crate::io::register_files`] and opened as direct descriptors on a
+    /// worker at the same time. The table is registered when the first file is,
+    /// and the kernel fails the registration if this is 0 or exceeds
+    /// `RLIMIT_NOFILE`. The default value is 1024.
+    ///
+    /// See also `IORING_REGISTER_FILES` in `man io_uring_register.2`.
+    pub fn fixed_files(mut self, fixed_files: u32) -> Self {
+        self.fixed_files = fixed_files;
+        self
+    }
+
+    /// Disables `opcode` as if the kernel doesn't support it.
+    ///
+    /// This is useful to exercise the fallbacks of operations on a kernel that
+    /// supports them.
+    ///
+    /// See also [`crate::io::is_supported`].
+    pub fn disable_opcode(mut self, opcode: Opcode) -> Self {
+        self.disabled_opcodes.push(opcode);
+        self
+    }
+
     /// Creates a runtime with the specified options.
     pub fn build(self) -> Result<Runtime> {
         let shared = Shared::new(self)?;
@@ -1,57 +1,416 @@
 use std::{
+    collections::VecDeque,
     io::{Error, ErrorKind, Result},
-    os::unix::io::{AsRawFd, FromRawFd, OwnedFd},
-    sync::Arc,
+    os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 
-use io_uring::{opcode, squeue, types, IoUring};
+use io_uring::{cqueue, opcode, squeue, types, Probe};
+
+use super::Builder;
+use crate::io::{apply_io_priority, Opcode};
 
 mod op;
-pub(super) use op::Op;
+use op::Submission;
+pub(super) use op::{BufOp, MultiOp, Op};
+
+mod ring;
+use ring::Ring;
 
 mod optable;
 use optable::OpTable;
+pub(super) use optable::Reaped;
+pub(crate) use optable::{Completion, Discard};
 
 pub(super) struct Driver {
-    io: IoUring,
+    id: u64,
+    io: Ring,
+    probe: Option<Probe>,
+    // Opcodes that are treated as unsupported regardless of the probe.
+    disabled_opcodes: Vec<Opcode>,
     table: OpTable,
+    // The wakers and discard functions of pulled completions, see
+    // `take_reaped`.
+    reaped: Reaped,
     eventfd: Arc<OwnedFd>,
-    eventbuf: [u8; 8],
+    // The buffer of the read of `eventfd`, which is boxed so that it doesn't
+    // move with the driver while the read is in flight.
+    eventbuf: Box<[u8; 8]>,
+    // Whether the read of `eventfd` is in flight, see `park`.
+    is_unpark_armed: bool,
+    // The payloads of the wakeups posted by other drivers, see `take_wakes`.
+    wakes: Vec<u32>,
+    // The number of slots in the fixed file table.
+    num_files: u32,
+    // The slots of the fixed file table in use, registered on demand.
+    files: Option<Vec<bool>>,
+    // Entries that don't fit in the submission queue, in the order they are
+    // pushed. Linked entries are kept together.
+    backlog: VecDeque<Vec<squeue::Entry>>,
+    #[cfg(feature = "test-hooks")]
+    hooks: Hooks,
+}
+
+/// The state of the test hooks, which are only built with the `test-hooks`
+/// feature.
+#[cfg(feature = "test-hooks")]
+#[derive(Default)]
+struct Hooks {
+    // Results to replace the next completions with, see `inject_results`.
+    injected: VecDeque<i32>,
+    // The I/O priority of the last added entry, see `last_io_priority`.
+    last_ioprio: u16,
+    // The opcode of the last added entry, see `last_opcode`.
+    last_opcode: u8,
 }
 
 impl Driver {
-    pub(super) fn new(unpark: Unpark) -> Result<Self> {
-        let io = IoUring::builder().setup_iopoll().build(4096)?;
+    pub(super) fn new(unpark: Unpark, builder: &Builder) -> Result<Self> {
+        let io = Ring::new(4096, builder.sqe128, builder.cqe32)?;
+        // Kernels before 5.6 don't support probing.
+        let mut probe = Probe::new();
+        let probe = io
+            .submitter()
+            .register_probe(&mut probe)
+            .ok()
+            .map(|_| probe);
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
         Ok(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
             io,
+            probe,
+            disabled_opcodes: builder.disabled_opcodes.clone(),
             table: OpTable::new(),
+            reaped: Reaped::default(),
             eventfd: unpark.0,
-            eventbuf: [0; 8],
+            eventbuf: Box::new([0; 8]),
+            is_unpark_armed: false,
+            wakes: Vec::new(),
+            num_files: builder.fixed_files,
+            files: None,
+            backlog: VecDeque::new(),
+            #[cfg(feature = "test-hooks")]
+            hooks: Hooks::default(),
         })
     }
 
     pub(super) unsafe fn add(&mut self, sqe: squeue::Entry) -> Result<Op> {
+        let sqe = Submission::Entry(self.prioritize(sqe));
+        self.add_submission(sqe)
+    }
+
+    /// Adds an op that resubmits `sqe` if it is interrupted.
+    unsafe fn add_submission(&mut self, sqe: Submission) -> Result<Op> {
+        let index = self.resubmit(&self.table.clone(), sqe.clone())?;
+        Ok(Op::new(self.table.clone(), index).retry_with(sqe))
+    }
+
+    /// Submits `sqe` again for an op of `table`.
+    ///
+    /// Returns the new index of the op, or an error if the op doesn't belong
+    /// to this driver.
+    pub(super) unsafe fn resubmit(&mut self, table: &OpTable, sqe: Submission) -> Result<usize> {
+        if !self.table.ptr_eq(table) {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "the op is submitted by another worker",
+            ));
+        }
+        // Cancels dropped ops first, so that they can't affect new ones.
+        self.push_cancelled();
+        // 128-byte entries can't be deferred, since the backlog holds 64-byte
+        // ones.
+        if let Submission::Entry128(_) = sqe {
+            self.reserve(1)?;
+        }
         let index = self.table.add();
-        assert!(index as u64 != Self::UNPARK_TOKEN);
-        self.push(sqe.user_data(index as u64))?;
-        Ok(Op::new(self.table.clone(), index))
+        assert!(!Self::is_internal(index as u64));
+        match sqe {
+            Submission::Entry(sqe) => self.push(sqe.user_data(index as u64)),
+            Submission::Entry128(sqe) => assert!(self.io.push128(&sqe.user_data(index as u64))),
+            Submission::WithTimeout(sqe, timeout) => {
+                self.push_linked(&[sqe.user_data(index as u64), timeout])
+            }
+        }
+        Ok(index)
+    }
+
+    /// Replaces the results of the next completions with `results`.
+    ///
+    /// This is a test hook to exercise the handling of rare results.
+    #[cfg(feature = "test-hooks")]
+    pub(super) fn inject_results(&mut self, results: &[i32]) {
+        self.hooks.injected.extend(results);
+    }
+
+    /// Returns the I/O priority of the last added entry.
+    ///
+    /// This is a test hook to observe the priority, see `last_io_priority`.
+    #[cfg(feature = "test-hooks")]
+    pub(super) fn last_ioprio(&self) -> u16 {
+        self.hooks.last_ioprio
+    }
+
+    /// Returns the opcode of the last added entry.
+    ///
+    /// This is a test hook to observe which path an operation takes, see
+    /// `last_opcode`.
+    #[cfg(feature = "test-hooks")]
+    pub(super) fn last_opcode(&self) -> u8 {
+        self.hooks.last_opcode
+    }
+
+    /// Adds a 128-byte operation.
+    ///
+    /// Returns an error if the ring doesn't use 128-byte SQEs.
+    pub(super) unsafe fn add128(&mut self, sqe: squeue::Entry128) -> Result<Op> {
+        if !self.io.is_sqe128() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the worker is not set up with 128-byte SQEs",
+            ));
+        }
+        self.add_submission(Submission::Entry128(sqe))
+    }
+
+    /// Adds an operation whose completion is ignored.
+    pub(super) unsafe fn add_detached(&mut self, sqe: squeue::Entry) {
+        self.push(sqe.user_data(Self::DETACHED_TOKEN));
+    }
+
+    /// Adds a multishot operation that produces multiple completions.
+    pub(super) unsafe fn add_multi(&mut self, sqe: squeue::Entry) -> Result<MultiOp> {
+        let sqe = self.prioritize(sqe);
+        self.push_cancelled();
+        let index = self.table.add_multi();
+        assert!(!Self::is_internal(index as u64));
+        self.push(sqe.user_data(index as u64));
+        Ok(MultiOp::new(self.table.clone(), index))
+    }
+
+    /// Adds an operation that is cancelled if it doesn't complete in `timeout`.
+    ///
+    /// The cancelled operation completes with `ECANCELED`. The timeout starts
+    /// over if the operation is resubmitted.
+    ///
+    /// The caller must keep `timeout` alive until the operation completes,
+    /// since the entries may be deferred.
+    pub(super) unsafe fn add_with_timeout(
+        &mut self,
+        sqe: squeue::Entry,
+        timeout: &types::Timespec,
+    ) -> Result<Op> {
+        let sqe = self.prioritize(sqe).flags(squeue::Flags::IO_LINK);
+        // The completion of the timeout is ignored, so that it can't be
+        // confused with another operation no matter when it fires.
+        let timeout = opcode::LinkTimeout::new(timeout)
+            .build()
+            .user_data(Self::TIMEOUT_TOKEN);
+        self.add_submission(Submission::WithTimeout(sqe, timeout))
+    }
+
+    /// Adds a chain of operations that are executed in order.
+    ///
+    /// If an operation fails or returns a short result, the remaining ones
+    /// complete with `ECANCELED`. An interrupted operation is resubmitted on
+    /// its own, and the remaining ones still complete with `ECANCELED`.
+    pub(super) unsafe fn add_linked(&mut self, sqes: Vec<squeue::Entry>) -> Result<Vec<Op>> {
+        self.push_cancelled();
+        let last = sqes.len().saturating_sub(1);
+        let mut ops = Vec::with_capacity(sqes.len());
+        let mut chain = Vec::with_capacity(sqes.len());
+        for (i, sqe) in sqes.into_iter().enumerate() {
+            let sqe = self.prioritize(sqe);
+            let index = self.table.add();
+            assert!(!Self::is_internal(index as u64));
+            let linked = if i < last {
+                sqe.clone().flags(squeue::Flags::IO_LINK)
+            } else {
+                sqe.clone()
+            };
+            chain.push(linked.user_data(index as u64));
+            ops.push(Op::new(self.table.clone(), index).retry_with(Submission::Entry(sqe)));
+        }
+        self.push_linked(&chain);
+        Ok(ops)
+    }
+
+    /// Returns the file descriptor of the ring.
+    pub(super) fn ring_fd(&self) -> RawFd {
+        self.io.as_raw_fd()
+    }
+
+    /// Wakes up the driver of the ring `fd` by posting a completion to it.
+    ///
+    /// The completion returns the target driver from parking, and delivers
+    /// `payload` to it, see [`Self::take_wakes`].
+    ///
+    /// See also `IORING_OP_MSG_RING` in `man io_uring_enter.2`.
+    pub(super) fn wake(&mut self, fd: RawFd, payload: u32) -> Result<()> {
+        if !self.is_supported(Opcode::MsgRing) {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "IORING_OP_MSG_RING is not supported by the kernel",
+            ));
+        }
+        let sqe = opcode::MsgRing::new(types::Fd(fd), payload, Self::WAKE_TOKEN).build();
+        unsafe {
+            self.add_detached(sqe);
+        }
+        self.submit().map(|_| ())
+    }
+
+    /// Returns the unique id of this driver.
+    pub(super) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Registers a ring of provided buffers for the buffer group `bgid`.
+    ///
+    /// See also `IORING_REGISTER_PBUF_RING` in `man io_uring_register.2`.
+    pub(super) fn register_buf_ring(&self, ring_addr: u64, entries: u16, bgid: u16) -> Result<()> {
+        let reg = BufReg {
+            ring_addr,
+            ring_entries: entries as u32,
+            bgid,
+            flags: 0,
+            resv: [0; 3],
+        };
+        self.register(Self::REGISTER_PBUF_RING, &reg as *const _ as *const _, 1)
+    }
+
+    /// Unregisters the ring of provided buffers for the buffer group `bgid`.
+    pub(super) fn unregister_buf_ring(&self, bgid: u16) -> Result<()> {
+        let reg = BufReg {
+            ring_addr: 0,
+            ring_entries: 0,
+            bgid,
+            flags: 0,
+            resv: [0; 3],
+        };
+        self.register(Self::UNREGISTER_PBUF_RING, &reg as *const _ as *const _, 1)
+    }
+
+    /// Registers buffers for fixed operations.
+    ///
+    /// See also `IORING_REGISTER_BUFFERS` in `man io_uring_register.2`.
+    pub(super) fn register_buffers(&self, iovecs: &[libc::iovec]) -> Result<()> {
+        self.register(
+            Self::REGISTER_BUFFERS,
+            iovecs.as_ptr() as *const _,
+            iovecs.len() as _,
+        )
+    }
+
+    /// Unregisters the buffers for fixed operations.
+    pub(super) fn unregister_buffers(&self) -> Result<()> {
+        self.register(Self::UNREGISTER_BUFFERS, std::ptr::null(), 0)
+    }
+
+    /// Registers `fds` in free slots of the fixed file table.
+    ///
+    /// Returns the slots of the files in order.
+    ///
+    /// See also `IORING_REGISTER_FILES` in `man io_uring_register.2`.
+    pub(super) fn register_files(&mut self, fds: &[RawFd]) -> Result<Vec<u32>> {
+        let slots = self.free_files(fds.len())?;
+        for (i, (&slot, &fd)) in slots.iter().zip(fds).enumerate() {
+            if let Err(e) = self.update_file(slot, fd) {
+                for &slot in &slots[..i] {
+                    let _ = self.update_file(slot, -1);
+                }
+                return Err(e);
+            }
+        }
+        let files = self.files.as_mut().unwrap();
+        for &slot in &slots {
+            files[slot as usize] = true;
+        }
+        Ok(slots)
+    }
+
+    /// Allocates a free slot of the fixed file table, for an operation to
+    /// install a direct descriptor into.
+    ///
+    /// The slot should be freed with [`Self::unregister_file`].
+    pub(super) fn alloc_file(&mut self) -> Result<u32> {
+        let slot = self.free_files(1)?[0];
+        self.files.as_mut().unwrap()[slot as usize] = true;
+        Ok(slot)
+    }
+
+    /// Replaces the file in the fixed file table `slot` with `fd`.
+    ///
+    /// An `fd` of `-1` clears the slot.
+    pub(super) fn update_file(&self, slot: u32, fd: RawFd) -> Result<()> {
+        let update = FilesUpdate {
+            offset: slot,
+            resv: 0,
+            fds: &fd as *const RawFd as u64,
+        };
+        self.register(
+            Self::REGISTER_FILES_UPDATE,
+            &update as *const _ as *const _,
+            1,
+        )
+    }
+
+    /// Clears the fixed file table `slot` and makes it free.
+    pub(super) fn unregister_file(&mut self, slot: u32) -> Result<()> {
+        self.update_file(slot, -1)?;
+        if let Some(used) = self
+            .files
+            .as_mut()
+            .and_then(|files| files.get_mut(slot as usize))
+        {
+            *used = false;
+        }
+        Ok(())
+    }
+
+    /// Returns true if the kernel supports the given opcode.
+    pub(super) fn is_supported(&self, opcode: Opcode) -> bool {
+        if self.disabled_opcodes.contains(&opcode) {
+            return false;
+        }
+        self.probe
+            .as_ref()
+            .map_or(false, |probe| probe.is_supported(opcode.code()))
+    }
+
+    /// Submits all pending entries to the kernel without waiting.
+    ///
+    /// Returns the number of entries submitted.
+    pub(super) fn flush(&mut self) -> Result<usize> {
+        self.push_cancelled();
+        self.submit()
     }
 
     pub(super) fn tick(&mut self) -> Result<()> {
+        self.push_cancelled();
         self.submit()?;
         self.pull();
         Ok(())
     }
 
     pub(super) fn park(&mut self) -> Result<()> {
-        // Register the eventfd to unpark this driver.
-        let fd = types::Fd(self.eventfd.as_raw_fd());
-        let buf = &mut self.eventbuf;
-        let sqe = opcode::Read::new(fd, buf.as_mut_ptr(), buf.len() as _)
-            .build()
-            .user_data(Self::UNPARK_TOKEN);
-        unsafe {
-            self.push(sqe)?;
+        self.push_cancelled();
+        // Registers the eventfd to unpark this driver. If the driver returns
+        // from an earlier park for another completion, its read is still in
+        // flight and serves this park too.
+        if !self.is_unpark_armed {
+            let fd = types::Fd(self.eventfd.as_raw_fd());
+            let buf = &mut *self.eventbuf;
+            let sqe = opcode::Read::new(fd, buf.as_mut_ptr(), buf.len() as _)
+                .build()
+                .user_data(Self::UNPARK_TOKEN);
+            unsafe {
+                self.push(sqe);
+            }
+            self.is_unpark_armed = true;
         }
         self.submit_and_wait(1)?;
         self.pull();
@@ -61,40 +420,205 @@ impl Driver {
 
 impl Driver {
     const UNPARK_TOKEN: u64 = u64::MAX;
+    const TIMEOUT_TOKEN: u64 = u64::MAX - 1;
+    const CANCEL_TOKEN: u64 = u64::MAX - 2;
+    const DETACHED_TOKEN: u64 = u64::MAX - 4;
+    const WAKE_TOKEN: u64 = u64::MAX - 3;
 
-    unsafe fn push(&mut self, sqe: squeue::Entry) -> Result<()> {
-        while {
-            let mut sq = self.io.submission();
-            sq.push(&sqe)
+    const REGISTER_BUFFERS: libc::c_uint = 0;
+    const UNREGISTER_BUFFERS: libc::c_uint = 1;
+    const REGISTER_FILES: libc::c_uint = 2;
+    const REGISTER_FILES_UPDATE: libc::c_uint = 6;
+    // The io_uring crate doesn't support these registrations yet.
+    const REGISTER_PBUF_RING: libc::c_uint = 22;
+    const UNREGISTER_PBUF_RING: libc::c_uint = 23;
+
+    /// Returns `n` free slots of the fixed file table.
+    fn free_files(&mut self, n: usize) -> Result<Vec<u32>> {
+        if self.files.is_none() {
+            // Registers a sparse table, whose slots are updated later.
+            let table = vec![-1; self.num_files as usize];
+            self.register(
+                Self::REGISTER_FILES,
+                table.as_ptr() as *const _,
+                self.num_files,
+            )?;
+            self.files = Some(vec![false; self.num_files as usize]);
+        }
+        let files = self.files.as_ref().unwrap();
+        let slots: Vec<u32> = files
+            .iter()
+            .enumerate()
+            .filter(|(_, &used)| !used)
+            .map(|(slot, _)| slot as u32)
+            .take(n)
+            .collect();
+        if slots.len() < n {
+            return Err(Error::new(ErrorKind::Other, "the fixed file table is full"));
         }
-        .is_err()
+        Ok(slots)
+    }
+
+    fn register(
+        &self,
+        opcode: libc::c_uint,
+        arg: *const libc::c_void,
+        nr_args: libc::c_uint,
+    ) -> Result<()> {
+        let fd = self.io.as_raw_fd();
+        let ret = unsafe { libc::syscall(libc::SYS_io_uring_register, fd, opcode, arg, nr_args) };
+        if ret >= 0 {
+            Ok(())
+        } else {
+            Err(Error::last_os_error())
+        }
+    }
+
+    /// Returns true if the token belongs to an internal entry, whose completion
+    /// is ignored.
+    fn is_internal(token: u64) -> bool {
+        token >= Self::DETACHED_TOKEN
+    }
+
+    /// Sets the I/O priority of the current task on `sqe`.
+    fn prioritize(&mut self, mut sqe: squeue::Entry) -> squeue::Entry {
+        apply_io_priority(&mut sqe);
+        #[cfg(feature = "test-hooks")]
         {
-            self.submit()?;
+            let raw = super::raw::RawSqe::from_entry(&sqe);
+            self.hooks.last_ioprio = raw.ioprio;
+            self.hooks.last_opcode = raw.opcode;
         }
-        Ok(())
+        sqe
     }
 
-    fn pull(&mut self) {
-        let mut cq = self.io.completion();
-        cq.sync();
-        for cqe in cq {
-            let token = cqe.user_data();
-            if token != Self::UNPARK_TOKEN {
-                let result = syscall_result(cqe.result());
-                self.table.complete(token as _, result);
+    /// Pushes entries to cancel the ops that have been dropped.
+    fn push_cancelled(&mut self) {
+        for index in self.table.take_cancelled() {
+            let sqe = opcode::AsyncCancel::new(index as u64)
+                .build()
+                .user_data(Self::CANCEL_TOKEN);
+            unsafe {
+                self.push(sqe);
             }
         }
     }
 
+    /// Pushes an entry to the submission queue.
+    ///
+    /// If the queue is full, the entry is deferred until a submission makes
+    /// room for it. Entries are always pushed in order, so an entry can't
+    /// overtake the deferred ones.
+    unsafe fn push(&mut self, sqe: squeue::Entry) {
+        if !self.backlog.is_empty() || !self.io.push(&sqe) {
+            self.backlog.push_back(vec![sqe]);
+        }
+    }
+
+    /// Pushes linked entries together so that no other entry gets between
+    /// them.
+    unsafe fn push_linked(&mut self, sqes: &[squeue::Entry]) {
+        if !self.backlog.is_empty() || !self.io.push_multiple(sqes) {
+            self.backlog.push_back(sqes.to_vec());
+        }
+    }
+
+    /// Moves the deferred entries to the submission queue, as many as fit.
+    fn push_backlog(&mut self) {
+        while let Some(sqes) = self.backlog.front() {
+            if !unsafe { self.io.push_multiple(sqes) } {
+                break;
+            }
+            self.backlog.pop_front();
+        }
+    }
+
+    /// Submits entries until there are no deferred ones and the submission
+    /// queue has room for `n` more.
+    fn reserve(&mut self, n: usize) -> Result<()> {
+        loop {
+            self.push_backlog();
+            if self.backlog.is_empty() && self.io.space() >= n {
+                return Ok(());
+            }
+            self.enter(0)?;
+        }
+    }
+
+    /// Takes the wakers and discard functions of the pulled completions.
+    ///
+    /// The caller must run them after releasing the driver, since they may
+    /// use the driver again.
+    pub(super) fn take_reaped(&mut self) -> Reaped {
+        std::mem::take(&mut self.reaped)
+    }
+
+    /// Takes the payloads of the wakeups that other drivers have posted to
+    /// this one, see [`Self::wake`].
+    pub(super) fn take_wakes(&mut self) -> Vec<u32> {
+        std::mem::take(&mut self.wakes)
+    }
+
+    fn pull(&mut self) {
+        let table = &mut self.table;
+        let reaped = &mut self.reaped;
+        let is_unpark_armed = &mut self.is_unpark_armed;
+        let wakes = &mut self.wakes;
+        #[cfg(feature = "test-hooks")]
+        let injected = &mut self.hooks.injected;
+        self.io.drain(|cqe| match cqe.user_data {
+            Self::UNPARK_TOKEN => *is_unpark_armed = false,
+            Self::WAKE_TOKEN => wakes.push(cqe.result as u32),
+            token if Self::is_internal(token) => {}
+            _ => {
+                #[cfg(feature = "test-hooks")]
+                let result = injected.pop_front().unwrap_or(cqe.result);
+                #[cfg(not(feature = "test-hooks"))]
+                let result = cqe.result;
+                let completion = Completion {
+                    result: syscall_result(result),
+                    flags: cqe.flags,
+                    extra: cqe.extra,
+                };
+                let more = cqueue::more(cqe.flags);
+                table.complete(cqe.user_data as _, completion, more, reaped);
+            }
+        });
+    }
+
     fn submit(&mut self) -> Result<usize> {
         self.submit_and_wait(0)
     }
 
+    /// Submits the pending entries, including the deferred ones, and waits
+    /// for `want` completions.
     fn submit_and_wait(&mut self, want: usize) -> Result<usize> {
+        let mut submitted = 0;
+        loop {
+            self.push_backlog();
+            if self.backlog.is_empty() {
+                break;
+            }
+            match self.enter(0) {
+                Ok(n) => submitted += n,
+                // The kernel is short of resources, so the remaining entries
+                // stay deferred until the next submission.
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        match self.enter(want) {
+            Ok(n) => Ok(submitted + n),
+            Err(e) if e.kind() == ErrorKind::WouldBlock && want == 0 => Ok(submitted),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn enter(&mut self, want: usize) -> Result<usize> {
         loop {
             match self.io.submit_and_wait(want) {
                 Ok(n) => {
-                    self.io.submission().sync();
+                    self.io.sync_submission();
                     return Ok(n);
                 }
                 Err(e) => match e.kind() {
@@ -109,6 +633,24 @@ impl Driver {
     }
 }
 
+/// See also `struct io_uring_buf_reg` in `man io_uring_register.2`.
+#[repr(C)]
+struct BufReg {
+    ring_addr: u64,
+    ring_entries: u32,
+    bgid: u16,
+    flags: u16,
+    resv: [u64; 3],
+}
+
+/// See also `struct io_uring_files_update` in `man io_uring_register.2`.
+#[repr(C)]
+struct FilesUpdate {
+    offset: u32,
+    resv: u32,
+    fds: u64,
+}
+
 #[derive(Clone)]
 pub(super) struct Unpark(Arc<OwnedFd>);
 
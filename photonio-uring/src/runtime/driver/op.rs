@@ -1,16 +1,63 @@
 use std::{
-    future::Future,
-    io::Result,
+    future::{poll_fn, Future},
+    io::{ErrorKind, Result},
     pin::Pin,
-    task::{Context, Poll},
+    task::{ready, Context, Poll},
 };
 
-use super::OpTable;
+use io_uring::squeue;
 
+use super::{Completion, Discard, OpTable};
+use crate::{
+    io::OpError,
+    runtime::{raw, worker::with_driver},
+};
+
+/// The maximum number of times to resubmit an op.
+///
+/// This bounds the work spent on a descriptor that keeps failing.
+const MAX_RETRIES: u8 = 8;
+
+/// The entries that an op submits.
+#[derive(Clone)]
+pub(super) enum Submission {
+    Entry(squeue::Entry),
+    Entry128(squeue::Entry128),
+    /// An entry that is linked to a timeout, whose completion is ignored.
+    WithTimeout(squeue::Entry, squeue::Entry),
+}
+
+impl Submission {
+    /// Returns the entry of the op, which describes it in its error.
+    fn entry(&self) -> &squeue::Entry {
+        match self {
+            Self::Entry(sqe) | Self::WithTimeout(sqe, _) => sqe,
+            Self::Entry128(sqe) => raw::head(sqe),
+        }
+    }
+}
+
+/// An op that completes once.
+///
+/// The op is cancelled if it is dropped before completion, without waiting for
+/// the cancellation. Since the kernel might still access the memory referenced
+/// by the op until then, an op that references memory must be wrapped in a
+/// [`BufOp`] that owns it.
+///
+/// An op that completes with `EINTR` is resubmitted transparently, with the
+/// same entries and hence the same buffers, up to [`MAX_RETRIES`] times.
+///
+/// Awaiting the op wraps its error in an [`OpError`] that describes the entry.
 pub(crate) struct Op {
     table: OpTable,
     index: usize,
     is_finished: bool,
+    discard: Option<Discard>,
+    // The entries to resubmit if the op is interrupted, which also describe
+    // the op in its error.
+    sqe: Option<Submission>,
+    retry_would_block: bool,
+    retries: u8,
 }
 
 impl Op {
@@ -19,13 +66,84 @@ impl Op {
             table,
             index,
             is_finished: false,
+            discard: None,
+            sqe: None,
+            retry_would_block: false,
+            retries: 0,
+        }
+    }
+
+    /// Makes this op resubmit `sqe` if it is interrupted.
+    pub(super) fn retry_with(mut self, sqe: Submission) -> Self {
+        self.sqe = Some(sqe);
+        self
+    }
+
+    /// Makes this op complete with the first result, without resubmitting.
+    pub(crate) fn without_retry(mut self) -> Self {
+        self.sqe = None;
+        self
+    }
+
+    /// Makes this op also resubmit if it completes with `EAGAIN`.
+    ///
+    /// This is for socket ops that are expected to wait for readiness, in
+    /// which case `EAGAIN` is spurious.
+    pub(crate) fn retry_would_block(mut self) -> Self {
+        self.retry_would_block = true;
+        self
+    }
+
+    /// Sets a function to release the completion, in case the op is dropped
+    /// before it completes.
+    pub(crate) fn on_discard(mut self, discard: impl FnMut(Completion) + Send + 'static) -> Self {
+        self.discard = Some(Box::new(discard));
+        self
+    }
+
+    /// Waits for the completion of this op, including its flags.
+    pub(crate) async fn completion(mut self) -> Completion {
+        poll_fn(|cx| self.poll_completion(cx)).await
+    }
+
+    fn poll_completion(&mut self, cx: &mut Context) -> Poll<Completion> {
+        loop {
+            let completion = ready!(self.table.poll(self.index, cx.waker()));
+            self.is_finished = true;
+            if !self.should_retry(&completion) {
+                return Poll::Ready(completion);
+            }
+            let sqe = self.sqe.clone().unwrap();
+            let table = self.table.clone();
+            // Gives up the retry if the task has moved to another worker.
+            match with_driver(|driver| unsafe { driver.resubmit(&table, sqe) }) {
+                Ok(index) => {
+                    self.index = index;
+                    self.is_finished = false;
+                    self.retries += 1;
+                }
+                Err(_) => return Poll::Ready(completion),
+            }
+        }
+    }
+
+    fn should_retry(&self, completion: &Completion) -> bool {
+        if self.sqe.is_none() || self.retries >= MAX_RETRIES {
+            return false;
+        }
+        match &completion.result {
+            Err(e) if e.kind() == ErrorKind::Interrupted => true,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => self.retry_would_block,
+            _ => false,
         }
     }
 }
 
 impl Drop for Op {
     fn drop(&mut self) {
-        assert!(self.is_finished);
+        if !self.is_finished {
+            self.table.cancel(self.index, self.discard.take());
+        }
     }
 }
 
@@ -33,10 +151,109 @@ impl Future for Op {
     type Output = Result<u32>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let result = ready!(self.poll_completion(cx)).result;
+        Poll::Ready(result.map_err(|e| match &self.sqe {
+            Some(sqe) => OpError::wrap(sqe.entry(), e),
+            None => e,
+        }))
+    }
+}
+
+/// An op that owns a buffer until the kernel is done with it.
+///
+/// The buffer can be any memory referenced by the op, like a path or a socket
+/// address, as long as moving it doesn't move that memory. If this is dropped
+/// before completion, the op is cancelled without waiting, and the buffer is
+/// released once the cancellation completes.
+pub(crate) struct BufOp<B: Send + 'static> {
+    op: Op,
+    buf: Option<B>,
+}
+
+impl<B: Send + 'static> BufOp<B> {
+    pub(crate) fn new(op: Op, buf: B) -> Self {
+        Self { op, buf: Some(buf) }
+    }
+
+    /// Waits for the completion of this op and returns the buffer.
+    pub(crate) async fn wait(mut self) -> (Result<u32>, B) {
+        let result = (&mut self.op).await;
+        (result, self.buf.take().unwrap())
+    }
+
+    /// Waits for the completion of this op, including its flags, and returns
+    /// the buffer.
+    pub(crate) async fn completion(mut self) -> (Completion, B) {
+        let completion = poll_fn(|cx| self.op.poll_completion(cx)).await;
+        (completion, self.buf.take().unwrap())
+    }
+}
+
+impl<B: Send + 'static> Drop for BufOp<B> {
+    fn drop(&mut self) {
+        if self.op.is_finished {
+            return;
+        }
+        // The op doesn't need to wait, since the buffer is kept by the table.
+        self.op.is_finished = true;
+        let mut buf = self.buf.take();
+        let mut release = self.op.discard.take();
+        let discard = move |completion| {
+            if let Some(release) = &mut release {
+                release(completion);
+            }
+            drop(buf.take());
+        };
+        self.op.table.cancel(self.op.index, Some(Box::new(discard)));
+    }
+}
+
+/// A multishot op that produces multiple completions.
+///
+/// The op is cancelled if it is dropped before it finishes.
+pub(crate) struct MultiOp {
+    table: OpTable,
+    index: usize,
+    is_finished: bool,
+    discard: Option<Discard>,
+}
+
+impl MultiOp {
+    pub(super) fn new(table: OpTable, index: usize) -> Self {
+        Self {
+            table,
+            index,
+            is_finished: false,
+            discard: None,
+        }
+    }
+
+    /// Sets a function to release the completions that are not taken, in case
+    /// the op is dropped before it finishes.
+    pub(crate) fn on_discard(mut self, discard: impl FnMut(Completion) + Send + 'static) -> Self {
+        self.discard = Some(Box::new(discard));
+        self
+    }
+
+    /// Returns the next completion of this op, or `None` if it has finished.
+    pub(crate) async fn next(&mut self) -> Option<Completion> {
+        if self.is_finished {
+            return None;
+        }
         let index = self.index;
-        self.table.poll(index, cx.waker()).map(|v| {
-            self.is_finished = true;
-            v
-        })
+        poll_fn(|cx| self.table.poll_multi(index, cx.waker()))
+            .await
+            .or_else(|| {
+                self.is_finished = true;
+                None
+            })
+    }
+}
+
+impl Drop for MultiOp {
+    fn drop(&mut self) {
+        if !self.is_finished {
+            self.table.cancel(self.index, self.discard.take());
+        }
     }
 }
@@ -1,4 +1,5 @@
 use std::{
+    collections::VecDeque,
     io::Result,
     sync::{Arc, Mutex},
     task::{Poll, Waker},
@@ -6,16 +7,68 @@ use std::{
 
 use slab::Slab;
 
+/// The completion of an op.
+pub(crate) struct Completion {
+    pub(crate) result: Result<u32>,
+    /// The `IORING_CQE_F_*` flags of the completion.
+    pub(crate) flags: u32,
+    /// The extra 16 bytes of a 32-byte completion, or zeros.
+    pub(crate) extra: [u64; 2],
+}
+
+/// A function to release the resources of a completion that nobody takes.
+pub(crate) type Discard = Box<dyn FnMut(Completion) + Send>;
+
 #[derive(Default)]
 enum OpState {
     #[default]
     Init,
     Polled(Waker),
-    Completed(Result<u32>),
+    Completed(Completion),
+    /// A multishot op that produces multiple completions.
+    Multi(MultiState),
+    /// The op has been dropped before completion. Its slot is kept until the
+    /// last completion arrives, so that the index can't be reused in the
+    /// meantime. The following completions are passed to the discard function
+    /// if any, once the last one arrives.
+    Cancelled(Option<Discard>, Vec<Completion>),
+}
+
+#[derive(Default)]
+struct MultiState {
+    waker: Option<Waker>,
+    completions: VecDeque<Completion>,
+    is_finished: bool,
+}
+
+#[derive(Default)]
+struct Inner {
+    ops: Slab<OpState>,
+    cancelled: Vec<usize>,
+}
+
+/// The wakers and discard functions of completions, to run once the table and
+/// the driver are released.
+///
+/// Both can run arbitrary code, like dropping the last reference to a buffer
+/// ring, which goes back to the driver.
+#[derive(Default)]
+pub(super) struct Reaped {
+    wakers: Vec<Waker>,
+    discards: Vec<(Discard, Vec<Completion>)>,
+}
+
+impl Reaped {
+    pub(super) fn run(self) {
+        self.wakers.into_iter().for_each(Waker::wake);
+        for (mut discard, completions) in self.discards {
+            completions.into_iter().for_each(&mut discard);
+        }
+    }
 }
 
 #[derive(Clone, Default)]
-pub(super) struct OpTable(Arc<Mutex<Slab<OpState>>>);
+pub(super) struct OpTable(Arc<Mutex<Inner>>);
 
 impl OpTable {
     pub(super) fn new() -> Self {
@@ -24,12 +77,17 @@ impl OpTable {
 
     pub(super) fn add(&mut self) -> usize {
         let mut table = self.0.lock().unwrap();
-        table.insert(OpState::default())
+        table.ops.insert(OpState::default())
     }
 
-    pub(super) fn poll(&mut self, index: usize, waker: &Waker) -> Poll<Result<u32>> {
+    pub(super) fn add_multi(&mut self) -> usize {
         let mut table = self.0.lock().unwrap();
-        let state = table.get_mut(index).unwrap();
+        table.ops.insert(OpState::Multi(MultiState::default()))
+    }
+
+    pub(super) fn poll(&mut self, index: usize, waker: &Waker) -> Poll<Completion> {
+        let mut table = self.0.lock().unwrap();
+        let state = table.ops.get_mut(index).unwrap();
         match std::mem::take(state) {
             OpState::Init => {
                 *state = OpState::Polled(waker.clone());
@@ -43,25 +101,138 @@ impl OpTable {
                 }
                 Poll::Pending
             }
-            OpState::Completed(result) => {
-                table.remove(index);
-                Poll::Ready(result)
+            OpState::Completed(completion) => {
+                table.ops.remove(index);
+                Poll::Ready(completion)
             }
+            OpState::Multi(..) | OpState::Cancelled(..) => unreachable!(),
+        }
+    }
+
+    /// Polls the next completion of a multishot op.
+    ///
+    /// Returns `None` once the op has finished and all its completions have
+    /// been taken.
+    pub(super) fn poll_multi(&mut self, index: usize, waker: &Waker) -> Poll<Option<Completion>> {
+        let mut table = self.0.lock().unwrap();
+        let multi = match table.ops.get_mut(index).unwrap() {
+            OpState::Multi(multi) => multi,
+            _ => unreachable!(),
+        };
+        if let Some(completion) = multi.completions.pop_front() {
+            return Poll::Ready(Some(completion));
+        }
+        if multi.is_finished {
+            table.ops.remove(index);
+            return Poll::Ready(None);
         }
+        match &multi.waker {
+            Some(w) if w.will_wake(waker) => {}
+            _ => multi.waker = Some(waker.clone()),
+        }
+        Poll::Pending
     }
 
-    pub(super) fn complete(&mut self, index: usize, result: Result<u32>) {
+    /// Completes an op.
+    ///
+    /// `more` indicates if more completions will arrive for a multishot op.
+    /// The wakers and discard functions to run are added to `reaped`, instead
+    /// of running with the table locked.
+    pub(super) fn complete(
+        &mut self,
+        index: usize,
+        completion: Completion,
+        more: bool,
+        reaped: &mut Reaped,
+    ) {
         let mut table = self.0.lock().unwrap();
-        let state = table.get_mut(index).unwrap();
+        let state = table.ops.get_mut(index).unwrap();
         match std::mem::take(state) {
             OpState::Init => {
-                *state = OpState::Completed(result);
+                *state = OpState::Completed(completion);
             }
             OpState::Polled(w) => {
-                *state = OpState::Completed(result);
-                w.wake();
+                *state = OpState::Completed(completion);
+                reaped.wakers.push(w);
             }
             OpState::Completed(..) => unreachable!(),
+            OpState::Multi(mut multi) => {
+                multi.completions.push_back(completion);
+                multi.is_finished = !more;
+                reaped.wakers.extend(multi.waker.take());
+                *state = OpState::Multi(multi);
+            }
+            OpState::Cancelled(discard, mut completions) => {
+                if discard.is_some() {
+                    completions.push(completion);
+                }
+                if more {
+                    *state = OpState::Cancelled(discard, completions);
+                } else {
+                    table.ops.remove(index);
+                    if let Some(discard) = discard {
+                        reaped.discards.push((discard, completions));
+                    }
+                }
+            }
         }
     }
+
+    /// Cancels an unfinished op.
+    ///
+    /// The op is queued to be cancelled in the kernel, see
+    /// [`Self::take_cancelled`]. Completions that haven't been taken yet are
+    /// passed to `discard` if any.
+    pub(super) fn cancel(&mut self, index: usize, discard: Option<Discard>) {
+        let mut reaped = Reaped::default();
+        {
+            let mut guard = self.0.lock().unwrap();
+            let table = &mut *guard;
+            let state = table.ops.get_mut(index).unwrap();
+            match std::mem::take(state) {
+                OpState::Init | OpState::Polled(_) => {
+                    *state = OpState::Cancelled(discard, Vec::new());
+                    table.cancelled.push(index);
+                }
+                OpState::Completed(completion) => {
+                    table.ops.remove(index);
+                    if let Some(discard) = discard {
+                        reaped.discards.push((discard, vec![completion]));
+                    }
+                }
+                OpState::Multi(multi) => {
+                    let completions = Vec::from(multi.completions);
+                    if multi.is_finished {
+                        table.ops.remove(index);
+                        if let Some(discard) = discard {
+                            reaped.discards.push((discard, completions));
+                        }
+                    } else {
+                        let completions = if discard.is_some() {
+                            completions
+                        } else {
+                            Vec::new()
+                        };
+                        *state = OpState::Cancelled(discard, completions);
+                        table.cancelled.push(index);
+                    }
+                }
+                OpState::Cancelled(..) => unreachable!(),
+            }
+        }
+        // The discard function might cancel other ops, so it runs after the
+        // table is unlocked.
+        reaped.run();
+    }
+
+    /// Returns true if both tables are the same one.
+    pub(super) fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Takes the ops to cancel in the kernel.
+    pub(super) fn take_cancelled(&mut self) -> Vec<usize> {
+        let mut table = self.0.lock().unwrap();
+        std::mem::take(&mut table.cancelled)
+    }
 }
@@ -0,0 +1,175 @@
+use std::{
+    io::Result,
+    os::unix::io::{AsRawFd, RawFd},
+};
+
+use io_uring::{cqueue, squeue, IoUring, Submitter};
+
+/// An io_uring instance with the entry sizes chosen at setup.
+///
+/// Entries are always built as 64-byte SQEs. They are widened on push if the
+/// ring uses 128-byte SQEs.
+pub(super) enum Ring {
+    Normal(IoUring),
+    Sqe128(IoUring<squeue::Entry128, cqueue::Entry>),
+    Cqe32(IoUring<squeue::Entry, cqueue::Entry32>),
+    Big(IoUring<squeue::Entry128, cqueue::Entry32>),
+}
+
+macro_rules! with_ring {
+    ($ring:expr, $io:ident => $body:expr) => {
+        match $ring {
+            Ring::Normal($io) => $body,
+            Ring::Sqe128($io) => $body,
+            Ring::Cqe32($io) => $body,
+            Ring::Big($io) => $body,
+        }
+    };
+}
+
+impl Ring {
+    pub(super) fn new(entries: u32, sqe128: bool, cqe32: bool) -> Result<Self> {
+        Ok(match (sqe128, cqe32) {
+            (false, false) => Self::Normal(IoUring::builder().setup_iopoll().build(entries)?),
+            (true, false) => Self::Sqe128(IoUring::builder().setup_iopoll().build(entries)?),
+            (false, true) => Self::Cqe32(IoUring::builder().setup_iopoll().build(entries)?),
+            (true, true) => Self::Big(IoUring::builder().setup_iopoll().build(entries)?),
+        })
+    }
+
+    pub(super) fn submitter(&self) -> Submitter<'_> {
+        with_ring!(self, io => io.submitter())
+    }
+
+    /// Pushes an entry to the submission queue.
+    ///
+    /// Returns false if the queue is full.
+    pub(super) unsafe fn push(&mut self, sqe: &squeue::Entry) -> bool {
+        with_ring!(self, io => push(io, sqe))
+    }
+
+    /// Pushes all entries to the submission queue, or none of them if there
+    /// is not enough space.
+    pub(super) unsafe fn push_multiple(&mut self, sqes: &[squeue::Entry]) -> bool {
+        with_ring!(self, io => push_multiple(io, sqes))
+    }
+
+    /// Returns the number of free entries in the submission queue.
+    pub(super) fn space(&mut self) -> usize {
+        with_ring!(self, io => {
+            let sq = io.submission();
+            sq.capacity() - sq.len()
+        })
+    }
+
+    /// Returns true if the ring uses 128-byte SQEs.
+    pub(super) fn is_sqe128(&self) -> bool {
+        matches!(self, Self::Sqe128(_) | Self::Big(_))
+    }
+
+    /// Pushes a 128-byte entry to the submission queue.
+    ///
+    /// Returns false if the queue is full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ring doesn't use 128-byte SQEs.
+    pub(super) unsafe fn push128(&mut self, sqe: &squeue::Entry128) -> bool {
+        match self {
+            Self::Sqe128(io) => io.submission().push(sqe).is_ok(),
+            Self::Big(io) => io.submission().push(sqe).is_ok(),
+            _ => panic!("the ring is not set up with 128-byte SQEs"),
+        }
+    }
+
+    pub(super) fn submit_and_wait(&self, want: usize) -> Result<usize> {
+        with_ring!(self, io => io.submit_and_wait(want))
+    }
+
+    /// Synchronizes the submission queue with the kernel.
+    pub(super) fn sync_submission(&mut self) {
+        with_ring!(self, io => io.submission().sync())
+    }
+
+    /// Drains the completion queue.
+    pub(super) fn drain(&mut self, f: impl FnMut(Cqe)) {
+        with_ring!(self, io => drain(io, f))
+    }
+}
+
+impl AsRawFd for Ring {
+    fn as_raw_fd(&self) -> RawFd {
+        with_ring!(self, io => io.as_raw_fd())
+    }
+}
+
+/// A completion entry of either size.
+pub(super) struct Cqe {
+    pub(super) user_data: u64,
+    pub(super) result: i32,
+    pub(super) flags: u32,
+    /// The extra 16 bytes of a 32-byte CQE, or zeros.
+    pub(super) extra: [u64; 2],
+}
+
+trait SqeEntry: squeue::EntryMarker {
+    fn widen(sqe: &squeue::Entry) -> Self;
+}
+
+impl SqeEntry for squeue::Entry {
+    fn widen(sqe: &squeue::Entry) -> Self {
+        sqe.clone()
+    }
+}
+
+impl SqeEntry for squeue::Entry128 {
+    fn widen(sqe: &squeue::Entry) -> Self {
+        sqe.clone().into()
+    }
+}
+
+trait CqeEntry: cqueue::EntryMarker {
+    fn to_cqe(&self) -> Cqe;
+}
+
+impl CqeEntry for cqueue::Entry {
+    fn to_cqe(&self) -> Cqe {
+        Cqe {
+            user_data: self.user_data(),
+            result: self.result(),
+            flags: self.flags(),
+            extra: [0; 2],
+        }
+    }
+}
+
+impl CqeEntry for cqueue::Entry32 {
+    fn to_cqe(&self) -> Cqe {
+        Cqe {
+            user_data: self.user_data(),
+            result: self.result(),
+            flags: self.flags(),
+            extra: *self.big_cqe(),
+        }
+    }
+}
+
+unsafe fn push<S: SqeEntry, C: CqeEntry>(io: &mut IoUring<S, C>, sqe: &squeue::Entry) -> bool {
+    io.submission().push(&S::widen(sqe)).is_ok()
+}
+
+unsafe fn push_multiple<S: SqeEntry, C: CqeEntry>(
+    io: &mut IoUring<S, C>,
+    sqes: &[squeue::Entry],
+) -> bool {
+    let sqes: Vec<S> = sqes.iter().map(S::widen).collect();
+    io.submission().push_multiple(&sqes).is_ok()
+}
+
+fn drain<S: SqeEntry, C: CqeEntry>(io: &mut IoUring<S, C>, mut f: impl FnMut(Cqe)) {
+    let mut cq = io.completion();
+    cq.sync();
+    for cqe in cq {
+        f(cqe.to_cqe());
+    }
+}
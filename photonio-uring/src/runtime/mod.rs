@@ -19,6 +19,46 @@ pub use worker::spawn;
 
 pub(crate) mod syscall;
 
+mod raw;
+
+mod blocking;
+pub(crate) use blocking::{unblock, unblock_detached};
+
+/// Replaces the results of the next completions on the current worker with
+/// `results`, which are negative error numbers or non-negative values.
+///
+/// This is a test hook to exercise the handling of rare results, like
+/// resubmitting interrupted operations. It is not part of the public API, and
+/// only built with the `test-hooks` feature.
+#[cfg(feature = "test-hooks")]
+#[doc(hidden)]
+pub fn inject_results(results: &[i32]) -> Result<()> {
+    syscall::inject(results)
+}
+
+/// Returns the `ioprio` field of the last operation submitted on the current
+/// worker.
+///
+/// This is a test hook to observe I/O priorities, which can't be observed in
+/// the scheduling of a test. It is not part of the public API, and only built
+/// with the `test-hooks` feature.
+#[cfg(feature = "test-hooks")]
+#[doc(hidden)]
+pub fn last_io_priority() -> Result<u16> {
+    syscall::last_io_priority()
+}
+
+/// Returns the opcode of the last operation submitted on the current worker.
+///
+/// This is a test hook to check that an operation is submitted through
+/// io_uring instead of its fallback. It is not part of the public API, and only
+/// built with the `test-hooks` feature.
+#[cfg(feature = "test-hooks")]
+#[doc(hidden)]
+pub fn last_opcode() -> Result<u8> {
+    syscall::last_opcode()
+}
+
 /// The PhotonIO runtime.
 pub struct Runtime(Shared);
 
@@ -0,0 +1,92 @@
+//! Entries for opcodes that the io_uring crate doesn't provide yet.
+
+use std::mem;
+
+use io_uring::squeue;
+
+/// The layout of a 64-byte SQE.
+///
+/// See also `struct io_uring_sqe` in `linux/io_uring.h`.
+#[repr(C)]
+#[derive(Default)]
+pub(crate) struct RawSqe {
+    pub(crate) opcode: u8,
+    pub(crate) flags: u8,
+    pub(crate) ioprio: u16,
+    pub(crate) fd: i32,
+    pub(crate) off: u64,
+    pub(crate) addr: u64,
+    pub(crate) len: u32,
+    pub(crate) op_flags: u32,
+    pub(crate) user_data: u64,
+    pub(crate) buf_index: u16,
+    pub(crate) personality: u16,
+    pub(crate) file_index: u32,
+    pub(crate) addr3: u64,
+    pub(crate) pad: u64,
+}
+
+impl RawSqe {
+    /// Creates an entry with `opcode` and all other fields zeroed.
+    pub(crate) fn new(opcode: u8) -> Self {
+        Self {
+            opcode,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn build(self) -> squeue::Entry {
+        // `squeue::Entry` is a `repr(C)` wrapper of `io_uring_sqe`.
+        unsafe { mem::transmute::<Self, squeue::Entry>(self) }
+    }
+
+    /// Views the fields of a built entry.
+    pub(crate) fn from_entry(sqe: &squeue::Entry) -> &Self {
+        unsafe { &*(sqe as *const squeue::Entry as *const Self) }
+    }
+
+    /// Modifies the fields of a built entry.
+    pub(crate) fn from_entry_mut(sqe: &mut squeue::Entry) -> &mut Self {
+        unsafe { &mut *(sqe as *mut squeue::Entry as *mut Self) }
+    }
+}
+
+/// Returns the first half of a 128-byte entry, which is laid out like a 64-byte
+/// one.
+pub(crate) fn head(sqe: &squeue::Entry128) -> &squeue::Entry {
+    // `squeue::Entry128` is a `repr(C)` pair of `squeue::Entry` and the extra
+    // 64 bytes.
+    unsafe { &*(sqe as *const squeue::Entry128 as *const squeue::Entry) }
+}
+
+macro_rules! opcodes {
+    ($($(#[$attr:meta])* $name:ident = $code:literal,)*) => {
+        $(
+            $(#[$attr])*
+            pub(crate) struct $name;
+
+            impl $name {
+                pub(crate) const CODE: u8 = $code;
+            }
+        )*
+    };
+}
+
+opcodes! {
+    /// `IORING_OP_FSETXATTR`, since Linux 5.19.
+    FSetXattr = 41,
+    /// `IORING_OP_SETXATTR`, since Linux 5.19.
+    SetXattr = 42,
+    /// `IORING_OP_FGETXATTR`, since Linux 5.19.
+    FGetXattr = 43,
+    /// `IORING_OP_GETXATTR`, since Linux 5.19.
+    GetXattr = 44,
+    /// `IORING_OP_WAITID`, since Linux 6.7.
+    WaitId = 50,
+    /// `IORING_OP_FUTEX_WAIT`, since Linux 6.7.
+    FutexWait = 51,
+    /// `IORING_OP_FUTEX_WAKE`, since Linux 6.7.
+    FutexWake = 52,
+    /// `IORING_OP_FTRUNCATE`, since Linux 6.9.
+    Ftruncate = 55,
+}
@@ -33,11 +33,7 @@ impl Shared {
         };
         let shared = Self(Arc::new(inner));
         for worker in &shared.0.workers {
-            worker.launch(
-                shared.clone(),
-                builder.thread_stack_size,
-                builder.event_interval,
-            )?;
+            worker.launch(shared.clone(), &builder)?;
         }
         Ok(shared)
     }
@@ -1,150 +1,1354 @@
 //! Asynchronous system calls.
 
 use std::{
-    ffi::CString,
-    io::{Error, ErrorKind, Result},
+    ffi::{CString, OsString},
+    future::Future,
+    io::{Error, ErrorKind, IoSlice, IoSliceMut, Result},
     mem,
     os::unix::{
-        ffi::OsStrExt,
-        io::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+        ffi::{OsStrExt, OsStringExt},
+        io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
     },
-    path::Path,
+    path::{Path, PathBuf},
+    ptr,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
-use io_uring::{opcode, types};
+use io_uring::{cqueue, opcode, squeue, types};
 use socket2::SockAddr;
 
-use super::worker::submit;
+use super::{
+    driver::{BufOp, MultiOp},
+    raw::{self, RawSqe},
+    unblock,
+    worker::{
+        self, flush, submit, submit128, submit_drained, submit_linked, submit_multi,
+        submit_with_timeout, with_driver,
+    },
+};
+use crate::io::{raw_os_error, AlignedBuf, FixedBuf, IoBuf, IoBufMut, Opcode};
+
+/// Returns true if the current worker supports `opcode`.
+pub(crate) fn is_supported(opcode: Opcode) -> bool {
+    worker::is_supported(opcode)
+}
+
+/// Returns an error of [`ErrorKind::Unsupported`] if the current worker doesn't
+/// support `opcode`.
+fn check_supported(opcode: Opcode) -> Result<()> {
+    if !is_supported(opcode) {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("{} is not supported by the kernel", opcode),
+        ));
+    }
+    Ok(())
+}
+
+/// Submits the pending operations of the current worker to the kernel.
+///
+/// Returns the number of operations submitted.
+pub(crate) fn submit_now() -> Result<usize> {
+    flush()
+}
+
+/// Replaces the results of the next completions on the current worker with
+/// `results`.
+#[cfg(feature = "test-hooks")]
+pub(crate) fn inject(results: &[i32]) -> Result<()> {
+    worker::inject_results(results)
+}
+
+/// Returns the I/O priority of the last operation submitted on the current
+/// worker.
+#[cfg(feature = "test-hooks")]
+pub(crate) fn last_io_priority() -> Result<u16> {
+    worker::last_ioprio()
+}
+
+/// Returns the opcode of the last operation submitted on the current worker.
+#[cfg(feature = "test-hooks")]
+pub(crate) fn last_opcode() -> Result<u8> {
+    worker::last_opcode()
+}
+
+/// Returns the id of the driver of the current worker.
+pub(crate) fn driver_id() -> Result<u64> {
+    with_driver(|driver| Ok(driver.id()))
+}
+
+/// Registers a ring of provided buffers with the driver of the current worker.
+///
+/// Returns the id of the driver.
+pub(crate) fn register_buf_ring(ring_addr: u64, entries: u16, bgid: u16) -> Result<u64> {
+    with_driver(|driver| {
+        driver.register_buf_ring(ring_addr, entries, bgid)?;
+        Ok(driver.id())
+    })
+}
+
+/// Unregisters a ring of provided buffers from the driver with `driver_id`.
+///
+/// Returns an error if the driver doesn't belong to the current worker.
+pub(crate) fn unregister_buf_ring(driver_id: u64, bgid: u16) -> Result<()> {
+    with_driver(|driver| {
+        if driver.id() != driver_id {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "the buffer ring is registered with another worker",
+            ));
+        }
+        driver.unregister_buf_ring(bgid)
+    })
+}
+
+/// Registers buffers for fixed operations with the driver of the current
+/// worker.
+///
+/// Returns the id of the driver.
+pub(crate) fn register_buffers(iovecs: &[libc::iovec]) -> Result<u64> {
+    with_driver(|driver| {
+        driver.register_buffers(iovecs).map_err(|e| {
+            if e.raw_os_error() == Some(libc::EBUSY) {
+                Error::new(
+                    ErrorKind::ResourceBusy,
+                    "fixed buffers are already registered with the current worker",
+                )
+            } else {
+                e
+            }
+        })?;
+        Ok(driver.id())
+    })
+}
+
+/// Unregisters the buffers for fixed operations from the driver with
+/// `driver_id`.
+///
+/// Returns an error if the driver doesn't belong to the current worker.
+pub(crate) fn unregister_buffers(driver_id: u64) -> Result<()> {
+    with_driver(|driver| {
+        if driver.id() != driver_id {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "the fixed buffers are registered with another worker",
+            ));
+        }
+        driver.unregister_buffers()
+    })
+}
+
+/// Registers `fds` in the fixed file table of the current worker.
+///
+/// Returns the id of the driver and the slots of the files.
+pub(crate) fn register_files(fds: &[RawFd]) -> Result<(u64, Vec<u32>)> {
+    with_driver(|driver| Ok((driver.id(), driver.register_files(fds)?)))
+}
+
+/// Replaces the file in `slot` of the fixed file table of the driver with
+/// `driver_id`.
+///
+/// Returns an error if the driver doesn't belong to the current worker.
+pub(crate) fn update_file(driver_id: u64, slot: u32, fd: RawFd) -> Result<()> {
+    with_driver(|driver| {
+        if driver.id() != driver_id {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "the fixed file is registered with another worker",
+            ));
+        }
+        driver.update_file(slot, fd)
+    })
+}
+
+/// Clears `slot` in the fixed file table of the driver with `driver_id`.
+///
+/// Returns an error if the driver doesn't belong to the current worker.
+pub(crate) fn unregister_file(driver_id: u64, slot: u32) -> Result<()> {
+    with_driver(|driver| {
+        if driver.id() != driver_id {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "the fixed file is registered with another worker",
+            ));
+        }
+        driver.unregister_file(slot)
+    })
+}
+
+/// Allocates a free slot in the fixed file table of the current worker.
+///
+/// Returns the id of the driver and the slot.
+pub(crate) fn alloc_file() -> Result<(u64, u32)> {
+    with_driver(|driver| Ok((driver.id(), driver.alloc_file()?)))
+}
+
+/// The target file of an operation.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Target<'a> {
+    Fd(BorrowedFd<'a>),
+    /// A slot in the fixed file table of the current worker.
+    Fixed(u32),
+}
+
+impl<'a> From<BorrowedFd<'a>> for Target<'a> {
+    fn from(fd: BorrowedFd<'a>) -> Self {
+        Self::Fd(fd)
+    }
+}
+
+/// Provides `nbufs` buffers of `len` bytes starting at `addr` to the buffer
+/// group `bgid`, with buffer ids starting at `bid`.
+///
+/// See also `IORING_OP_PROVIDE_BUFFERS` in `man io_uring_enter.2`.
+pub(crate) async fn provide_buffers(
+    addr: usize,
+    len: usize,
+    nbufs: u16,
+    bgid: u16,
+    bid: u16,
+) -> Result<()> {
+    check_supported(Opcode::ProvideBuffers)?;
+    let sqe = opcode::ProvideBuffers::new(addr as *mut u8, len as _, nbufs, bgid, bid).build();
+    submit(sqe)?.await.map(|_| ())
+}
+
+/// This function is similar to [`provide_buffers`], except that it doesn't wait
+/// for the operation to complete.
+pub(crate) fn provide_buffers_detached(
+    driver_id: u64,
+    addr: usize,
+    len: usize,
+    nbufs: u16,
+    bgid: u16,
+    bid: u16,
+) -> Result<()> {
+    let sqe = opcode::ProvideBuffers::new(addr as *mut u8, len as _, nbufs, bgid, bid).build();
+    submit_detached(driver_id, sqe)
+}
+
+/// Removes `nbufs` buffers from the buffer group `bgid` without waiting for
+/// the operation to complete.
+pub(crate) fn remove_buffers_detached(driver_id: u64, nbufs: u16, bgid: u16) -> Result<()> {
+    let sqe = opcode::RemoveBuffers::new(nbufs, bgid).build();
+    submit_detached(driver_id, sqe)
+}
+
+/// Submits an operation whose completion is ignored to the driver with
+/// `driver_id`.
+///
+/// Returns an error if the driver doesn't belong to the current worker.
+fn submit_detached(driver_id: u64, sqe: squeue::Entry) -> Result<()> {
+    with_driver(|driver| {
+        if driver.id() != driver_id {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "the driver doesn't belong to the current worker",
+            ));
+        }
+        unsafe { driver.add_detached(sqe) };
+        Ok(())
+    })
+}
+
+/// Reads from `fd` into a buffer selected from the buffer group `bgid`.
+///
+/// Returns the number of bytes read and the id of the selected buffer, if any.
+///
+/// If the returned future is dropped, the buffer selected by the read is passed
+/// to `discard`.
+pub(crate) async fn read_select(
+    fd: BorrowedFd<'_>,
+    len: usize,
+    bgid: u16,
+    mut discard: impl FnMut(u16) + Send + 'static,
+) -> Result<(usize, Option<u16>)> {
+    let fd = types::Fd(fd.as_raw_fd());
+    let sqe = opcode::Read::new(fd, ptr::null_mut(), len as _)
+        .offset(-1)
+        .buf_group(bgid)
+        .build()
+        .flags(squeue::Flags::BUFFER_SELECT);
+    let op = submit(sqe)?.on_discard(move |completion| {
+        if let Some(bid) = cqueue::buffer_select(completion.flags) {
+            discard(bid);
+        }
+    });
+    let completion = op.completion().await;
+    let bid = cqueue::buffer_select(completion.flags);
+    completion.result.map(|n| (n as usize, bid))
+}
+
+/// Submits an operation that fails with [`ErrorKind::TimedOut`] if it doesn't
+/// complete in `timeout`, and returns the result with `buf`.
+///
+/// `buf` is the memory referenced by `sqe`, which is owned by the operation
+/// like in [`BufOp`]. The operation is cancelled in the kernel when the timeout
+/// fires.
+async fn submit_timeout<B: Send + 'static>(
+    sqe: squeue::Entry,
+    buf: B,
+    timeout: Duration,
+) -> (Result<u32>, B) {
+    if timeout.is_zero() {
+        let err = Error::new(ErrorKind::TimedOut, "operation timed out");
+        return (Err(err), buf);
+    }
+    // The timespec is owned by the op too, since the entries might be
+    // deferred.
+    let ts = Box::new(
+        types::Timespec::new()
+            .sec(timeout.as_secs())
+            .nsec(timeout.subsec_nanos()),
+    );
+    let op = match submit_with_timeout(sqe, &ts) {
+        Ok(op) => op,
+        Err(e) => return (Err(e), buf),
+    };
+    let (result, (_, buf)) = BufOp::new(op, (ts, buf)).wait().await;
+    let result = result.map_err(|e| match raw_os_error(&e) {
+        Some(libc::ECANCELED) => Error::new(ErrorKind::TimedOut, "operation timed out"),
+        _ => e,
+    });
+    (result, buf)
+}
+
+/// Waits for `duration`.
+///
+/// See also `IORING_OP_TIMEOUT` in `man io_uring_enter.2`.
+pub(crate) async fn sleep(duration: Duration) -> Result<()> {
+    let ts = Box::new(
+        types::Timespec::new()
+            .sec(duration.as_secs())
+            .nsec(duration.subsec_nanos()),
+    );
+    let sqe = opcode::Timeout::new(&*ts).build();
+    match BufOp::new(submit(sqe)?, ts).wait().await.0 {
+        Err(e) if raw_os_error(&e) == Some(libc::ETIME) => Ok(()),
+        result => result.map(|_| ()),
+    }
+}
+
+/// Submits an operation that does nothing.
+///
+/// See also `IORING_OP_NOP` in `man io_uring_enter.2`.
+pub(crate) async fn nop() -> Result<()> {
+    let sqe = opcode::Nop::new().build();
+    submit(sqe)?.await.map(|_| ())
+}
+
+/// Submits a custom entry.
+///
+/// Returns the raw result and the flags of the completion.
+pub(crate) async unsafe fn submit_raw(sqe: squeue::Entry) -> Result<(i32, u32)> {
+    let completion = submit(sqe)?.without_retry().completion().await;
+    let res = match completion.result {
+        Ok(n) => n as i32,
+        Err(e) => -e.raw_os_error().unwrap_or(libc::EIO),
+    };
+    Ok((res, completion.flags))
+}
 
 /// See also `man open.2`.
 pub(crate) async fn open(path: &Path, flags: libc::c_int, mode: libc::mode_t) -> Result<OwnedFd> {
+    openat(None, path, flags, mode).await
+}
+
+/// See also `man openat.2`.
+///
+/// If `dirfd` is `None`, a relative `path` is resolved against the current
+/// working directory.
+pub(crate) async fn openat(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: &Path,
+    flags: libc::c_int,
+    mode: libc::mode_t,
+) -> Result<OwnedFd> {
     let path = new_path_str(path)?;
-    let fd = unsafe { libc::open(path.as_c_str().as_ptr(), flags | libc::O_CLOEXEC) };
-    if fd < 0 {
-        return Err(Error::last_os_error());
-    }
+    let sqe = opcode::OpenAt::new(dir_fd(dirfd), path.as_c_str().as_ptr())
+        .flags(flags | libc::O_CLOEXEC)
+        .mode(mode)
+        .build();
+    let fd = BufOp::new(submit(sqe)?, path).wait().await.0?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as _) })
+}
+
+/// This function is similar to [`openat`], except that the file is installed
+/// into `slot` of the fixed file table as a direct descriptor.
+pub(crate) async fn openat_direct(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: &Path,
+    flags: libc::c_int,
+    mode: libc::mode_t,
+    slot: u32,
+) -> Result<()> {
+    let path = new_path_str(path)?;
+    // Direct descriptors don't support `O_CLOEXEC`.
+    let sqe = opcode::OpenAt::new(dir_fd(dirfd), path.as_c_str().as_ptr())
+        .flags(flags)
+        .mode(mode)
+        .file_index(Some(dest_slot(slot)?))
+        .build();
+    BufOp::new(submit(sqe)?, path).wait().await.0.map(|_| ())
+}
+
+/// See also `man openat2.2`.
+///
+/// If `dirfd` is `None`, a relative `path` is resolved against the current
+/// working directory.
+pub(crate) async fn openat2(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: &Path,
+    flags: libc::c_int,
+    mode: libc::mode_t,
+    resolve: u64,
+) -> Result<OwnedFd> {
+    check_supported(Opcode::OpenAt2)?;
+    let path = new_path_str(path)?;
+    let how = Box::new(
+        types::OpenHow::new()
+            .flags((flags | libc::O_CLOEXEC) as _)
+            .mode(mode as _)
+            .resolve(resolve),
+    );
+    let sqe = opcode::OpenAt2::new(dir_fd(dirfd), path.as_c_str().as_ptr(), &*how).build();
+    let fd = BufOp::new(submit(sqe)?, (path, how)).wait().await.0?;
     Ok(unsafe { OwnedFd::from_raw_fd(fd as _) })
 }
 
 /// See also `man close.2`.
-#[allow(dead_code)]
+///
+/// The descriptor is released even if the close fails.
 pub(crate) async fn close(fd: OwnedFd) -> Result<()> {
-    let fd = types::Fd(fd.as_raw_fd());
-    let sqe = opcode::Close::new(fd).build();
-    submit(sqe)?.await.map(|_| ())
+    let sqe = opcode::Close::new(types::Fd(fd.as_raw_fd())).build();
+    let op = submit(sqe)?;
+    // The op owns the descriptor once it is submitted.
+    let _ = fd.into_raw_fd();
+    op.await.map(|_| ())
+}
+
+/// This function is similar to [`close`], except that it doesn't wait for the
+/// operation to complete.
+///
+/// The descriptor is closed synchronously if there is no running worker on
+/// the current thread.
+pub(crate) fn close_detached(fd: OwnedFd) {
+    let raw = fd.into_raw_fd();
+    let sqe = opcode::Close::new(types::Fd(raw)).build();
+    let result = with_driver(|driver| {
+        unsafe { driver.add_detached(sqe) };
+        Ok(())
+    });
+    if result.is_err() {
+        drop(unsafe { OwnedFd::from_raw_fd(raw) });
+    }
 }
 
 /// See also `man fstat.2`.
 pub(crate) async fn fstat(fd: BorrowedFd<'_>) -> Result<libc::statx> {
-    let fd = fd.as_raw_fd();
-    let path = CString::new("").unwrap();
-    let mut stat = unsafe { mem::zeroed() };
-    if unsafe {
-        libc::statx(
-            fd,
-            path.as_ptr(),
-            libc::AT_EMPTY_PATH,
-            libc::STATX_ALL,
-            &mut stat as *mut _ as *mut _,
+    statx(
+        Some(fd),
+        Path::new(""),
+        libc::AT_EMPTY_PATH,
+        libc::STATX_ALL,
+    )
+    .await
+}
+
+/// See also `man statx.2`.
+///
+/// If `dirfd` is `None`, a relative `path` is resolved against the current
+/// working directory. Falls back to the blocking system call if the kernel
+/// doesn't support the operation.
+pub(crate) async fn statx(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: &Path,
+    flags: libc::c_int,
+    mask: libc::c_uint,
+) -> Result<libc::statx> {
+    let path = new_path_str(path)?;
+    let stat = if is_supported(Opcode::Statx) {
+        let mut stat: Box<libc::statx> = Box::new(unsafe { mem::zeroed() });
+        let sqe = opcode::Statx::new(
+            dir_fd(dirfd),
+            path.as_c_str().as_ptr(),
+            &mut *stat as *mut _ as *mut _,
         )
-    } == 0
-    {
-        Ok(stat)
+        .flags(flags)
+        .mask(mask)
+        .build();
+        let (result, (_, stat)) = BufOp::new(submit(sqe)?, (path, stat)).wait().await;
+        result?;
+        *stat
     } else {
-        Err(Error::last_os_error())
+        let dirfd = owned_dir_fd(dirfd)?;
+        unblock(move || {
+            let mut stat: libc::statx = unsafe { mem::zeroed() };
+            let dirfd = raw_dir_fd(&dirfd);
+            if unsafe { libc::statx(dirfd, path.as_ptr(), flags, mask, &mut stat) } < 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(stat)
+        })
+        .await?
+    };
+    // The kernel might not fill all requested fields, but the type is always
+    // expected.
+    if mask & libc::STATX_TYPE != 0 && stat.stx_mask & libc::STATX_TYPE == 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "statx doesn't return the file type",
+        ));
     }
+    Ok(stat)
 }
 
 /// See also `man fsync.2`.
-pub(crate) async fn fsync(fd: BorrowedFd<'_>) -> Result<()> {
-    fsync_inner(fd, types::FsyncFlags::empty()).await
+pub(crate) async fn fsync<'a>(fd: impl Into<Target<'a>>) -> Result<()> {
+    fsync_inner(fd.into(), types::FsyncFlags::empty()).await
 }
 
 /// See also `man fdatasync.2`.
-pub(crate) async fn fdatasync(fd: BorrowedFd<'_>) -> Result<()> {
-    fsync_inner(fd, types::FsyncFlags::DATASYNC).await
+pub(crate) async fn fdatasync<'a>(fd: impl Into<Target<'a>>) -> Result<()> {
+    fsync_inner(fd.into(), types::FsyncFlags::DATASYNC).await
+}
+
+async fn fsync_inner(fd: Target<'_>, flags: types::FsyncFlags) -> Result<()> {
+    let sqe = match fd {
+        Target::Fd(fd) => opcode::Fsync::new(types::Fd(fd.as_raw_fd())),
+        Target::Fixed(slot) => opcode::Fsync::new(types::Fixed(slot)),
+    };
+    let sqe = sqe.flags(flags).build();
+    submit(sqe)?.await.map(|_| ())
+}
+
+/// This function is similar to [`fdatasync`], except that it starts only after
+/// all previously submitted operations of the current worker have completed.
+pub(crate) async fn fdatasync_drained(fd: BorrowedFd<'_>) -> Result<()> {
+    let fd = types::Fd(fd.as_raw_fd());
+    let sqe = opcode::Fsync::new(fd)
+        .flags(types::FsyncFlags::DATASYNC)
+        .build();
+    submit_drained(sqe)?.await.map(|_| ())
+}
+
+/// See also `man sync_file_range.2`.
+///
+/// Falls back to `fdatasync` if the kernel doesn't support the operation.
+pub(crate) async fn sync_file_range(
+    fd: BorrowedFd<'_>,
+    offset: u64,
+    nbytes: u32,
+    flags: libc::c_uint,
+) -> Result<()> {
+    if !is_supported(Opcode::SyncFileRange) {
+        return fdatasync(fd).await;
+    }
+    let fd = types::Fd(fd.as_raw_fd());
+    let sqe = opcode::SyncFileRange::new(fd, nbytes)
+        .offset(offset as _)
+        .flags(flags as _)
+        .build();
+    submit(sqe)?.await.map(|_| ())
+}
+
+/// See also `man ftruncate.2`.
+///
+/// Falls back to the blocking system call on the blocking thread pool if the
+/// kernel doesn't support the operation.
+pub(crate) async fn ftruncate(fd: BorrowedFd<'_>, len: libc::off64_t) -> Result<()> {
+    if !is_supported(Opcode::Ftruncate) {
+        let fd = owned_fd(fd)?;
+        return unblock(move || {
+            if unsafe { libc::ftruncate64(fd.as_raw_fd(), len) } == 0 {
+                Ok(())
+            } else {
+                Err(Error::last_os_error())
+            }
+        })
+        .await;
+    }
+    let sqe = RawSqe {
+        fd: fd.as_raw_fd(),
+        off: len as u64,
+        ..RawSqe::new(raw::Ftruncate::CODE)
+    };
+    submit(sqe.build())?.await.map(|_| ())
+}
+
+// io_uring has no opcodes for the system calls from here to `copy_file_range`,
+// so they run on the blocking thread pool.
+
+/// See also `man fchmod.2`.
+pub(crate) async fn fchmod(fd: BorrowedFd<'_>, mode: libc::mode_t) -> Result<()> {
+    let fd = owned_fd(fd)?;
+    unblock(move || {
+        if unsafe { libc::fchmod(fd.as_raw_fd(), mode) } == 0 {
+            Ok(())
+        } else {
+            Err(Error::last_os_error())
+        }
+    })
+    .await
+}
+
+/// See also `man fchmodat.2`.
+///
+/// If `dirfd` is `None`, a relative `path` is resolved against the current
+/// working directory.
+pub(crate) async fn fchmodat(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: &Path,
+    mode: libc::mode_t,
+) -> Result<()> {
+    let dirfd = owned_dir_fd(dirfd)?;
+    let path = new_path_str(path)?;
+    unblock(move || {
+        if unsafe { libc::fchmodat(raw_dir_fd(&dirfd), path.as_ptr(), mode, 0) } == 0 {
+            Ok(())
+        } else {
+            Err(Error::last_os_error())
+        }
+    })
+    .await
+}
+
+/// See also `man fchown.2`.
+///
+/// An id of `None` leaves the owner or the group unchanged.
+pub(crate) async fn fchown(fd: BorrowedFd<'_>, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+    let fd = owned_fd(fd)?;
+    let uid = uid.unwrap_or(u32::MAX);
+    let gid = gid.unwrap_or(u32::MAX);
+    unblock(move || {
+        if unsafe { libc::fchown(fd.as_raw_fd(), uid, gid) } == 0 {
+            Ok(())
+        } else {
+            Err(Error::last_os_error())
+        }
+    })
+    .await
+}
+
+/// See also `man futimens.3`.
+pub(crate) async fn futimens(fd: BorrowedFd<'_>, times: [libc::timespec; 2]) -> Result<()> {
+    let fd = owned_fd(fd)?;
+    unblock(move || {
+        if unsafe { libc::futimens(fd.as_raw_fd(), times.as_ptr()) } == 0 {
+            Ok(())
+        } else {
+            Err(Error::last_os_error())
+        }
+    })
+    .await
+}
+
+/// See also `man utimensat.2`.
+///
+/// If `dirfd` is `None`, a relative `path` is resolved against the current
+/// working directory.
+pub(crate) async fn utimensat(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: &Path,
+    times: [libc::timespec; 2],
+    flags: libc::c_int,
+) -> Result<()> {
+    let dirfd = owned_dir_fd(dirfd)?;
+    let path = new_path_str(path)?;
+    unblock(move || {
+        let dirfd = raw_dir_fd(&dirfd);
+        if unsafe { libc::utimensat(dirfd, path.as_ptr(), times.as_ptr(), flags) } == 0 {
+            Ok(())
+        } else {
+            Err(Error::last_os_error())
+        }
+    })
+    .await
+}
+
+/// See also `man fstatfs.2`.
+pub(crate) async fn fstatfs(fd: BorrowedFd<'_>) -> Result<libc::statfs64> {
+    let fd = owned_fd(fd)?;
+    unblock(move || {
+        let mut stat = unsafe { std::mem::zeroed::<libc::statfs64>() };
+        if unsafe { libc::fstatfs64(fd.as_raw_fd(), &mut stat) } == 0 {
+            Ok(stat)
+        } else {
+            Err(Error::last_os_error())
+        }
+    })
+    .await
+}
+
+/// See also `man statfs.2`.
+pub(crate) async fn statfs(path: &Path) -> Result<libc::statfs64> {
+    let path = new_path_str(path)?;
+    unblock(move || {
+        let mut stat = unsafe { std::mem::zeroed::<libc::statfs64>() };
+        if unsafe { libc::statfs64(path.as_ptr(), &mut stat) } == 0 {
+            Ok(stat)
+        } else {
+            Err(Error::last_os_error())
+        }
+    })
+    .await
+}
+
+/// See also `man inotify_add_watch.2`.
+pub(crate) async fn inotify_add_watch(
+    fd: BorrowedFd<'_>,
+    path: &Path,
+    mask: u32,
+) -> Result<libc::c_int> {
+    let fd = owned_fd(fd)?;
+    let path = new_path_str(path)?;
+    unblock(move || {
+        let wd = unsafe { libc::inotify_add_watch(fd.as_raw_fd(), path.as_ptr(), mask) };
+        if wd >= 0 {
+            Ok(wd)
+        } else {
+            Err(Error::last_os_error())
+        }
+    })
+    .await
+}
+
+/// See also `man copy_file_range.2`.
+pub(crate) async fn copy_file_range(
+    fd_in: BorrowedFd<'_>,
+    off_in: libc::off64_t,
+    fd_out: BorrowedFd<'_>,
+    off_out: libc::off64_t,
+    len: usize,
+) -> Result<usize> {
+    let fd_in = owned_fd(fd_in)?;
+    let fd_out = owned_fd(fd_out)?;
+    unblock(move || {
+        let mut off_in = off_in;
+        let mut off_out = off_out;
+        let ret = unsafe {
+            libc::copy_file_range(
+                fd_in.as_raw_fd(),
+                &mut off_in,
+                fd_out.as_raw_fd(),
+                &mut off_out,
+                len,
+                0,
+            )
+        };
+        if ret >= 0 {
+            Ok(ret as usize)
+        } else {
+            Err(Error::last_os_error())
+        }
+    })
+    .await
+}
+
+/// See also `man fallocate.2`.
+pub(crate) async fn fallocate(
+    fd: BorrowedFd<'_>,
+    offset: libc::off64_t,
+    len: libc::off64_t,
+    mode: libc::c_int,
+) -> Result<()> {
+    let fd = types::Fd(fd.as_raw_fd());
+    check_supported(Opcode::Fallocate)?;
+    let sqe = opcode::Fallocate::new(fd, len as _)
+        .offset(offset as _)
+        .mode(mode)
+        .build();
+    submit(sqe)?.await.map(|_| ())
+}
+
+/// Gets the value of the extended attribute `name` of `fd`, with a buffer of
+/// `len` bytes.
+///
+/// Falls back to the blocking system call if the kernel doesn't support the
+/// operation.
+///
+/// See also `man fgetxattr.2`.
+pub(crate) async fn fgetxattr(fd: BorrowedFd<'_>, name: &str, len: usize) -> Result<Vec<u8>> {
+    let name = new_name_str(name)?;
+    let mut value = Vec::with_capacity(len);
+    if !is_supported(Opcode::FGetXattr) {
+        let fd = owned_fd(fd)?;
+        return unblock(move || {
+            let n = unsafe {
+                libc::fgetxattr(fd.as_raw_fd(), name.as_ptr(), value.as_mut_ptr() as _, len)
+            };
+            xattr_value(n, value)
+        })
+        .await;
+    }
+    let sqe = RawSqe {
+        fd: fd.as_raw_fd(),
+        addr: name.as_ptr() as u64,
+        len: len as u32,
+        off: value.as_mut_ptr() as u64,
+        ..RawSqe::new(raw::FGetXattr::CODE)
+    };
+    let (result, (_, value)) = BufOp::new(submit(sqe.build())?, (name, value)).wait().await;
+    xattr_value(result? as _, value)
+}
+
+/// Gets the value of the extended attribute `name` of `path`, with a buffer of
+/// `len` bytes.
+///
+/// Falls back to the blocking system call if the kernel doesn't support the
+/// operation.
+///
+/// See also `man getxattr.2`.
+pub(crate) async fn getxattr(path: &Path, name: &str, len: usize) -> Result<Vec<u8>> {
+    let path = new_path_str(path)?;
+    let name = new_name_str(name)?;
+    let mut value = Vec::with_capacity(len);
+    if !is_supported(Opcode::GetXattr) {
+        return unblock(move || {
+            let n = unsafe {
+                libc::getxattr(path.as_ptr(), name.as_ptr(), value.as_mut_ptr() as _, len)
+            };
+            xattr_value(n, value)
+        })
+        .await;
+    }
+    let sqe = RawSqe {
+        addr: name.as_ptr() as u64,
+        len: len as u32,
+        off: value.as_mut_ptr() as u64,
+        addr3: path.as_ptr() as u64,
+        ..RawSqe::new(raw::GetXattr::CODE)
+    };
+    let op = submit(sqe.build())?;
+    let (result, (_, _, value)) = BufOp::new(op, (path, name, value)).wait().await;
+    xattr_value(result? as _, value)
+}
+
+fn xattr_value(n: isize, mut value: Vec<u8>) -> Result<Vec<u8>> {
+    if n < 0 {
+        return Err(Error::last_os_error());
+    }
+    unsafe { value.set_len(n as usize) };
+    Ok(value)
+}
+
+/// Sets the value of the extended attribute `name` of `fd`.
+///
+/// Falls back to the blocking system call if the kernel doesn't support the
+/// operation.
+///
+/// See also `man fsetxattr.2`.
+pub(crate) async fn fsetxattr(
+    fd: BorrowedFd<'_>,
+    name: &str,
+    value: &[u8],
+    flags: libc::c_int,
+) -> Result<()> {
+    let name = new_name_str(name)?;
+    if !is_supported(Opcode::FSetXattr) {
+        let fd = owned_fd(fd)?;
+        let value = value.to_vec();
+        return unblock(move || {
+            let ret = unsafe {
+                libc::fsetxattr(
+                    fd.as_raw_fd(),
+                    name.as_ptr(),
+                    value.as_ptr() as _,
+                    value.len(),
+                    flags,
+                )
+            };
+            if ret < 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(())
+        })
+        .await;
+    }
+    let value = value.to_vec();
+    let sqe = RawSqe {
+        fd: fd.as_raw_fd(),
+        addr: name.as_ptr() as u64,
+        len: value.len() as u32,
+        off: value.as_ptr() as u64,
+        op_flags: flags as u32,
+        ..RawSqe::new(raw::FSetXattr::CODE)
+    };
+    let op = submit(sqe.build())?;
+    BufOp::new(op, (name, value)).wait().await.0.map(|_| ())
+}
+
+/// Sets the value of the extended attribute `name` of `path`.
+///
+/// Falls back to the blocking system call if the kernel doesn't support the
+/// operation.
+///
+/// See also `man setxattr.2`.
+pub(crate) async fn setxattr(
+    path: &Path,
+    name: &str,
+    value: &[u8],
+    flags: libc::c_int,
+) -> Result<()> {
+    let path = new_path_str(path)?;
+    let name = new_name_str(name)?;
+    if !is_supported(Opcode::SetXattr) {
+        let value = value.to_vec();
+        return unblock(move || {
+            let ret = unsafe {
+                libc::setxattr(
+                    path.as_ptr(),
+                    name.as_ptr(),
+                    value.as_ptr() as _,
+                    value.len(),
+                    flags,
+                )
+            };
+            if ret < 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(())
+        })
+        .await;
+    }
+    let value = value.to_vec();
+    let sqe = RawSqe {
+        addr: name.as_ptr() as u64,
+        len: value.len() as u32,
+        off: value.as_ptr() as u64,
+        op_flags: flags as u32,
+        addr3: path.as_ptr() as u64,
+        ..RawSqe::new(raw::SetXattr::CODE)
+    };
+    let op = submit(sqe.build())?;
+    BufOp::new(op, (path, name, value))
+        .wait()
+        .await
+        .0
+        .map(|_| ())
+}
+
+/// Lists the names of the extended attributes of `fd`, with a buffer of `len`
+/// bytes.
+///
+/// io_uring doesn't provide a listxattr opcode, so runs it on the blocking
+/// thread pool instead.
+///
+/// See also `man flistxattr.2`.
+pub(crate) async fn flistxattr(fd: BorrowedFd<'_>, len: usize) -> Result<Vec<u8>> {
+    let fd = owned_fd(fd)?;
+    unblock(move || {
+        let mut list = Vec::with_capacity(len);
+        let n = unsafe { libc::flistxattr(fd.as_raw_fd(), list.as_mut_ptr() as _, len) };
+        xattr_value(n, list)
+    })
+    .await
 }
 
-async fn fsync_inner(fd: BorrowedFd<'_>, flags: types::FsyncFlags) -> Result<()> {
+/// See also `man posix_fadvise.2`.
+pub(crate) async fn fadvise(
+    fd: BorrowedFd<'_>,
+    offset: libc::off64_t,
+    len: libc::off64_t,
+    advice: libc::c_int,
+) -> Result<()> {
+    check_supported(Opcode::Fadvise)?;
     let fd = types::Fd(fd.as_raw_fd());
-    let sqe = opcode::Fsync::new(fd).flags(flags).build();
+    let sqe = opcode::Fadvise::new(fd, len as _, advice)
+        .offset(offset as _)
+        .build();
+    submit(sqe)?.await.map(|_| ())
+}
+
+/// Initiates readahead of `[offset, offset + len)` of `fd` into the page
+/// cache, without waiting for the pages to be read.
+///
+/// See also `POSIX_FADV_WILLNEED` in `man posix_fadvise.2`.
+pub(crate) async fn readahead(
+    fd: BorrowedFd<'_>,
+    offset: libc::off64_t,
+    len: libc::off64_t,
+) -> Result<()> {
+    if is_supported(Opcode::Fadvise) {
+        return fadvise(fd, offset, len, libc::POSIX_FADV_WILLNEED).await;
+    }
+    let fd = owned_fd(fd)?;
+    unblock(move || {
+        let fd = fd.as_raw_fd();
+        match unsafe { libc::posix_fadvise64(fd, offset, len, libc::POSIX_FADV_WILLNEED) } {
+            0 => Ok(()),
+            err => Err(Error::from_raw_os_error(err)),
+        }
+    })
+    .await
+}
+
+/// See also `man madvise.2`.
+///
+/// The address is passed as an integer so that the returned future borrows
+/// nothing.
+pub(crate) async fn madvise(addr: usize, len: usize, advice: libc::c_int) -> Result<()> {
+    check_supported(Opcode::Madvise)?;
+    let sqe = opcode::Madvise::new(addr as *const libc::c_void, len as _, advice).build();
     submit(sqe)?.await.map(|_| ())
 }
 
 /// See also `man mkdir.2`.
 pub(crate) async fn mkdir(path: &Path, mode: libc::mode_t) -> Result<()> {
+    mkdirat(None, path, mode).await
+}
+
+/// See also `man mkdirat.2`.
+///
+/// If `dirfd` is `None`, a relative `path` is resolved against the current
+/// working directory.
+pub(crate) async fn mkdirat(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: &Path,
+    mode: libc::mode_t,
+) -> Result<()> {
     let path = new_path_str(path)?;
-    let sqe = opcode::MkDirAt::new(types::Fd(libc::AT_FDCWD), path.as_c_str().as_ptr())
+    check_supported(Opcode::MkDirAt)?;
+    let sqe = opcode::MkDirAt::new(dir_fd(dirfd), path.as_c_str().as_ptr())
         .mode(mode)
         .build();
-    submit(sqe)?.await.map(|_| ())
+    BufOp::new(submit(sqe)?, path).wait().await.0.map(|_| ())
 }
 
 /// See also `man rmdir.2`.
 pub(crate) async fn rmdir(path: &Path) -> Result<()> {
-    unlink_inner(path, libc::AT_REMOVEDIR).await
+    unlinkat(None, path, libc::AT_REMOVEDIR).await
 }
 
 /// See also `man unlink.2`.
 pub(crate) async fn unlink(path: &Path) -> Result<()> {
-    unlink_inner(path, 0).await
+    unlinkat(None, path, 0).await
 }
 
-async fn unlink_inner(path: &Path, flags: libc::c_int) -> Result<()> {
+/// See also `man unlinkat.2`.
+///
+/// If `dirfd` is `None`, a relative `path` is resolved against the current
+/// working directory.
+pub(crate) async fn unlinkat(
+    dirfd: Option<BorrowedFd<'_>>,
+    path: &Path,
+    flags: libc::c_int,
+) -> Result<()> {
     let path = new_path_str(path)?;
-    let sqe = opcode::UnlinkAt::new(types::Fd(libc::AT_FDCWD), path.as_c_str().as_ptr())
+    check_supported(Opcode::UnlinkAt)?;
+    let sqe = opcode::UnlinkAt::new(dir_fd(dirfd), path.as_c_str().as_ptr())
         .flags(flags)
         .build();
-    submit(sqe)?.await.map(|_| ())
+    BufOp::new(submit(sqe)?, path).wait().await.0.map(|_| ())
 }
 
 /// See also `man rename.2`.
 pub(crate) async fn rename(oldpath: &Path, newpath: &Path) -> Result<()> {
+    renameat2(None, oldpath, None, newpath, 0).await
+}
+
+/// See also `man renameat2.2`.
+///
+/// If a dirfd is `None`, the corresponding relative path is resolved against
+/// the current working directory.
+pub(crate) async fn renameat2(
+    olddirfd: Option<BorrowedFd<'_>>,
+    oldpath: &Path,
+    newdirfd: Option<BorrowedFd<'_>>,
+    newpath: &Path,
+    flags: libc::c_uint,
+) -> Result<()> {
     let oldpath = new_path_str(oldpath)?;
     let newpath = new_path_str(newpath)?;
-    if unsafe {
-        libc::renameat(
-            libc::AT_FDCWD,
-            oldpath.as_c_str().as_ptr(),
-            libc::AT_FDCWD,
-            newpath.as_c_str().as_ptr(),
-        )
-    } == 0
-    {
-        Ok(())
-    } else {
-        Err(Error::last_os_error())
+    check_supported(Opcode::RenameAt)?;
+    let sqe = opcode::RenameAt::new(
+        dir_fd(olddirfd),
+        oldpath.as_c_str().as_ptr(),
+        dir_fd(newdirfd),
+        newpath.as_c_str().as_ptr(),
+    )
+    .flags(flags)
+    .build();
+    let op = submit(sqe)?;
+    BufOp::new(op, (oldpath, newpath))
+        .wait()
+        .await
+        .0
+        .map(|_| ())
+}
+
+/// See also `man linkat.2`.
+///
+/// If a dirfd is `None`, the corresponding relative path is resolved against
+/// the current working directory.
+pub(crate) async fn linkat(
+    olddirfd: Option<BorrowedFd<'_>>,
+    oldpath: &Path,
+    newdirfd: Option<BorrowedFd<'_>>,
+    newpath: &Path,
+    flags: libc::c_int,
+) -> Result<()> {
+    let oldpath = new_path_str(oldpath)?;
+    let newpath = new_path_str(newpath)?;
+    check_supported(Opcode::LinkAt)?;
+    let sqe = opcode::LinkAt::new(
+        dir_fd(olddirfd),
+        oldpath.as_c_str().as_ptr(),
+        dir_fd(newdirfd),
+        newpath.as_c_str().as_ptr(),
+    )
+    .flags(flags)
+    .build();
+    let op = submit(sqe)?;
+    BufOp::new(op, (oldpath, newpath))
+        .wait()
+        .await
+        .0
+        .map(|_| ())
+}
+
+/// See also `man symlinkat.2`.
+///
+/// If `newdirfd` is `None`, a relative `linkpath` is resolved against the
+/// current working directory.
+pub(crate) async fn symlinkat(
+    target: &Path,
+    newdirfd: Option<BorrowedFd<'_>>,
+    linkpath: &Path,
+) -> Result<()> {
+    let target = new_path_str(target)?;
+    let linkpath = new_path_str(linkpath)?;
+    check_supported(Opcode::SymlinkAt)?;
+    let sqe = opcode::SymlinkAt::new(
+        dir_fd(newdirfd),
+        target.as_c_str().as_ptr(),
+        linkpath.as_c_str().as_ptr(),
+    )
+    .build();
+    let op = submit(sqe)?;
+    BufOp::new(op, (target, linkpath))
+        .wait()
+        .await
+        .0
+        .map(|_| ())
+}
+
+/// See also `man readlinkat.2`.
+///
+/// If `dirfd` is `None`, a relative `path` is resolved against the current
+/// working directory.
+pub(crate) async fn readlinkat(dirfd: Option<BorrowedFd<'_>>, path: &Path) -> Result<PathBuf> {
+    // io_uring doesn't provide a readlink opcode, so runs it on the blocking
+    // thread pool instead.
+    let dirfd = owned_dir_fd(dirfd)?;
+    let path = new_path_str(path)?;
+    unblock(move || {
+        let mut buf = Vec::<u8>::with_capacity(256);
+        loop {
+            let n = unsafe {
+                libc::readlinkat(
+                    raw_dir_fd(&dirfd),
+                    path.as_ptr(),
+                    buf.as_mut_ptr() as *mut _,
+                    buf.capacity(),
+                )
+            };
+            if n < 0 {
+                return Err(Error::last_os_error());
+            }
+            let n = n as usize;
+            // The link might have been truncated if it fills the buffer.
+            if n < buf.capacity() {
+                unsafe { buf.set_len(n) };
+                return Ok(PathBuf::from(OsString::from_vec(buf)));
+            }
+            buf.reserve(buf.capacity() * 2);
+        }
+    })
+    .await
+}
+
+/// See also `man realpath.3`.
+pub(crate) async fn realpath(path: &Path) -> Result<PathBuf> {
+    // Resolving a path takes a system call for each component, none of which
+    // io_uring provides, so runs it on the blocking thread pool instead.
+    let path = path.to_path_buf();
+    unblock(move || std::fs::canonicalize(path)).await
+}
+
+/// Reads directory entries from `fd` into the spare capacity of `buf`, and
+/// returns the buffer with the entries read.
+///
+/// An empty buffer means that the end of the directory is reached.
+///
+/// See also `man getdents64.2`.
+pub(crate) async fn getdents64(fd: Arc<OwnedFd>, mut buf: Vec<u8>) -> Result<Vec<u8>> {
+    // io_uring doesn't provide a getdents opcode, so runs it on the blocking
+    // thread pool instead. The descriptor is shared so that it outlives a
+    // cancelled read.
+    unblock(move || {
+        buf.clear();
+        let n = unsafe {
+            libc::syscall(
+                libc::SYS_getdents64,
+                fd.as_raw_fd(),
+                buf.as_mut_ptr(),
+                buf.capacity(),
+            )
+        };
+        if n < 0 {
+            return Err(Error::last_os_error());
+        }
+        // The kernel has initialized the first `n` bytes.
+        unsafe { buf.set_len(n as usize) };
+        Ok(buf)
+    })
+    .await
+}
+
+/// See also `man socket.2`.
+///
+/// The returned socket is always created with `SOCK_CLOEXEC`. Falls back to
+/// the blocking system call if the kernel doesn't support the operation.
+pub(crate) async fn socket(
+    domain: libc::c_int,
+    ty: libc::c_int,
+    protocol: libc::c_int,
+) -> Result<OwnedFd> {
+    let ty = ty | libc::SOCK_CLOEXEC;
+    if !is_supported(Opcode::Socket) {
+        let fd = unsafe { libc::socket(domain, ty, protocol) };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        return Ok(unsafe { OwnedFd::from_raw_fd(fd) });
     }
+    let sqe = opcode::Socket::new(domain, ty, protocol).build();
+    let fd = submit(sqe)?.await?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as _) })
 }
 
 /// See also `man accept.2`.
 pub(crate) async fn accept(fd: BorrowedFd<'_>) -> Result<(OwnedFd, SockAddr)> {
     let fd = types::Fd(fd.as_raw_fd());
-    let mut addr: libc::sockaddr_storage = unsafe { mem::zeroed() };
-    let mut addr_len = mem::size_of_val(&addr) as libc::socklen_t;
-    let sqe = opcode::Accept::new(fd, &mut addr as *mut _ as *mut _, &mut addr_len)
+    let mut addr = AddrBuf::new();
+    let sqe = opcode::Accept::new(fd, addr.as_mut_ptr(), &mut addr.len)
         .flags(libc::O_CLOEXEC)
         .build();
-    let conn = submit(sqe)?.await?;
-    unsafe {
-        let conn = OwnedFd::from_raw_fd(conn as _);
-        let sock_addr = SockAddr::new(addr, addr_len);
-        Ok((conn, sock_addr))
+    let op = submit(sqe)?.retry_would_block();
+    let (result, addr) = BufOp::new(op, addr).wait().await;
+    let conn = unsafe { OwnedFd::from_raw_fd(result? as _) };
+    // The kernel fills the address on completion, so it must only be read
+    // after the operation has completed.
+    Ok((conn, unsafe { addr.to_sock_addr() }))
+}
+
+/// This function is similar to [`accept`], except that the connection is
+/// installed into `slot` of the fixed file table as a direct descriptor.
+pub(crate) async fn accept_direct(fd: BorrowedFd<'_>, slot: u32) -> Result<SockAddr> {
+    let fd = types::Fd(fd.as_raw_fd());
+    let mut addr = AddrBuf::new();
+    // Direct descriptors don't support `O_CLOEXEC`.
+    let sqe = opcode::Accept::new(fd, addr.as_mut_ptr(), &mut addr.len)
+        .file_index(Some(dest_slot(slot)?))
+        .build();
+    let op = submit(sqe)?.retry_would_block();
+    let (result, addr) = BufOp::new(op, addr).wait().await;
+    result?;
+    Ok(unsafe { addr.to_sock_addr() })
+}
+
+/// A socket address for the kernel to fill, which is owned by an op.
+struct AddrBuf {
+    addr: libc::sockaddr_storage,
+    len: libc::socklen_t,
+}
+
+impl AddrBuf {
+    fn new() -> Box<Self> {
+        Box::new(Self {
+            addr: unsafe { mem::zeroed() },
+            len: mem::size_of::<libc::sockaddr_storage>() as _,
+        })
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut libc::sockaddr {
+        &mut self.addr as *mut _ as *mut _
+    }
+
+    /// Returns the address.
+    ///
+    /// # Safety
+    ///
+    /// The kernel must have filled the address.
+    unsafe fn to_sock_addr(&self) -> SockAddr {
+        SockAddr::new(self.addr, self.len)
+    }
+}
+
+/// A multishot operation that accepts connections.
+pub(crate) struct AcceptMulti(MultiOp);
+
+impl AcceptMulti {
+    /// Returns the next accepted connection, or `None` if the operation has
+    /// terminated.
+    pub(crate) async fn next(&mut self) -> Option<Result<OwnedFd>> {
+        let completion = self.0.next().await?;
+        Some(
+            completion
+                .result
+                .map(|fd| unsafe { OwnedFd::from_raw_fd(fd as _) }),
+        )
     }
 }
 
+/// Accepts connections on `fd` until an error occurs.
+///
+/// Connections accepted after the returned operation is dropped are closed.
+///
+/// See also `man accept.2`.
+pub(crate) fn accept_multi(fd: BorrowedFd<'_>) -> Result<AcceptMulti> {
+    let fd = types::Fd(fd.as_raw_fd());
+    let sqe = opcode::AcceptMulti::new(fd).flags(libc::O_CLOEXEC).build();
+    let op = submit_multi(sqe)?.on_discard(|completion| {
+        if let Ok(fd) = completion.result {
+            unsafe { libc::close(fd as _) };
+        }
+    });
+    Ok(AcceptMulti(op))
+}
+
 /// See also `man connect.2`.
 pub(crate) async fn connect(fd: BorrowedFd<'_>, addr: SockAddr) -> Result<()> {
     let fd = types::Fd(fd.as_raw_fd());
+    let addr = Box::new(addr);
     let sqe = opcode::Connect::new(fd, addr.as_ptr(), addr.len()).build();
-    submit(sqe)?.await.map(|_| ())
+    BufOp::new(submit(sqe)?, addr).wait().await.0.map(|_| ())
 }
 
 /// See also `man shutdown.2`.
-pub(crate) async fn shutdown(fd: BorrowedFd<'_>, how: libc::c_int) -> Result<()> {
-    let fd = types::Fd(fd.as_raw_fd());
-    let sqe = opcode::Shutdown::new(fd, how).build();
-    submit(sqe)?.await.map(|_| ())
+///
+/// Falls back to the blocking system call if the kernel doesn't support the
+/// operation, which never blocks anyway.
+pub(crate) async fn shutdown<'a>(fd: impl Into<Target<'a>>, how: libc::c_int) -> Result<()> {
+    let fd = fd.into();
+    if !is_supported(Opcode::Shutdown) {
+        // Direct descriptors can't be used outside of the ring.
+        let fd = match fd {
+            Target::Fd(fd) => fd.as_raw_fd(),
+            Target::Fixed(_) => return check_supported(Opcode::Shutdown),
+        };
+        if unsafe { libc::shutdown(fd, how) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        return Ok(());
+    }
+    let sqe = match fd {
+        Target::Fd(fd) => opcode::Shutdown::new(types::Fd(fd.as_raw_fd()), how),
+        Target::Fixed(slot) => opcode::Shutdown::new(types::Fixed(slot), how),
+    };
+    submit(sqe.build())?.await.map(|_| ())
 }
 
 /// See also `man read.2`.
@@ -154,15 +1358,116 @@ pub(crate) async fn read<'a>(fd: BorrowedFd<'a>, buf: &'a mut [u8]) -> Result<us
 
 /// See also `man pread.2`.
 pub(crate) async fn pread<'a>(
+    fd: impl Into<Target<'a>>,
+    buf: &'a mut [u8],
+    pos: libc::off64_t,
+) -> Result<usize> {
+    let mut bounce = new_bounce(buf.as_ptr(), buf.len());
+    let sqe = {
+        // The pointer is scoped, so that the future stays `Send`.
+        let (ptr, len) = (bounce.as_mut_ptr(), bounce.len() as _);
+        match fd.into() {
+            Target::Fd(fd) => opcode::Read::new(types::Fd(fd.as_raw_fd()), ptr, len),
+            Target::Fixed(slot) => opcode::Read::new(types::Fixed(slot), ptr, len),
+        }
+    };
+    let sqe = sqe.offset(pos).build();
+    let (result, bounce) = BufOp::new(submit(sqe)?, bounce).wait().await;
+    scatter_read(result, &bounce, [buf])
+}
+
+/// This function is similar to [`pread`], except that it reads into an owned
+/// buffer, which is returned with the result.
+///
+/// The read starts at the beginning of the buffer, up to its total capacity.
+pub(crate) async fn pread_owned<B: IoBufMut>(
+    fd: BorrowedFd<'_>,
+    mut buf: B,
+    pos: libc::off64_t,
+) -> (Result<usize>, B) {
+    let fd = types::Fd(fd.as_raw_fd());
+    let len = buf.bytes_total().min(u32::MAX as usize);
+    let sqe = opcode::Read::new(fd, buf.stable_mut_ptr(), len as _)
+        .offset(pos)
+        .build();
+    let op = match submit(sqe) {
+        Ok(op) => op,
+        Err(e) => return (Err(e), buf),
+    };
+    let (result, mut buf) = BufOp::new(op, buf).wait().await;
+    let result = result.map(|n| {
+        // The kernel has initialized the first `n` bytes.
+        unsafe { buf.set_init(n as _) };
+        n as _
+    });
+    (result, buf)
+}
+
+/// This function is similar to [`pread`], except that it fails with
+/// [`ErrorKind::TimedOut`] if the read doesn't complete in `timeout`.
+pub(crate) async fn pread_timeout<'a>(
     fd: BorrowedFd<'a>,
     buf: &'a mut [u8],
     pos: libc::off64_t,
+    timeout: Duration,
 ) -> Result<usize> {
     let fd = types::Fd(fd.as_raw_fd());
-    let sqe = opcode::Read::new(fd, buf.as_mut_ptr(), buf.len() as _)
+    let mut bounce = new_bounce(buf.as_ptr(), buf.len());
+    let sqe = opcode::Read::new(fd, bounce.as_mut_ptr(), bounce.len() as _)
         .offset(pos)
         .build();
-    submit(sqe)?.await.map(|n| n as _)
+    let (result, bounce) = submit_timeout(sqe, bounce, timeout).await;
+    scatter_read(result, &bounce, [buf])
+}
+
+/// Reads into the whole fixed buffer `buf`, and returns the result with the
+/// buffer.
+pub(crate) async fn read_fixed(
+    fd: BorrowedFd<'_>,
+    mut buf: FixedBuf,
+    pos: libc::off64_t,
+) -> (Result<usize>, FixedBuf) {
+    let fd = types::Fd(fd.as_raw_fd());
+    let op = check_supported(Opcode::ReadFixed)
+        .and_then(|_| buf.index())
+        .and_then(|index| {
+            let sqe = opcode::ReadFixed::new(fd, buf.as_mut_ptr(), buf.len() as _, index)
+                .offset(pos)
+                .build();
+            submit(sqe)
+        });
+    match op {
+        Ok(op) => {
+            let (result, buf) = BufOp::new(op, buf).wait().await;
+            (result.map(|n| n as _), buf)
+        }
+        Err(e) => (Err(e), buf),
+    }
+}
+
+/// Writes the whole fixed buffer `buf`, and returns the result with the
+/// buffer.
+pub(crate) async fn write_fixed(
+    fd: BorrowedFd<'_>,
+    buf: FixedBuf,
+    pos: libc::off64_t,
+) -> (Result<usize>, FixedBuf) {
+    let fd = types::Fd(fd.as_raw_fd());
+    let op = check_supported(Opcode::WriteFixed)
+        .and_then(|_| buf.index())
+        .and_then(|index| {
+            let sqe = opcode::WriteFixed::new(fd, buf.as_ptr(), buf.len() as _, index)
+                .offset(pos)
+                .build();
+            submit(sqe)
+        });
+    match op {
+        Ok(op) => {
+            let (result, buf) = BufOp::new(op, buf).wait().await;
+            (result.map(|n| n as _), buf)
+        }
+        Err(e) => (Err(e), buf),
+    }
 }
 
 /// See also `man write.2`.
@@ -172,15 +1477,783 @@ pub(crate) async fn write<'a>(fd: BorrowedFd<'a>, buf: &'a [u8]) -> Result<usize
 
 /// See also `man pwrite.2`.
 pub(crate) async fn pwrite<'a>(
+    fd: impl Into<Target<'a>>,
+    buf: &'a [u8],
+    pos: libc::off64_t,
+) -> Result<usize> {
+    // The data is copied for the same reason as in `new_bounce`.
+    let bounce = copy_bounce(buf);
+    let sqe = {
+        // The pointer is scoped, so that the future stays `Send`.
+        let (ptr, len) = (bounce.as_ptr(), bounce.len() as _);
+        match fd.into() {
+            Target::Fd(fd) => opcode::Write::new(types::Fd(fd.as_raw_fd()), ptr, len),
+            Target::Fixed(slot) => opcode::Write::new(types::Fixed(slot), ptr, len),
+        }
+    };
+    let sqe = sqe.offset(pos).build();
+    let op = submit(sqe)?;
+    BufOp::new(op, bounce).wait().await.0.map(|n| n as _)
+}
+
+/// This function is similar to [`pwrite`], except that it fails with
+/// [`ErrorKind::TimedOut`] if the write doesn't complete in `timeout`.
+pub(crate) async fn pwrite_timeout<'a>(
     fd: BorrowedFd<'a>,
     buf: &'a [u8],
     pos: libc::off64_t,
+    timeout: Duration,
 ) -> Result<usize> {
     let fd = types::Fd(fd.as_raw_fd());
-    let sqe = opcode::Write::new(fd, buf.as_ptr(), buf.len() as _)
+    let bounce = copy_bounce(buf);
+    let sqe = opcode::Write::new(fd, bounce.as_ptr(), bounce.len() as _)
         .offset(pos)
         .build();
-    submit(sqe)?.await.map(|n| n as _)
+    submit_timeout(sqe, bounce, timeout).await.0.map(|n| n as _)
+}
+
+/// Writes `buf` at `pos` and then synchronizes the data of the file, in one
+/// submission.
+///
+/// Returns the results of both operations. If the write fails or is short,
+/// the synchronization fails with `ECANCELED`.
+pub(crate) async fn pwrite_fdatasync<'a>(
+    fd: BorrowedFd<'a>,
+    buf: &'a [u8],
+    pos: libc::off64_t,
+) -> Result<(Result<usize>, Result<()>)> {
+    let fd = types::Fd(fd.as_raw_fd());
+    let bounce = copy_bounce(buf);
+    let write = opcode::Write::new(fd, bounce.as_ptr(), bounce.len() as _)
+        .offset(pos)
+        .build();
+    let sync = opcode::Fsync::new(fd)
+        .flags(types::FsyncFlags::DATASYNC)
+        .build();
+    let mut ops = submit_linked(vec![write, sync])?.into_iter();
+    let (write, sync) = (BufOp::new(ops.next().unwrap(), bounce), ops.next().unwrap());
+    // Awaits both operations, so that no completion is left behind.
+    let write = write.wait().await.0.map(|n| n as usize);
+    let sync = sync.await.map(|_| ());
+    Ok((write, sync))
+}
+
+/// See also `man splice.2`.
+///
+/// An offset of `-1` means the current position of a non-pipe descriptor. Pipes
+/// must always use `-1`.
+pub(crate) async fn splice(
+    fd_in: BorrowedFd<'_>,
+    off_in: libc::off64_t,
+    fd_out: BorrowedFd<'_>,
+    off_out: libc::off64_t,
+    len: u32,
+    flags: libc::c_uint,
+) -> Result<usize> {
+    let fd_in = types::Fd(fd_in.as_raw_fd());
+    let fd_out = types::Fd(fd_out.as_raw_fd());
+    check_supported(Opcode::Splice)?;
+    let sqe = opcode::Splice::new(fd_in, off_in, fd_out, off_out, len)
+        .flags(flags as _)
+        .build();
+    submit(sqe)?.await.map(|n| n as usize)
+}
+
+/// See also `man tee.2`.
+pub(crate) async fn tee(
+    fd_in: BorrowedFd<'_>,
+    fd_out: BorrowedFd<'_>,
+    len: u32,
+    flags: libc::c_uint,
+) -> Result<usize> {
+    let fd_in = types::Fd(fd_in.as_raw_fd());
+    let fd_out = types::Fd(fd_out.as_raw_fd());
+    check_supported(Opcode::Tee)?;
+    let sqe = opcode::Tee::new(fd_in, fd_out, len)
+        .flags(flags as _)
+        .build();
+    submit(sqe)?.await.map(|n| n as usize)
+}
+
+/// Submits the command `cmd_op` to the driver of `fd`.
+///
+/// Returns the result and the extra 16 bytes of the completion.
+///
+/// # Safety
+///
+/// `cmd` must be a valid command for the driver, and any memory it refers to
+/// must stay valid until the operation completes, even if the future is
+/// dropped.
+pub(crate) async unsafe fn uring_cmd(
+    fd: BorrowedFd<'_>,
+    cmd_op: u32,
+    cmd: [u8; 80],
+) -> Result<(u32, [u64; 2])> {
+    check_supported(Opcode::UringCmd)?;
+    let fd = types::Fd(fd.as_raw_fd());
+    let sqe = opcode::UringCmd80::new(fd, cmd_op).cmd(cmd).build();
+    let completion = submit128(sqe)?.completion().await;
+    completion.result.map(|n| (n, completion.extra))
+}
+
+/// The futex word is 32 bits. The futex is not private, so that it works
+/// across processes.
+const FUTEX2_SIZE_U32: i32 = 0x02;
+
+/// Waits on the futex word at `addr` if it equals `expected`.
+///
+/// Returns an error of `EAGAIN` if the word doesn't equal `expected`.
+///
+/// The kernel only reads the word when the wait starts, and never writes it,
+/// so `addr` doesn't need to stay valid if the future is dropped. An invalid
+/// `addr` fails the operation with `EFAULT`.
+///
+/// See also `FUTEX_WAIT_BITSET` in `man futex.2`.
+pub(crate) async fn futex_wait(addr: usize, expected: u32, mask: u32) -> Result<()> {
+    check_supported(Opcode::FutexWait)?;
+    let sqe = RawSqe {
+        fd: FUTEX2_SIZE_U32,
+        off: expected as u64,
+        addr: addr as u64,
+        addr3: mask as u64,
+        ..RawSqe::new(raw::FutexWait::CODE)
+    };
+    submit(sqe.build())?.await.map(|_| ())
+}
+
+/// Wakes up at most `count` waiters of the futex word at `addr`.
+///
+/// Returns the number of waiters woken up.
+///
+/// See also `FUTEX_WAKE_BITSET` in `man futex.2`.
+pub(crate) async fn futex_wake(addr: usize, count: u32, mask: u32) -> Result<usize> {
+    check_supported(Opcode::FutexWake)?;
+    let sqe = RawSqe {
+        fd: FUTEX2_SIZE_U32,
+        off: count as u64,
+        addr: addr as u64,
+        addr3: mask as u64,
+        ..RawSqe::new(raw::FutexWake::CODE)
+    };
+    submit(sqe.build())?.await.map(|n| n as usize)
+}
+
+/// Waits for the child process `pid` to change state.
+///
+/// Falls back to polling a pidfd of the child if the kernel doesn't support
+/// the operation.
+///
+/// See also `man waitid.2`.
+pub(crate) async fn waitid(pid: libc::pid_t, options: libc::c_int) -> Result<libc::siginfo_t> {
+    if !is_supported(Opcode::WaitId) {
+        return waitid_pidfd(pid, options).await;
+    }
+    let mut info: Box<libc::siginfo_t> = Box::new(unsafe { mem::zeroed() });
+    let sqe = RawSqe {
+        fd: pid,
+        len: libc::P_PID,
+        file_index: options as u32,
+        off: &mut *info as *mut _ as u64,
+        ..RawSqe::new(raw::WaitId::CODE)
+    };
+    let (result, info) = BufOp::new(submit(sqe.build())?, info).wait().await;
+    result?;
+    Ok(*info)
+}
+
+async fn waitid_pidfd(pid: libc::pid_t, options: libc::c_int) -> Result<libc::siginfo_t> {
+    const P_PIDFD: libc::idtype_t = 3;
+
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+    if fd < 0 {
+        let err = Error::last_os_error();
+        // The child has been reaped, which `waitid` reports as `ECHILD`.
+        if err.raw_os_error() == Some(libc::ESRCH) {
+            return Err(Error::from_raw_os_error(libc::ECHILD));
+        }
+        return Err(err);
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd as _) };
+    loop {
+        let mut info: libc::siginfo_t = unsafe { mem::zeroed() };
+        let options = options | libc::WNOHANG;
+        if unsafe { libc::waitid(P_PIDFD, fd.as_raw_fd() as _, &mut info, options) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        // The pid is zero if the child hasn't changed state yet.
+        if unsafe { info.si_pid() } != 0 {
+            return Ok(info);
+        }
+        // The pidfd becomes readable when the child exits.
+        poll_add(fd.as_fd(), libc::POLLIN).await?;
+    }
+}
+
+/// Waits until one of the `events` is ready on `fd`.
+///
+/// Returns the ready events. Dropping the returned future before completion
+/// cancels the operation.
+///
+/// See also `man poll.2`.
+pub(crate) async fn poll_add(fd: BorrowedFd<'_>, events: libc::c_short) -> Result<libc::c_short> {
+    let fd = types::Fd(fd.as_raw_fd());
+    let sqe = opcode::PollAdd::new(fd, events as _).build();
+    submit(sqe)?.await.map(|revents| revents as _)
+}
+
+/// See also `man send.2`.
+pub(crate) async fn send<'a>(
+    fd: impl Into<Target<'a>>,
+    buf: &'a [u8],
+    flags: libc::c_int,
+) -> Result<usize> {
+    // The data is copied for the same reason as in `new_bounce`.
+    send_owned(fd, copy_bounce(buf), flags).await.0
+}
+
+/// This function is similar to [`send`], except that it sends from an owned
+/// buffer, which is returned with the result.
+pub(crate) async fn send_owned<'a, B: IoBuf>(
+    fd: impl Into<Target<'a>>,
+    buf: B,
+    flags: libc::c_int,
+) -> (Result<usize>, B) {
+    let sqe = {
+        // The pointer is scoped, so that the future stays `Send`.
+        let (ptr, len) = (buf.stable_ptr(), buf.bytes_init().min(u32::MAX as usize));
+        match fd.into() {
+            Target::Fd(fd) => opcode::Send::new(types::Fd(fd.as_raw_fd()), ptr, len as _),
+            Target::Fixed(slot) => opcode::Send::new(types::Fixed(slot), ptr, len as _),
+        }
+    };
+    let sqe = sqe.flags(flags).build();
+    let op = match submit(sqe) {
+        Ok(op) => op.retry_would_block(),
+        Err(e) => return (Err(e), buf),
+    };
+    let (result, buf) = BufOp::new(op, buf).wait().await;
+    (result.map(|n| n as _), buf)
+}
+
+/// Sends `buf` without copying it into the socket buffer.
+///
+/// The kernel references the buffer until it notifies that the data has been
+/// sent, so the buffer is owned by the operation until then, even if the
+/// operation is dropped. Falls back to [`send`] if the kernel or the socket
+/// doesn't support zero-copy sends.
+///
+/// See also `IORING_OP_SEND_ZC` in `man io_uring_enter.2`.
+pub(crate) async fn send_zc<B: IoBuf>(
+    fd: BorrowedFd<'_>,
+    buf: B,
+    flags: libc::c_int,
+) -> (Result<usize>, B) {
+    // The flag of the notification completion.
+    const IORING_CQE_F_NOTIF: u32 = 1 << 3;
+
+    let len = buf.bytes_init().min(u32::MAX as usize);
+    if !is_supported(Opcode::SendZc) {
+        return send_owned(fd, buf, flags).await;
+    }
+    let sqe = opcode::SendZc::new(types::Fd(fd.as_raw_fd()), buf.stable_ptr(), len as _)
+        .flags(flags)
+        .build();
+    let holder = Arc::new(Mutex::new(Some(buf)));
+    let mut op = match submit_multi(sqe) {
+        // The discard function keeps the buffer until the last completion if
+        // the operation is dropped.
+        Ok(op) => {
+            let holder = holder.clone();
+            op.on_discard(move |_| {
+                let _ = &holder;
+            })
+        }
+        Err(e) => return (Err(e), holder.lock().unwrap().take().unwrap()),
+    };
+    let mut result = Err(Error::from_raw_os_error(libc::EIO));
+    while let Some(completion) = op.next().await {
+        if completion.flags & IORING_CQE_F_NOTIF == 0 {
+            result = completion.result.map(|n| n as _);
+        }
+    }
+    drop(op);
+    let buf = holder.lock().unwrap().take().unwrap();
+    match result {
+        Err(e) if matches!(raw_os_error(&e), Some(libc::EOPNOTSUPP | libc::EINVAL)) => {
+            send_owned(fd, buf, flags).await
+        }
+        result => (result, buf),
+    }
+}
+
+/// See also `man recv.2`.
+pub(crate) async fn recv<'a>(
+    fd: BorrowedFd<'a>,
+    buf: &'a mut [u8],
+    flags: libc::c_int,
+) -> Result<usize> {
+    let fd = types::Fd(fd.as_raw_fd());
+    let mut bounce = new_bounce(buf.as_ptr(), buf.len());
+    let sqe = opcode::Recv::new(fd, bounce.as_mut_ptr(), bounce.len() as _)
+        .flags(flags)
+        .build();
+    let op = submit(sqe)?.retry_would_block();
+    let (result, bounce) = BufOp::new(op, bounce).wait().await;
+    scatter_read(result, &bounce, [buf])
+}
+
+/// This function is similar to [`send`], except that it fails with
+/// [`ErrorKind::TimedOut`] if the send doesn't complete in `timeout`.
+pub(crate) async fn send_timeout<'a>(
+    fd: BorrowedFd<'a>,
+    buf: &'a [u8],
+    flags: libc::c_int,
+    timeout: Duration,
+) -> Result<usize> {
+    let fd = types::Fd(fd.as_raw_fd());
+    let bounce = copy_bounce(buf);
+    let sqe = opcode::Send::new(fd, bounce.as_ptr(), bounce.len() as _)
+        .flags(flags)
+        .build();
+    submit_timeout(sqe, bounce, timeout).await.0.map(|n| n as _)
+}
+
+/// This function is similar to [`recv`], except that it fails with
+/// [`ErrorKind::TimedOut`] if the receive doesn't complete in `timeout`.
+pub(crate) async fn recv_timeout<'a>(
+    fd: BorrowedFd<'a>,
+    buf: &'a mut [u8],
+    flags: libc::c_int,
+    timeout: Duration,
+) -> Result<usize> {
+    let fd = types::Fd(fd.as_raw_fd());
+    let mut bounce = new_bounce(buf.as_ptr(), buf.len());
+    let sqe = opcode::Recv::new(fd, bounce.as_mut_ptr(), bounce.len() as _)
+        .flags(flags)
+        .build();
+    let (result, bounce) = submit_timeout(sqe, bounce, timeout).await;
+    scatter_read(result, &bounce, [buf])
+}
+
+/// A multishot operation that receives data into buffers selected from a
+/// buffer group.
+pub(crate) struct RecvMulti(MultiOp);
+
+impl RecvMulti {
+    /// Returns the length of the next received data and the id of the selected
+    /// buffer, or `None` if the operation has terminated.
+    pub(crate) async fn next(&mut self) -> Option<Result<(usize, Option<u16>)>> {
+        let completion = self.0.next().await?;
+        let bid = cqueue::buffer_select(completion.flags);
+        Some(completion.result.map(|n| (n as usize, bid)))
+    }
+}
+
+/// Receives data on `fd` into buffers selected from the buffer group `bgid`
+/// until an error occurs.
+///
+/// If the returned operation is dropped, the buffers of the following
+/// completions are passed to `discard`.
+///
+/// See also `man recv.2`.
+pub(crate) fn recv_multi(
+    fd: BorrowedFd<'_>,
+    bgid: u16,
+    mut discard: impl FnMut(u16) + Send + 'static,
+) -> Result<RecvMulti> {
+    let fd = types::Fd(fd.as_raw_fd());
+    let sqe = opcode::RecvMulti::new(fd, bgid)
+        .build()
+        .flags(squeue::Flags::BUFFER_SELECT);
+    let op = submit_multi(sqe)?.on_discard(move |completion| {
+        if let Some(bid) = cqueue::buffer_select(completion.flags) {
+            discard(bid);
+        }
+    });
+    Ok(RecvMulti(op))
+}
+
+/// A message header and the memory it refers to, which is owned by an op.
+///
+/// The data is kept in one buffer.
+struct Msg {
+    hdr: libc::msghdr,
+    iov: libc::iovec,
+    addr: libc::sockaddr_storage,
+    data: AlignedBuf,
+    control: Vec<u8>,
+}
+
+// SAFETY: The pointers in the header only refer to the message itself.
+unsafe impl Send for Msg {}
+
+impl Msg {
+    fn new(mut data: AlignedBuf, control: Vec<u8>) -> Box<Self> {
+        let mut msg = Box::new(Self {
+            hdr: unsafe { mem::zeroed() },
+            iov: libc::iovec {
+                iov_base: data.as_mut_ptr() as _,
+                iov_len: data.len(),
+            },
+            addr: unsafe { mem::zeroed() },
+            data,
+            control,
+        });
+        let msg_ref = &mut *msg;
+        msg_ref.hdr.msg_name = &mut msg_ref.addr as *mut _ as *mut _;
+        msg_ref.hdr.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as _;
+        msg_ref.hdr.msg_iov = &mut msg_ref.iov;
+        msg_ref.hdr.msg_iovlen = 1;
+        if !msg_ref.control.is_empty() {
+            msg_ref.hdr.msg_control = msg_ref.control.as_mut_ptr() as *mut _;
+            msg_ref.hdr.msg_controllen = msg_ref.control.len() as _;
+        }
+        msg
+    }
+}
+
+/// See also `man sendmsg.2`.
+///
+/// The data of `bufs` is sent from one copied buffer, for the same reason as in
+/// [`new_bounce`].
+pub(crate) async fn sendmsg<'a>(
+    fd: BorrowedFd<'a>,
+    bufs: &'a [IoSlice<'_>],
+    addr: Option<SockAddr>,
+    control: &'a [u8],
+    flags: libc::c_int,
+) -> Result<usize> {
+    let mut msg = Msg::new(gather(bufs), control.to_vec());
+    match &addr {
+        Some(addr) => {
+            let len = addr.len() as usize;
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    addr.as_ptr() as *const u8,
+                    &mut msg.addr as *mut _ as *mut u8,
+                    len,
+                )
+            };
+            msg.hdr.msg_namelen = len as _;
+        }
+        None => {
+            msg.hdr.msg_name = ptr::null_mut();
+            msg.hdr.msg_namelen = 0;
+        }
+    }
+    let fd = types::Fd(fd.as_raw_fd());
+    let sqe = opcode::SendMsg::new(fd, &msg.hdr).flags(flags as _).build();
+    let op = submit(sqe)?.retry_would_block();
+    BufOp::new(op, msg).wait().await.0.map(|n| n as _)
+}
+
+/// The result of [`recvmsg`].
+pub(crate) struct RecvMsg {
+    /// The number of bytes received.
+    pub(crate) len: usize,
+    /// The source address of the message.
+    pub(crate) addr: SockAddr,
+    /// The number of bytes of control messages received.
+    pub(crate) control_len: usize,
+    /// The `MSG_*` flags of the received message.
+    pub(crate) flags: libc::c_int,
+}
+
+/// See also `man recvmsg.2`.
+pub(crate) async fn recvmsg<'a>(
+    fd: BorrowedFd<'a>,
+    bufs: &'a mut [IoSliceMut<'_>],
+    control: &'a mut [u8],
+    flags: libc::c_int,
+) -> Result<RecvMsg> {
+    // The message is received into owned buffers, and copied to `bufs` and
+    // `control` after completion, see `new_bounce`.
+    let len = bufs.iter().map(|buf| buf.len()).sum();
+    let data = new_bounce(bufs.first().map_or(ptr::null(), |buf| buf.as_ptr()), len);
+    let mut msg = Msg::new(data, vec![0; control.len()]);
+    let fd = types::Fd(fd.as_raw_fd());
+    let sqe = opcode::RecvMsg::new(fd, &mut msg.hdr)
+        .flags(flags as _)
+        .build();
+    let op = submit(sqe)?.retry_would_block();
+    let (result, msg) = BufOp::new(op, msg).wait().await;
+    let Msg {
+        hdr,
+        addr,
+        data,
+        control: received,
+        ..
+    } = *msg;
+    let len = scatter_read(result, &data, bufs.iter_mut().map(|buf| &mut **buf))?;
+    // The kernel fills the address and the lengths on completion.
+    let control_len = (hdr.msg_controllen as usize).min(control.len());
+    control[..control_len].copy_from_slice(&received[..control_len]);
+    Ok(RecvMsg {
+        len,
+        addr: unsafe { SockAddr::new(addr, hdr.msg_namelen) },
+        control_len,
+        flags: hdr.msg_flags,
+    })
+}
+
+/// See also `man sendto.2`.
+pub(crate) async fn send_to<'a>(
+    fd: BorrowedFd<'a>,
+    buf: &'a [u8],
+    addr: SockAddr,
+    flags: libc::c_int,
+) -> Result<usize> {
+    let bufs = [IoSlice::new(buf)];
+    sendmsg(fd, &bufs, Some(addr), &[], flags).await
+}
+
+/// See also `man recvfrom.2`.
+pub(crate) async fn recv_from<'a>(
+    fd: BorrowedFd<'a>,
+    buf: &'a mut [u8],
+    flags: libc::c_int,
+) -> Result<(usize, SockAddr)> {
+    let mut bufs = [IoSliceMut::new(buf)];
+    let msg = recvmsg(fd, &mut bufs, &mut [], flags).await?;
+    Ok((msg.len, msg.addr))
+}
+
+/// Receives up to `bufs.len()` datagrams without blocking.
+///
+/// Returns the length and the source address of each datagram received.
+/// Returns an error of `EAGAIN` if no datagram is ready.
+///
+/// io_uring doesn't provide a recvmmsg opcode, and this never blocks, so it
+/// calls the system call directly.
+///
+/// See also `man recvmmsg.2`.
+///
+/// # Safety
+///
+/// Each buffer of `bufs` must be valid for writes.
+pub(crate) unsafe fn try_recvmmsg(
+    fd: BorrowedFd<'_>,
+    bufs: &[libc::iovec],
+) -> Result<Vec<(usize, SockAddr)>> {
+    let mut addrs: Vec<libc::sockaddr_storage> = vec![mem::zeroed(); bufs.len()];
+    let mut msgs: Vec<libc::mmsghdr> = bufs
+        .iter()
+        .zip(addrs.iter_mut())
+        .map(|(iov, addr)| {
+            let mut msg: libc::mmsghdr = mem::zeroed();
+            msg.msg_hdr.msg_name = addr as *mut _ as *mut _;
+            msg.msg_hdr.msg_namelen = mem::size_of_val(addr) as _;
+            msg.msg_hdr.msg_iov = iov as *const _ as *mut _;
+            msg.msg_hdr.msg_iovlen = 1;
+            msg
+        })
+        .collect();
+    let n = libc::recvmmsg(
+        fd.as_raw_fd(),
+        msgs.as_mut_ptr(),
+        msgs.len() as _,
+        libc::MSG_DONTWAIT,
+        ptr::null_mut(),
+    );
+    if n < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(msgs
+        .iter()
+        .zip(addrs)
+        .take(n as usize)
+        .map(|(msg, addr)| {
+            let len = msg.msg_len as usize;
+            (len, SockAddr::new(addr, msg.msg_hdr.msg_namelen))
+        })
+        .collect())
+}
+
+/// See also `man readv.2`.
+pub(crate) fn readv<'a>(
+    fd: BorrowedFd<'a>,
+    bufs: &'a mut [IoSliceMut<'_>],
+) -> impl Future<Output = Result<usize>> + 'a {
+    preadv(fd, bufs, -1)
+}
+
+/// See also `man preadv.2`.
+pub(crate) fn preadv<'a>(
+    fd: BorrowedFd<'a>,
+    bufs: &'a mut [IoSliceMut<'_>],
+    pos: libc::off64_t,
+) -> impl Future<Output = Result<usize>> + 'a {
+    preadv2(fd, bufs, pos, 0)
+}
+
+/// See also `man preadv2.2`.
+pub(crate) fn preadv2<'a>(
+    fd: BorrowedFd<'a>,
+    bufs: &'a mut [IoSliceMut<'_>],
+    pos: libc::off64_t,
+    flags: libc::c_int,
+) -> impl Future<Output = Result<usize>> + 'a {
+    // The data is read into one owned buffer and then scattered to `bufs`,
+    // see `new_bounce`. The slices are collected here, so that the future
+    // only borrows them for `'a`.
+    let len = bufs.iter().map(|buf| buf.len()).sum();
+    let first = bufs.first().map_or(ptr::null(), |buf| buf.as_ptr());
+    let iov = IoVec::new(new_bounce(first, len));
+    let bufs: Vec<&'a mut [u8]> = bufs.iter_mut().map(|buf| &mut **buf).collect();
+    let fd = types::Fd(fd.as_raw_fd());
+    async move {
+        let sqe = opcode::Readv::new(fd, &iov.iov, 1)
+            .offset(pos)
+            .rw_flags(flags as _)
+            .build();
+        let (result, iov) = BufOp::new(submit(sqe)?, iov).wait().await;
+        scatter_read(result, &iov.buf, bufs)
+    }
+}
+
+/// See also `man writev.2`.
+pub(crate) fn writev<'a>(
+    fd: BorrowedFd<'a>,
+    bufs: &'a [IoSlice<'_>],
+) -> impl Future<Output = Result<usize>> + 'a {
+    pwritev(fd, bufs, -1)
+}
+
+/// See also `man pwritev.2`.
+pub(crate) fn pwritev<'a>(
+    fd: BorrowedFd<'a>,
+    bufs: &'a [IoSlice<'_>],
+    pos: libc::off64_t,
+) -> impl Future<Output = Result<usize>> + 'a {
+    pwritev2(fd, bufs, pos, 0)
+}
+
+/// See also `man pwritev2.2`.
+pub(crate) fn pwritev2<'a>(
+    fd: BorrowedFd<'a>,
+    bufs: &'a [IoSlice<'_>],
+    pos: libc::off64_t,
+    flags: libc::c_int,
+) -> impl Future<Output = Result<usize>> + 'a {
+    // The data is written from one copied buffer, for the same reason as in
+    // `new_bounce`.
+    let data = gather(bufs);
+    let fd = types::Fd(fd.as_raw_fd());
+    async move {
+        let iov = IoVec::new(data);
+        let sqe = opcode::Writev::new(fd, &iov.iov, 1)
+            .offset(pos)
+            .rw_flags(flags as _)
+            .build();
+        BufOp::new(submit(sqe)?, iov).wait().await.0.map(|n| n as _)
+    }
+}
+
+/// A buffer and the `iovec` that refers to it, which is owned by a vectored
+/// op.
+struct IoVec {
+    iov: libc::iovec,
+    buf: AlignedBuf,
+}
+
+// SAFETY: The `iovec` only refers to the buffer.
+unsafe impl Send for IoVec {}
+
+impl IoVec {
+    fn new(mut buf: AlignedBuf) -> Box<Self> {
+        let iov = libc::iovec {
+            iov_base: buf.as_mut_ptr() as _,
+            iov_len: buf.len(),
+        };
+        Box::new(Self { iov, buf })
+    }
+}
+
+/// Returns a bounce buffer of `len` bytes, for the kernel to access instead of
+/// the borrowed memory at `ptr`.
+///
+/// A dropped future doesn't wait for the cancellation of its operation, so the
+/// kernel must not access memory that the future only borrows. Such operations
+/// go through an owned buffer instead, which is kept by the operation until
+/// the kernel is done with it. The buffer is aligned like `ptr`, up to a page,
+/// so that direct I/O still works.
+fn new_bounce(ptr: *const u8, len: usize) -> AlignedBuf {
+    const MAX_ALIGN: usize = 4096;
+    let align = 1 << (ptr as usize | MAX_ALIGN).trailing_zeros();
+    AlignedBuf::new(len, align)
+}
+
+/// Returns a bounce buffer with a copy of `buf`, see [`new_bounce`].
+fn copy_bounce(buf: &[u8]) -> AlignedBuf {
+    let mut bounce = new_bounce(buf.as_ptr(), buf.len());
+    bounce.copy_from_slice(buf);
+    bounce
+}
+
+/// Copies the bytes that the kernel has read into `bounce` to `bufs` in order,
+/// see [`new_bounce`].
+fn scatter_read<'a>(
+    result: Result<u32>,
+    bounce: &[u8],
+    bufs: impl IntoIterator<Item = &'a mut [u8]>,
+) -> Result<usize> {
+    let n = result? as usize;
+    let mut rest = &bounce[..n];
+    for buf in bufs {
+        if rest.is_empty() {
+            break;
+        }
+        let len = buf.len().min(rest.len());
+        buf[..len].copy_from_slice(&rest[..len]);
+        rest = &rest[len..];
+    }
+    Ok(n)
+}
+
+/// Copies the data of `bufs` into one bounce buffer, see [`new_bounce`].
+fn gather(bufs: &[IoSlice<'_>]) -> AlignedBuf {
+    let len = bufs.iter().map(|buf| buf.len()).sum();
+    let ptr = bufs.first().map_or(ptr::null(), |buf| buf.as_ptr());
+    let mut data = new_bounce(ptr, len);
+    let mut rest = &mut data[..];
+    for buf in bufs {
+        let (head, tail) = mem::take(&mut rest).split_at_mut(buf.len());
+        head.copy_from_slice(buf);
+        rest = tail;
+    }
+    data
+}
+
+/// Duplicates `fd` for work on the blocking thread pool.
+///
+/// The work keeps running if its future is dropped, after the borrow of `fd`
+/// ends. If it only copied the number of `fd`, the descriptor might be closed
+/// by then, and the number reused for another file.
+fn owned_fd(fd: BorrowedFd<'_>) -> Result<OwnedFd> {
+    fd.try_clone_to_owned()
+}
+
+/// This function is similar to [`owned_fd`], except that `None` stands for the
+/// current working directory, see [`raw_dir_fd`].
+fn owned_dir_fd(dirfd: Option<BorrowedFd<'_>>) -> Result<Option<OwnedFd>> {
+    dirfd.map(owned_fd).transpose()
+}
+
+fn raw_dir_fd(dirfd: &Option<OwnedFd>) -> RawFd {
+    dirfd.as_ref().map_or(libc::AT_FDCWD, |fd| fd.as_raw_fd())
+}
+
+fn dir_fd(dirfd: Option<BorrowedFd<'_>>) -> types::Fd {
+    types::Fd(dirfd.map_or(libc::AT_FDCWD, |fd| fd.as_raw_fd()))
+}
+
+fn dest_slot(slot: u32) -> Result<types::DestinationSlot> {
+    types::DestinationSlot::try_from_slot_target(slot)
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid fixed file slot"))
+}
+
+fn new_name_str(name: &str) -> Result<CString> {
+    CString::new(name).map_err(|e| Error::new(ErrorKind::InvalidInput, e))
 }
 
 fn new_path_str(path: &Path) -> Result<CString> {
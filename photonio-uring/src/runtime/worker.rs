@@ -1,15 +1,26 @@
-use std::{cell::RefCell, collections::VecDeque, future::Future, io::Result, sync::Mutex, thread};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    os::unix::io::{AsRawFd, BorrowedFd, OwnedFd, RawFd},
+    sync::Mutex,
+    thread,
+};
 
 use futures::channel::mpsc;
-use io_uring::squeue;
+use io_uring::{squeue, types};
 use log::trace;
 use scoped_tls::scoped_thread_local;
 
 use super::{
-    driver::{Driver, Op, Unpark},
-    Shared,
+    driver::{Driver, MultiOp, Op, Unpark},
+    Builder, Shared,
+};
+use crate::{
+    io::Opcode,
+    task::{JoinHandle, Schedule, Task},
 };
-use crate::task::{JoinHandle, Schedule, Task};
 
 enum Message {
     Shutdown,
@@ -34,16 +45,16 @@ impl Local {
         rx: Receiver,
         unpark: Unpark,
         shared: Shared,
-        event_interval: usize,
+        builder: &Builder,
     ) -> Result<Self> {
-        let driver = Driver::new(unpark)?;
+        let driver = Driver::new(unpark, builder)?;
         Ok(Self {
             id,
             shared,
             rx: RefCell::new(rx),
             driver: RefCell::new(driver),
             run_queue: RefCell::new(VecDeque::new()),
-            event_interval,
+            event_interval: builder.event_interval,
         })
     }
 
@@ -64,14 +75,19 @@ impl Local {
                 }
             }
             trace!("worker {} polled {} tasks", self.id, num_tasks);
-            {
+            let (reaped, wakes) = {
                 let mut driver = self.driver.borrow_mut();
                 if num_tasks > 0 {
                     driver.tick()?;
                 } else {
                     driver.park()?;
                 }
+                (driver.take_reaped(), driver.take_wakes())
+            };
+            for from in wakes {
+                trace!("worker {} is woken by worker {}", self.id, from);
             }
+            reaped.run();
         }
     }
 
@@ -99,6 +115,9 @@ pub(super) struct Worker {
     tx: Sender,
     rx: Mutex<Option<Receiver>>,
     unpark: Unpark,
+    // A duplicate of the ring fd of the worker, so that the ring stays valid
+    // for other workers to post messages to even after the worker exits.
+    ring: Mutex<Option<OwnedFd>>,
 }
 
 impl Worker {
@@ -110,22 +129,21 @@ impl Worker {
             tx,
             rx: Mutex::new(Some(rx)),
             unpark,
+            ring: Mutex::new(None),
         })
     }
 
-    pub(super) fn launch(
-        &self,
-        shared: Shared,
-        stack_size: usize,
-        event_interval: usize,
-    ) -> Result<()> {
+    pub(super) fn launch(&self, shared: Shared, builder: &Builder) -> Result<()> {
         let rx = self.rx.lock().unwrap().take().unwrap();
-        let local = Local::new(self.id, rx, self.unpark.clone(), shared, event_interval)?;
+        let local = Local::new(self.id, rx, self.unpark.clone(), shared, builder)?;
+        let ring_fd = local.driver.borrow().ring_fd();
+        let ring = unsafe { BorrowedFd::borrow_raw(ring_fd) }.try_clone_to_owned()?;
+        *self.ring.lock().unwrap() = Some(ring);
         let thread_name = format!("photonio-worker/{}", self.id);
         trace!("launch {}", thread_name);
         thread::Builder::new()
             .name(thread_name)
-            .stack_size(stack_size)
+            .stack_size(builder.thread_stack_size)
             .spawn(move || enter(local))?;
         Ok(())
     }
@@ -137,9 +155,24 @@ impl Worker {
     {
         let (task, handle) = Task::new(id, future, Scheduler);
         self.tx.unbounded_send(Message::Schedule(task)).unwrap();
-        self.unpark.unpark().unwrap();
+        self.wake();
         handle
     }
+
+    /// Wakes up this worker to receive messages.
+    ///
+    /// On a worker thread, this posts a message to the ring of this worker
+    /// directly, with the id of the current worker as its payload, which
+    /// saves the eventfd round trip. Otherwise, or if the
+    /// kernel doesn't support it, this falls back to the eventfd.
+    fn wake(&self) {
+        if let Some(ring) = &*self.ring.lock().unwrap() {
+            if wake_ring(ring.as_raw_fd()) {
+                return;
+            }
+        }
+        self.unpark.unpark().unwrap();
+    }
 }
 
 impl Drop for Worker {
@@ -164,9 +197,100 @@ where
 }
 
 pub(super) fn submit(op: squeue::Entry) -> Result<Op> {
+    with_driver(|driver| unsafe { driver.add(op) })
+}
+
+pub(super) fn submit128(op: squeue::Entry128) -> Result<Op> {
+    with_driver(|driver| unsafe { driver.add128(op) })
+}
+
+pub(super) fn submit_multi(op: squeue::Entry) -> Result<MultiOp> {
+    with_driver(|driver| unsafe { driver.add_multi(op) })
+}
+
+/// The caller must keep `timeout` alive until the op completes.
+pub(super) fn submit_with_timeout(op: squeue::Entry, timeout: &types::Timespec) -> Result<Op> {
+    with_driver(|driver| unsafe { driver.add_with_timeout(op, timeout) })
+}
+
+/// Submits an operation that starts only after all previously submitted
+/// operations have completed.
+pub(super) fn submit_drained(op: squeue::Entry) -> Result<Op> {
+    submit(op.flags(squeue::Flags::IO_DRAIN))
+}
+
+pub(super) fn submit_linked(ops: Vec<squeue::Entry>) -> Result<Vec<Op>> {
+    with_driver(|driver| unsafe { driver.add_linked(ops) })
+}
+
+#[cfg(feature = "test-hooks")]
+pub(super) fn inject_results(results: &[i32]) -> Result<()> {
+    with_driver(|driver| {
+        driver.inject_results(results);
+        Ok(())
+    })
+}
+
+#[cfg(feature = "test-hooks")]
+pub(super) fn last_ioprio() -> Result<u16> {
+    with_driver(|driver| Ok(driver.last_ioprio()))
+}
+
+#[cfg(feature = "test-hooks")]
+pub(super) fn last_opcode() -> Result<u8> {
+    with_driver(|driver| Ok(driver.last_opcode()))
+}
+
+pub(super) fn flush() -> Result<usize> {
+    with_driver(|driver| driver.flush())
+}
+
+/// Runs `f` with the driver of the current worker.
+///
+/// Returns an error if there is no running worker on the current thread, for
+/// example, when the runtime is shutting down, or if the driver is already in
+/// use further up the stack.
+pub(super) fn with_driver<T>(f: impl FnOnce(&mut Driver) -> Result<T>) -> Result<T> {
+    if !CURRENT.is_set() {
+        return Err(Error::new(
+            ErrorKind::Other,
+            "no running worker on the current thread",
+        ));
+    }
+    CURRENT.with(|local| {
+        let mut driver = local.driver.try_borrow_mut().map_err(|_| {
+            Error::new(
+                ErrorKind::Other,
+                "the driver of the current worker is in use",
+            )
+        })?;
+        f(&mut driver)
+    })
+}
+
+/// Wakes up the ring `fd` through the driver of the current worker.
+///
+/// Returns false if this is not possible.
+fn wake_ring(fd: RawFd) -> bool {
+    if !CURRENT.is_set() {
+        return false;
+    }
+    CURRENT.with(|local| {
+        // The driver might be in use if this is called during a completion.
+        match local.driver.try_borrow_mut() {
+            Ok(mut driver) => driver.wake(fd, local.id as u32).is_ok(),
+            Err(_) => false,
+        }
+    })
+}
+
+pub(super) fn is_supported(opcode: Opcode) -> bool {
+    if !CURRENT.is_set() {
+        return false;
+    }
     CURRENT.with(|local| {
-        let mut driver = local.driver.borrow_mut();
-        unsafe { driver.add(op) }
+        let driver = local.driver.borrow();
+        driver.is_supported(opcode)
     })
 }
 
@@ -0,0 +1,4 @@
+//! Synchronization primitives for asynchronous tasks.
+
+mod notify;
+pub use notify::Notify;
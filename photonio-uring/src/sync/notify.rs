@@ -0,0 +1,55 @@
+use std::{
+    io::{ErrorKind, Result},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::io;
+
+/// Matches all waiters of a futex word.
+const MASK: u32 = u32::MAX;
+
+/// Notifies a task to wake up through a futex word.
+///
+/// The word can be placed in shared memory to notify tasks in other processes.
+/// A notification is stored as a permit in the word if no task is waiting, so
+/// a notification before [`Self::notified`] is not lost. At most one permit is
+/// stored.
+///
+/// This requires the futex operations of io_uring, which are available since
+/// Linux 6.7.
+#[derive(Debug)]
+pub struct Notify<'a> {
+    word: &'a AtomicU32,
+}
+
+impl<'a> Notify<'a> {
+    /// Creates a notifier on `word`.
+    ///
+    /// A zero word means no permit.
+    pub fn new(word: &'a AtomicU32) -> Self {
+        Self { word }
+    }
+
+    /// Waits for a notification.
+    ///
+    /// Returns immediately if there is a permit, which is consumed.
+    pub async fn notified(&self) -> Result<()> {
+        loop {
+            if self.word.swap(0, Ordering::AcqRel) != 0 {
+                return Ok(());
+            }
+            match io::futex_wait(self.word, 0, MASK).await {
+                Ok(()) => {}
+                // The word has changed, so checks it again.
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Notifies a waiting task, or stores a permit if there is none.
+    pub async fn notify_one(&self) -> Result<()> {
+        self.word.store(1, Ordering::Release);
+        io::futex_wake(self.word, 1, MASK).await.map(|_| ())
+    }
+}
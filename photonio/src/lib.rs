@@ -36,8 +36,10 @@
 //! ## Limitations
 //!
 //! - Dropping an unfinished future for asynchronous filesystem or networking
-//!   operations will result in a panic. However, this behavior might be change
-//!   in the future.
+//!   operations cancels the operation without waiting for the cancellation.
+//!   Operations on borrowed buffers go through an owned copy, since the kernel
+//!   might still access the buffers after the future is dropped. Use the
+//!   owned-buffer methods to avoid the copy.
 //! - The current multi-thread runtime uses a naive round-robin fashion to
 //!   schedule tasks. A work-stealing scheduler will be added in the future.
 
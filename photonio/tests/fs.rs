@@ -1,6 +1,17 @@
+#![feature(io_error_more)]
+
+use std::{
+    io::ErrorKind,
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::Path,
+};
+
 use photonio::{
-    fs::{File, OpenOptions},
-    io::{Read, ReadAt, Write, WriteAt},
+    fs::{self, File, OpenOptions},
+    io::{
+        self, BufWriter, IoSlice, IoSliceMut, Read, ReadAt, ReadAtExt, ReadExt, Write, WriteAt,
+        WriteAtExt, WriteExt,
+    },
 };
 
 #[photonio::test(env_logger = true)]
@@ -24,3 +35,1278 @@ async fn file() {
     let meta = file.metadata().await.unwrap();
     assert_eq!(meta.len(), 5);
 }
+
+#[photonio::test]
+async fn open_options() {
+    let path = "/tmp/test_open_options.txt";
+    let _ = fs::remove_file(path).await;
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .await
+        .unwrap();
+    file.write_all(b"hello").await.unwrap();
+    let err = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+    // Appends go to the end of the file.
+    let mut file = OpenOptions::new().append(true).open(path).await.unwrap();
+    file.write_all(b"world").await.unwrap();
+    let mut buf = Vec::new();
+    File::open(path)
+        .await
+        .unwrap()
+        .read_to_end(&mut buf)
+        .await
+        .unwrap();
+    assert_eq!(buf, b"helloworld");
+
+    // Contradictory options are rejected before the file is touched.
+    // (read, append, truncate, create)
+    for (read, append, truncate, create) in [
+        (false, false, false, false),
+        (true, false, true, false),
+        (true, false, false, true),
+        (false, true, true, false),
+    ] {
+        let err = OpenOptions::new()
+            .read(read)
+            .append(append)
+            .truncate(truncate)
+            .create(create)
+            .open(path)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+    let meta = fs::metadata(path).await.unwrap();
+    assert_eq!(meta.len(), 10);
+}
+
+#[photonio::test]
+async fn file_try_clone() {
+    let path = "/tmp/test_file_try_clone.txt";
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .await
+        .unwrap();
+    let clone = file.try_clone().await.unwrap();
+    file.write_all_at(b"hello", 0).await.unwrap();
+    clone.sync_all().await.unwrap();
+    let mut buf = [0; 5];
+    clone.read_exact_at(&mut buf, 0).await.unwrap();
+    assert_eq!(&buf, b"hello");
+    drop(clone);
+    file.write_all_at(b"world", 5).await.unwrap();
+    assert_eq!(fs::read(path).await.unwrap(), b"helloworld");
+
+    // Clones share the append mode, and each closes its own descriptor.
+    let mut file = OpenOptions::new().append(true).open(path).await.unwrap();
+    let mut clone = file.try_clone().await.unwrap();
+    file.write_all(b"!").await.unwrap();
+    clone.write_all(b"?").await.unwrap();
+    drop(file);
+    clone.write_all(b".").await.unwrap();
+    clone.sync_data().await.unwrap();
+    assert_eq!(fs::read(path).await.unwrap(), b"helloworld!?.");
+}
+
+#[photonio::test]
+async fn file_lock() {
+    let path = "/tmp/test_file_lock.txt";
+    let a = File::create(path).await.unwrap();
+    let b = File::open(path).await.unwrap();
+
+    // Files opened separately contend, even in the same process.
+    let guard = a.lock_exclusive().await.unwrap();
+    assert!(b.try_lock_shared().unwrap().is_none());
+    assert!(b.try_lock_exclusive().unwrap().is_none());
+    // Files from `try_clone` share the lock.
+    let c = a.try_clone().await.unwrap();
+    let clone_guard = c.try_lock_exclusive().unwrap();
+    assert!(clone_guard.is_some());
+    std::mem::forget(clone_guard);
+
+    // Waiting acquires the lock once it is released.
+    let (shared, ()) = futures::join!(b.lock_shared(), async { drop(guard) });
+    let shared = shared.unwrap();
+    let other = a.try_lock_shared().unwrap().unwrap();
+    assert!(File::open(path)
+        .await
+        .unwrap()
+        .try_lock_exclusive()
+        .unwrap()
+        .is_none());
+    shared.unlock().unwrap();
+    drop(other);
+    let guard = b.try_lock_exclusive().unwrap().unwrap();
+    assert!(std::ptr::eq(guard.file(), &b));
+    b.unlock().unwrap();
+    assert!(a.try_lock_exclusive().unwrap().is_some());
+}
+
+#[photonio::test]
+#[ignore = "forks the test process"]
+async fn file_lock_fork() {
+    use std::ffi::CString;
+
+    let path = "/tmp/test_file_lock_fork.txt";
+    let file = File::create(path).await.unwrap();
+    let guard = file.lock_exclusive().await.unwrap();
+
+    // The child only makes async-signal-safe calls, since the parent has
+    // other threads.
+    let cpath = CString::new(path).unwrap();
+    let mut lock = unsafe { std::mem::zeroed::<libc::flock>() };
+    lock.l_type = libc::F_WRLCK as _;
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        let code = unsafe {
+            let fd = libc::open(cpath.as_ptr(), libc::O_RDWR);
+            if fd < 0 {
+                2
+            } else if libc::fcntl(fd, libc::F_OFD_SETLK, &lock) < 0 {
+                0
+            } else {
+                1
+            }
+        };
+        unsafe { libc::_exit(code) };
+    }
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert!(libc::WIFEXITED(status));
+    assert_eq!(libc::WEXITSTATUS(status), 0);
+    drop(guard);
+}
+
+#[photonio::test]
+async fn file_write_all() {
+    let path = "/tmp/test_write_all.txt";
+
+    let mut file = File::create(path).await.unwrap();
+    assert_eq!(file.write(b"").await.unwrap(), 0);
+    file.write_all(b"").await.unwrap();
+    file.write_all(b"hello").await.unwrap();
+    file.flush().await.unwrap();
+    file.shutdown().await.unwrap();
+
+    let mut buf = [0; 5];
+    let file = File::open(path).await.unwrap();
+    file.read_exact_at(&mut buf, 0).await.unwrap();
+    assert_eq!(&buf, b"hello");
+    assert_eq!(file.metadata().await.unwrap().len(), 5);
+}
+
+#[photonio::test]
+async fn file_vectored() {
+    let path = "/tmp/test_vectored.txt";
+
+    let mut file = File::create(path).await.unwrap();
+    let mut bufs = [
+        IoSlice::new(b""),
+        IoSlice::new(b"hello"),
+        IoSlice::new(b""),
+        IoSlice::new(b"world"),
+    ];
+    file.write_all_vectored(&mut bufs).await.unwrap();
+
+    let mut file = File::open(path).await.unwrap();
+    let mut buf = [0; 10];
+    file.read_exact_at(&mut buf, 0).await.unwrap();
+    assert_eq!(&buf, b"helloworld");
+
+    let mut head = [0; 5];
+    let mut tail = [0; 5];
+    let mut bufs = [IoSliceMut::new(&mut head), IoSliceMut::new(&mut tail)];
+    let n = file.read_vectored(&mut bufs).await.unwrap();
+    assert_eq!(n, 10);
+    assert_eq!(&head, b"hello");
+    assert_eq!(&tail, b"world");
+
+    // Short writes advance the buffers, across empty ones and within others.
+    let mut writer = ShortWriter(File::create(path).await.unwrap());
+    let mut bufs = [
+        IoSlice::new(b"hello"),
+        IoSlice::new(b""),
+        IoSlice::new(b"world"),
+    ];
+    writer.write_all_vectored(&mut bufs).await.unwrap();
+    let mut buf = [0; 10];
+    let file = File::open(path).await.unwrap();
+    file.read_exact_at(&mut buf, 0).await.unwrap();
+    assert_eq!(&buf, b"helloworld");
+    assert_eq!(file.metadata().await.unwrap().len(), 10);
+}
+
+/// A file writer that writes at most 3 bytes of the first non-empty buffer at
+/// a time.
+struct ShortWriter(File);
+
+impl Write for ShortWriter {
+    type Write<'a> = <File as Write>::Write<'a>;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        let n = buf.len().min(3);
+        self.0.write(&buf[..n])
+    }
+
+    type WriteVectored<'a> = <File as Write>::Write<'a>;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        let buf = bufs
+            .iter()
+            .find(|b| !b.is_empty())
+            .map_or(&[][..], |b| &**b);
+        self.write(buf)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    type Flush<'a> = <File as Write>::Flush<'a>;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        self.0.flush()
+    }
+
+    type Shutdown<'a> = <File as Write>::Shutdown<'a>;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        self.0.shutdown()
+    }
+}
+
+#[photonio::test]
+async fn file_positional() {
+    let path = "/tmp/test_positional.txt";
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .await
+        .unwrap();
+    file.write_all_at(b"world", 4096).await.unwrap();
+    file.write_all_at(b"hello", 0).await.unwrap();
+    assert_eq!(file.metadata().await.unwrap().len(), 4101);
+
+    // Positional reads don't share a cursor, so they can run concurrently.
+    let mut head = [0; 5];
+    let mut tail = [0; 5];
+    let (a, b) = futures::join!(
+        file.read_exact_at(&mut tail, 4096),
+        file.read_exact_at(&mut head, 0)
+    );
+    a.unwrap();
+    b.unwrap();
+    assert_eq!(&head, b"hello");
+    assert_eq!(&tail, b"world");
+}
+
+#[photonio::test]
+async fn file_set_len() {
+    let path = "/tmp/test_set_len.txt";
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .await
+        .unwrap();
+    file.write_all_at(b"helloworld", 0).await.unwrap();
+
+    // Shrinks the file.
+    file.set_len(5).await.unwrap();
+    assert_eq!(file.metadata().await.unwrap().len(), 5);
+    let mut buf = [0; 10];
+    assert_eq!(file.read_at(&mut buf, 0).await.unwrap(), 5);
+    assert_eq!(&buf[..5], b"hello");
+
+    // Extends the file with a sparse tail.
+    file.set_len(10).await.unwrap();
+    assert_eq!(file.metadata().await.unwrap().len(), 10);
+    file.read_exact_at(&mut buf, 0).await.unwrap();
+    assert_eq!(&buf, b"hello\0\0\0\0\0");
+}
+
+#[photonio::test]
+async fn read_to_end() {
+    let path = "/tmp/test_read_to_end.txt";
+
+    // Larger than the first probe, and not a multiple of the buffer growth.
+    let data: Vec<u8> = (0..10000).map(|i| (i % 251) as u8).collect();
+    let mut file = File::create(path).await.unwrap();
+    file.write_all(&data).await.unwrap();
+    drop(file);
+
+    let mut file = File::open(path).await.unwrap();
+    let mut buf = b"prefix".to_vec();
+    assert_eq!(file.read_to_end(&mut buf).await.unwrap(), data.len());
+    assert_eq!(&buf[..6], b"prefix");
+    assert_eq!(&buf[6..], &data[..]);
+    // Reads nothing more at EOF.
+    assert_eq!(file.read_to_end(&mut buf).await.unwrap(), 0);
+    assert_eq!(buf.len(), data.len() + 6);
+    assert_eq!(fs::read(path).await.unwrap(), data);
+
+    let path = "/tmp/test_read_to_end_empty.txt";
+    File::create(path).await.unwrap();
+    let mut file = File::open(path).await.unwrap();
+    let mut buf = Vec::new();
+    assert_eq!(file.read_to_end(&mut buf).await.unwrap(), 0);
+    assert!(buf.is_empty());
+    assert!(fs::read(path).await.unwrap().is_empty());
+    assert_eq!(fs::read_to_string(path).await.unwrap(), "");
+}
+
+#[photonio::test]
+async fn read_write() {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    let path = "/tmp/test_read_write.txt";
+
+    fs::write(path, b"").await.unwrap();
+    assert!(fs::read(path).await.unwrap().is_empty());
+    assert_eq!(fs::read_to_string(path).await.unwrap(), "");
+
+    // Replaces the previous contents.
+    fs::write(path, "hello world").await.unwrap();
+    fs::write(path, "hello").await.unwrap();
+    assert_eq!(fs::read_to_string(path).await.unwrap(), "hello");
+
+    // Larger than the initial buffer of a file whose size is unknown.
+    let data: Vec<u8> = (0..9 << 20).map(|i| (i % 251) as u8).collect();
+    fs::write(path, &data).await.unwrap();
+    assert_eq!(fs::read(path).await.unwrap(), data);
+
+    // Files in procfs report a size of zero but have contents.
+    let status = fs::read_to_string("/proc/self/status").await.unwrap();
+    assert!(status.contains("Name:"));
+
+    // The file grows while it is read, so any prefix of the final contents is
+    // a valid result.
+    fs::write(path, b"").await.unwrap();
+    let expect = data.clone();
+    let done = Arc::new(AtomicBool::new(false));
+    let writer = thread::spawn({
+        let done = done.clone();
+        move || {
+            use std::io::Write;
+
+            let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+            for chunk in data.chunks(4096) {
+                file.write_all(chunk).unwrap();
+            }
+            done.store(true, Ordering::Release);
+        }
+    });
+    let mut last = 0;
+    while !done.load(Ordering::Acquire) {
+        let buf = fs::read(path).await.unwrap();
+        assert!(buf.len() >= last);
+        assert!(expect.starts_with(&buf));
+        last = buf.len();
+    }
+    writer.join().unwrap();
+    assert_eq!(fs::read(path).await.unwrap(), expect);
+}
+
+#[photonio::test]
+async fn buf_writer() {
+    let path = "/tmp/test_buf_writer.txt";
+
+    let file = File::create(path).await.unwrap();
+    let mut writer = BufWriter::with_capacity(64, file);
+    let mut expect = Vec::new();
+    for i in 0..100usize {
+        // Mixes writes that fit in the buffer with ones that don't.
+        let len = if i % 10 == 9 { 100 + i } else { i % 7 };
+        let buf: Vec<u8> = (0..len).map(|j| (i + j) as u8).collect();
+        writer.write_all(&buf).await.unwrap();
+        expect.extend_from_slice(&buf);
+        assert!(writer.buffer().len() <= writer.capacity());
+    }
+    let mut bufs = [
+        IoSlice::new(b"vec"),
+        IoSlice::new(&[7; 80]),
+        IoSlice::new(b"tor"),
+    ];
+    writer.write_all_vectored(&mut bufs).await.unwrap();
+    expect.extend_from_slice(b"vec");
+    expect.extend_from_slice(&[7; 80]);
+    expect.extend_from_slice(b"tor");
+
+    assert!(writer.has_unflushed_data());
+    let mut file = writer.into_inner().await.unwrap();
+    file.flush().await.unwrap();
+    assert_eq!(fs::read(path).await.unwrap(), expect);
+
+    let mut writer = BufWriter::new(file);
+    writer.write_all(b"tail").await.unwrap();
+    writer.flush().await.unwrap();
+    assert!(!writer.has_unflushed_data());
+    expect.extend_from_slice(b"tail");
+    assert_eq!(fs::read(path).await.unwrap(), expect);
+}
+
+#[photonio::test]
+async fn io_copy() {
+    let from = "/tmp/test_io_copy_from.txt";
+    let to = "/tmp/test_io_copy_to.txt";
+
+    let data: Vec<u8> = (0..10 << 20).map(|i: usize| (i % 251) as u8).collect();
+    let mut src = File::create(from).await.unwrap();
+    src.write_all(&data).await.unwrap();
+    drop(src);
+
+    // Copies from and to the current positions.
+    let mut src = File::open(from).await.unwrap();
+    let mut head = [0; 16];
+    src.read_exact(&mut head).await.unwrap();
+    let mut dst = File::create(to).await.unwrap();
+    dst.write_all(b"head").await.unwrap();
+    let n = io::copy(&mut src, &mut dst).await.unwrap();
+    assert_eq!(n, data.len() as u64 - 16);
+    dst.write_all(b"tail").await.unwrap();
+    dst.flush().await.unwrap();
+
+    let out = fs::read(to).await.unwrap();
+    assert_eq!(out.len(), data.len() - 16 + 8);
+    assert_eq!(&out[..4], b"head");
+    assert!(out[4..out.len() - 4] == data[16..]);
+    assert_eq!(&out[out.len() - 4..], b"tail");
+}
+
+#[photonio::test]
+async fn read_to_string() {
+    let path = "/tmp/test_read_to_string.txt";
+
+    let text = "héllo wörld\n".repeat(100);
+    let mut file = File::create(path).await.unwrap();
+    file.write_all(text.as_bytes()).await.unwrap();
+    drop(file);
+    let mut file = File::open(path).await.unwrap();
+    let mut buf = String::from("> ");
+    assert_eq!(file.read_to_string(&mut buf).await.unwrap(), text.len());
+    assert_eq!(buf, format!("> {text}"));
+    assert_eq!(fs::read_to_string(path).await.unwrap(), text);
+
+    // Invalid UTF-8 leaves the buffer unchanged.
+    let mut file = File::create(path).await.unwrap();
+    file.write_all(b"valid \xff\xfe invalid").await.unwrap();
+    drop(file);
+    let mut file = File::open(path).await.unwrap();
+    let mut buf = String::from("keep");
+    let err = file.read_to_string(&mut buf).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(buf, "keep");
+    let err = fs::read_to_string(path).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[photonio::test]
+async fn remove() {
+    let dir = "/tmp/test_remove";
+    let path = "/tmp/test_remove/file.txt";
+    let _ = std::fs::remove_dir_all(dir);
+
+    fs::create_dir(dir).await.unwrap();
+    File::create(path).await.unwrap();
+    let err = fs::remove_dir(dir).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DirectoryNotEmpty);
+
+    fs::remove_file(path).await.unwrap();
+    let err = fs::remove_file(path).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    fs::remove_dir(dir).await.unwrap();
+    let err = fs::remove_dir(dir).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[photonio::test]
+async fn read_dir() {
+    use std::{
+        collections::HashSet,
+        ffi::{OsStr, OsString},
+        os::unix::ffi::OsStrExt,
+    };
+
+    let dir = "/tmp/test_read_dir";
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir(dir).unwrap();
+    let mut expect = HashSet::new();
+    for i in 0..10000 {
+        let name = format!("file{i}");
+        std::fs::write(Path::new(dir).join(&name), b"").unwrap();
+        expect.insert(OsString::from(name));
+    }
+    let invalid = OsStr::from_bytes(b"invalid \xff\xfe");
+    std::fs::create_dir(Path::new(dir).join(invalid)).unwrap();
+    expect.insert(invalid.to_owned());
+
+    // Small batches need many reads.
+    let mut iter = fs::read_dir(dir).await.unwrap();
+    iter.set_batch_size(4096);
+    let mut names = HashSet::new();
+    while let Some(entry) = iter.next_entry().await.unwrap() {
+        assert_eq!(entry.path(), Path::new(dir).join(entry.file_name()));
+        let file_type = entry.file_type().await.unwrap();
+        assert_eq!(file_type.is_dir(), entry.file_name() == invalid);
+        assert_eq!(entry.metadata().await.unwrap().is_dir(), file_type.is_dir());
+        assert!(names.insert(entry.file_name()), "duplicate entry");
+    }
+    assert_eq!(names, expect);
+    assert!(iter.next_entry().await.unwrap().is_none());
+
+    // Entries removed during the iteration are not returned twice, and the
+    // others are all returned.
+    let mut iter = fs::read_dir(dir).await.unwrap();
+    let first = iter.next_entry().await.unwrap().unwrap().file_name();
+    let mut removed = HashSet::new();
+    for i in (0..10000).step_by(2) {
+        let name = OsString::from(format!("file{i}"));
+        if name != first {
+            std::fs::remove_file(Path::new(dir).join(&name)).unwrap();
+            removed.insert(name);
+        }
+    }
+    let mut names = HashSet::from([first]);
+    while let Some(entry) = iter.next_entry().await.unwrap() {
+        assert!(names.insert(entry.file_name()), "duplicate entry");
+    }
+    for name in expect.difference(&removed) {
+        assert!(names.contains(name), "missing entry {name:?}");
+    }
+
+    let err = fs::read_dir("/tmp/test_read_dir/file1").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotADirectory);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[photonio::test]
+async fn canonicalize() {
+    use std::os::unix::fs::symlink;
+
+    let root = "/tmp/test_canonicalize";
+    let _ = std::fs::remove_dir_all(root);
+    std::fs::create_dir_all(format!("{root}/a/b")).unwrap();
+    std::fs::write(format!("{root}/a/b/file"), b"").unwrap();
+    // link -> a/b, up -> link/.., chain -> up/b/file
+    symlink("a/b", format!("{root}/link")).unwrap();
+    symlink("link/..", format!("{root}/up")).unwrap();
+    symlink(format!("{root}/up/b/file"), format!("{root}/chain")).unwrap();
+    // loop1 -> loop2 -> loop1
+    symlink("loop2", format!("{root}/loop1")).unwrap();
+    symlink("loop1", format!("{root}/loop2")).unwrap();
+    symlink("missing", format!("{root}/dangling")).unwrap();
+
+    let file = Path::new(root).join("a/b/file");
+    let cases = [
+        ("a/b/file", file.clone()),
+        ("link/file", file.clone()),
+        ("link/../b/./file", file.clone()),
+        ("up/b", Path::new(root).join("a/b")),
+        ("chain", file.clone()),
+    ];
+    for (path, expect) in cases {
+        let path = Path::new(root).join(path);
+        assert_eq!(fs::canonicalize(&path).await.unwrap(), expect);
+        assert_eq!(std::fs::canonicalize(&path).unwrap(), expect);
+    }
+
+    let err = fs::canonicalize(format!("{root}/loop1")).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::FilesystemLoop);
+    let err = fs::canonicalize(format!("{root}/a/missing"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    let err = fs::canonicalize(format!("{root}/dangling"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    // Relative paths are resolved against the current directory.
+    let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+    assert_eq!(fs::canonicalize(".").await.unwrap(), cwd);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[photonio::test]
+async fn remove_dir_all() {
+    use std::os::unix::fs::symlink;
+
+    let root = "/tmp/test_remove_dir_all";
+    let outside = "/tmp/test_remove_dir_all_outside";
+    let _ = std::fs::remove_dir_all(root);
+    let _ = std::fs::remove_dir_all(outside);
+    std::fs::create_dir_all(format!("{outside}/dir")).unwrap();
+    std::fs::write(format!("{outside}/file"), b"keep").unwrap();
+
+    // Several levels deep, with many files in some directories.
+    let mut dir = root.to_owned();
+    for level in 0..8 {
+        dir = format!("{dir}/{level}");
+        std::fs::create_dir_all(&dir).unwrap();
+        for i in 0..50 {
+            std::fs::write(format!("{dir}/file{i}"), b"x").unwrap();
+        }
+        symlink(outside, format!("{dir}/dir_link")).unwrap();
+        symlink(format!("{outside}/file"), format!("{dir}/file_link")).unwrap();
+        symlink(
+            "/tmp/test_remove_dir_all_missing",
+            format!("{dir}/dangling"),
+        )
+        .unwrap();
+    }
+    fs::remove_dir_all(root).await.unwrap();
+    assert!(!Path::new(root).exists());
+
+    // The symlinks are removed, not followed.
+    assert_eq!(std::fs::read(format!("{outside}/file")).unwrap(), b"keep");
+    assert!(Path::new(&format!("{outside}/dir")).is_dir());
+
+    // A symlink to a directory is removed itself.
+    let link = "/tmp/test_remove_dir_all_link";
+    let _ = std::fs::remove_file(link);
+    symlink(outside, link).unwrap();
+    fs::remove_dir_all(link).await.unwrap();
+    assert!(std::fs::symlink_metadata(link).is_err());
+    assert!(Path::new(outside).is_dir());
+
+    let err = fs::remove_dir_all(root).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    // Concurrent removals skip what the other one removes.
+    #[cfg(all(target_os = "linux", not(feature = "tokio")))]
+    {
+        for i in 0..100 {
+            std::fs::create_dir_all(format!("{root}/{}/{}", i % 3, i % 7)).unwrap();
+            std::fs::write(format!("{root}/{}/{}/file{i}", i % 3, i % 7), b"x").unwrap();
+        }
+        let (a, b) = futures::join!(fs::remove_dir_all(root), fs::remove_dir_all(root));
+        a.unwrap();
+        b.unwrap();
+        assert!(!Path::new(root).exists());
+    }
+    std::fs::remove_dir_all(outside).unwrap();
+}
+
+#[photonio::test]
+async fn tempfile() {
+    fn list(dir: &str) -> Vec<String> {
+        let mut names = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    let dir = "/tmp/test_tempfile";
+    let target = "/tmp/test_tempfile/target";
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir(dir).unwrap();
+    std::fs::write(target, b"old").unwrap();
+
+    let mut file = fs::tempfile_in(dir).await.unwrap();
+    file.write_all(b"new").await.unwrap();
+    file.sync_all().await.unwrap();
+    assert_eq!(list(dir), ["target"]);
+    let err = file.persist(target, false).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    assert_eq!(std::fs::read(target).unwrap(), b"old");
+    file.persist_overwrite(target, true).await.unwrap();
+    assert_eq!(std::fs::read(target).unwrap(), b"new");
+    assert_eq!(list(dir), ["target"]);
+
+    let mut file = fs::tempfile_in(dir).await.unwrap();
+    file.write_all(b"other").await.unwrap();
+    file.persist("/tmp/test_tempfile/other", true)
+        .await
+        .unwrap();
+    assert_eq!(list(dir), ["other", "target"]);
+    assert_eq!(std::fs::read("/tmp/test_tempfile/other").unwrap(), b"other");
+
+    // A file that is not persisted leaves nothing behind.
+    drop(fs::tempfile_in(dir).await.unwrap());
+    assert_eq!(list(dir), ["other", "target"]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[photonio::test]
+async fn rename() {
+    let from = "/tmp/test_rename_from.txt";
+    let to = "/tmp/test_rename_to.txt";
+
+    File::create(from)
+        .await
+        .unwrap()
+        .write_all(b"from")
+        .await
+        .unwrap();
+    File::create(to)
+        .await
+        .unwrap()
+        .write_all(b"to")
+        .await
+        .unwrap();
+    fs::rename(from, to).await.unwrap();
+    let mut buf = [0; 4];
+    File::open(to)
+        .await
+        .unwrap()
+        .read_exact_at(&mut buf, 0)
+        .await
+        .unwrap();
+    assert_eq!(&buf, b"from");
+    let err = File::open(from).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[photonio::test]
+async fn create_dir_all() {
+    let root = "/tmp/test_create_dir_all";
+    let _ = std::fs::remove_dir_all(root);
+
+    // Creates a deep path from scratch.
+    let deep = "/tmp/test_create_dir_all/a/b/c/d";
+    fs::create_dir_all(deep).await.unwrap();
+    assert!(std::fs::metadata(deep).unwrap().is_dir());
+
+    // Creates a path with an existing prefix, or an existing path.
+    fs::create_dir_all("/tmp/test_create_dir_all/a/b/e/f")
+        .await
+        .unwrap();
+    fs::create_dir_all(deep).await.unwrap();
+
+    // Concurrent creators racing on the same tree.
+    let (a, b) = futures::join!(
+        fs::create_dir_all("/tmp/test_create_dir_all/x/y/z"),
+        fs::create_dir_all("/tmp/test_create_dir_all/x/y/z"),
+    );
+    a.unwrap();
+    b.unwrap();
+
+    // Fails if a component is a regular file.
+    File::create("/tmp/test_create_dir_all/file").await.unwrap();
+    let err = fs::create_dir_all("/tmp/test_create_dir_all/file/a")
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotADirectory);
+    let err = fs::create_dir_all("/tmp/test_create_dir_all/file")
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+}
+
+#[photonio::test]
+async fn dir_builder() {
+    use std::os::unix::fs::DirBuilderExt;
+
+    // Reads the umask without changing it, since tests run concurrently.
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    let umask = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Umask:"))
+        .map(|mask| u32::from_str_radix(mask.trim(), 8).unwrap())
+        .unwrap();
+    let mode_of = |path: &str| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+    let root = "/tmp/test_dir_builder";
+    let _ = std::fs::remove_dir_all(root);
+    fs::DirBuilder::new()
+        .mode(0o700)
+        .create(root)
+        .await
+        .unwrap();
+    assert_eq!(mode_of(root), 0o700 & !umask);
+    let err = fs::DirBuilder::new().create(root).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    let err = fs::DirBuilder::new()
+        .create("/tmp/test_dir_builder/a/b")
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    // The mode applies to new parents too, and is masked by the umask.
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true).mode(0o750);
+    builder.create("/tmp/test_dir_builder/a/b").await.unwrap();
+    assert_eq!(mode_of("/tmp/test_dir_builder/a"), 0o750 & !umask);
+    assert_eq!(mode_of("/tmp/test_dir_builder/a/b"), 0o750 & !umask);
+
+    // Existing directories are left untouched.
+    builder.mode(0o711);
+    builder
+        .create("/tmp/test_dir_builder/a/b/c/d")
+        .await
+        .unwrap();
+    builder.create("/tmp/test_dir_builder/a").await.unwrap();
+    assert_eq!(mode_of(root), 0o700 & !umask);
+    assert_eq!(mode_of("/tmp/test_dir_builder/a"), 0o750 & !umask);
+    assert_eq!(mode_of("/tmp/test_dir_builder/a/b"), 0o750 & !umask);
+    assert_eq!(mode_of("/tmp/test_dir_builder/a/b/c"), 0o711 & !umask);
+    assert_eq!(mode_of("/tmp/test_dir_builder/a/b/c/d"), 0o711 & !umask);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[photonio::test]
+async fn metadata() {
+    let dir = "/tmp/test_metadata";
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir(dir).unwrap();
+    let file = "/tmp/test_metadata/file";
+    let link = "/tmp/test_metadata/link";
+    let dangling = "/tmp/test_metadata/dangling";
+    std::fs::write(file, b"hello").unwrap();
+    std::os::unix::fs::symlink(file, link).unwrap();
+    std::os::unix::fs::symlink("/tmp/test_metadata/missing", dangling).unwrap();
+
+    let meta = fs::metadata(dir).await.unwrap();
+    assert!(meta.is_dir());
+
+    // Follows the symlink to the file.
+    let meta = fs::metadata(link).await.unwrap();
+    assert!(meta.is_file());
+    assert_eq!(meta.len(), 5);
+    let meta = fs::symlink_metadata(link).await.unwrap();
+    assert!(meta.is_symlink());
+
+    let err = fs::metadata(dangling).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    let meta = fs::symlink_metadata(dangling).await.unwrap();
+    assert!(meta.is_symlink());
+
+    let err = fs::metadata("/tmp/test_metadata/missing")
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[photonio::test]
+async fn metadata_matches_std() {
+    use std::os::unix::fs::FileTypeExt;
+
+    let dir = "/tmp/test_metadata_matches_std";
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir(dir).unwrap();
+    let file = "/tmp/test_metadata_matches_std/file";
+    let link = "/tmp/test_metadata_matches_std/link";
+    std::fs::write(file, b"hello").unwrap();
+    std::fs::set_permissions(file, std::fs::Permissions::from_mode(0o640)).unwrap();
+    std::os::unix::fs::symlink(file, link).unwrap();
+
+    for path in [dir, file, link] {
+        let meta = fs::symlink_metadata(path).await.unwrap();
+        let expect = std::fs::symlink_metadata(path).unwrap();
+        let file_type = meta.file_type();
+        assert_eq!(file_type.is_dir(), expect.is_dir());
+        assert_eq!(file_type.is_file(), expect.is_file());
+        assert_eq!(file_type.is_symlink(), expect.is_symlink());
+        assert!(!file_type.is_fifo() && !file_type.is_socket());
+        assert_eq!(meta.len(), expect.len());
+        assert_eq!(meta.permissions(), expect.permissions());
+        assert_eq!(meta.modified().unwrap(), expect.modified().unwrap());
+        assert_eq!(meta.accessed().unwrap(), expect.accessed().unwrap());
+        assert_eq!(meta.created().ok(), expect.created().ok());
+        assert_eq!(meta.ino(), expect.ino());
+        assert_eq!(meta.dev(), expect.dev());
+        assert_eq!(meta.nlink(), expect.nlink());
+        assert_eq!(meta.uid(), expect.uid());
+        assert_eq!(meta.gid(), expect.gid());
+        assert_eq!(meta.blksize(), expect.blksize());
+        assert_eq!(meta.blocks(), expect.blocks());
+    }
+    let meta = fs::metadata(file).await.unwrap();
+    assert_eq!(meta.permissions().mode() & 0o777, 0o640);
+    assert!(!meta.permissions().readonly());
+}
+
+#[photonio::test]
+async fn set_permissions() {
+    use photonio::fs::FileExt;
+
+    let path = "/tmp/test_set_permissions.txt";
+    let _ = std::fs::remove_file(path);
+    fs::write(path, b"hello").await.unwrap();
+
+    let mut perm = fs::metadata(path).await.unwrap().permissions();
+    let old = perm.clone();
+    perm.set_mode(0o400);
+    fs::set_permissions(path, perm).await.unwrap();
+    let perm = fs::metadata(path).await.unwrap().permissions();
+    assert_eq!(perm.mode() & 0o777, 0o400);
+    assert!(perm.readonly());
+    // The superuser bypasses permission checks.
+    if unsafe { libc::geteuid() } != 0 {
+        let err = OpenOptions::new().write(true).open(path).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    }
+
+    let file = File::open(path).await.unwrap();
+    file.set_permissions(old.clone()).await.unwrap();
+    assert_eq!(file.metadata().await.unwrap().permissions(), old);
+    let mut perm = old;
+    perm.set_readonly(true);
+    file.set_permissions(perm).await.unwrap();
+    assert!(fs::metadata(path).await.unwrap().permissions().readonly());
+    perm = fs::metadata(path).await.unwrap().permissions();
+    perm.set_readonly(false);
+    file.set_permissions(perm).await.unwrap();
+    OpenOptions::new().write(true).open(path).await.unwrap();
+
+    // Leaves the owner and the group unchanged.
+    let meta = file.metadata().await.unwrap();
+    file.set_owner(None, None).await.unwrap();
+    file.set_owner(Some(meta.uid()), Some(meta.gid()))
+        .await
+        .unwrap();
+    let after = file.metadata().await.unwrap();
+    assert_eq!((after.uid(), after.gid()), (meta.uid(), meta.gid()));
+    fs::remove_file(path).await.unwrap();
+}
+
+#[photonio::test]
+async fn file_metadata() {
+    use std::{
+        ffi::CString,
+        time::{Duration, SystemTime},
+    };
+
+    let path = "/tmp/test_file_metadata.txt";
+
+    let file = File::create(path).await.unwrap();
+    file.write_all_at(&[1; 4096], 0).await.unwrap();
+    let mtime = SystemTime::UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_789);
+    let times = [
+        libc::timespec {
+            tv_sec: 0,
+            tv_nsec: libc::UTIME_OMIT,
+        },
+        libc::timespec {
+            tv_sec: 1_600_000_000,
+            tv_nsec: 123_456_789,
+        },
+    ];
+    let cpath = CString::new(path).unwrap();
+    assert_eq!(
+        unsafe { libc::utimensat(libc::AT_FDCWD, cpath.as_ptr(), times.as_ptr(), 0) },
+        0
+    );
+
+    let meta = file.metadata().await.unwrap();
+    assert!(meta.is_file());
+    assert!(!meta.is_dir());
+    assert_eq!(meta.len(), 4096);
+    assert_eq!(meta.modified().unwrap(), mtime);
+    assert_eq!(meta.mtime(), 1_600_000_000);
+    assert!(meta.accessed().is_ok());
+    let meta = fs::metadata(path).await.unwrap();
+    assert_eq!(meta.modified().unwrap(), mtime);
+}
+
+#[photonio::test]
+async fn set_times() {
+    use std::time::{Duration, SystemTime};
+
+    use photonio::fs::FileTimes;
+
+    let dir = "/tmp/test_set_times";
+    let path = "/tmp/test_set_times/file";
+    let link = "/tmp/test_set_times/link";
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir(dir).unwrap();
+    std::fs::write(path, b"hello").unwrap();
+    std::os::unix::fs::symlink(path, link).unwrap();
+
+    let mtime = SystemTime::UNIX_EPOCH + Duration::new(1_500_000_000, 987_654_321);
+    let atime = SystemTime::UNIX_EPOCH + Duration::new(1_400_000_000, 1);
+    let file = File::open(path).await.unwrap();
+    file.set_times(FileTimes::new().set_modified(mtime).set_accessed(atime))
+        .await
+        .unwrap();
+    let meta = file.metadata().await.unwrap();
+    assert_eq!(meta.modified().unwrap(), mtime);
+    assert_eq!(meta.accessed().unwrap(), atime);
+    assert_eq!(std::fs::metadata(path).unwrap().modified().unwrap(), mtime);
+
+    // Only the set timestamp changes.
+    let atime = SystemTime::UNIX_EPOCH + Duration::new(1_600_000_000, 5);
+    fs::set_file_times(link, FileTimes::new().set_accessed(atime))
+        .await
+        .unwrap();
+    let meta = fs::metadata(path).await.unwrap();
+    assert_eq!(meta.modified().unwrap(), mtime);
+    assert_eq!(meta.accessed().unwrap(), atime);
+
+    // Times before the epoch.
+    let mtime = SystemTime::UNIX_EPOCH - Duration::new(1, 250_000_000);
+    file.set_modified(mtime).await.unwrap();
+    assert_eq!(fs::metadata(path).await.unwrap().modified().unwrap(), mtime);
+    assert_eq!(fs::metadata(path).await.unwrap().mtime(), -2);
+
+    // The link itself changes without following it.
+    let ltime = SystemTime::UNIX_EPOCH + Duration::new(1_000_000_000, 42);
+    fs::set_symlink_file_times(link, FileTimes::new().set_modified(ltime))
+        .await
+        .unwrap();
+    let meta = fs::symlink_metadata(link).await.unwrap();
+    assert_eq!(meta.modified().unwrap(), ltime);
+    assert_eq!(fs::metadata(path).await.unwrap().modified().unwrap(), mtime);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[photonio::test]
+async fn try_exists() {
+    let dir = "/tmp/test_try_exists";
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir(dir).unwrap();
+    let file = "/tmp/test_try_exists/file";
+    std::fs::write(file, b"").unwrap();
+    let dangling = "/tmp/test_try_exists/dangling";
+    std::os::unix::fs::symlink("/tmp/test_try_exists/missing", dangling).unwrap();
+    let looped = "/tmp/test_try_exists/loop";
+    std::os::unix::fs::symlink(looped, looped).unwrap();
+
+    assert!(fs::try_exists(file).await.unwrap());
+    assert!(!fs::try_exists("/tmp/test_try_exists/missing")
+        .await
+        .unwrap());
+    assert!(!fs::try_exists("/tmp/test_try_exists/file/a").await.unwrap());
+    assert!(!fs::symlink_exists("/tmp/test_try_exists/file/a")
+        .await
+        .unwrap());
+    assert!(!fs::try_exists(dangling).await.unwrap());
+    assert!(fs::symlink_exists(dangling).await.unwrap());
+    let err = fs::try_exists(looped).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::FilesystemLoop);
+    assert!(fs::symlink_exists(looped).await.unwrap());
+
+    // The superuser can search any directory.
+    if unsafe { libc::geteuid() } != 0 {
+        let locked = "/tmp/test_try_exists/locked";
+        std::fs::create_dir(locked).unwrap();
+        std::fs::set_permissions(locked, std::fs::Permissions::from_mode(0)).unwrap();
+        let err = fs::try_exists("/tmp/test_try_exists/locked/a")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        std::fs::set_permissions(locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[photonio::test]
+async fn statfs() {
+    const TMPFS_MAGIC: u64 = 0x0102_1994;
+
+    let stats = fs::statfs("/tmp").await.unwrap();
+    assert!(stats.available_bytes() <= stats.free_bytes());
+    assert!(stats.free_bytes() <= stats.total_bytes());
+    assert!(stats.free_inodes() <= stats.total_inodes());
+    assert!(stats.block_size() > 0);
+    let err = fs::statfs("/tmp/test_statfs_missing").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    // Writing to a tmpfs takes available space.
+    let stats = fs::statfs("/dev/shm").await.unwrap();
+    if stats.fs_type() != TMPFS_MAGIC {
+        return;
+    }
+    let path = "/dev/shm/test_statfs";
+    let file = File::create(path).await.unwrap();
+    file.write_all_at(&vec![1; 16 << 20], 0).await.unwrap();
+    let after = file.statfs().await.unwrap();
+    assert_eq!(after.fs_type(), TMPFS_MAGIC);
+    assert_eq!(after.total_bytes(), stats.total_bytes());
+    assert!(stats.available_bytes() >= after.available_bytes() + (8 << 20));
+    fs::remove_file(path).await.unwrap();
+}
+
+#[photonio::test]
+async fn links() {
+    let dir = "/tmp/test_links";
+    let _ = std::fs::remove_dir_all(dir);
+    fs::create_dir(dir).await.unwrap();
+    let file = "/tmp/test_links/file";
+    File::create(file).await.unwrap();
+
+    let symlink = "/tmp/test_links/symlink";
+    fs::symlink(file, symlink).await.unwrap();
+    assert_eq!(fs::read_link(symlink).await.unwrap(), Path::new(file));
+    let err = fs::read_link(file).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    assert_eq!(fs::metadata(file).await.unwrap().nlink(), 1);
+    fs::hard_link(file, "/tmp/test_links/hard_link")
+        .await
+        .unwrap();
+    assert_eq!(fs::metadata(file).await.unwrap().nlink(), 2);
+}
+
+async fn check_copy(from: &str, to: &str) {
+    let data: Vec<u8> = (0..(1 << 20) + 7).map(|i| (i % 251) as u8).collect();
+    let mut file = File::create(from).await.unwrap();
+    file.write_all(&data).await.unwrap();
+    std::fs::set_permissions(from, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+    assert_eq!(fs::copy(from, to).await.unwrap(), data.len() as u64);
+    assert_eq!(std::fs::read(to).unwrap(), data);
+    let meta = fs::metadata(to).await.unwrap();
+    assert_eq!(meta.mode() & 0o777, 0o640);
+}
+
+#[photonio::test]
+async fn copy() {
+    check_copy("/tmp/test_copy_src.txt", "/tmp/test_copy_dst.txt").await;
+
+    // Copies across filesystems if there is a separate tmpfs.
+    if Path::new("/dev/shm").is_dir() {
+        check_copy("/tmp/test_copy_src.txt", "/dev/shm/test_copy_dst.txt").await;
+        std::fs::remove_file("/dev/shm/test_copy_dst.txt").unwrap();
+    }
+
+    let err = fs::copy("/tmp", "/tmp/test_copy_dir").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[photonio::test]
+async fn copy_sparse() {
+    let from = "/tmp/test_copy_sparse_src.txt";
+    let to = "/tmp/test_copy_sparse_dst.txt";
+
+    let file = File::create(from).await.unwrap();
+    file.write_all_at(b"head", 0).await.unwrap();
+    file.write_all_at(b"tail", 16 << 20).await.unwrap();
+    let len = (16 << 20) + 4;
+
+    assert_eq!(fs::copy(from, to).await.unwrap(), len);
+    let data = std::fs::read(to).unwrap();
+    assert_eq!(data.len() as u64, len);
+    assert_eq!(&data[..4], b"head");
+    assert!(data[4..16 << 20].iter().all(|&b| b == 0));
+    assert_eq!(&data[16 << 20..], b"tail");
+}
+
+#[photonio::test]
+async fn chain() {
+    let path = "/tmp/test_chain.txt";
+    std::fs::write(path, b"body").unwrap();
+
+    let file = File::open(path).await.unwrap();
+    let mut reader = b"head".as_slice().chain(file);
+    let mut buf = [0; 6];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"headbo");
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).await.unwrap();
+    assert_eq!(rest, b"dy");
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[photonio::test]
+async fn shared_file() {
+    use std::sync::Arc;
+
+    use photonio::task;
+
+    let path = "/tmp/test_shared_file.txt";
+    let data: Vec<u8> = (0..1 << 16).map(|i| i as u8).collect();
+    std::fs::write(path, &data).unwrap();
+
+    // Positional reads don't need exclusive access.
+    let file = Arc::new(File::open(path).await.unwrap());
+    let tasks: Vec<_> = (0..2)
+        .map(|i| {
+            let file = file.clone();
+            task::spawn(async move {
+                let mut buf = vec![0; 1 << 15];
+                file.read_exact_at(&mut buf, i << 15).await.unwrap();
+                buf
+            })
+        })
+        .collect();
+    let mut read = Vec::new();
+    for task in tasks {
+        read.extend(task.await.unwrap());
+    }
+    assert!(read == data);
+    file.metadata().await.unwrap();
+
+    // Sequential I/O through references shares the position in the kernel.
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .await
+        .unwrap();
+    let (mut a, mut b) = (&file, &file);
+    a.write_all(b"ab").await.unwrap();
+    b.write_all(b"cd").await.unwrap();
+    let mut buf = [0; 2];
+    a.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, data[4..6]);
+    file.sync_all().await.unwrap();
+    assert_eq!(&std::fs::read(path).unwrap()[..4], b"abcd");
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[photonio::test]
+async fn append_records() {
+    const RECORD: usize = 1024;
+    const COUNT: usize = 100;
+
+    let path = "/tmp/test_append_records.txt";
+    File::create(path).await.unwrap();
+
+    // Each record is written at the end of the file as a whole, so concurrent
+    // appenders through different handles don't tear records.
+    let append = |byte: u8| async move {
+        let mut file = OpenOptions::new().append(true).open(path).await.unwrap();
+        let record = [byte; RECORD];
+        for _ in 0..COUNT {
+            file.write_all(&record).await.unwrap();
+        }
+        file
+    };
+    let (file, _) = futures::join!(append(b'a'), append(b'b'));
+    let data = fs::read(path).await.unwrap();
+    assert_eq!(data.len(), 2 * COUNT * RECORD);
+    for record in data.chunks(RECORD) {
+        assert!(record.iter().all(|&b| b == record[0]));
+    }
+    assert_eq!(data.iter().filter(|&&b| b == b'a').count(), COUNT * RECORD);
+
+    // Positional writes would also go to the end, so they are rejected.
+    let err = file.write_at(b"hello", 0).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(fs::metadata(path).await.unwrap().len(), data.len() as u64);
+}
@@ -0,0 +1,895 @@
+use std::{
+    future::{ready, Ready},
+    io::{Error, ErrorKind, IoSlice, IoSliceMut, Result},
+    sync::Mutex,
+};
+
+use photonio::io::{
+    self, BufRead, BufReadExt, BufReader, BufWriter, Cursor, Read, ReadAt, ReadAtExt, ReadExt,
+    Seek, SeekFrom, Write, WriteAt, WriteAtExt, WriteExt,
+};
+
+/// An in-memory object that completes at most `max_len` bytes per operation,
+/// and fails with `Interrupted` every other operation.
+struct Mock {
+    data: Mutex<Vec<u8>>,
+    max_len: usize,
+    ops: Mutex<usize>,
+}
+
+impl Mock {
+    fn new(len: usize, max_len: usize) -> Self {
+        Self {
+            data: Mutex::new(vec![0; len]),
+            max_len,
+            ops: Mutex::new(0),
+        }
+    }
+
+    fn interrupt(&self) -> bool {
+        let mut ops = self.ops.lock().unwrap();
+        *ops += 1;
+        *ops % 2 == 1
+    }
+}
+
+impl ReadAt for Mock {
+    type ReadAt<'a> = Ready<Result<usize>>;
+
+    fn read_at<'a>(&'a self, buf: &'a mut [u8], pos: u64) -> Self::ReadAt<'a> {
+        if self.interrupt() {
+            return ready(Err(Error::from(ErrorKind::Interrupted)));
+        }
+        let data = self.data.lock().unwrap();
+        let pos = (pos as usize).min(data.len());
+        let n = buf.len().min(self.max_len).min(data.len() - pos);
+        buf[..n].copy_from_slice(&data[pos..pos + n]);
+        ready(Ok(n))
+    }
+}
+
+impl WriteAt for Mock {
+    type WriteAt<'a> = Ready<Result<usize>>;
+
+    fn write_at<'a>(&'a self, buf: &'a [u8], pos: u64) -> Self::WriteAt<'a> {
+        if self.interrupt() {
+            return ready(Err(Error::from(ErrorKind::Interrupted)));
+        }
+        // Writes nothing past the end, like a full device.
+        let mut data = self.data.lock().unwrap();
+        let pos = (pos as usize).min(data.len());
+        let n = buf.len().min(self.max_len).min(data.len() - pos);
+        data[pos..pos + n].copy_from_slice(&buf[..n]);
+        ready(Ok(n))
+    }
+}
+
+#[photonio::test]
+async fn write_all_at_partial() {
+    let mock = Mock::new(16, 3);
+    mock.write_all_at(b"hello world", 2).await.unwrap();
+    assert_eq!(&mock.data.lock().unwrap()[2..13], b"hello world");
+    assert_eq!(mock.data.lock().unwrap()[..2], [0; 2]);
+
+    let mut buf = [0; 11];
+    mock.read_exact_at(&mut buf, 2).await.unwrap();
+    assert_eq!(&buf, b"hello world");
+
+    // Empty writes succeed without touching the object.
+    let ops = *mock.ops.lock().unwrap();
+    mock.write_all_at(b"", 0).await.unwrap();
+    assert_eq!(*mock.ops.lock().unwrap(), ops);
+
+    let err = mock.write_all_at(b"overflow", 12).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WriteZero);
+    assert_eq!(&mock.data.lock().unwrap()[12..], b"over");
+    let err = mock.read_exact_at(&mut buf, 12).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
+
+/// A writer that records whether each write is vectored.
+#[derive(Default)]
+struct Recorder {
+    data: Vec<u8>,
+    is_vectored: bool,
+    writes: usize,
+    vectored_writes: usize,
+}
+
+impl Recorder {
+    // Completes at most this many bytes per write.
+    const MAX_LEN: usize = 4;
+}
+
+impl Write for Recorder {
+    type Write<'a> = Ready<Result<usize>>;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        self.writes += 1;
+        let n = buf.len().min(Self::MAX_LEN);
+        self.data.extend_from_slice(&buf[..n]);
+        ready(Ok(n))
+    }
+
+    type WriteVectored<'a> = Ready<Result<usize>>;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        if !self.is_vectored {
+            let buf = bufs
+                .iter()
+                .find(|b| !b.is_empty())
+                .map_or(&[][..], |b| &**b);
+            return self.write(buf);
+        }
+        self.vectored_writes += 1;
+        let mut n = 0;
+        for buf in bufs {
+            let m = buf.len().min(Self::MAX_LEN - n);
+            self.data.extend_from_slice(&buf[..m]);
+            n += m;
+        }
+        ready(Ok(n))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.is_vectored
+    }
+
+    type Flush<'a> = Ready<Result<()>>;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        ready(Ok(()))
+    }
+
+    type Shutdown<'a> = Ready<Result<()>>;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        ready(Ok(()))
+    }
+}
+
+#[photonio::test]
+async fn write_all_vectored_hint() {
+    let parts: [&[u8]; 5] = [b"", b"he", b"llo", b"", b" world"];
+
+    // Passes the buffers through to vectored writes.
+    let mut writer = Recorder {
+        is_vectored: true,
+        ..Default::default()
+    };
+    let mut bufs = parts.map(IoSlice::new);
+    writer.write_all_vectored(&mut bufs).await.unwrap();
+    assert_eq!(writer.data, b"hello world");
+    assert_eq!(writer.vectored_writes, 3);
+    assert_eq!(writer.writes, 0);
+
+    // Coalesces the buffers otherwise.
+    let mut writer = Recorder::default();
+    let mut bufs = parts.map(IoSlice::new);
+    writer.write_all_vectored(&mut bufs).await.unwrap();
+    assert_eq!(writer.data, b"hello world");
+    assert_eq!(writer.vectored_writes, 0);
+    assert_eq!(writer.writes, 3);
+}
+
+/// A reader that returns at most `max_len` bytes per read.
+struct Source {
+    data: Vec<u8>,
+    pos: usize,
+    max_len: usize,
+    reads: usize,
+}
+
+impl Source {
+    fn new(len: usize, max_len: usize) -> Self {
+        Self {
+            data: (0..len).map(|i| i as u8).collect(),
+            pos: 0,
+            max_len,
+            reads: 0,
+        }
+    }
+}
+
+impl Read for Source {
+    type Read<'a> = Ready<Result<usize>>;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        self.reads += 1;
+        let data = &self.data[self.pos..];
+        let n = buf.len().min(self.max_len).min(data.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        self.pos += n;
+        ready(Ok(n))
+    }
+
+    type ReadVectored<'a> = Ready<Result<usize>>;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        match bufs.iter_mut().find(|b| !b.is_empty()) {
+            Some(buf) => self.read(buf),
+            None => ready(Ok(0)),
+        }
+    }
+}
+
+#[photonio::test]
+async fn buf_reader_dribble() {
+    let mut reader = BufReader::with_capacity(16, Source::new(100, 1));
+    assert_eq!(reader.fill_buf().await.unwrap(), &[0]);
+    assert_eq!(reader.buffer(), &[0]);
+    reader.consume(1);
+    assert!(reader.buffer().is_empty());
+
+    let mut buf = [0; 4];
+    assert_eq!(reader.read(&mut buf).await.unwrap(), 1);
+    assert_eq!(buf[0], 1);
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [2, 3, 4, 5]);
+
+    let mut buf = Vec::new();
+    assert_eq!(reader.read_to_end(&mut buf).await.unwrap(), 94);
+    assert_eq!(buf, (6..100).collect::<Vec<u8>>());
+    assert!(reader.fill_buf().await.unwrap().is_empty());
+}
+
+#[photonio::test]
+async fn buf_reader_chunks() {
+    let mut reader = BufReader::with_capacity(16, Source::new(100, usize::MAX));
+
+    // Small reads are served from the buffer.
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [0, 1, 2, 3]);
+    assert_eq!(reader.buffer().len(), 12);
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [4, 5, 6, 7]);
+    assert_eq!(reader.get_ref().reads, 1);
+
+    // Large reads drain the buffer first, and then bypass it.
+    let mut buf = [0; 32];
+    assert_eq!(reader.read(&mut buf).await.unwrap(), 8);
+    assert_eq!(buf[..8], [8, 9, 10, 11, 12, 13, 14, 15]);
+    assert_eq!(reader.read(&mut buf).await.unwrap(), 32);
+    assert_eq!(buf[0], 16);
+    assert!(reader.buffer().is_empty());
+    assert_eq!(reader.get_ref().reads, 2);
+
+    let (mut a, mut b) = ([0; 2], [0; 4]);
+    let mut bufs = [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)];
+    assert_eq!(reader.read_vectored(&mut bufs).await.unwrap(), 6);
+    assert_eq!((a, b), ([48, 49], [50, 51, 52, 53]));
+
+    // The unread data survives unwrapping.
+    let (source, rest) = reader.into_parts();
+    assert_eq!(rest, (54..64).collect::<Vec<u8>>());
+    assert_eq!(source.pos, 64);
+}
+
+#[photonio::test]
+async fn buf_writer_coalesce() {
+    let mut writer = BufWriter::with_capacity(8, Recorder::default());
+    for _ in 0..3 {
+        writer.write_all(b"ab").await.unwrap();
+    }
+    assert_eq!(writer.buffer(), b"ababab");
+    assert_eq!(writer.get_ref().writes, 0);
+
+    // Flushes the buffer before a write that doesn't fit.
+    writer.write_all(b"cde").await.unwrap();
+    assert_eq!(writer.get_ref().data, b"ababab");
+    assert_eq!(writer.buffer(), b"cde");
+
+    // Writes larger than the buffer bypass it, and the rest of a partial
+    // write is buffered.
+    writer.write_all(b"0123456789").await.unwrap();
+    assert_eq!(writer.get_ref().data, b"abababcde0123");
+    assert_eq!(writer.buffer(), b"456789");
+
+    writer.write_all(b"xy").await.unwrap();
+    let (recorder, rest) = writer.into_parts();
+    assert_eq!(rest, b"456789xy");
+    assert_eq!(recorder.data, b"abababcde0123");
+}
+
+/// A reader that is interrupted once, and then fails at the end of its data.
+struct Faulty {
+    source: Source,
+    interrupted: bool,
+}
+
+impl Read for Faulty {
+    type Read<'a> = Ready<Result<usize>>;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        if !self.interrupted {
+            self.interrupted = true;
+            return ready(Err(ErrorKind::Interrupted.into()));
+        }
+        if self.source.pos == self.source.data.len() {
+            return ready(Err(Error::new(ErrorKind::Other, "broken source")));
+        }
+        self.source.read(buf)
+    }
+
+    type ReadVectored<'a> = Ready<Result<usize>>;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        match bufs.iter_mut().find(|b| !b.is_empty()) {
+            Some(buf) => self.read(buf),
+            None => ready(Ok(0)),
+        }
+    }
+}
+
+/// A reader that inspects every byte of its buffers before reading, so that
+/// Miri catches reads into uninitialized memory.
+struct Inspect(Source);
+
+impl Read for Inspect {
+    type Read<'a> = Ready<Result<usize>>;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        std::hint::black_box(buf.iter().fold(0u8, |acc, &b| acc ^ b));
+        self.0.read(buf)
+    }
+
+    type ReadVectored<'a> = Ready<Result<usize>>;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        match bufs.iter_mut().find(|b| !b.is_empty()) {
+            Some(buf) => self.read(buf),
+            None => ready(Ok(0)),
+        }
+    }
+}
+
+#[photonio::test]
+async fn read_buf() {
+    use std::mem::MaybeUninit;
+
+    use photonio::io::ReadBuf;
+
+    let mut storage = [MaybeUninit::uninit(); 16];
+    let mut buf = ReadBuf::uninit(&mut storage);
+    let mut reader = Inspect(Source::new(100, 4));
+    assert_eq!(reader.read_buf(&mut buf).await.unwrap(), 4);
+    assert_eq!(buf.filled(), [0, 1, 2, 3]);
+    // The fallback zeroes the whole unfilled part.
+    assert_eq!(buf.initialized()[4..], [0; 12]);
+    assert_eq!(reader.read_buf(&mut buf).await.unwrap(), 4);
+    assert_eq!(buf.filled(), [0, 1, 2, 3, 4, 5, 6, 7]);
+
+    // Readers that opt out only initialize what they read.
+    let mut storage = [MaybeUninit::uninit(); 16];
+    let mut buf = ReadBuf::uninit(&mut storage);
+    let mut cursor = Cursor::new(b"abc");
+    assert_eq!(cursor.read_buf(&mut buf).await.unwrap(), 3);
+    assert_eq!(buf.filled(), b"abc");
+    assert_eq!(buf.initialized().len(), 3);
+    assert_eq!(cursor.read_at_buf(&mut buf, 1).await.unwrap(), 2);
+    assert_eq!(buf.filled(), b"abcbc");
+
+    buf.put_slice(b"de");
+    assert_eq!(buf.initialize_unfilled_to(2), [0, 0]);
+    buf.advance(2);
+    assert_eq!(buf.filled(), b"abcbcde\0\0");
+    assert_eq!(buf.remaining(), 7);
+    buf.clear();
+    assert!(buf.filled().is_empty());
+    assert_eq!(buf.initialized().len(), 9);
+}
+
+#[photonio::test]
+async fn read_into_uninit() {
+    let data: Vec<u8> = (0..100).collect();
+
+    let mut buf = Vec::new();
+    let mut reader = Inspect(Source::new(100, 7));
+    assert_eq!(reader.read_to_end(&mut buf).await.unwrap(), 100);
+    assert_eq!(buf, data);
+
+    // Spare capacity is never initialized by the caller.
+    let mut buf = Vec::with_capacity(13);
+    let mut reader = BufReader::with_capacity(16, Inspect(Source::new(100, 7)));
+    assert_eq!(reader.read_to_end(&mut buf).await.unwrap(), 100);
+    assert_eq!(buf, data);
+
+    let mut buf = Vec::with_capacity(13);
+    let mut reader = BufReader::with_capacity(16, Cursor::new(&data));
+    assert_eq!(reader.read_to_end(&mut buf).await.unwrap(), 100);
+    assert_eq!(buf, data);
+}
+
+#[photonio::test]
+async fn copy() {
+    let mut reader = Source::new(100, 7);
+    let mut writer = Recorder::default();
+    assert_eq!(io::copy(&mut reader, &mut writer).await.unwrap(), 100);
+    assert_eq!(writer.data, (0..100).collect::<Vec<u8>>());
+
+    let mut reader = Source::new(100, usize::MAX);
+    let mut writer = Recorder::default();
+    let n = io::copy_with_buf_size(&mut reader, &mut writer, 16)
+        .await
+        .unwrap();
+    assert_eq!(n, 100);
+    assert_eq!(reader.reads, 8);
+    let err = io::copy_with_buf_size(&mut reader, &mut writer, 0)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[photonio::test]
+async fn copy_broken_source() {
+    let mut reader = Faulty {
+        source: Source::new(50, 10),
+        interrupted: false,
+    };
+    let mut writer = Recorder::default();
+    let err = io::copy(&mut reader, &mut writer).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    // Everything read before the error is written.
+    assert_eq!(writer.data, (0..50).collect::<Vec<u8>>());
+}
+
+#[photonio::test]
+async fn take() {
+    // The limit is smaller than the buffer.
+    let mut reader = Source::new(100, usize::MAX).take(10);
+    let mut buf = [0; 32];
+    assert_eq!(reader.read(&mut buf).await.unwrap(), 10);
+    assert_eq!(buf[..10], (0..10).collect::<Vec<u8>>());
+    assert_eq!(reader.limit(), 0);
+    assert_eq!(reader.read(&mut buf).await.unwrap(), 0);
+    // The underlying reader doesn't read past the limit.
+    assert_eq!(reader.get_ref().pos, 10);
+
+    // Resetting the limit continues with the underlying reader.
+    reader.set_limit(6);
+    let (mut a, mut b) = ([0; 4], [0; 4]);
+    let mut bufs = [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)];
+    assert_eq!(reader.read_vectored(&mut bufs).await.unwrap(), 4);
+    assert_eq!(a, [10, 11, 12, 13]);
+    let mut buf = [0; 4];
+    assert_eq!(reader.read(&mut buf).await.unwrap(), 2);
+    assert_eq!(buf[..2], [14, 15]);
+    assert_eq!(reader.get_ref().pos, 16);
+
+    let mut source = reader.into_inner();
+    let mut buf = [0; 4];
+    source.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [16, 17, 18, 19]);
+}
+
+#[photonio::test]
+async fn take_zero() {
+    let mut reader = Source::new(100, usize::MAX).take(0);
+    let mut buf = [0; 4];
+    assert_eq!(reader.read(&mut buf).await.unwrap(), 0);
+    assert_eq!(reader.get_ref().reads, 0);
+    let err = reader.read_exact(&mut buf).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[photonio::test]
+async fn take_read_to_end() {
+    let mut reader = Source::new(100, 7).take(50);
+    let mut buf = Vec::new();
+    assert_eq!(reader.read_to_end(&mut buf).await.unwrap(), 50);
+    assert_eq!(buf, (0..50).collect::<Vec<u8>>());
+    assert_eq!(reader.get_ref().pos, 50);
+
+    // The limit also applies to the buffered data.
+    let mut reader = BufReader::with_capacity(16, Source::new(100, usize::MAX)).take(5);
+    assert_eq!(reader.fill_buf().await.unwrap(), &[0, 1, 2, 3, 4]);
+    reader.consume(3);
+    assert_eq!(reader.fill_buf().await.unwrap(), &[3, 4]);
+    reader.consume(10);
+    assert!(reader.fill_buf().await.unwrap().is_empty());
+    assert_eq!(reader.get_ref().buffer(), &(5..16).collect::<Vec<u8>>()[..]);
+}
+
+#[photonio::test]
+async fn chain() {
+    let mut reader = Source::new(6, usize::MAX).chain(Source::new(6, usize::MAX));
+    // A single read doesn't span both readers.
+    let mut buf = [0; 4];
+    assert_eq!(reader.read(&mut buf).await.unwrap(), 4);
+    assert_eq!(reader.read(&mut buf).await.unwrap(), 2);
+    assert_eq!(buf[..2], [4, 5]);
+    assert_eq!(reader.get_ref().1.reads, 0);
+
+    let mut reader = Source::new(6, 4).chain(Source::new(6, 4));
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [0, 1, 2, 3, 4, 5, 0, 1]);
+    let mut buf = Vec::new();
+    assert_eq!(reader.read_to_end(&mut buf).await.unwrap(), 4);
+    assert_eq!(buf, [2, 3, 4, 5]);
+    let (first, second) = reader.into_inner();
+    assert_eq!((first.pos, second.pos), (6, 6));
+}
+
+#[photonio::test]
+async fn chain_buf_read() {
+    let mut reader = Cursor::new(b"head").chain(BufReader::new(Cursor::new(b"body")));
+    assert_eq!(reader.fill_buf().await.unwrap(), b"head");
+    reader.consume(3);
+    assert_eq!(reader.fill_buf().await.unwrap(), b"d");
+    reader.consume(1);
+    assert_eq!(reader.fill_buf().await.unwrap(), b"body");
+    reader.consume(4);
+    assert!(reader.fill_buf().await.unwrap().is_empty());
+}
+
+#[photonio::test]
+async fn read_line() {
+    // The lines are longer than the buffer.
+    let data = b"HELO example.com\r\nMAIL FROM:<a@example.com>\nQUIT";
+    let mut reader = BufReader::with_capacity(4, data.as_slice());
+    let mut line = String::new();
+    assert_eq!(reader.read_line(&mut line).await.unwrap(), 18);
+    assert_eq!(line, "HELO example.com\r\n");
+    assert_eq!(reader.read_line(&mut line).await.unwrap(), 26);
+    assert_eq!(line, "HELO example.com\r\nMAIL FROM:<a@example.com>\n");
+    line.clear();
+    // The last line ends at EOF.
+    assert_eq!(reader.read_line(&mut line).await.unwrap(), 4);
+    assert_eq!(line, "QUIT");
+    assert_eq!(reader.read_line(&mut line).await.unwrap(), 0);
+
+    let mut reader = BufReader::with_capacity(4, b"a,bc,".as_slice());
+    let mut buf = Vec::new();
+    assert_eq!(reader.read_until(b',', &mut buf).await.unwrap(), 2);
+    assert_eq!(reader.read_until(b',', &mut buf).await.unwrap(), 3);
+    assert_eq!(buf, b"a,bc,");
+    assert_eq!(reader.read_until(b',', &mut buf).await.unwrap(), 0);
+}
+
+#[photonio::test]
+async fn read_line_invalid() {
+    let mut reader = BufReader::with_capacity(4, b"ok\n\xff\xfe\nnext\n".as_slice());
+    let mut line = String::new();
+    reader.read_line(&mut line).await.unwrap();
+    let err = reader.read_line(&mut line).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(line, "ok\n");
+    // The invalid line is consumed.
+    line.clear();
+    reader.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "next\n");
+}
+
+#[photonio::test]
+async fn lines() {
+    let data = b"+PING\r\n\r\n+PONG\nunterminated";
+    let mut lines = Cursor::new(data).lines();
+    assert_eq!(lines.next_line().await.unwrap().unwrap(), "+PING");
+    assert_eq!(lines.next_line().await.unwrap().unwrap(), "");
+    assert_eq!(lines.next_line().await.unwrap().unwrap(), "+PONG");
+    assert_eq!(lines.next_line().await.unwrap().unwrap(), "unterminated");
+    assert!(lines.next_line().await.unwrap().is_none());
+}
+
+#[photonio::test]
+async fn cursor_read() {
+    let mut cursor = Cursor::new((0..10).collect::<Vec<u8>>());
+    let mut buf = [0; 4];
+    cursor.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [0, 1, 2, 3]);
+    assert_eq!(cursor.position(), 4);
+    cursor.read_exact_at(&mut buf, 6).await.unwrap();
+    assert_eq!(buf, [6, 7, 8, 9]);
+    assert_eq!(cursor.position(), 4);
+
+    assert_eq!(cursor.seek(SeekFrom::End(-2)).await.unwrap(), 8);
+    assert_eq!(cursor.fill_buf().await.unwrap(), &[8, 9]);
+    assert_eq!(cursor.seek(SeekFrom::Current(-7)).await.unwrap(), 1);
+    let err = cursor.seek(SeekFrom::Current(-2)).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(cursor.stream_position().await.unwrap(), 1);
+
+    // Reads beyond the end return EOF.
+    cursor.set_position(20);
+    assert_eq!(cursor.read(&mut buf).await.unwrap(), 0);
+    assert_eq!(cursor.read_at(&mut buf, 20).await.unwrap(), 0);
+}
+
+#[photonio::test]
+async fn cursor_write() {
+    let mut cursor = Cursor::new(Vec::new());
+    cursor.write_all(b"hello").await.unwrap();
+    cursor.set_position(2);
+    cursor.write_all(b"LLO world").await.unwrap();
+    // Writing beyond the end fills the gap with zeros.
+    cursor.set_position(13);
+    let bufs = [IoSlice::new(b"a"), IoSlice::new(b"b")];
+    assert_eq!(cursor.write_vectored(&bufs).await.unwrap(), 2);
+    assert_eq!(cursor.get_ref(), b"heLLO world\0\0ab");
+
+    // Writing to a fixed size buffer stops at its end.
+    let mut buf = [0; 4];
+    let mut cursor = Cursor::new(&mut buf[..]);
+    assert_eq!(cursor.write(b"abc").await.unwrap(), 3);
+    assert_eq!(cursor.write(b"def").await.unwrap(), 1);
+    let err = cursor.write_all(b"g").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WriteZero);
+    assert_eq!(&buf, b"abcd");
+
+    let mut cursor = Cursor::new(vec![0; 4].into_boxed_slice());
+    let bufs = [IoSlice::new(b"abc"), IoSlice::new(b"def")];
+    assert_eq!(cursor.write_vectored(&bufs).await.unwrap(), 4);
+    assert_eq!(&cursor.into_inner()[..], b"abcd");
+}
+
+#[photonio::test]
+async fn cursor_adapters() {
+    let mut reader = BufReader::with_capacity(4, Cursor::new(b"0123456789")).take(6);
+    let mut writer = BufWriter::with_capacity(4, Cursor::new(Vec::new()));
+    assert_eq!(io::copy(&mut reader, &mut writer).await.unwrap(), 6);
+    writer.flush().await.unwrap();
+    assert_eq!(writer.get_ref().get_ref(), b"012345");
+    // The limit applies before the buffer, so nothing is read past it.
+    assert_eq!(reader.into_inner().into_inner().position(), 6);
+}
+
+#[photonio::test]
+async fn empty_sink_repeat() {
+    let mut reader = io::repeat(0).take(1 << 20);
+    let mut writer = io::sink();
+    assert_eq!(io::copy(&mut reader, &mut writer).await.unwrap(), 1 << 20);
+
+    let mut buf = [1; 4];
+    let mut empty = io::empty();
+    assert_eq!(empty.read(&mut buf).await.unwrap(), 0);
+    assert_eq!(empty.read_at(&mut buf, 100).await.unwrap(), 0);
+    assert!(empty.fill_buf().await.unwrap().is_empty());
+    assert_eq!(io::copy(&mut empty, &mut writer).await.unwrap(), 0);
+
+    let repeat = io::repeat(7);
+    repeat.read_exact_at(&mut buf, 1 << 40).await.unwrap();
+    assert_eq!(buf, [7; 4]);
+    let (mut a, mut b) = ([0; 2], [0; 3]);
+    let mut bufs = [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)];
+    assert_eq!(io::repeat(9).read_vectored(&mut bufs).await.unwrap(), 5);
+    assert_eq!((a, b), ([9; 2], [9; 3]));
+
+    let bufs = [IoSlice::new(b"ab"), IoSlice::new(b"cde")];
+    assert_eq!(writer.write_vectored(&bufs).await.unwrap(), 5);
+    writer.write_all_at(b"abc", 10).await.unwrap();
+}
+
+#[photonio::test]
+async fn slices() {
+    let mut reader: &[u8] = b"0123456789";
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"0123");
+    assert_eq!(reader, b"456789");
+    let (mut a, mut b) = ([0; 2], [0; 8]);
+    let mut bufs = [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)];
+    assert_eq!(reader.read_vectored(&mut bufs).await.unwrap(), 6);
+    assert_eq!((&a, &b[..4]), (b"45", &b"6789"[..]));
+    assert!(reader.is_empty());
+    let err = reader.read_exact(&mut buf).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+    let mut data = [0; 6];
+    let mut writer = &mut data[..];
+    writer.write_all(b"abc").await.unwrap();
+    let bufs = [IoSlice::new(b"de"), IoSlice::new(b"fgh")];
+    assert_eq!(writer.write_vectored(&bufs).await.unwrap(), 3);
+    assert!(writer.is_empty());
+    let err = writer.write_all(b"i").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WriteZero);
+    assert_eq!(&data, b"abcdef");
+
+    let mut writer = Vec::new();
+    let mut bufs = [IoSlice::new(b"ab"), IoSlice::new(b""), IoSlice::new(b"cd")];
+    writer.write_all_vectored(&mut bufs).await.unwrap();
+    let mut reader: &[u8] = &[7; 100];
+    assert_eq!(io::copy(&mut reader, &mut writer).await.unwrap(), 100);
+    assert_eq!(&writer[..4], b"abcd");
+    assert_eq!(writer[4..], [7; 100]);
+}
+
+#[cfg(feature = "futures-io")]
+#[photonio::test]
+async fn compat_round_trip() {
+    use photonio::io::compat::{Compat, FromFutures};
+
+    // Goes through the poll-based traits and back.
+    let data: Vec<u8> = (0..100).collect();
+    let mut reader = FromFutures::new(Compat::with_capacity(16, Cursor::new(data.clone())));
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [0, 1, 2, 3]);
+    let mut rest = Vec::new();
+    assert_eq!(reader.read_to_end(&mut rest).await.unwrap(), 96);
+    assert_eq!(rest, data[4..]);
+
+    let mut writer = FromFutures::new(Compat::with_capacity(16, Cursor::new(Vec::new())));
+    writer.write_all(&data).await.unwrap();
+    writer.flush().await.unwrap();
+    writer.shutdown().await.unwrap();
+    let cursor = writer.into_inner().into_inner().await.unwrap();
+    assert_eq!(cursor.into_inner(), data);
+}
+
+#[photonio::test]
+async fn boxed() {
+    use photonio::io::{BoxedReader, BoxedWriter};
+
+    let mut readers = vec![
+        BoxedReader::new(Cursor::new(b"abc".to_vec())),
+        BoxedReader::new(b"defg".take(3)),
+    ];
+    let mut buf = String::new();
+    for reader in &mut readers {
+        reader.read_to_string(&mut buf).await.unwrap();
+    }
+    assert_eq!(buf, "abcdef");
+
+    let mut a = [0; 3];
+    let mut b = [0; 3];
+    let mut bufs = [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)];
+    let mut reader = BoxedReader::new(Cursor::new(b"xyz".to_vec()));
+    assert_eq!(reader.read_vectored(&mut bufs).await.unwrap(), 3);
+    assert_eq!(&a, b"xyz");
+
+    let mut writer = BoxedWriter::new(BufWriter::new(Cursor::new(Vec::new())));
+    writer
+        .write_all_vectored(&mut [IoSlice::new(b"ab"), IoSlice::new(b"cd")])
+        .await
+        .unwrap();
+    writer.flush().await.unwrap();
+    writer.shutdown().await.unwrap();
+}
+
+#[photonio::test]
+async fn incomplete() {
+    use photonio::io::IncompleteIo;
+
+    let mut buf = [0; 10];
+    let err = Source::new(7, 3).read_exact(&mut buf).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    let incomplete = IncompleteIo::from_error(&err).unwrap();
+    assert_eq!(incomplete.done(), 7);
+    assert_eq!(incomplete.position(), None);
+    assert_eq!(buf[..7], [0, 1, 2, 3, 4, 5, 6]);
+
+    let cursor = Cursor::new(vec![1; 9]);
+    let err = cursor.read_exact_at(&mut buf, 2).await.unwrap_err();
+    let incomplete = IncompleteIo::from_error(&err).unwrap();
+    assert_eq!(incomplete.done(), 7);
+    assert_eq!(incomplete.position(), Some(9));
+    assert_eq!(
+        err.to_string(),
+        "failed to fill whole buffer after 7 bytes at position 9"
+    );
+
+    let mut data = [0; 7];
+    let err = (&mut data[..]).write_all(&[1; 10]).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WriteZero);
+    assert_eq!(IncompleteIo::from_error(&err).unwrap().done(), 7);
+    let mut writer = &mut data[..];
+    let err = writer
+        .write_all_vectored(&mut [IoSlice::new(&[1; 4]), IoSlice::new(&[2; 6])])
+        .await
+        .unwrap_err();
+    assert_eq!(IncompleteIo::from_error(&err).unwrap().done(), 7);
+
+    let mock = Mock::new(16, 3);
+    let err = mock.write_all_at(b"overflow", 12).await.unwrap_err();
+    let incomplete = IncompleteIo::from_error(&err).unwrap();
+    assert_eq!(incomplete.done(), 4);
+    assert_eq!(incomplete.position(), Some(16));
+}
+
+/// A stream that reads back what is written to it.
+#[derive(Default)]
+struct Loopback {
+    data: Vec<u8>,
+    writes: usize,
+}
+
+impl Read for Loopback {
+    type Read<'a> = Ready<Result<usize>>;
+
+    fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Self::Read<'a> {
+        let n = buf.len().min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data.drain(..n);
+        ready(Ok(n))
+    }
+
+    type ReadVectored<'a> = Ready<Result<usize>>;
+
+    fn read_vectored<'a>(&'a mut self, bufs: &'a mut [IoSliceMut<'_>]) -> Self::ReadVectored<'a> {
+        match bufs.iter_mut().find(|b| !b.is_empty()) {
+            Some(buf) => self.read(buf),
+            None => ready(Ok(0)),
+        }
+    }
+}
+
+impl Write for Loopback {
+    type Write<'a> = Ready<Result<usize>>;
+
+    fn write<'a>(&'a mut self, buf: &'a [u8]) -> Self::Write<'a> {
+        self.writes += 1;
+        self.data.extend_from_slice(buf);
+        ready(Ok(buf.len()))
+    }
+
+    type WriteVectored<'a> = Ready<Result<usize>>;
+
+    fn write_vectored<'a>(&'a mut self, bufs: &'a [IoSlice<'_>]) -> Self::WriteVectored<'a> {
+        let buf = bufs.iter().find(|b| !b.is_empty()).map_or(&[][..], |b| b);
+        self.write(buf)
+    }
+
+    type Flush<'a> = Ready<Result<()>>;
+
+    fn flush(&mut self) -> Self::Flush<'_> {
+        ready(Ok(()))
+    }
+
+    type Shutdown<'a> = Ready<Result<()>>;
+
+    fn shutdown(&mut self) -> Self::Shutdown<'_> {
+        ready(Ok(()))
+    }
+}
+
+#[photonio::test]
+async fn buf_stream() {
+    use photonio::io::BufStream;
+
+    let mut stream = BufStream::with_capacity(8, 16, Loopback::default());
+    stream.write_all(b"one\n").await.unwrap();
+    stream.write_all(b"two\n").await.unwrap();
+    assert_eq!(stream.get_ref().writes, 0);
+
+    // The read flushes the writes, which are coalesced.
+    let mut line = String::new();
+    stream.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "one\n");
+    assert_eq!(stream.get_ref().writes, 1);
+
+    // Reads served from the buffer don't flush.
+    stream.write_all(b"three\n").await.unwrap();
+    assert_eq!(stream.fill_buf().await.unwrap(), b"two\n");
+    stream.consume(4);
+    assert_eq!(stream.get_ref().writes, 1);
+    let mut buf = [0; 6];
+    stream.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"three\n");
+    assert_eq!(stream.get_ref().writes, 2);
+
+    stream.write_all(b"four").await.unwrap();
+    let loopback = stream.into_inner().await.unwrap();
+    assert_eq!(loopback.data, b"four");
+}
+
+#[photonio::test]
+async fn aligned_buf() {
+    use photonio::io::AlignedBuf;
+
+    for align in [1, 512, 4096, 1 << 16] {
+        let mut buf = AlignedBuf::new(3 * 4096, align);
+        assert_eq!(buf.as_ptr() as usize % align, 0);
+        assert_eq!(buf.len(), 3 * 4096);
+        assert_eq!(buf.align(), align);
+        assert!(buf.iter().all(|&b| b == 0));
+        buf[..5].copy_from_slice(b"hello");
+        let clone = buf.clone();
+        assert_eq!(clone.as_ptr() as usize % align, 0);
+        assert_eq!(&clone[..5], b"hello");
+    }
+    let buf = AlignedBuf::new(0, 4096);
+    assert!(buf.is_empty());
+    assert_eq!(buf.as_ptr() as usize % 4096, 0);
+}
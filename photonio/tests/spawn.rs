@@ -0,0 +1,26 @@
+use std::time::Instant;
+
+use futures::channel::oneshot;
+use log::trace;
+use photonio::task;
+
+#[photonio::test(num_threads = 2)]
+async fn pingpong() {
+    const ROUNDS: usize = 1000;
+
+    // Tasks are dispatched in a round-robin fashion, so consecutive tasks run
+    // on different workers.
+    let start = Instant::now();
+    for i in 0..ROUNDS {
+        let (tx, rx) = oneshot::channel();
+        task::spawn(async move {
+            tx.send(i).unwrap();
+        });
+        assert_eq!(rx.await.unwrap(), i);
+    }
+    trace!(
+        "{} cross-worker round trips in {:?}",
+        ROUNDS,
+        start.elapsed()
+    );
+}
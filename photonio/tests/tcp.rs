@@ -1,3 +1,5 @@
+use std::io::ErrorKind;
+
 use log::trace;
 use photonio::{
     io::{Read, Write},
@@ -36,3 +38,1030 @@ async fn recv(mut stream: TcpStream) {
     let mut byte = [0; 1];
     stream.read(&mut byte).await.unwrap();
 }
+
+#[photonio::test]
+async fn write_to_closed_peer() {
+    use std::io::IoSlice;
+
+    use photonio::io::WriteExt;
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let mut stream = TcpStream::connect(server_addr).await.unwrap();
+    let (peer, _) = server.accept().await.unwrap();
+    drop(peer);
+
+    // The first writes might succeed before the peer resets the connection.
+    let buf = [0; 1024];
+    let err = loop {
+        if let Err(err) = stream.write(&buf).await {
+            break err;
+        }
+    };
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::BrokenPipe | ErrorKind::ConnectionReset
+    ));
+
+    // Vectored writes don't raise `SIGPIPE` either.
+    let err = loop {
+        let mut bufs = [IoSlice::new(&buf), IoSlice::new(&buf)];
+        if let Err(err) = stream.write_all_vectored(&mut bufs).await {
+            break err;
+        }
+    };
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::BrokenPipe | ErrorKind::ConnectionReset
+    ));
+}
+
+#[photonio::test]
+async fn copy_to_file() {
+    use photonio::{
+        fs::{self, File},
+        io::{self, WriteExt},
+    };
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let data: Vec<u8> = (0..1 << 20).map(|i: usize| (i % 251) as u8).collect();
+    let expect = data.clone();
+    let task = task::spawn(async move {
+        let mut stream = TcpStream::connect(server_addr).await.unwrap();
+        stream.write_all(&data).await.unwrap();
+    });
+
+    let path = "/tmp/test_tcp_copy_to_file.txt";
+    let (mut stream, _) = server.accept().await.unwrap();
+    let mut file = File::create(path).await.unwrap();
+    // Copies until the peer closes the connection.
+    let n = io::copy(&mut stream, &mut file).await.unwrap();
+    assert_eq!(n, expect.len() as u64);
+    task.await.unwrap();
+    assert!(fs::read(path).await.unwrap() == expect);
+}
+
+#[cfg(all(target_os = "linux", not(feature = "tokio")))]
+#[photonio::test]
+async fn copy_bidirectional() {
+    use std::net::Shutdown;
+
+    use photonio::io::{self, ReadExt, WriteExt};
+
+    // The proxy sits between a client and a server.
+    let front = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let back = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mut client = TcpStream::connect(front.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (mut inbound, _) = front.accept().await.unwrap();
+    let mut outbound = TcpStream::connect(back.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (mut server, _) = back.accept().await.unwrap();
+    let proxy =
+        task::spawn(async move { io::copy_bidirectional(&mut inbound, &mut outbound).await });
+
+    // The half-close of the client reaches the server, which can still
+    // respond.
+    let request = vec![1; 200 << 10];
+    client.write_all(&request).await.unwrap();
+    client.shutdown(Shutdown::Write).await.unwrap();
+    let mut buf = Vec::new();
+    server.read_to_end(&mut buf).await.unwrap();
+    assert!(buf == request);
+    server.write_all(b"response").await.unwrap();
+    server.shutdown(Shutdown::Write).await.unwrap();
+    let mut buf = Vec::new();
+    client.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"response");
+
+    let (to_server, to_client) = proxy.await.unwrap().unwrap();
+    assert_eq!(to_server, request.len() as u64);
+    assert_eq!(to_client, 8);
+}
+
+#[cfg(all(target_os = "linux", not(feature = "tokio")))]
+#[photonio::test]
+async fn copy_bidirectional_buffered() {
+    use std::net::Shutdown;
+
+    use photonio::io::{self, BufStream, ReadExt, WriteExt};
+
+    // Buffered streams are not backed by descriptors, so the data goes through
+    // their methods.
+    let front = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let back = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mut client = TcpStream::connect(front.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (inbound, _) = front.accept().await.unwrap();
+    let outbound = TcpStream::connect(back.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (mut server, _) = back.accept().await.unwrap();
+    let proxy = task::spawn(async move {
+        let mut inbound = BufStream::new(inbound);
+        let mut outbound = BufStream::new(outbound);
+        io::copy_bidirectional(&mut inbound, &mut outbound).await
+    });
+
+    let request = vec![1; 200 << 10];
+    client.write_all(&request).await.unwrap();
+    client.shutdown(Shutdown::Write).await.unwrap();
+    let mut buf = Vec::new();
+    server.read_to_end(&mut buf).await.unwrap();
+    assert!(buf == request);
+    server.write_all(b"response").await.unwrap();
+    server.shutdown(Shutdown::Write).await.unwrap();
+    let mut buf = Vec::new();
+    client.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"response");
+
+    let (to_server, to_client) = proxy.await.unwrap().unwrap();
+    assert_eq!(to_server, request.len() as u64);
+    assert_eq!(to_client, 8);
+}
+
+#[cfg(all(target_os = "linux", not(feature = "tokio")))]
+#[photonio::test]
+async fn copy_bidirectional_closed_peer() {
+    use photonio::io::{self, WriteExt};
+
+    let front = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let back = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mut client = TcpStream::connect(front.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (mut inbound, _) = front.accept().await.unwrap();
+    let mut outbound = TcpStream::connect(back.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (server, _) = back.accept().await.unwrap();
+    let proxy =
+        task::spawn(async move { io::copy_bidirectional(&mut inbound, &mut outbound).await });
+
+    // The server goes away while the client is still sending, which fails the
+    // proxy instead of raising `SIGPIPE`.
+    drop(server);
+    let send = task::spawn(async move {
+        let buf = vec![1; 64 << 10];
+        while client.write_all(&buf).await.is_ok() {}
+    });
+    let err = proxy.await.unwrap().unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::BrokenPipe | ErrorKind::ConnectionReset
+    ));
+    // The proxy closes the client connection when it returns.
+    send.await.unwrap();
+}
+
+#[cfg(all(target_os = "linux", not(feature = "tokio")))]
+#[photonio::test]
+async fn send_recv_with_flags() {
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let (peer, _) = server.accept().await.unwrap();
+
+    stream
+        .send_with_flags(b"hello", libc::MSG_NOSIGNAL)
+        .await
+        .unwrap();
+    let mut buf = [0; 5];
+    // Peeking leaves the data in the receive queue.
+    assert_eq!(
+        peer.recv_with_flags(&mut buf, libc::MSG_PEEK)
+            .await
+            .unwrap(),
+        5
+    );
+    assert_eq!(peer.recv_with_flags(&mut buf, 0).await.unwrap(), 5);
+    assert_eq!(&buf, b"hello");
+}
+
+#[cfg(all(target_os = "linux", not(feature = "tokio")))]
+#[photonio::test]
+async fn half_close() {
+    use std::net::Shutdown;
+
+    use photonio::io::{ReadExt, WriteExt};
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let mut stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut peer, _) = server.accept().await.unwrap();
+
+    // The client sends a request and signals EOF.
+    stream.write_all(b"request").await.unwrap();
+    stream.shutdown(Shutdown::Write).await.unwrap();
+    let err = stream.write(b"more").await.unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::BrokenPipe | ErrorKind::NotConnected
+    ));
+
+    // The server reads until EOF and then sends a response.
+    let mut buf = [0; 7];
+    peer.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"request");
+    assert_eq!(peer.read(&mut buf).await.unwrap(), 0);
+    peer.write_all(b"response").await.unwrap();
+    peer.shutdown(Shutdown::Write).await.unwrap();
+
+    // The client can still read the response after the half-close.
+    let mut buf = [0; 8];
+    stream.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"response");
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+}
+
+#[photonio::test]
+async fn write_shutdown() {
+    use photonio::io::{ReadExt, WriteExt};
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let mut stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut peer, _) = server.accept().await.unwrap();
+
+    // Empty writes succeed without sending anything.
+    stream.write_all(b"").await.unwrap();
+    stream.write_all(b"hello").await.unwrap();
+    Write::flush(&mut stream).await.unwrap();
+    Write::shutdown(&mut stream).await.unwrap();
+
+    let mut buf = [0; 5];
+    peer.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+    assert_eq!(peer.read(&mut buf).await.unwrap(), 0);
+}
+
+#[photonio::test]
+async fn connect_many() {
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let num_conns = 4096;
+    let start = std::time::Instant::now();
+    for _ in 0..num_conns {
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let (peer, _) = server.accept().await.unwrap();
+        assert_eq!(stream.local_addr().unwrap(), peer.peer_addr().unwrap());
+    }
+    trace!("opened {} connections in {:?}", num_conns, start.elapsed());
+}
+
+#[cfg(all(target_os = "linux", not(feature = "tokio")))]
+#[photonio::test]
+async fn read_timeout() {
+    use std::time::{Duration, Instant};
+
+    use photonio::io::WriteExt;
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut peer, _) = server.accept().await.unwrap();
+
+    let mut buf = [0; 5];
+    let start = Instant::now();
+    let err = stream
+        .read_timeout(&mut buf, Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    let err = stream
+        .read_timeout(&mut buf, Duration::ZERO)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+    // Reads that complete in time must not leave timeouts behind that affect
+    // later operations.
+    for _ in 0..100 {
+        peer.write_all(b"hello").await.unwrap();
+        let n = stream
+            .read_timeout(&mut buf, Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(&buf[..n], &b"hello"[..n]);
+        if n < 5 {
+            let mut rest = [0; 5];
+            let m = stream
+                .read_timeout(&mut rest, Duration::from_secs(1))
+                .await
+                .unwrap();
+            assert_eq!(n + m, 5);
+        }
+    }
+    std::thread::sleep(Duration::from_millis(20));
+    peer.write_all(b"world").await.unwrap();
+    let n = stream
+        .read_timeout(&mut buf, Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(&buf[..n], &b"world"[..n]);
+}
+
+#[cfg(all(target_os = "linux", not(feature = "tokio")))]
+#[photonio::test]
+async fn readiness() {
+    use std::cell::Cell;
+
+    use futures::FutureExt;
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let (peer, _) = server.accept().await.unwrap();
+
+    // Dropping a pending wait cancels it.
+    assert!(stream.readable().now_or_never().is_none());
+    peer.send_with_flags(b"hello", 0).await.unwrap();
+    stream.readable().await.unwrap();
+    let mut buf = [0; 5];
+    assert_eq!(stream.recv_with_flags(&mut buf, 0).await.unwrap(), 5);
+
+    // Fills the send buffer until it would block.
+    let buf = [0; 4096];
+    let mut sent = 0;
+    loop {
+        match stream
+            .send_with_flags(&buf, libc::MSG_DONTWAIT | libc::MSG_NOSIGNAL)
+            .await
+        {
+            Ok(n) => sent += n,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    let drained = Cell::new(0);
+    let writable = async {
+        stream.writable().await.unwrap();
+        assert!(drained.get() > 0);
+    };
+    let drain = async {
+        let mut buf = [0; 4096];
+        while drained.get() < sent {
+            let n = peer.recv_with_flags(&mut buf, 0).await.unwrap();
+            drained.set(drained.get() + n);
+        }
+    };
+    futures::join!(writable, drain);
+}
+
+#[cfg(all(target_os = "linux", not(feature = "tokio")))]
+#[photonio::test]
+async fn accept_multi() {
+    use std::time::Instant;
+
+    async fn connect_flood(addr: SocketAddr, num_conns: usize) -> Vec<TcpStream> {
+        let tasks: Vec<_> = (0..num_conns)
+            .map(|_| task::spawn(async move { TcpStream::connect(addr).await.unwrap() }))
+            .collect();
+        let mut streams = Vec::new();
+        for task in tasks {
+            streams.push(task.await.unwrap());
+        }
+        streams
+    }
+
+    let num_conns = 512;
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+
+    let start = Instant::now();
+    let clients = task::spawn(connect_flood(server_addr, num_conns));
+    for _ in 0..num_conns {
+        server.accept().await.unwrap();
+    }
+    clients.await.unwrap();
+    trace!(
+        "single-shot accepted {} connections in {:?}",
+        num_conns,
+        start.elapsed()
+    );
+
+    let start = Instant::now();
+    let clients = task::spawn(connect_flood(server_addr, num_conns));
+    let mut accept = server.accept_multi();
+    let mut peers = Vec::new();
+    for _ in 0..num_conns {
+        let (stream, addr) = accept.next().await.unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+        peers.push(addr);
+    }
+    let clients = clients.await.unwrap();
+    trace!(
+        "multishot accepted {} connections in {:?}",
+        num_conns,
+        start.elapsed()
+    );
+    let mut locals: Vec<_> = clients.iter().map(|c| c.local_addr().unwrap()).collect();
+    locals.sort();
+    peers.sort();
+    assert_eq!(locals, peers);
+
+    // Dropping the stream cancels the operation, and later connections are
+    // still accepted by single-shot accepts.
+    drop(accept);
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let (_, addr) = server.accept().await.unwrap();
+    assert_eq!(stream.local_addr().unwrap(), addr);
+}
+
+#[cfg(all(target_os = "linux", not(feature = "tokio")))]
+#[photonio::test]
+async fn recv_multi() {
+    use photonio::io::{BufRing, WriteExt};
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut peer, _) = server.accept().await.unwrap();
+    let ring = BufRing::new(4, 8).unwrap();
+    let mut recv = stream.recv_multi(&ring);
+
+    // Buffers are recycled as soon as they are dropped.
+    let data: Vec<u8> = (0..1024).map(|i| i as u8).collect();
+    let mut received = Vec::new();
+    for chunk in data.chunks(16) {
+        peer.write_all(chunk).await.unwrap();
+        let target = received.len() + chunk.len();
+        while received.len() < target {
+            let buf = recv.next().await.unwrap();
+            received.extend_from_slice(&buf);
+        }
+    }
+    assert_eq!(received, data);
+
+    // Holding all buffers exhausts the ring without losing data.
+    peer.write_all(&data[..64]).await.unwrap();
+    let mut held = Vec::new();
+    let err = loop {
+        match recv.next().await {
+            Ok(buf) => held.push(buf),
+            Err(e) => break e,
+        }
+    };
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    assert_eq!(held.len(), ring.entries());
+    let mut received: Vec<u8> = held.iter().flat_map(|buf| buf.iter().copied()).collect();
+    drop(held);
+    while received.len() < 64 {
+        let buf = recv.next().await.unwrap();
+        received.extend_from_slice(&buf);
+    }
+    assert_eq!(received, &data[..64]);
+
+    // An empty buffer means EOF.
+    drop(peer);
+    assert!(recv.next().await.unwrap().is_empty());
+}
+
+#[photonio::test]
+async fn drop_pending_read() {
+    use photonio::io::{ReadExt, WriteExt};
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let mut stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut peer, _) = server.accept().await.unwrap();
+
+    // Drops reads that never become ready.
+    let mut buf = vec![0u8; 64];
+    for _ in 0..8 {
+        let mut read = Box::pin(stream.read(&mut buf));
+        assert!(futures::poll!(&mut read).is_pending());
+        drop(read);
+    }
+    // The buffer can be reused, since the cancelled reads don't touch it
+    // anymore, and they don't consume any data.
+    buf.fill(0xff);
+    peer.write_all(b"hello").await.unwrap();
+    let mut got = [0; 5];
+    stream.read_exact(&mut got).await.unwrap();
+    assert_eq!(&got, b"hello");
+    assert!(buf.iter().all(|&b| b == 0xff));
+}
+
+#[photonio::test]
+async fn accept_peer_addr() {
+    for addr in ["127.0.0.1:0", "[::1]:0"] {
+        let server = match TcpListener::bind(addr).await {
+            Ok(server) => server,
+            // IPv6 might be disabled.
+            Err(_) if addr.starts_with('[') => continue,
+            Err(e) => panic!("{e}"),
+        };
+        let server_addr = server.local_addr().unwrap();
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let (peer, peer_addr) = server.accept().await.unwrap();
+        assert_eq!(peer_addr, stream.local_addr().unwrap());
+        assert_eq!(peer.peer_addr().unwrap(), peer_addr);
+        assert_eq!(peer.local_addr().unwrap(), server_addr);
+    }
+}
+
+#[cfg(all(target_os = "linux", not(feature = "tokio")))]
+#[photonio::test]
+async fn read_owned() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use photonio::io::{IoBuf, IoBufMut, WriteExt};
+
+    // A buffer that records when it is released.
+    struct Buf(Vec<u8>, Arc<AtomicBool>);
+
+    unsafe impl IoBuf for Buf {
+        fn stable_ptr(&self) -> *const u8 {
+            self.0.stable_ptr()
+        }
+
+        fn bytes_init(&self) -> usize {
+            self.0.bytes_init()
+        }
+    }
+
+    unsafe impl IoBufMut for Buf {
+        fn stable_mut_ptr(&mut self) -> *mut u8 {
+            self.0.stable_mut_ptr()
+        }
+
+        fn bytes_total(&mut self) -> usize {
+            self.0.bytes_total()
+        }
+
+        unsafe fn set_init(&mut self, n: usize) {
+            self.0.set_init(n)
+        }
+    }
+
+    impl Drop for Buf {
+        fn drop(&mut self) {
+            self.1.store(true, Ordering::Release);
+        }
+    }
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut peer, _) = server.accept().await.unwrap();
+
+    peer.write_all(b"hello").await.unwrap();
+    let (n, buf) = stream.read_owned(Vec::with_capacity(16)).await;
+    assert_eq!(n.unwrap(), 5);
+    assert_eq!(buf, b"hello");
+
+    // The buffer is kept until the cancelled read completes.
+    let released = Arc::new(AtomicBool::new(false));
+    let buf = Buf(Vec::with_capacity(16), released.clone());
+    let mut read = Box::pin(stream.read_owned(buf));
+    assert!(futures::poll!(&mut read).is_pending());
+    drop(read);
+    assert!(!released.load(Ordering::Acquire));
+    while !released.load(Ordering::Acquire) {
+        task::yield_now().await;
+    }
+
+    // The cancelled read doesn't consume any data.
+    peer.write_all(b"world").await.unwrap();
+    let (n, buf) = stream.read_owned(vec![0; 5].into_boxed_slice()).await;
+    assert_eq!(n.unwrap(), 5);
+    assert_eq!(&*buf, b"world");
+}
+
+#[cfg(all(target_os = "linux", not(feature = "tokio")))]
+#[photonio::test]
+async fn write_zc() {
+    use std::time::Instant;
+
+    use photonio::io::{ReadExt, WriteExt};
+
+    const LEN: usize = 1 << 20;
+    const ROUNDS: usize = 16;
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let mut stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut peer, _) = server.accept().await.unwrap();
+    let drain = task::spawn(async move {
+        let mut buf = vec![0; LEN];
+        let mut total = 0;
+        for _ in 0..2 * ROUNDS {
+            peer.read_exact(&mut buf).await.unwrap();
+            assert!(buf.iter().all(|&b| b == 7));
+            total += buf.len();
+        }
+        total
+    });
+
+    let mut buf = vec![7; LEN];
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        stream.write_all(&buf).await.unwrap();
+    }
+    let elapsed = start.elapsed();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let mut left = LEN;
+        while left > 0 {
+            buf.truncate(left);
+            buf.fill(7);
+            let ptr = buf.as_ptr();
+            let (n, returned) = stream.write_zc(buf).await;
+            assert_eq!(returned.as_ptr(), ptr);
+            buf = returned;
+            // The buffer is only returned after the notification, so the
+            // kernel no longer reads it and this doesn't reach the peer.
+            buf.fill(0);
+            left -= n.unwrap();
+        }
+    }
+    trace!(
+        "{} MiB over loopback: write {:?}, write_zc {:?}",
+        ROUNDS,
+        elapsed,
+        start.elapsed()
+    );
+    assert_eq!(drain.await.unwrap(), 2 * ROUNDS * LEN);
+}
+
+#[cfg(all(target_os = "linux", not(feature = "tokio")))]
+#[photonio::test]
+async fn accept_direct() {
+    use photonio::io::{ReadExt, WriteExt};
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    for _ in 0..4 {
+        let mut stream = TcpStream::connect(server_addr).await.unwrap();
+        let (mut peer, peer_addr) = server.accept_direct().await.unwrap();
+        assert_eq!(peer_addr, stream.local_addr().unwrap());
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        peer.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+        peer.write_all(b"pong").await.unwrap();
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+        // Closing the slot closes the connection.
+        peer.close().unwrap();
+        assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+    }
+
+    // Writes to a closed peer fail without raising `SIGPIPE`.
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut peer, _) = server.accept_direct().await.unwrap();
+    drop(stream);
+    let buf = [0; 1024];
+    let err = loop {
+        if let Err(err) = peer.write(&buf).await {
+            break err;
+        }
+    };
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::BrokenPipe | ErrorKind::ConnectionReset
+    ));
+}
+
+#[photonio::test]
+async fn into_split() {
+    use futures::{channel::mpsc, StreamExt};
+    use photonio::io::{ReadExt, WriteExt};
+
+    const LEN: usize = 1 << 20;
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let echo = task::spawn(async move {
+        let (stream, _) = server.accept().await.unwrap();
+        let (mut reader, mut writer) = stream.into_split();
+        let (tx, mut rx) = mpsc::unbounded::<Vec<u8>>();
+        let read = task::spawn(async move {
+            let mut buf = vec![0; 4096];
+            loop {
+                let n = reader.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break reader;
+                }
+                tx.unbounded_send(buf[..n].to_vec()).unwrap();
+            }
+        });
+        let write = task::spawn(async move {
+            while let Some(buf) = rx.next().await {
+                writer.write_all(&buf).await.unwrap();
+            }
+            writer.shutdown().await.unwrap();
+            writer
+        });
+        let reader = read.await.unwrap();
+        let writer = write.await.unwrap();
+        reader.reunite(writer).unwrap();
+    });
+
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let local_addr = stream.local_addr().unwrap();
+    let (mut reader, mut writer) = stream.into_split();
+    assert_eq!(reader.local_addr().unwrap(), local_addr);
+    assert_eq!(writer.peer_addr().unwrap(), server_addr);
+    let data: Vec<u8> = (0..LEN).map(|i| i as u8).collect();
+    let expect = data.clone();
+    // Writes everything before reading, which only completes if the echo
+    // server reads and writes concurrently.
+    let write = task::spawn(async move {
+        writer.write_all(&data).await.unwrap();
+        writer.shutdown().await.unwrap();
+        writer
+    });
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert!(buf == expect);
+    let writer = write.await.unwrap();
+    let stream = writer.reunite(reader).unwrap();
+    assert_eq!(stream.local_addr().unwrap(), local_addr);
+    echo.await.unwrap();
+}
+
+#[photonio::test]
+async fn write_half_to_closed_peer() {
+    use std::io::IoSlice;
+
+    use photonio::io::WriteExt;
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let (peer, _) = server.accept().await.unwrap();
+    drop(peer);
+
+    // The first writes might succeed before the peer resets the connection.
+    let (_reader, mut writer) = stream.into_split();
+    let buf = [0; 1024];
+    let err = loop {
+        let mut bufs = [IoSlice::new(&buf), IoSlice::new(&buf)];
+        if let Err(err) = writer.write_all_vectored(&mut bufs).await {
+            break err;
+        }
+    };
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::BrokenPipe | ErrorKind::ConnectionReset
+    ));
+}
+
+#[photonio::test]
+async fn reunite_mismatched() {
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let a = TcpStream::connect(server_addr).await.unwrap();
+    let b = TcpStream::connect(server_addr).await.unwrap();
+    let (a_reader, a_writer) = a.into_split();
+    let (b_reader, b_writer) = b.into_split();
+    let err = a_reader.reunite(b_writer).unwrap_err();
+    // The halves are returned so that they can be reunited with their pairs.
+    err.0.reunite(a_writer).unwrap();
+    err.1.reunite(b_reader).unwrap();
+}
+
+#[photonio::test]
+async fn split() {
+    use photonio::io::{self, ReadExt, WriteExt};
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut peer, _) = server.accept().await.unwrap();
+    let (mut reader, mut writer) = io::split(stream);
+    writer.write_all(b"ping").await.unwrap();
+    let mut buf = [0; 4];
+    peer.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+    peer.write_all(b"pong").await.unwrap();
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"pong");
+    let mut stream = reader.unsplit(writer);
+    stream.write_all(b"done").await.unwrap();
+    peer.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"done");
+}
+
+#[photonio::test]
+async fn chain() {
+    use photonio::io::{ReadExt, WriteExt};
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let mut stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut peer, _) = server.accept().await.unwrap();
+    stream.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+    Write::shutdown(&mut stream).await.unwrap();
+
+    // Replays a peeked prefix before the rest of the request.
+    let mut peeked = [0; 4];
+    peer.read_exact(&mut peeked).await.unwrap();
+    let mut reader = peeked.as_slice().chain(peer);
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"GET / HT");
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).await.unwrap();
+    assert_eq!(rest, b"TP/1.1\r\n\r\n");
+}
+
+#[cfg(feature = "futures-io")]
+#[photonio::test]
+async fn compat_stream() {
+    use futures::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use photonio::io::compat::Compat;
+
+    let data: Vec<u8> = (0..1 << 20).map(|i| (i % 251) as u8).collect();
+    let expect = data.clone();
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let recv = task::spawn(async move {
+        let (peer, _) = server.accept().await.unwrap();
+        let mut reader = BufReader::new(Compat::with_capacity(4096, peer));
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line).await.unwrap();
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+        (line, buf)
+    });
+
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let mut writer = Compat::new(stream);
+    writer.write_all(b"header\n").await.unwrap();
+    writer.write_all(&data).await.unwrap();
+    // Closing flushes the buffered writes and shuts down the stream.
+    writer.close().await.unwrap();
+    let (line, buf) = recv.await.unwrap();
+    assert_eq!(line, b"header\n");
+    assert!(buf == expect);
+}
+
+#[cfg(feature = "tokio-compat")]
+#[photonio::test]
+async fn tokio_compat_frames() {
+    use photonio::io::compat::TokioCompat;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let echo = task::spawn(async move {
+        let (peer, _) = server.accept().await.unwrap();
+        let mut peer = TokioCompat::new(peer);
+        // Echoes length-prefixed frames until the client shuts down.
+        loop {
+            let len = match peer.read_u32().await {
+                Ok(len) => len,
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => panic!("{e}"),
+            };
+            let mut frame = vec![0; len as usize];
+            peer.read_exact(&mut frame).await.unwrap();
+            peer.write_u32(len).await.unwrap();
+            peer.write_all(&frame).await.unwrap();
+            peer.flush().await.unwrap();
+        }
+    });
+
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let mut stream = TokioCompat::new(stream);
+    let sent = [b"hello".to_vec(), Vec::new(), vec![7; 64 << 10]];
+    for frame in &sent {
+        stream.write_u32(frame.len() as u32).await.unwrap();
+        stream.write_all(frame).await.unwrap();
+        stream.flush().await.unwrap();
+        let len = stream.read_u32().await.unwrap();
+        let mut echoed = vec![0; len as usize];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(&echoed, frame);
+    }
+    // Shutting down the stream ends the frames of the server.
+    stream.shutdown().await.unwrap();
+    echo.await.unwrap();
+}
+
+#[photonio::test]
+async fn boxed_streams() {
+    use photonio::io::{BoxedStream, Cursor, ReadExt, WriteExt};
+
+    async fn ping(stream: &mut BoxedStream) -> [u8; 4] {
+        stream.write_all(b"ping").await.unwrap();
+        stream.flush().await.unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        buf
+    }
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let echo = task::spawn(async move {
+        let (mut peer, _) = server.accept().await.unwrap();
+        let mut buf = [0; 4];
+        peer.read_exact(&mut buf).await.unwrap();
+        peer.write_all(&buf).await.unwrap();
+    });
+
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    // The cursor reads what follows the bytes it overwrites.
+    let cursor = Cursor::new(b"....pong".to_vec());
+    let mut streams = vec![BoxedStream::new(stream), BoxedStream::new(cursor)];
+    assert_eq!(&ping(&mut streams[0]).await, b"ping");
+    assert_eq!(&ping(&mut streams[1]).await, b"pong");
+    echo.await.unwrap();
+}
+
+#[photonio::test]
+async fn buf_stream_pipeline() {
+    use photonio::io::{BufReadExt, BufReader, BufStream, WriteExt};
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let echo = task::spawn(async move {
+        let (peer, _) = server.accept().await.unwrap();
+        let mut lines = BufReader::new(peer).lines();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            let peer = lines.get_mut().get_mut();
+            peer.write_all(format!("{line}\n").as_bytes())
+                .await
+                .unwrap();
+        }
+    });
+
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let mut stream = BufStream::new(stream);
+    for i in 0..3 {
+        let request = format!("request {i}\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+    }
+    // The buffered requests are not sent yet, so this goes first.
+    stream.get_mut().write_all(b"direct\n").await.unwrap();
+
+    let mut line = String::new();
+    stream.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "direct\n");
+    for i in 0..3 {
+        line.clear();
+        stream.read_line(&mut line).await.unwrap();
+        assert_eq!(line, format!("request {i}\n"));
+    }
+    Write::shutdown(&mut stream).await.unwrap();
+    echo.await.unwrap();
+}
+
+#[photonio::test]
+async fn timeout_ext() {
+    use std::time::Duration;
+
+    use photonio::io::{ReadExt, ReadTimeoutExt, WriteExt, WriteTimeoutExt};
+
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let mut stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut peer, _) = server.accept().await.unwrap();
+
+    let mut buf = [0; 5];
+    let err = stream
+        .read_exact_timeout(&mut buf, Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    peer.write_all(b"hello").await.unwrap();
+    stream
+        .read_exact_timeout(&mut buf, Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(&buf, b"hello");
+
+    // Readers that don't expose their descriptor race against a timer.
+    let mut limited = stream.take(5);
+    let err = ReadTimeoutExt::read_timeout(&mut limited, &mut buf, Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    peer.write_all(b"world").await.unwrap();
+    let n = ReadTimeoutExt::read_timeout(&mut limited, &mut buf, Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(&buf[..n], &b"world"[..n]);
+    let mut stream = limited.into_inner();
+
+    // Fills the buffers of both sides, since the peer doesn't read.
+    let data = vec![0; 64 << 20];
+    let err = stream
+        .write_all_timeout(&data, Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+}
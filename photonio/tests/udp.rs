@@ -0,0 +1,211 @@
+#![cfg(all(target_os = "linux", not(feature = "tokio")))]
+
+use std::{
+    io::{IoSlice, IoSliceMut},
+    mem,
+    os::unix::io::AsRawFd,
+};
+
+use photonio::net::{ControlMessage, UdpSocket};
+
+#[photonio::test]
+async fn send_recv_msg() {
+    let a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let a_addr = a.local_addr().unwrap();
+    let b_addr = b.local_addr().unwrap();
+
+    // Asks for the destination address of received packets.
+    let on: libc::c_int = 1;
+    let ret = unsafe {
+        libc::setsockopt(
+            b.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_PKTINFO,
+            &on as *const _ as *const _,
+            mem::size_of_val(&on) as _,
+        )
+    };
+    assert_eq!(ret, 0);
+
+    let bufs = [IoSlice::new(b"hello"), IoSlice::new(b"world")];
+    let n = a.send_msg(&bufs, Some(b_addr), &[]).await.unwrap();
+    assert_eq!(n, 10);
+
+    let mut head = [0; 5];
+    let mut tail = [0; 5];
+    let mut bufs = [IoSliceMut::new(&mut head), IoSliceMut::new(&mut tail)];
+    let control_len = ControlMessage::space(mem::size_of::<libc::in_pktinfo>());
+    let (n, addr, control, flags) = b.recv_msg(&mut bufs, control_len).await.unwrap();
+    assert_eq!(n, 10);
+    assert_eq!(addr, a_addr);
+    assert_eq!(&head, b"hello");
+    assert_eq!(&tail, b"world");
+    assert_eq!(control.len(), 1);
+    assert_eq!(control[0].level, libc::IPPROTO_IP);
+    assert_eq!(control[0].ty, libc::IP_PKTINFO);
+    assert_eq!(control[0].data.len(), mem::size_of::<libc::in_pktinfo>());
+    assert!(!flags.is_truncated());
+    assert!(!flags.is_control_truncated());
+
+    // Truncated data and control messages are reported in the flags.
+    let bufs = [IoSlice::new(b"helloworld")];
+    a.send_msg(&bufs, Some(b_addr), &[]).await.unwrap();
+    let mut bufs = [IoSliceMut::new(&mut head)];
+    let (n, _, control, flags) = b.recv_msg(&mut bufs, 0).await.unwrap();
+    assert_eq!(n, 5);
+    assert_eq!(&head, b"hello");
+    assert!(control.is_empty());
+    assert!(flags.is_truncated());
+    assert!(flags.is_control_truncated());
+}
+
+#[photonio::test]
+async fn send_to_recv_from() {
+    let a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let a_addr = a.local_addr().unwrap();
+    let b_addr = b.local_addr().unwrap();
+
+    let mut buf = [0; 16];
+    assert_eq!(a.send_to(b"ping", b_addr).await.unwrap(), 4);
+    let (n, addr) = b.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"ping");
+    assert_eq!(addr, a_addr);
+
+    assert_eq!(b.send_to(b"pong", addr).await.unwrap(), 4);
+    let (n, addr) = a.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"pong");
+    assert_eq!(addr, b_addr);
+}
+
+#[photonio::test]
+async fn readable() {
+    use futures::FutureExt;
+
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    let peer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+    assert!(socket.readable().now_or_never().is_none());
+    socket.writable().await.unwrap();
+    peer.send_to(b"hello", addr).await.unwrap();
+    socket.readable().await.unwrap();
+    let mut buf = [0; 5];
+    let (n, from) = socket.recv_from(&mut buf).await.unwrap();
+    assert_eq!(n, 5);
+    assert_eq!(from, peer.local_addr().unwrap());
+}
+
+#[photonio::test]
+async fn recv_batch() {
+    // Few enough datagrams to fit in the receive buffer.
+    const N: usize = 64;
+    const BATCH: usize = 16;
+
+    let a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let a_addr = a.local_addr().unwrap();
+    let b_addr = b.local_addr().unwrap();
+
+    let send = photonio::task::spawn(async move {
+        for i in 0..N as u32 {
+            a.send_to(&i.to_le_bytes(), b_addr).await.unwrap();
+        }
+    });
+
+    // Floods the socket before receiving, so that datagrams pile up.
+    send.await.unwrap();
+
+    let mut received = Vec::new();
+    let mut num_calls = 0;
+    let mut addrs = vec![a_addr; BATCH];
+    while received.len() < N {
+        let mut bufs: Vec<Vec<u8>> = (0..BATCH).map(|_| Vec::with_capacity(16)).collect();
+        let n = b.recv_batch(&mut bufs, &mut addrs).await.unwrap();
+        assert!((1..=BATCH).contains(&n));
+        num_calls += 1;
+        for (buf, addr) in bufs.iter().zip(&addrs).take(n) {
+            assert_eq!(*addr, a_addr);
+            received.push(u32::from_le_bytes(buf[..].try_into().unwrap()));
+        }
+    }
+    // Datagrams are not reordered on loopback.
+    assert!(received.iter().enumerate().all(|(i, &v)| v == i as u32));
+    assert!(num_calls < N);
+}
+
+#[photonio::test]
+async fn connected() {
+    let a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let c = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let a_addr = a.local_addr().unwrap();
+    let b_addr = b.local_addr().unwrap();
+
+    assert!(a.peer_addr().is_err());
+    a.connect(b_addr).await.unwrap();
+    b.connect(a_addr).await.unwrap();
+    assert_eq!(a.peer_addr().unwrap(), b_addr);
+
+    // Datagrams from other addresses are filtered out.
+    c.send_to(b"noise", b_addr).await.unwrap();
+    assert_eq!(a.send(b"ping").await.unwrap(), 4);
+    let mut buf = [0; 16];
+    let n = b.recv(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"ping");
+
+    // Zero-length datagrams round-trip.
+    assert_eq!(b.send(b"").await.unwrap(), 0);
+    assert_eq!(a.recv(&mut buf).await.unwrap(), 0);
+    assert_eq!(b.send(b"pong").await.unwrap(), 4);
+    let n = a.recv(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"pong");
+}
+
+#[photonio::test]
+async fn truncated() {
+    let a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let a_addr = a.local_addr().unwrap();
+    let b_addr = b.local_addr().unwrap();
+
+    // The rest of a long datagram is discarded, not left for the next receive.
+    a.send_to(b"hello world", b_addr).await.unwrap();
+    a.send_to(b"", b_addr).await.unwrap();
+    a.send_to(b"bye", b_addr).await.unwrap();
+    let mut buf = [0; 5];
+    let (n, addr) = b.recv_from(&mut buf).await.unwrap();
+    assert_eq!((n, addr), (5, a_addr));
+    assert_eq!(&buf, b"hello");
+    let (n, addr) = b.recv_from(&mut buf).await.unwrap();
+    assert_eq!((n, addr), (0, a_addr));
+    let (n, _) = b.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"bye");
+}
+
+#[photonio::test]
+async fn ipv6() {
+    let a = match UdpSocket::bind("[::1]:0").await {
+        Ok(socket) => socket,
+        // IPv6 might be disabled.
+        Err(e) if e.kind() == std::io::ErrorKind::AddrNotAvailable => return,
+        Err(e) => panic!("{e}"),
+    };
+    let b = UdpSocket::bind("[::1]:0").await.unwrap();
+    let a_addr = a.local_addr().unwrap();
+    let b_addr = b.local_addr().unwrap();
+    assert!(a_addr.is_ipv6());
+
+    a.send_to(b"ping", b_addr).await.unwrap();
+    let mut buf = [0; 16];
+    let (n, addr) = b.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"ping");
+    assert_eq!(addr, a_addr);
+
+    b.connect(a_addr).await.unwrap();
+    b.send(b"pong").await.unwrap();
+    let (n, addr) = a.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"pong");
+    assert_eq!(addr, b_addr);
+}
@@ -0,0 +1,286 @@
+#![cfg(all(target_os = "linux", not(feature = "tokio")))]
+
+use std::{
+    io::{ErrorKind, IoSlice},
+    net::Shutdown,
+    os::unix::io::{AsFd, AsRawFd, FromRawFd, IntoRawFd, OwnedFd},
+    path::Path,
+};
+
+use photonio::{
+    io::{self, PipeWriter, ReadExt, WriteExt},
+    net::{UnixDatagram, UnixListener, UnixListenerOptions, UnixStream},
+};
+
+#[photonio::test]
+async fn unix_stream() {
+    let dir = "/tmp/test_unix_stream";
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir(dir).unwrap();
+    let path = Path::new(dir).join("sock");
+
+    let listener = UnixListenerOptions::new()
+        .unlink_on_drop(true)
+        .bind(&path)
+        .await
+        .unwrap();
+    assert_eq!(listener.local_addr().unwrap().as_pathname(), Some(&*path));
+
+    let mut client = UnixStream::connect(&path).await.unwrap();
+    let (mut server, addr) = listener.accept().await.unwrap();
+    // Clients are not bound to any path.
+    assert!(addr.is_unnamed());
+    assert!(addr.as_pathname().is_none());
+    assert_eq!(client.peer_addr().unwrap().as_pathname(), Some(&*path));
+
+    client.write_all(b"hello").await.unwrap();
+    let mut buf = [0; 5];
+    server.read_exact(&mut buf).await.unwrap();
+    server.write_all(&buf).await.unwrap();
+    client.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+
+    // The socket file is removed in the background.
+    drop(listener);
+    for _ in 0..100 {
+        if !path.exists() {
+            break;
+        }
+        io::nop().await.unwrap();
+    }
+    assert!(!path.exists());
+    std::fs::remove_dir(dir).unwrap();
+}
+
+#[photonio::test]
+async fn unix_stream_pair() {
+    let (mut a, mut b) = UnixStream::pair().unwrap();
+    assert!(a.local_addr().unwrap().is_unnamed());
+    a.write_all(b"ping").await.unwrap();
+    let mut buf = [0; 4];
+    b.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+    b.write_all(b"pong").await.unwrap();
+    a.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"pong");
+
+    // Reads return EOF once the peer is closed, and writes fail without
+    // raising `SIGPIPE`.
+    drop(b);
+    let mut buf = Vec::new();
+    assert_eq!(a.read_to_end(&mut buf).await.unwrap(), 0);
+    let err = a.write_all(b"ping").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+    let mut bufs = [IoSlice::new(b"pi"), IoSlice::new(b"ng")];
+    let err = a.write_all_vectored(&mut bufs).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+}
+
+#[photonio::test]
+async fn unix_stream_connect_error() {
+    let dir = "/tmp/test_unix_stream_connect_error";
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir(dir).unwrap();
+    let path = Path::new(dir).join("sock");
+
+    let err = UnixStream::connect(&path).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    // The socket file is left behind by default, and nothing listens on it.
+    drop(UnixListener::bind(&path).await.unwrap());
+    let err = UnixStream::connect(&path).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+    let err = UnixListener::bind(&path).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AddrInUse);
+
+    // Stale socket files can be replaced, but not other files.
+    let listener = UnixListenerOptions::new()
+        .unlink_existing(true)
+        .bind(&path)
+        .await
+        .unwrap();
+    UnixStream::connect(&path).await.unwrap();
+    drop(listener);
+    let other = Path::new(dir).join("file");
+    std::fs::write(&other, b"").unwrap();
+    let err = UnixListenerOptions::new()
+        .unlink_existing(true)
+        .bind(&other)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AddrInUse);
+    assert!(other.exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[photonio::test]
+async fn unix_datagram() {
+    let dir = "/tmp/test_unix_datagram";
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir(dir).unwrap();
+    let server_path = Path::new(dir).join("server");
+    let client_path = Path::new(dir).join("client");
+
+    let server = UnixDatagram::bind(&server_path).await.unwrap();
+    let client = UnixDatagram::bind(&client_path).await.unwrap();
+    assert_eq!(
+        server.local_addr().unwrap().as_pathname(),
+        Some(&*server_path)
+    );
+
+    let n = client.send_to(b"hello", &server_path).await.unwrap();
+    assert_eq!(n, 5);
+    let mut buf = [0; 16];
+    let (n, addr) = server.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"hello");
+    assert_eq!(addr.as_pathname(), Some(&*client_path));
+    server.send_to_addr(b"world", &addr).await.unwrap();
+    let (n, addr) = client.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"world");
+    assert_eq!(addr.as_pathname(), Some(&*server_path));
+
+    // A connected socket has a peer address.
+    client.connect(&server_path).await.unwrap();
+    assert_eq!(
+        client.peer_addr().unwrap().as_pathname(),
+        Some(&*server_path)
+    );
+    client.send(b"again").await.unwrap();
+    let n = server.recv(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"again");
+
+    // Unbound sources have unnamed addresses.
+    let unbound = UnixDatagram::unbound().await.unwrap();
+    unbound.send_to(b"anonymous", &server_path).await.unwrap();
+    let (n, addr) = server.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"anonymous");
+    assert!(addr.is_unnamed());
+    assert!(addr.as_pathname().is_none());
+    assert!(addr.as_abstract_name().is_none());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[photonio::test]
+async fn unix_datagram_abstract() {
+    let server = UnixDatagram::bind("\0photonio_test_unix_datagram_abstract")
+        .await
+        .unwrap();
+    let server_addr = server.local_addr().unwrap();
+    assert_eq!(
+        server_addr.as_abstract_name(),
+        Some(&b"photonio_test_unix_datagram_abstract"[..])
+    );
+    assert!(server_addr.as_pathname().is_none());
+
+    let client = UnixDatagram::bind("\0photonio_test_unix_datagram_client")
+        .await
+        .unwrap();
+    client.send_to_addr(b"ping", &server_addr).await.unwrap();
+    let mut buf = [0; 16];
+    let (n, addr) = server.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"ping");
+    assert_eq!(
+        addr.as_abstract_name(),
+        Some(&b"photonio_test_unix_datagram_client"[..])
+    );
+    // Replies reach the abstract source.
+    server.send_to_addr(b"pong", &addr).await.unwrap();
+    let n = client.recv(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"pong");
+}
+
+#[photonio::test]
+async fn unix_datagram_pair() {
+    let (a, b) = UnixDatagram::pair().unwrap();
+    a.send(b"ping").await.unwrap();
+    b.send(b"pong").await.unwrap();
+    let mut buf = [0; 2];
+    // Datagrams that don't fit are truncated.
+    assert_eq!(b.recv(&mut buf).await.unwrap(), 2);
+    assert_eq!(&buf, b"pi");
+    let mut buf = [0; 16];
+    let n = a.recv(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"pong");
+
+    // Sends fail once the peer shuts down its read half.
+    b.shutdown(Shutdown::Read).await.unwrap();
+    let err = a.send(b"ping").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+}
+
+fn is_cloexec(fd: &OwnedFd) -> bool {
+    let flags = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFD) };
+    assert!(flags >= 0);
+    flags & libc::FD_CLOEXEC != 0
+}
+
+#[photonio::test]
+async fn unix_stream_fds() {
+    let (a, b) = UnixStream::pair().unwrap();
+    let (mut reader, writer) = io::pipe().unwrap();
+
+    // Another task sends the write end and closes its own.
+    let task = photonio::task::spawn(async move {
+        let n = a.send_with_fds(b"fd", &[writer.as_fd()]).await.unwrap();
+        assert_eq!(n, 2);
+        drop(writer);
+        a
+    });
+
+    let mut buf = [0; 16];
+    let mut fds = Vec::new();
+    let (n, nfds, flags) = b.recv_with_fds(&mut buf, &mut fds).await.unwrap();
+    assert_eq!(&buf[..n], b"fd");
+    assert_eq!(nfds, 1);
+    assert!(!flags.is_control_truncated());
+    assert!(is_cloexec(&fds[0]));
+    let a = task.await.unwrap();
+
+    let mut writer = unsafe { PipeWriter::from_raw_fd(fds.pop().unwrap().into_raw_fd()) };
+    writer.write_all(b"hello").await.unwrap();
+    drop(writer);
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"hello");
+
+    // Data without descriptors is received as usual.
+    a.send_with_fds(b"none", &[]).await.unwrap();
+    let mut buf = [0; 16];
+    let (n, nfds, flags) = b.recv_with_fds(&mut buf, &mut fds).await.unwrap();
+    assert_eq!(&buf[..n], b"none");
+    assert_eq!(nfds, 0);
+    assert!(!flags.is_control_truncated());
+    assert!(fds.is_empty());
+
+    let err = a.send_with_fds(b"", &[reader.as_fd()]).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[photonio::test]
+async fn unix_datagram_fds() {
+    let (a, b) = UnixDatagram::pair().unwrap();
+    let (mut reader, writer) = io::pipe().unwrap();
+
+    // Several descriptors are sent in one message, in order.
+    let null = std::fs::File::open("/dev/null").unwrap();
+    a.send_with_fds(b"fds", &[null.as_fd(), writer.as_fd()])
+        .await
+        .unwrap();
+    drop(writer);
+
+    let mut buf = [0; 16];
+    let mut fds = Vec::new();
+    let (n, nfds, flags) = b.recv_with_fds(&mut buf, &mut fds).await.unwrap();
+    assert_eq!(&buf[..n], b"fds");
+    assert_eq!(nfds, 2);
+    assert!(!flags.is_truncated());
+    assert!(!flags.is_control_truncated());
+    assert!(fds.iter().all(is_cloexec));
+
+    let mut writer = unsafe { PipeWriter::from_raw_fd(fds.pop().unwrap().into_raw_fd()) };
+    writer.write_all(b"hello").await.unwrap();
+    drop(writer);
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"hello");
+}
@@ -0,0 +1,844 @@
+#![cfg(all(target_os = "linux", not(feature = "tokio")))]
+#![feature(io_error_more)]
+
+use std::{
+    io::ErrorKind,
+    mem,
+    os::{
+        fd::{AsFd, AsRawFd},
+        unix::fs::MetadataExt,
+    },
+    path::Path,
+};
+
+use log::trace;
+use photonio::{
+    fs::{self, Advice, Dir, File, OpenOptions, SyncRangeFlags},
+    io::{
+        self, OpError, Opcode, Read, ReadAt, ReadAtExt, ReadExt, Seek, SeekFrom, WriteAt,
+        WriteAtExt, WriteExt,
+    },
+    runtime::last_opcode,
+};
+
+#[photonio::test]
+async fn allocate() {
+    let path = "/tmp/test_allocate.txt";
+
+    let file = File::create(path).await.unwrap();
+    file.allocate(0, 1 << 20).await.unwrap();
+    let meta = file.metadata().await.unwrap();
+    assert_eq!(meta.len(), 1 << 20);
+    assert!(meta.blocks() * 512 >= 1 << 20);
+}
+
+#[photonio::test]
+async fn punch_hole() {
+    let path = "/tmp/test_punch_hole.txt";
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .await
+        .unwrap();
+    file.write_all_at(&[1; 8192], 0).await.unwrap();
+    match file.punch_hole(0, 4096).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::Unsupported => return,
+        Err(e) => panic!("{e}"),
+    }
+    assert_eq!(file.metadata().await.unwrap().len(), 8192);
+    let mut buf = [1; 4096];
+    file.read_exact_at(&mut buf, 0).await.unwrap();
+    assert_eq!(buf, [0; 4096]);
+}
+
+#[photonio::test]
+async fn set_len_read_only() {
+    let path = "/tmp/test_set_len_read_only.txt";
+
+    File::create(path).await.unwrap();
+    let file = File::open(path).await.unwrap();
+    let err = file.set_len(10).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+}
+
+#[photonio::test]
+async fn set_len_opcode() {
+    let path = "/tmp/test_set_len_opcode.txt";
+
+    // Kernels before 6.9 fall back to the blocking thread pool.
+    if !io::is_supported(Opcode::Ftruncate) {
+        return;
+    }
+    let file = File::create(path).await.unwrap();
+    file.set_len(4096).await.unwrap();
+    // `IORING_OP_FTRUNCATE`, see `linux/io_uring.h`.
+    assert_eq!(last_opcode().unwrap(), 55);
+    assert_eq!(file.metadata().await.unwrap().len(), 4096);
+}
+
+#[photonio::test]
+async fn set_len_during_read() {
+    const LEN: usize = 8 << 20;
+    let path = "/tmp/test_set_len_during_read.bin";
+
+    let data: Vec<u8> = (0..LEN).map(|i| (i % 251) as u8).collect();
+    std::fs::write(path, &data).unwrap();
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .await
+        .unwrap();
+
+    // The read is submitted first, and the truncation might land while it
+    // copies. Either way, it returns a prefix of the file as it was.
+    let mut buf = vec![0; LEN];
+    let (n, ()) = futures::join!(file.read_at(&mut buf, 0), async {
+        file.set_len(4096).await.unwrap();
+    });
+    let n = n.unwrap();
+    assert!((4096..=LEN).contains(&n));
+    assert!(buf[..n] == data[..n]);
+
+    // Reads after the truncation are short.
+    assert_eq!(file.read_at(&mut buf, 0).await.unwrap(), 4096);
+    assert_eq!(file.read_at(&mut buf, 8192).await.unwrap(), 0);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[photonio::test]
+async fn write_at_read_only() {
+    let path = "/tmp/test_write_at_read_only.txt";
+
+    File::create(path).await.unwrap();
+    let file = File::open(path).await.unwrap();
+    let fd = file.as_raw_fd();
+    let err = file.write_at(&[1; 4096], 81920).await.unwrap_err();
+    assert_eq!(err.raw_os_error(), None);
+    assert_eq!(io::raw_os_error(&err), Some(libc::EBADF));
+    let msg = format!("{err}");
+    let prefix = format!("write(fd={fd}, len=4096, off=81920): ");
+    assert!(msg.starts_with(&prefix), "{msg}");
+
+    let op = err.get_ref().unwrap().downcast_ref::<OpError>().unwrap();
+    assert_eq!(op.opcode(), Opcode::Write);
+    assert_eq!(op.fd(), Some(fd));
+    assert!(!op.is_fixed_file());
+    assert_eq!(op.byte_count(), Some(4096));
+    assert_eq!(op.offset(), Some(81920));
+}
+
+#[photonio::test]
+async fn remove_invalid_path() {
+    let err = fs::remove_file("/tmp/test\0remove").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidFilename);
+    let err = fs::remove_dir("/tmp/test\0remove").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidFilename);
+}
+
+#[photonio::test]
+async fn rename_noreplace() {
+    let from = "/tmp/test_rename_noreplace_from.txt";
+    let to = "/tmp/test_rename_noreplace_to.txt";
+    let _ = std::fs::remove_file(to);
+
+    File::create(from).await.unwrap();
+    fs::rename_noreplace(from, to).await.unwrap();
+    File::create(from).await.unwrap();
+    let err = fs::rename_noreplace(from, to).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+}
+
+#[photonio::test]
+async fn dir() {
+    let path = "/tmp/test_dir";
+    let _ = std::fs::remove_dir_all(path);
+    std::fs::create_dir(path).unwrap();
+
+    let dir = Dir::open(path).await.unwrap();
+    assert!(dir.metadata().await.unwrap().is_dir());
+
+    let mut file = dir.create_file_at("a.txt").await.unwrap();
+    file.write_all(b"hello").await.unwrap();
+    dir.rename_at("a.txt", "b.txt").await.unwrap();
+    dir.sync_all().await.unwrap();
+
+    let meta = dir.metadata_at("b.txt").await.unwrap();
+    assert_eq!(meta.len(), 5);
+    let file = dir.open_file_at("b.txt").await.unwrap();
+    let mut buf = [0; 5];
+    file.read_exact_at(&mut buf, 0).await.unwrap();
+    assert_eq!(&buf, b"hello");
+    let err = dir.open_file_at("a.txt").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    // Nothing leaks into the current working directory.
+    assert!(!Path::new("a.txt").exists());
+    assert!(!Path::new("b.txt").exists());
+    assert!(Path::new("/tmp/test_dir/b.txt").exists());
+
+    dir.remove_file_at("b.txt").await.unwrap();
+    assert!(!Path::new("/tmp/test_dir/b.txt").exists());
+}
+
+#[photonio::test]
+async fn open_beneath() {
+    let path = "/tmp/test_open_beneath";
+    let _ = std::fs::remove_dir_all(path);
+    std::fs::create_dir(path).unwrap();
+    std::fs::write("/tmp/test_open_beneath/file", b"hello").unwrap();
+    std::os::unix::fs::symlink("/tmp/test_open_beneath/file", "/tmp/test_open_beneath/link")
+        .unwrap();
+
+    let dir = Dir::open(path).await.unwrap();
+    match dir.open_beneath("file").await {
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::Unsupported => return,
+        Err(e) => panic!("{e}"),
+    }
+    let err = dir
+        .open_beneath("../test_open_beneath/file")
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::CrossesDevices);
+    let err = dir
+        .open_beneath("/tmp/test_open_beneath/file")
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::CrossesDevices);
+    // The absolute symlink escapes the directory too.
+    let err = dir.open_beneath("link").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::CrossesDevices);
+
+    let err = OpenOptions::new()
+        .read(true)
+        .resolve_no_symlinks(true)
+        .open("/tmp/test_open_beneath/link")
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::FilesystemLoop);
+}
+
+#[photonio::test]
+async fn advise() {
+    let path = "/tmp/test_advise.txt";
+
+    let file = File::create(path).await.unwrap();
+    file.allocate(0, 1 << 20).await.unwrap();
+    file.advise(0, 0, Advice::Sequential).await.unwrap();
+    file.advise(0, 4096, Advice::DontNeed).await.unwrap();
+
+    // Closes the file behind its back to get an invalid descriptor.
+    assert_eq!(unsafe { libc::close(file.as_raw_fd()) }, 0);
+    assert!(file.advise(0, 0, Advice::Normal).await.is_err());
+    assert!(file.advise_strict(0, 0, Advice::Normal).await.is_err());
+    mem::forget(file);
+}
+
+#[photonio::test]
+async fn readahead() {
+    let path = "/tmp/test_readahead.txt";
+
+    let file = File::create(path).await.unwrap();
+    file.write_all_at(&[1; 1 << 16], 0).await.unwrap();
+    drop(file);
+    let file = File::open(path).await.unwrap();
+    file.readahead(0, 1 << 16).await.unwrap();
+    file.readahead(4096, 0).await.unwrap();
+    let mut buf = vec![0; 1 << 16];
+    file.read_exact_at(&mut buf, 0).await.unwrap();
+    assert!(buf.iter().all(|&b| b == 1));
+}
+
+#[photonio::test]
+async fn seek() {
+    let path = "/tmp/test_seek.txt";
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .await
+        .unwrap();
+    file.write_all(b"hello").await.unwrap();
+    assert_eq!(file.stream_position().await.unwrap(), 5);
+    assert_eq!(file.seek(SeekFrom::End(0)).await.unwrap(), 5);
+    file.write_all(b" world").await.unwrap();
+    assert_eq!(file.stream_position().await.unwrap(), 11);
+
+    assert_eq!(file.seek(SeekFrom::Current(-5)).await.unwrap(), 6);
+    let mut buf = [0; 5];
+    file.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"world");
+    assert_eq!(file.seek(SeekFrom::Start(0)).await.unwrap(), 0);
+    let mut buf = [0; 11];
+    file.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello world");
+
+    // Positional operations don't move the cursor.
+    file.seek(SeekFrom::Start(2)).await.unwrap();
+    let mut buf = [0; 3];
+    file.read_exact_at(&mut buf, 6).await.unwrap();
+    assert_eq!(&buf, b"wor");
+    file.write_all_at(b"HE", 0).await.unwrap();
+    file.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"llo");
+    assert_eq!(file.stream_position().await.unwrap(), 5);
+
+    let err = file.seek(SeekFrom::Current(-6)).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = file.seek(SeekFrom::End(-12)).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(file.stream_position().await.unwrap(), 5);
+
+    // Seeks past the end are allowed.
+    assert_eq!(file.seek(SeekFrom::End(2)).await.unwrap(), 13);
+    let mut buf = [0; 1];
+    assert_eq!(file.read(&mut buf).await.unwrap(), 0);
+}
+
+#[photonio::test]
+async fn seek_append() {
+    let path = "/tmp/test_seek_append.txt";
+
+    File::create(path)
+        .await
+        .unwrap()
+        .write_all_at(b"hello", 0)
+        .await
+        .unwrap();
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .open(path)
+        .await
+        .unwrap();
+    assert_eq!(file.stream_position().await.unwrap(), 0);
+    file.write_all(b" world").await.unwrap();
+    assert_eq!(file.stream_position().await.unwrap(), 11);
+    file.seek(SeekFrom::Start(6)).await.unwrap();
+    let mut buf = [0; 5];
+    file.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"world");
+}
+
+#[photonio::test]
+async fn io_priority() {
+    use photonio::{
+        io::{with_io_priority, IoPriority, IoPriorityClass},
+        runtime::last_io_priority,
+    };
+
+    let path = "/tmp/test_io_priority.txt";
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .await
+        .unwrap();
+    let err = file.with_io_priority(IoPriorityClass::Idle, 8).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let idle = file.with_io_priority(IoPriorityClass::Idle, 0).unwrap();
+    let idle_value = IoPriority::new(IoPriorityClass::Idle, 0).unwrap().value();
+    assert_eq!(idle.priority().value(), idle_value);
+    idle.write_at(b"hello", 0).await.unwrap();
+    assert_eq!(last_io_priority().unwrap(), idle_value);
+    let mut buf = [0; 5];
+    idle.read_at(&mut buf, 0).await.unwrap();
+    assert_eq!(last_io_priority().unwrap(), idle_value);
+    assert_eq!(&buf, b"hello");
+    // Synchronizations don't carry priorities.
+    idle.sync_all().await.unwrap();
+    assert_eq!(last_io_priority().unwrap(), 0);
+    file.read_at(&mut buf, 0).await.unwrap();
+    assert_eq!(last_io_priority().unwrap(), 0);
+
+    let best_effort = IoPriority::new(IoPriorityClass::BestEffort, 4).unwrap();
+    assert_eq!(best_effort.class(), IoPriorityClass::BestEffort);
+    assert_eq!(best_effort.level(), 4);
+    with_io_priority(best_effort, async {
+        file.read_at(&mut buf, 0).await.unwrap();
+        assert_eq!(last_io_priority().unwrap(), best_effort.value());
+        // The priority of the handle overrides the default one.
+        idle.read_at(&mut buf, 0).await.unwrap();
+        assert_eq!(last_io_priority().unwrap(), idle_value);
+    })
+    .await;
+    file.read_at(&mut buf, 0).await.unwrap();
+    assert_eq!(last_io_priority().unwrap(), 0);
+}
+
+#[photonio::test]
+async fn sync_range() {
+    let path = "/tmp/test_sync_range.txt";
+
+    let file = File::create(path).await.unwrap();
+    let flags = SyncRangeFlags::WAIT_BEFORE | SyncRangeFlags::WRITE | SyncRangeFlags::WAIT_AFTER;
+    let buf = [1u8; 4096];
+    for i in 0..4 {
+        let offset = i * buf.len() as u64;
+        file.write_all_at(&buf, offset).await.unwrap();
+        file.sync_range(offset, buf.len() as u64, flags)
+            .await
+            .unwrap();
+    }
+    // A zero length syncs to the end of the file.
+    file.sync_range(0, 0, flags).await.unwrap();
+    file.sync_range(0, 0, SyncRangeFlags::WRITE).await.unwrap();
+    file.sync_data().await.unwrap();
+    assert_eq!(file.metadata().await.unwrap().len(), 4 * 4096);
+    assert!(file.sync_range(u64::MAX, 1, flags).await.is_err());
+}
+
+#[photonio::test]
+async fn read_at_timeout() {
+    use std::time::Duration;
+
+    let path = "/tmp/test_read_at_timeout.txt";
+
+    let file = File::create(path).await.unwrap();
+    file.write_all_at(b"hello", 0).await.unwrap();
+    let file = File::open(path).await.unwrap();
+    let mut buf = [0; 5];
+    let n = file
+        .read_at_timeout(&mut buf, 0, Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(&buf[..n], b"hello");
+    let err = file
+        .read_at_timeout(&mut buf, 0, Duration::ZERO)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+}
+
+#[photonio::test]
+async fn fixed_buf() {
+    use std::{os::unix::fs::OpenOptionsExt, time::Instant};
+
+    use photonio::io::FixedBufPool;
+
+    const BLOCKS: u64 = 256;
+
+    let path = "/tmp/test_fixed_buf.txt";
+
+    let pool = FixedBufPool::new(2, 4096).unwrap();
+    assert_eq!(pool.count(), 2);
+    assert_eq!(pool.buf_len(), 4096);
+    let err = FixedBufPool::new(1, 4096).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ResourceBusy);
+
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create(true).truncate(true);
+    let file = match options.custom_flags(libc::O_DIRECT).open(path).await {
+        Ok(file) => file,
+        // Some file systems like tmpfs don't support O_DIRECT.
+        Err(e) if io::raw_os_error(&e) == Some(libc::EINVAL) => {
+            options.custom_flags(0).open(path).await.unwrap()
+        }
+        Err(e) => panic!("{e}"),
+    };
+
+    let mut wbuf = pool.get().unwrap();
+    let mut rbuf = pool.get().unwrap();
+    assert!(pool.get().is_none());
+    assert_eq!(wbuf.as_ptr() as usize % 4096, 0);
+    let start = Instant::now();
+    for i in 0..BLOCKS {
+        wbuf.fill(i as u8);
+        let (n, buf) = file.write_at_fixed(wbuf, i * 4096).await;
+        assert_eq!(n.unwrap(), 4096);
+        wbuf = buf;
+    }
+    for i in 0..BLOCKS {
+        let (n, buf) = file.read_at_fixed(rbuf, i * 4096).await;
+        assert_eq!(n.unwrap(), 4096);
+        rbuf = buf;
+        assert!(rbuf.iter().all(|&b| b == i as u8));
+    }
+    trace!(
+        "fixed buffers: {} blocks of 4 KiB written and read in {:?}",
+        BLOCKS,
+        start.elapsed()
+    );
+    assert_eq!(file.metadata().await.unwrap().len(), BLOCKS * 4096);
+    drop(wbuf);
+    assert!(pool.get().is_some());
+}
+
+#[photonio::test]
+async fn fixed_file() {
+    use photonio::io::{register_files, ReadAt};
+
+    let path = "/tmp/test_fixed_file.txt";
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .await
+        .unwrap();
+    let file = file.register().unwrap();
+    let file_fd = file.file().as_fd();
+    file.write_all_at(b"hello", 0).await.unwrap();
+    file.sync_data().await.unwrap();
+    let mut buf = [0; 5];
+    file.read_exact_at(&mut buf, 0).await.unwrap();
+    assert_eq!(&buf, b"hello");
+    assert_eq!(file.file().metadata().await.unwrap().len(), 5);
+
+    // The table has 1024 slots, one of which is in use.
+    let err = register_files(&[file_fd; 1024]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+    let fds = register_files(&[file_fd; 1023]).unwrap();
+    assert!(register_files(&[file_fd]).is_err());
+    fds.into_iter().next().unwrap().unregister().unwrap();
+    let fd = register_files(&[file_fd]).unwrap().pop().unwrap();
+
+    // A fixed file can be pointed at another file.
+    let other = File::open("/dev/zero").await.unwrap();
+    fd.update(other.as_fd()).unwrap();
+    drop(fd);
+
+    let file = file.into_file();
+    let n = file.read_at(&mut buf, 1).await.unwrap();
+    assert_eq!(&buf[..n], b"ello");
+}
+
+#[photonio::test]
+async fn write_at_sync() {
+    let path = "/tmp/test_write_at_sync.txt";
+
+    let file = File::create(path).await.unwrap();
+    assert_eq!(file.write_at_sync(b"hello", 0).await.unwrap(), 5);
+    assert_eq!(file.write_at_sync(b" world", 5).await.unwrap(), 6);
+    assert_eq!(std::fs::read(path).unwrap(), b"hello world");
+
+    // The write fails, so the linked sync is cancelled.
+    let file = File::open(path).await.unwrap();
+    for _ in 0..8 {
+        assert!(file.write_at_sync(b"hello", 0).await.is_err());
+    }
+    let mut buf = [0; 11];
+    file.read_exact_at(&mut buf, 0).await.unwrap();
+    assert_eq!(&buf, b"hello world");
+}
+
+#[photonio::test]
+async fn barrier_sync() {
+    use std::task::Poll;
+
+    let path = "/tmp/test_barrier_sync.txt";
+
+    let file = File::create(path).await.unwrap();
+    let bufs: Vec<_> = (0..64u8).map(|i| vec![i; 4096]).collect();
+    let mut writes: Vec<_> = bufs
+        .iter()
+        .enumerate()
+        .map(|(i, buf)| Box::pin(file.write_at(buf, i as u64 * 4096)))
+        .collect();
+    // Submits all writes before the barrier.
+    for write in &mut writes {
+        let _ = futures::poll!(write);
+    }
+    file.barrier_sync().await.unwrap();
+    // All writes have completed once the barrier completes.
+    for write in &mut writes {
+        match futures::poll!(write) {
+            Poll::Ready(n) => assert_eq!(n.unwrap(), 4096),
+            Poll::Pending => panic!("a write completes after the barrier"),
+        }
+    }
+    assert_eq!(file.metadata().await.unwrap().len(), 64 * 4096);
+}
+
+#[photonio::test]
+async fn read_at_owned() {
+    let path = "/tmp/test_read_at_owned.txt";
+
+    let file = File::create(path).await.unwrap();
+    file.write_all_at(b"hello", 0).await.unwrap();
+    let file = File::open(path).await.unwrap();
+    let (n, buf) = file.read_at_owned(Vec::with_capacity(8), 1).await;
+    assert_eq!(n.unwrap(), 4);
+    assert_eq!(buf, b"ello");
+    let (n, buf) = file.read_at_owned(buf, u64::MAX).await;
+    assert_eq!(n.unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(buf, b"ello");
+}
+
+#[photonio::test]
+async fn open_direct() {
+    use photonio::io::register_files;
+
+    let path = "/tmp/test_open_direct.txt";
+
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create(true).truncate(true);
+    let file = options.open_direct(path).await.unwrap();
+    file.write_all_at(b"hello", 0).await.unwrap();
+    file.sync_data().await.unwrap();
+    let mut buf = [0; 5];
+    file.read_exact_at(&mut buf, 0).await.unwrap();
+    assert_eq!(&buf, b"hello");
+    file.close().unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), b"hello");
+
+    // Leaves one free slot in the table.
+    let other = File::open(path).await.unwrap();
+    let _fds = register_files(&[other.as_fd(); 1023]).unwrap();
+    let file = options.open_direct(path).await.unwrap();
+    assert!(options.open_direct(path).await.is_err());
+    // The slot is reused after the file is closed.
+    drop(file);
+    let file = options.open_direct(path).await.unwrap();
+    file.write_all_at(b"world", 0).await.unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), b"world");
+}
+
+#[photonio::test]
+async fn xattr() {
+    const PATH: &str = "/dev/shm/test_xattr";
+
+    let file = File::create(PATH).await.unwrap();
+    match file.set_xattr("user.photonio", b"hello").await {
+        // tmpfs supports user attributes since Linux 6.6.
+        Err(e) if e.kind() == ErrorKind::Unsupported => {
+            std::fs::remove_file(PATH).unwrap();
+            return;
+        }
+        r => r.unwrap(),
+    }
+    assert_eq!(
+        file.get_xattr("user.photonio").await.unwrap().as_deref(),
+        Some(&b"hello"[..])
+    );
+    assert_eq!(file.get_xattr("user.missing").await.unwrap(), None);
+
+    // Overwrites with a value larger than the initial buffer.
+    let value = vec![1u8; 1024];
+    fs::set_xattr(PATH, "user.photonio", &value).await.unwrap();
+    assert_eq!(
+        fs::get_xattr(PATH, "user.photonio").await.unwrap(),
+        Some(value)
+    );
+
+    file.set_xattr("user.other", b"").await.unwrap();
+    let mut names = file.list_xattr().await.unwrap();
+    names.sort();
+    assert_eq!(names, ["user.other", "user.photonio"]);
+    std::fs::remove_file(PATH).unwrap();
+}
+
+#[photonio::test]
+async fn direct_io() {
+    use photonio::io::AlignedBuf;
+
+    let path = "/tmp/test_direct_io.bin";
+
+    let file = File::create(path).await.unwrap();
+    assert_eq!(file.alignment(), None);
+    drop(file);
+    let file = match OpenOptions::new()
+        .read(true)
+        .write(true)
+        .direct(true)
+        .open(path)
+        .await
+    {
+        Ok(file) => file,
+        // Some file systems like tmpfs don't support O_DIRECT.
+        Err(e) if e.kind() == ErrorKind::InvalidInput => return,
+        Err(e) => panic!("{e}"),
+    };
+    let align = file.alignment().unwrap();
+    assert!(align.is_power_of_two() && align >= 512);
+
+    let mut buf = AlignedBuf::new(2 * align, align);
+    buf.fill(7);
+    file.write_all_at(&buf, 0).await.unwrap();
+    let mut rbuf = AlignedBuf::new(2 * align, align);
+    file.read_exact_at(&mut rbuf, 0).await.unwrap();
+    assert_eq!(&rbuf[..], &buf[..]);
+
+    // Unaligned I/O fails before reaching the kernel, with the reason.
+    let check = |err: std::io::Error, what: &str| {
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(err.to_string().contains(what), "{err}");
+    };
+    check(
+        file.read_at(&mut rbuf[1..], 0).await.unwrap_err(),
+        "address",
+    );
+    check(
+        file.read_at(&mut rbuf[..align - 1], 0).await.unwrap_err(),
+        "length",
+    );
+    check(file.write_at(&buf, 1).await.unwrap_err(), "position");
+
+    // Reads and writes at the position of the file are checked the same way.
+    let mut file = file;
+    check(file.write(&buf[..1]).await.unwrap_err(), "length");
+    file.write_all(&buf).await.unwrap();
+    file.seek(SeekFrom::Start(1)).await.unwrap();
+    check(file.read(&mut rbuf).await.unwrap_err(), "position");
+    file.seek(SeekFrom::Start(0)).await.unwrap();
+    file.read_exact(&mut rbuf).await.unwrap();
+    assert_eq!(&rbuf[..], &buf[..]);
+}
+
+#[photonio::test]
+async fn close_on_drop() {
+    use photonio::runtime::inject_results;
+
+    let path = "/tmp/test_close_on_drop.txt";
+    // Counts the descriptors of this file only, since other tests open files
+    // concurrently.
+    let open_fds = || {
+        std::fs::read_dir("/proc/self/fd")
+            .unwrap()
+            .filter_map(|entry| std::fs::read_link(entry.ok()?.path()).ok())
+            .filter(|target| target == Path::new(path))
+            .count()
+    };
+
+    File::create(path).await.unwrap();
+    for _ in 0..10000 {
+        drop(File::open(path).await.unwrap());
+    }
+    // The closes are submitted on drop and complete with later operations.
+    for _ in 0..100 {
+        if open_fds() == 0 {
+            break;
+        }
+        io::nop().await.unwrap();
+    }
+    assert_eq!(open_fds(), 0);
+
+    // Explicit closes report errors, and the descriptor is released anyway.
+    let file = File::open(path).await.unwrap();
+    assert_eq!(open_fds(), 1);
+    inject_results(&[-libc::EIO]).unwrap();
+    let err = file.close().await.unwrap_err();
+    assert_eq!(io::raw_os_error(&err), Some(libc::EIO));
+    assert_eq!(open_fds(), 0);
+    File::open(path).await.unwrap().close().await.unwrap();
+    assert_eq!(open_fds(), 0);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[photonio::test]
+async fn watcher() {
+    use fs::{Event, EventMask, Watcher};
+
+    let dir = "/tmp/test_watcher";
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir(dir).unwrap();
+
+    let mut watcher = Watcher::new().unwrap();
+    let mask = EventMask::CREATE | EventMask::MODIFY | EventMask::MOVE | EventMask::DELETE;
+    let wd = watcher.watch(dir, mask).await.unwrap();
+    assert_eq!(watcher.watch(dir, mask).await.unwrap(), wd);
+
+    let a = Path::new(dir).join("a");
+    let b = Path::new(dir).join("b");
+    let file = File::create(&a).await.unwrap();
+    file.write_all_at(b"hello", 0).await.unwrap();
+    drop(file);
+    std::fs::rename(&a, &b).unwrap();
+    std::fs::remove_file(&b).unwrap();
+
+    let event = |mask, cookie, name: &str| Event {
+        wd,
+        mask,
+        cookie,
+        name: Some(name.into()),
+    };
+    assert_eq!(
+        watcher.next_event().await.unwrap(),
+        event(EventMask::CREATE, 0, "a")
+    );
+    assert_eq!(
+        watcher.next_event().await.unwrap(),
+        event(EventMask::MODIFY, 0, "a")
+    );
+    let from = watcher.next_event().await.unwrap();
+    assert_ne!(from.cookie, 0);
+    assert_eq!(from, event(EventMask::MOVED_FROM, from.cookie, "a"));
+    assert_eq!(
+        watcher.next_event().await.unwrap(),
+        event(EventMask::MOVED_TO, from.cookie, "b")
+    );
+    assert_eq!(
+        watcher.next_event().await.unwrap(),
+        event(EventMask::DELETE, 0, "b")
+    );
+
+    // Removing the watch queues a final event without a name.
+    watcher.unwatch(wd).unwrap();
+    let event = watcher.next_event().await.unwrap();
+    assert!(event.mask.contains(EventMask::IGNORED));
+    assert_eq!(event.name, None);
+    assert!(watcher.unwatch(wd).is_err());
+    std::fs::remove_dir(dir).unwrap();
+}
+
+#[photonio::test]
+async fn rw_flags() {
+    use std::io::{IoSlice, IoSliceMut};
+
+    use fs::RwFlags;
+
+    let path = "/tmp/test_rw_flags.txt";
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .await
+        .unwrap();
+    let bufs = [IoSlice::new(b"hello"), IoSlice::new(b" world")];
+    let n = file
+        .write_vectored_at_flags(&bufs, 0, RwFlags::DSYNC)
+        .await
+        .unwrap();
+    assert_eq!(n, 11);
+    let (mut a, mut b) = ([0; 5], [0; 6]);
+    let mut bufs = [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)];
+    let n = file
+        .read_vectored_at_flags(&mut bufs, 0, RwFlags::empty())
+        .await
+        .unwrap();
+    assert_eq!(n, 11);
+    assert_eq!((&a, &b), (b"hello", b" world"));
+
+    // Reads of data that is not cached fail instead of waiting, on the
+    // filesystems that support it.
+    file.sync_all().await.unwrap();
+    file.advise(0, 0, Advice::DontNeed).await.unwrap();
+    let mut bufs = [IoSliceMut::new(&mut a)];
+    match file
+        .read_vectored_at_flags(&mut bufs, 0, RwFlags::NOWAIT)
+        .await
+    {
+        Ok(n) => assert_eq!(n, 5),
+        Err(e) if io::raw_os_error(&e) == Some(libc::EOPNOTSUPP) => {}
+        Err(e) => assert_eq!(e.kind(), ErrorKind::WouldBlock),
+    }
+    std::fs::remove_file(path).unwrap();
+}
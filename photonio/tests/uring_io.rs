@@ -0,0 +1,587 @@
+#![cfg(all(target_os = "linux", not(feature = "tokio")))]
+
+use std::{
+    io::ErrorKind,
+    os::unix::io::{AsFd, AsRawFd},
+    ptr,
+};
+
+use photonio::{
+    fs::File,
+    io::{self, Read, ReadAt, ReadExt, Write, WriteExt},
+};
+
+#[photonio::test]
+async fn madvise() {
+    const LEN: usize = 1 << 20;
+
+    let addr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            LEN,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(addr, libc::MAP_FAILED);
+    let addr = addr as *mut u8;
+    unsafe {
+        ptr::write_bytes(addr, 1, LEN);
+        io::madvise(addr, LEN, libc::MADV_WILLNEED).await.unwrap();
+        io::madvise(addr, LEN, libc::MADV_DONTNEED).await.unwrap();
+        // Private anonymous pages are zero-filled after DONTNEED.
+        assert_eq!(*addr, 0);
+        assert_eq!(libc::munmap(addr as _, LEN), 0);
+    }
+}
+
+#[photonio::test]
+async fn splice() {
+    const LEN: usize = 1 << 20;
+
+    let src_path = "/tmp/test_splice_src.txt";
+    let dst_path = "/tmp/test_splice_dst.txt";
+    let data: Vec<u8> = (0..LEN).map(|i| (i % 251) as u8).collect();
+    let mut src = File::create(src_path).await.unwrap();
+    src.write_all(&data).await.unwrap();
+    let src = File::open(src_path).await.unwrap();
+    let dst = File::create(dst_path).await.unwrap();
+    let (reader, writer) = io::pipe().unwrap();
+
+    // The pipe is much smaller than the file, so both sides see short splices.
+    let fill = async move {
+        let mut pos = 0;
+        while pos < LEN {
+            let n = io::splice(
+                src.as_fd(),
+                Some(pos as u64),
+                writer.as_fd(),
+                None,
+                LEN - pos,
+            )
+            .await
+            .unwrap();
+            assert!(n > 0);
+            pos += n;
+        }
+        // Closes the write end so that the other side sees EOF.
+        drop(writer);
+    };
+    let drain = async {
+        let mut pos = 0;
+        loop {
+            let n = io::splice(reader.as_fd(), None, dst.as_fd(), Some(pos as u64), LEN)
+                .await
+                .unwrap();
+            if n == 0 {
+                break pos;
+            }
+            pos += n;
+        }
+    };
+    let (_, len) = futures::join!(fill, drain);
+    assert_eq!(len, LEN);
+
+    let mut buf = vec![0; LEN];
+    let mut dst = File::open(dst_path).await.unwrap();
+    dst.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, data);
+}
+
+#[photonio::test]
+async fn pipe() {
+    let (mut reader, mut writer) = io::pipe().unwrap();
+    writer.write_all(b"hello").await.unwrap();
+    let mut buf = [0; 5];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+
+    drop(writer);
+    assert_eq!(reader.read(&mut buf).await.unwrap(), 0);
+
+    let (reader, mut writer) = io::pipe().unwrap();
+    drop(reader);
+    let err = writer.write(b"hello").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+}
+
+#[photonio::test]
+async fn tee() {
+    let (mut reader, mut writer) = io::pipe().unwrap();
+    let (mut mirror_reader, mirror_writer) = io::pipe().unwrap();
+
+    writer.write_all(b"hello").await.unwrap();
+    assert_eq!(reader.tee_to(&mirror_writer, 5).await.unwrap(), 5);
+    let mut buf = [0; 5];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+    mirror_reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+
+    // Waits for data on an empty pipe.
+    let (n, _) = futures::join!(reader.tee_to(&mirror_writer, 5), async {
+        writer.write_all(b"world").await.unwrap();
+    });
+    assert_eq!(n.unwrap(), 5);
+    mirror_reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"world");
+
+    // Returns zero once the pipe is drained and closed.
+    reader.read_exact(&mut buf).await.unwrap();
+    drop(writer);
+    assert_eq!(reader.tee_to(&mirror_writer, 5).await.unwrap(), 0);
+}
+
+#[photonio::test]
+async fn buf_group() {
+    use futures::future::join_all;
+    use photonio::io::BufGroup;
+
+    let group = BufGroup::new(8, 16).await.unwrap();
+    let num_pipes = 64;
+    let num_msgs = 16;
+    let mut readers = Vec::new();
+    let mut writers = Vec::new();
+    for _ in 0..num_pipes {
+        let (reader, writer) = io::pipe().unwrap();
+        readers.push(reader);
+        writers.push(writer);
+    }
+
+    // More concurrent reads than buffers, each checks that its data is not
+    // corrupted by the others.
+    let read = |i: usize, reader: io::PipeReader| {
+        let group = &group;
+        async move {
+            let mut received = Vec::new();
+            while received.len() < num_msgs * 16 {
+                match group.read(reader.as_fd()).await {
+                    Ok(buf) => {
+                        assert!(!buf.is_empty());
+                        received.extend_from_slice(&buf);
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        photonio::task::yield_now().await;
+                    }
+                    Err(e) => panic!("{}", e),
+                }
+            }
+            assert!(received.iter().all(|&b| b == i as u8));
+        }
+    };
+    let write = |i: usize, mut writer: io::PipeWriter| async move {
+        for _ in 0..num_msgs {
+            writer.write_all(&[i as u8; 16]).await.unwrap();
+            photonio::task::yield_now().await;
+        }
+    };
+    let reads = join_all(readers.into_iter().enumerate().map(|(i, r)| read(i, r)));
+    let writes = join_all(writers.into_iter().enumerate().map(|(i, w)| write(i, w)));
+    futures::join!(reads, writes);
+    assert_eq!(group.replenish().await.unwrap(), 0);
+}
+
+#[photonio::test]
+async fn buf_group_drop_read() {
+    use photonio::io::BufGroup;
+
+    // The only buffer of the group is provided again after each dropped read,
+    // whether the read is cancelled or completes after the drop.
+    let group = BufGroup::new(1, 16).await.unwrap();
+    for _ in 0..8 {
+        let (reader, mut writer) = io::pipe().unwrap();
+        let mut read = Box::pin(group.read(reader.as_fd()));
+        assert!(futures::poll!(&mut read).is_pending());
+        writer.write_all(b"hello").await.unwrap();
+        drop(read);
+    }
+    let (reader, mut writer) = io::pipe().unwrap();
+    writer.write_all(b"hello").await.unwrap();
+    let buf = group.read(reader.as_fd()).await.unwrap();
+    assert_eq!(&buf[..], b"hello");
+}
+
+#[test]
+fn disable_opcode() {
+    use photonio::{
+        fs::Advice,
+        io::Opcode,
+        runtime::{Builder, Runtime},
+    };
+
+    async fn run() -> (bool, Option<ErrorKind>, bool) {
+        let file = File::open("Cargo.toml").await.unwrap();
+        let is_file = file.metadata().await.unwrap().is_file();
+        let advise = file
+            .advise_strict(0, 0, Advice::Sequential)
+            .await
+            .err()
+            .map(|e| e.kind());
+        file.advise(0, 0, Advice::Sequential).await.unwrap();
+        (is_file, advise, io::is_supported(Opcode::Statx))
+    }
+
+    // Both paths must behave the same, except for the error of operations
+    // without fallbacks.
+    let rt = Runtime::new().unwrap();
+    let (is_file, _, _) = rt.block_on(run());
+    assert!(is_file);
+
+    let rt = Builder::new()
+        .num_threads(1)
+        .disable_opcode(Opcode::Statx)
+        .disable_opcode(Opcode::Fadvise)
+        .build()
+        .unwrap();
+    let (is_file, advise, statx) = rt.block_on(run());
+    assert!(is_file);
+    assert_eq!(advise, Some(ErrorKind::Unsupported));
+    assert!(!statx);
+    assert_eq!(Opcode::Fadvise.to_string(), "IORING_OP_FADVISE");
+}
+
+#[test]
+fn fixed_files() {
+    use photonio::runtime::Builder;
+
+    let rt = Builder::new()
+        .num_threads(1)
+        .fixed_files(4)
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let file = File::open("Cargo.toml").await.unwrap();
+        let fd = file.as_fd();
+        assert!(io::register_files(&[fd; 5]).is_err());
+        let fds = io::register_files(&[fd; 4]).unwrap();
+        assert!(io::register_files(&[fd]).is_err());
+        drop(fds);
+        assert_eq!(io::register_files(&[fd; 4]).unwrap().len(), 4);
+    });
+}
+
+#[photonio::test]
+async fn nop() {
+    use futures::future::join_all;
+
+    const N: usize = 1000;
+
+    let mut nops = Box::pin(join_all((0..N).map(|_| io::nop())));
+    // Polls all nops once to submit them, and then pushes them to the kernel.
+    let _ = futures::poll!(&mut nops);
+    io::submit_now().unwrap();
+    let results = nops.await;
+    assert_eq!(results.len(), N);
+    assert!(results.into_iter().all(|r| r.is_ok()));
+}
+
+#[photonio::test]
+async fn nop_backpressure() {
+    use futures::future::join_all;
+
+    // Ten times the size of the submission queue.
+    const N: usize = 40960;
+
+    let results = join_all((0..N).map(|_| io::nop())).await;
+    assert_eq!(results.len(), N);
+    assert!(results.into_iter().all(|r| r.is_ok()));
+}
+
+#[photonio::test]
+async fn submit_raw() {
+    use photonio::io::io_uring::{opcode, types};
+
+    let res = unsafe { io::submit_raw(opcode::Nop::new().build()).await };
+    assert_eq!(res.unwrap(), 0);
+
+    let file = File::open("Cargo.toml").await.unwrap();
+    let mut expect = [0; 64];
+    let n = file.read_at(&mut expect, 8).await.unwrap();
+    let mut buf = [0; 64];
+    let fd = types::Fd(file.as_raw_fd());
+    let sqe = opcode::Read::new(fd, buf.as_mut_ptr(), buf.len() as _)
+        .offset(8)
+        .build();
+    let (res, flags) = unsafe { io::submit_raw_with_cqe_flags(sqe).await.unwrap() };
+    assert_eq!(res, n as i32);
+    assert_eq!(flags, 0);
+    assert_eq!(buf, expect);
+
+    // Failures are returned as negated errno values.
+    let sqe = opcode::Read::new(types::Fd(-1), buf.as_mut_ptr(), buf.len() as _).build();
+    let res = unsafe { io::submit_raw(sqe).await.unwrap() };
+    assert_eq!(res, -libc::EBADF);
+}
+
+#[test]
+fn uring_cmd() {
+    use photonio::{
+        io::Opcode,
+        runtime::{Builder, Runtime},
+    };
+
+    // Regular files don't support passthrough commands. Returns `None` if the
+    // kernel doesn't support them at all.
+    async fn run() -> Option<ErrorKind> {
+        let file = File::open("Cargo.toml").await.unwrap();
+        let err = unsafe { io::uring_cmd(file.as_fd(), 0, &[0; 16]) }
+            .await
+            .unwrap_err();
+        io::is_supported(Opcode::UringCmd).then(|| err.kind())
+    }
+
+    let rt = Runtime::new().unwrap();
+    if rt.block_on(run()).is_none() {
+        return;
+    }
+    assert_eq!(rt.block_on(run()), Some(ErrorKind::InvalidInput));
+
+    let rt = Builder::new()
+        .num_threads(1)
+        .sqe128(true)
+        .cqe32(true)
+        .build()
+        .unwrap();
+    assert_eq!(rt.block_on(run()), Some(ErrorKind::Unsupported));
+}
+
+/// Identifies the controller of `/dev/ng0n1` through NVMe passthrough.
+#[test]
+#[ignore]
+fn uring_cmd_nvme() {
+    use photonio::runtime::Builder;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct NvmeUringCmd {
+        opcode: u8,
+        flags: u8,
+        rsvd1: u16,
+        nsid: u32,
+        cdw2: u32,
+        cdw3: u32,
+        metadata: u64,
+        addr: u64,
+        metadata_len: u32,
+        data_len: u32,
+        cdw10: u32,
+        cdw11: u32,
+        cdw12: u32,
+        cdw13: u32,
+        cdw14: u32,
+        cdw15: u32,
+        timeout_ms: u32,
+        rsvd2: u32,
+    }
+
+    // _IOWR('N', 0x82, struct nvme_uring_cmd)
+    const NVME_URING_CMD_ADMIN: u32 = 0xC048_4E82;
+    const NVME_ADMIN_IDENTIFY: u8 = 0x06;
+
+    let rt = Builder::new()
+        .num_threads(1)
+        .sqe128(true)
+        .cqe32(true)
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let file = File::open("/dev/ng0n1").await.unwrap();
+        let mut data = vec![0u8; 4096];
+        let cmd = NvmeUringCmd {
+            opcode: NVME_ADMIN_IDENTIFY,
+            addr: data.as_mut_ptr() as u64,
+            data_len: data.len() as u32,
+            // Identifies the controller.
+            cdw10: 1,
+            ..Default::default()
+        };
+        let cmd = unsafe {
+            std::slice::from_raw_parts(
+                &cmd as *const _ as *const u8,
+                std::mem::size_of::<NvmeUringCmd>(),
+            )
+        };
+        let (result, _) = unsafe { io::uring_cmd(file.as_fd(), NVME_URING_CMD_ADMIN, cmd) }
+            .await
+            .unwrap();
+        assert_eq!(result, 0);
+        // The PCI vendor id is never zero.
+        assert_ne!(&data[..2], &[0, 0]);
+    });
+}
+
+#[photonio::test]
+async fn notify() {
+    use std::sync::atomic::AtomicU32;
+
+    use photonio::{io::Opcode, sync::Notify, task};
+
+    if !io::is_supported(Opcode::FutexWait) {
+        return;
+    }
+
+    static WORD: AtomicU32 = AtomicU32::new(0);
+    let notify = Notify::new(&WORD);
+
+    // Wakes before waiting.
+    notify.notify_one().await.unwrap();
+    notify.notified().await.unwrap();
+
+    // Waits before waking.
+    let waiter = task::spawn(async { Notify::new(&WORD).notified().await });
+    for _ in 0..8 {
+        task::yield_now().await;
+    }
+    notify.notify_one().await.unwrap();
+    waiter.await.unwrap().unwrap();
+}
+
+/// Notifies a task from another process through shared memory.
+#[photonio::test]
+#[ignore]
+async fn notify_shared() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use photonio::sync::Notify;
+
+    let addr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            4,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(addr, libc::MAP_FAILED);
+    let word = unsafe { &*(addr as *const AtomicU32) };
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        // The child only uses async-signal-safe calls.
+        unsafe {
+            libc::usleep(100_000);
+            word.store(1, Ordering::Release);
+            libc::syscall(libc::SYS_futex, addr, libc::FUTEX_WAKE, 1);
+            libc::_exit(0);
+        }
+    }
+    Notify::new(word).notified().await.unwrap();
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert_eq!(status, 0);
+    unsafe { libc::munmap(addr, 4) };
+}
+
+#[photonio::test]
+async fn retry_interrupted() {
+    use photonio::{
+        net::{TcpListener, TcpStream},
+        runtime::inject_results,
+    };
+
+    let (mut reader, mut writer) = io::pipe().unwrap();
+    let mut buf = [0; 5];
+
+    // Interrupted operations are resubmitted with the same buffer.
+    writer.write_all(b"hello").await.unwrap();
+    inject_results(&[-libc::EINTR, -libc::EINTR]).unwrap();
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+
+    // Gives up after a bounded number of retries.
+    inject_results(&[-libc::EINTR; 64]).unwrap();
+    let err = io::nop().await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Interrupted);
+    // Drains the remaining injected results.
+    while io::nop().await.is_err() {}
+
+    // `EAGAIN` is only retried for sockets.
+    writer.write_all(b"hello").await.unwrap();
+    inject_results(&[-libc::EAGAIN]).unwrap();
+    let err = reader.read(&mut buf).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    let (mut server, _) = listener.accept().await.unwrap();
+    client.write_all(b"hello").await.unwrap();
+    inject_results(&[-libc::EAGAIN]).unwrap();
+    server.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+
+    // Operations with a timeout follow the same policy.
+    let timeout = std::time::Duration::from_secs(10);
+    client.write_all(b"hello").await.unwrap();
+    inject_results(&[-libc::EINTR]).unwrap();
+    assert_eq!(server.read_timeout(&mut buf, timeout).await.unwrap(), 5);
+    assert_eq!(&buf, b"hello");
+    client.write_all(b"hello").await.unwrap();
+    inject_results(&[-libc::EIO]).unwrap();
+    let err = server.read_timeout(&mut buf, timeout).await.unwrap_err();
+    let err = err
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<io::OpError>()
+        .unwrap();
+    assert_eq!(err.raw_os_error(), Some(libc::EIO));
+}
+
+#[photonio::test]
+async fn owned_fd() {
+    use std::{
+        io::{Read as _, Seek as _, Write as _},
+        os::unix::io::OwnedFd,
+    };
+
+    use photonio::net::{TcpListener, TcpStream};
+
+    let path = "/tmp/test_owned_fd.txt";
+
+    // The position moves with the descriptor in both directions.
+    let mut std_file = std::fs::File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .unwrap();
+    std_file.write_all(b"hello").unwrap();
+    let mut file = File::from(OwnedFd::from(std_file));
+    file.write_all(b" world").await.unwrap();
+    let mut std_file = std::fs::File::from(OwnedFd::from(file));
+    assert_eq!(std_file.stream_position().unwrap(), 11);
+    std_file.rewind().unwrap();
+    let mut buf = String::new();
+    std_file.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "hello world");
+    std::fs::remove_file(path).unwrap();
+
+    // Non-blocking sockets are switched to blocking mode, so reads wait for
+    // data instead of failing.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let listener = TcpListener::from(OwnedFd::from(listener));
+    let addr = listener.local_addr().unwrap();
+    let std_client = std::net::TcpStream::connect(addr).unwrap();
+    std_client.set_nonblocking(true).unwrap();
+    let mut client = TcpStream::from(OwnedFd::from(std_client));
+    let (mut server, _) = listener.accept().await.unwrap();
+    let mut buf = [0; 5];
+    let (read, write) = futures::join!(client.read_exact(&mut buf), async {
+        io::nop().await.unwrap();
+        server.write_all(b"hello").await
+    });
+    read.unwrap();
+    write.unwrap();
+    assert_eq!(&buf, b"hello");
+
+    let mut std_client = std::net::TcpStream::from(OwnedFd::from(client));
+    std_client.write_all(b"world").unwrap();
+    server.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"world");
+}
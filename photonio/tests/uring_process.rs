@@ -0,0 +1,51 @@
+#![cfg(all(target_os = "linux", not(feature = "tokio")))]
+
+use std::process::Command;
+
+use photonio::{
+    io::{self, Opcode},
+    process::Child,
+    runtime::last_opcode,
+};
+
+#[photonio::test]
+async fn wait() {
+    let mut child = Child::from(Command::new("/bin/true").spawn().unwrap());
+    assert_eq!(child.wait().await.unwrap().code(), Some(0));
+    // The status is cached after the child is reaped.
+    assert_eq!(child.wait().await.unwrap().code(), Some(0));
+
+    let mut child = Child::from(Command::new("/bin/false").spawn().unwrap());
+    assert_eq!(child.wait().await.unwrap().code(), Some(1));
+}
+
+#[photonio::test]
+async fn wait_exited() {
+    // The child exits before the wait is submitted.
+    let mut child = Child::from(Command::new("/bin/true").spawn().unwrap());
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(child.wait().await.unwrap().success());
+}
+
+#[photonio::test]
+async fn wait_reaped() {
+    // Another waiter reaps the child first.
+    let mut child = Child::from(Command::new("/bin/true").spawn().unwrap());
+    let mut status = 0;
+    let pid = child.id() as libc::pid_t;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    let err = child.wait().await.unwrap_err();
+    assert_eq!(io::raw_os_error(&err), Some(libc::ECHILD));
+}
+
+#[photonio::test]
+async fn wait_opcode() {
+    // Kernels before 6.7 fall back to polling a pidfd.
+    if !io::is_supported(Opcode::WaitId) {
+        return;
+    }
+    let mut child = Child::from(Command::new("/bin/true").spawn().unwrap());
+    assert!(child.wait().await.unwrap().success());
+    // The child is reaped by `IORING_OP_WAITID`, see `linux/io_uring.h`.
+    assert_eq!(last_opcode().unwrap(), 50);
+}
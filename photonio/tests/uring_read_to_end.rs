@@ -0,0 +1,95 @@
+#![cfg(all(target_os = "linux", not(feature = "tokio")))]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    io::Write as _,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use photonio::{fs::File, io::ReadExt};
+
+const LEN: usize = 8 << 20;
+
+/// Counts the allocations that could hold the whole file.
+struct CountingAlloc;
+
+static LARGE_ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() >= LEN {
+            LARGE_ALLOCS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size >= LEN {
+            LARGE_ALLOCS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+#[photonio::test]
+async fn read_to_end() {
+    let path = "/tmp/test_read_to_end.bin";
+    let data: Vec<u8> = (0..LEN).map(|i| i as u8).collect();
+    std::fs::write(path, &data).unwrap();
+
+    // A file of a stable size is read with a single allocation.
+    let before = LARGE_ALLOCS.load(Ordering::Relaxed);
+    let mut buf = Vec::new();
+    let n = File::open(path)
+        .await
+        .unwrap()
+        .read_to_end(&mut buf)
+        .await
+        .unwrap();
+    assert_eq!(LARGE_ALLOCS.load(Ordering::Relaxed) - before, 1);
+    assert_eq!(n, LEN);
+    assert!(buf == data);
+    drop(buf);
+
+    // The size is counted from the current position, and data appended after
+    // the size is queried is read too, which grows the buffer past the hint.
+    let mut file = File::open(path).await.unwrap();
+    let mut head = [0; 5];
+    file.read_exact(&mut head).await.unwrap();
+    assert_eq!(&head[..], &data[..5]);
+    let before = LARGE_ALLOCS.load(Ordering::Relaxed);
+    let mut buf = Vec::new();
+    let mut read = Box::pin(file.read_to_end(&mut buf));
+    // The read reserves the hint right after it queries the size.
+    while LARGE_ALLOCS.load(Ordering::Relaxed) == before {
+        assert!(futures::poll!(&mut read).is_pending());
+        photonio::task::yield_now().await;
+    }
+    let tail = vec![7; 4096];
+    let mut appender = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+    appender.write_all(&tail).unwrap();
+    let n = read.await.unwrap();
+    assert_eq!(n, LEN - 5 + tail.len());
+    assert!(buf[..LEN - 5] == data[5..]);
+    assert!(buf[LEN - 5..] == tail);
+    std::fs::remove_file(path).unwrap();
+
+    // Files in /proc report a size of zero, so the buffer grows past the hint.
+    let expected = std::fs::read("/proc/self/cmdline").unwrap();
+    let mut buf = b"prefix".to_vec();
+    let n = File::open("/proc/self/cmdline")
+        .await
+        .unwrap()
+        .read_to_end(&mut buf)
+        .await
+        .unwrap();
+    assert_eq!(n, expected.len());
+    assert_eq!(&buf[6..], &expected[..]);
+}